@@ -0,0 +1,29 @@
+//! Compares AnonymousMmap::zero against a manual fill(0) over the same mapping.
+
+use anonymous_mmap::AnonymousMmap;
+use std::time::{Duration, Instant};
+
+const LEN: usize = 64 * 1024 * 1024;
+const ROUNDS: u32 = 20;
+
+fn time(mut f: impl FnMut()) -> Duration {
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        f();
+    }
+    start.elapsed() / ROUNDS
+}
+
+fn main() {
+    let mut mmap = AnonymousMmap::new(LEN).expect("mmap");
+    let zero = time(|| {
+        mmap.zero();
+        std::hint::black_box(&mmap);
+    });
+    let fill = time(|| {
+        mmap.view_mut(..).expect("view").as_slice_mut().fill(0);
+        std::hint::black_box(&mmap);
+    });
+    println!("zero():  {:?} / {} MiB", zero, LEN >> 20);
+    println!("fill(0): {:?} / {} MiB", fill, LEN >> 20);
+}
@@ -0,0 +1,205 @@
+//! Opt-in global accounting of the live [`AnonymousMmap`]s to find the ones never given to
+//! [`AnonymousMmap::try_drop`]
+//!
+//! Without the `accounting` feature the hooks are empty and nothing is kept.
+
+use crate::AnonymousMmap;
+#[cfg(feature = "accounting")]
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+// Live mappings and their bytes including guard pages.
+#[cfg(feature = "accounting")]
+struct Counters {
+    mappings: AtomicUsize,
+    bytes: AtomicUsize,
+}
+
+#[cfg(feature = "accounting")]
+impl Counters {
+    const fn new() -> Self {
+        Self {
+            mappings: AtomicUsize::new(0),
+            bytes: AtomicUsize::new(0),
+        }
+    }
+    fn add(&self, mappings: usize, bytes: usize) {
+        self.mappings.fetch_add(mappings, Ordering::Relaxed);
+        self.bytes.fetch_add(bytes, Ordering::Relaxed);
+    }
+    fn sub(&self, mappings: usize, bytes: usize) {
+        self.mappings.fetch_sub(mappings, Ordering::Relaxed);
+        self.bytes.fetch_sub(bytes, Ordering::Relaxed);
+    }
+    fn get(&self) -> (usize, usize) {
+        (
+            self.mappings.load(Ordering::Relaxed),
+            self.bytes.load(Ordering::Relaxed),
+        )
+    }
+}
+
+#[cfg(feature = "accounting")]
+static LIVE: Counters = Counters::new();
+
+/// Number of live mappings and their bytes including guard pages, counting from creation
+/// until a successful [`AnonymousMmap::try_drop`] or giving up the ownership through
+/// [`AnonymousMmap::into_raw`] / [`AnonymousMmap::leak`].
+///
+/// Ranges unmapped through [`AnonymousMmap::unmap_range`] still count until the mapping is
+/// dropped. Requires the `accounting` feature.
+#[cfg(feature = "accounting")]
+#[inline]
+pub fn live_mappings() -> (usize, usize) {
+    LIVE.get()
+}
+
+#[inline]
+#[cfg_attr(not(feature = "accounting"), allow(unused_variables))]
+pub(crate) fn created(mmap: &mut AnonymousMmap) {
+    #[cfg(feature = "accounting")]
+    LIVE.add(1, mmap.len + mmap.guard);
+    #[cfg(feature = "leak_backtraces")]
+    registry::insert(mmap);
+}
+
+#[inline]
+#[cfg_attr(not(feature = "accounting"), allow(unused_variables))]
+pub(crate) fn resized(old: usize, new: usize) {
+    #[cfg(feature = "accounting")]
+    match new > old {
+        true => LIVE.add(0, new - old),
+        false => LIVE.sub(0, old - new),
+    }
+}
+
+#[inline]
+#[cfg_attr(not(feature = "accounting"), allow(unused_variables))]
+pub(crate) fn released(mmap: &AnonymousMmap) {
+    #[cfg(feature = "accounting")]
+    LIVE.sub(1, mmap.len + mmap.guard);
+    #[cfg(feature = "leak_backtraces")]
+    registry::remove(mmap);
+}
+
+#[cfg(feature = "leak_backtraces")]
+mod registry {
+
+    use crate::AnonymousMmap;
+    use core::sync::atomic::{AtomicU64, Ordering};
+    use std::backtrace::Backtrace;
+    use std::collections::BTreeMap;
+    use std::sync::{Mutex, MutexGuard, PoisonError};
+
+    static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+    static CREATED: Mutex<BTreeMap<u64, Backtrace>> = Mutex::new(BTreeMap::new());
+
+    // A panic while holding the lock leaves the map itself intact.
+    pub(super) fn lock() -> MutexGuard<'static, BTreeMap<u64, Backtrace>> {
+        CREATED.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub(super) fn insert(mmap: &mut AnonymousMmap) {
+        mmap.id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        // Capturing each creation is only affordable in debug builds.
+        if cfg!(debug_assertions) {
+            lock().insert(mmap.id, Backtrace::force_capture());
+        }
+    }
+
+    pub(super) fn remove(mmap: &AnonymousMmap) {
+        if cfg!(debug_assertions) {
+            lock().remove(&mmap.id);
+        }
+    }
+}
+
+/// Write where each live mapping was created to w returning the number of them, e.g. at
+/// the end of a test suite or before exit.
+///
+/// The creation backtraces are only captured in debug builds, in release builds nothing is
+/// written. Requires the `leak_backtraces` feature.
+#[cfg(feature = "leak_backtraces")]
+#[inline]
+pub fn write_leaks(w: &mut impl std::io::Write) -> std::io::Result<usize> {
+    let created = registry::lock();
+    for (id, backtrace) in created.iter() {
+        writeln!(w, "AnonymousMmap #{} created at:\n{}", id, backtrace)?;
+    }
+    Ok(created.len())
+}
+
+/// Print where each live mapping was created to stderr through [`write_leaks`].
+#[cfg(feature = "leak_backtraces")]
+#[inline]
+pub fn dump_leaks() {
+    // Nothing to report a failing stderr to.
+    let _ = write_leaks(&mut std::io::stderr());
+}
+
+#[cfg(all(test, feature = "accounting"))]
+mod test {
+
+    use super::*;
+    use crate::page_size;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(&[(1, 4096)], &[], (1, 4096))]
+    #[case(&[(1, 4096), (1, 8192)], &[(1, 4096)], (1, 8192))]
+    #[case(&[(2, 100)], &[(2, 100)], (0, 0))]
+    fn counters(
+        #[case] added: &[(usize, usize)],
+        #[case] removed: &[(usize, usize)],
+        #[case] expected: (usize, usize),
+    ) {
+        let counters = Counters::new();
+        for (mappings, bytes) in added {
+            counters.add(*mappings, *bytes);
+        }
+        for (mappings, bytes) in removed {
+            counters.sub(*mappings, *bytes);
+        }
+        assert_eq!(counters.get(), expected);
+    }
+
+    // Other tests map and unmap concurrently so only the lower bound holds.
+    #[test]
+    fn live_while_held() {
+        let page = page_size();
+        let mmap = AnonymousMmap::new(page * 3).unwrap();
+        let stack = AnonymousMmap::new_stack(page).unwrap();
+        let (mappings, bytes) = live_mappings();
+        assert!(mappings >= 2);
+        assert!(bytes >= page * 5);
+        unsafe { mmap.try_drop().unwrap() };
+        unsafe { stack.try_drop().unwrap() };
+    }
+
+    #[cfg(feature = "leak_backtraces")]
+    #[test]
+    fn leak_backtraces_until_dropped() {
+        let mmap = AnonymousMmap::new(page_size()).unwrap();
+        let (head, tail) = AnonymousMmap::new(page_size() * 2)
+            .unwrap()
+            .split_off(page_size())
+            .unwrap();
+        let marker = |id: u64| format!("AnonymousMmap #{} created at:", id);
+        let mut out = Vec::new();
+        assert!(write_leaks(&mut out).unwrap() >= 3);
+        let out = String::from_utf8(out).unwrap();
+        for id in [mmap.id, head.id, tail.id] {
+            assert!(out.contains(&marker(id)), "{}", out);
+        }
+        assert!(out.contains("leak_backtraces_until_dropped"), "{}", out);
+        let ids = [mmap.id, head.id, tail.id];
+        unsafe { mmap.try_drop().unwrap() };
+        unsafe { head.try_drop().unwrap() };
+        unsafe { tail.try_drop().unwrap() };
+        let mut out = Vec::new();
+        write_leaks(&mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        for id in ids {
+            assert!(!out.contains(&marker(id)), "{}", out);
+        }
+    }
+}
@@ -0,0 +1,228 @@
+//! madvise(2) based advice over ranges of the mapping
+
+use crate::{AnonymousMmap, AnonymousMmapError, OsError};
+
+impl AnonymousMmap {
+    #[inline]
+    pub(crate) fn madvise(
+        &self,
+        offset: usize,
+        len: usize,
+        advice: libc::c_int,
+    ) -> Result<(), AnonymousMmapError> {
+        let p = self.page_range(offset, len)?;
+        // SAFETY: The range is checked to be within the mapping.
+        if unsafe { libc::madvise(p, len, advice) } != 0 {
+            let os_err = OsError::last_os_error();
+            return Err(AnonymousMmapError::MadviseFailed(os_err));
+        }
+        Ok(())
+    }
+    /// Return the pages of the given page-aligned range to the kernel using MADV_DONTNEED
+    /// without unmapping them so pointers stay valid.
+    ///
+    /// The pages are released immediately and for private mappings read back zero-filled on
+    /// the next touch. For shared mappings the contents are retained by the shared backing.
+    ///
+    /// Zeroing changes the bytes under any view, so the mapping is borrowed exclusively and no
+    /// view can outlive the call:
+    ///
+    /// ```compile_fail
+    /// use anonymous_mmap::AnonymousMmap;
+    ///
+    /// let mut mmap = AnonymousMmap::new_private(4096).unwrap();
+    /// let view = mmap.view(..).unwrap();
+    /// mmap.decommit(0, 4096).unwrap();
+    /// assert_eq!(view.as_slice()[0], 0);
+    /// ```
+    #[inline]
+    pub fn decommit(&mut self, offset: usize, len: usize) -> Result<(), AnonymousMmapError> {
+        self.madvise(offset, len, libc::MADV_DONTNEED)
+    }
+    /// Lazily return the pages of the given page-aligned range to the kernel using MADV_FREE.
+    ///
+    /// Unlike [`Self::decommit`] the pages are only reclaimed under memory pressure and until
+    /// then any write cancels the free, meaning the contents are either the old or zero-filled.
+    /// MADV_FREE only works on private mappings and is rejected with EINVAL on MAP_SHARED.
+    /// The mapping is borrowed exclusively for the same reason as [`Self::decommit`].
+    #[inline]
+    pub fn decommit_lazy(&mut self, offset: usize, len: usize) -> Result<(), AnonymousMmapError> {
+        self.madvise(offset, len, libc::MADV_FREE)
+    }
+    /// Deactivate the pages of the given page-aligned range using MADV_COLD so they are the
+    /// first reclaimed under memory pressure, e.g. for cache entries gone cold.
+    ///
+    /// Unlike [`Self::decommit`] and [`Self::decommit_lazy`] the contents are always kept and
+    /// nothing is freed until the kernel actually needs the memory. Kernels before 5.4 reject
+    /// this with EINVAL which is surfaced as [`AnonymousMmapError::Unsupported`].
+    #[inline]
+    pub fn advise_cold(&self, offset: usize, len: usize) -> Result<(), AnonymousMmapError> {
+        self.madvise_since(offset, len, libc::MADV_COLD, "MADV_COLD")
+    }
+    /// Reclaim the pages of the given page-aligned range right away using MADV_PAGEOUT,
+    /// writing them out to swap where they are faulted back in with the contents intact.
+    ///
+    /// As opposed to [`Self::advise_cold`] the reclaim is immediate rather than deferred to
+    /// memory pressure and unlike [`Self::decommit`] the contents are kept. Without swap
+    /// anonymous pages stay resident. Kernels before 5.4 reject this with EINVAL which is
+    /// surfaced as [`AnonymousMmapError::Unsupported`].
+    #[inline]
+    pub fn advise_pageout(&self, offset: usize, len: usize) -> Result<(), AnonymousMmapError> {
+        self.madvise_since(offset, len, libc::MADV_PAGEOUT, "MADV_PAGEOUT")
+    }
+    // Advice newer kernels know where EINVAL past the range checks means unknown advice.
+    #[inline]
+    fn madvise_since(
+        &self,
+        offset: usize,
+        len: usize,
+        advice: libc::c_int,
+        name: &'static str,
+    ) -> Result<(), AnonymousMmapError> {
+        match self.madvise(offset, len, advice) {
+            Err(AnonymousMmapError::MadviseFailed(e)) if e.raw_os_error() == Some(libc::EINVAL) => {
+                Err(AnonymousMmapError::Unsupported(name, e))
+            }
+            r => r,
+        }
+    }
+    /// Toggle whether forked children see the mapping zero-filled (MADV_WIPEONFORK) or
+    /// inherit the contents (MADV_KEEPONFORK) e.g. for per-process PRNG pools and nonces.
+    ///
+    /// Kernels before 4.14 and MAP_SHARED mappings reject this with EINVAL which is
+    /// surfaced as [`AnonymousMmapError::Unsupported`].
+    #[inline]
+    pub fn wipe_on_fork(&self, enabled: bool) -> Result<(), AnonymousMmapError> {
+        let advice = match enabled {
+            true => libc::MADV_WIPEONFORK,
+            false => libc::MADV_KEEPONFORK,
+        };
+        self.madvise_since(0, self.len, advice, "MADV_WIPEONFORK")
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use crate::page_size;
+    use rstest::rstest;
+
+    #[test]
+    fn decommit_zeroes() {
+        let page = page_size();
+        let mut mmap = AnonymousMmap::new_private(page * 2).unwrap();
+        mmap.view_mut(..).unwrap().as_slice_mut().fill(0xAB);
+        mmap.decommit(page, page).unwrap();
+        let view = mmap.view(..).unwrap();
+        assert!(view.as_slice()[..page].iter().all(|b| *b == 0xAB));
+        assert!(view.as_slice()[page..].iter().all(|b| *b == 0));
+    }
+
+    #[test]
+    fn decommit_lazy_shared_rejected() {
+        let page = page_size();
+        let mut private = AnonymousMmap::new_private(page).unwrap();
+        private.decommit_lazy(0, page).unwrap();
+        let mut shared = AnonymousMmap::new(page).unwrap();
+        match shared.decommit_lazy(0, page) {
+            Err(AnonymousMmapError::MadviseFailed(e)) => {
+                assert_eq!(e.raw_os_error(), Some(libc::EINVAL))
+            }
+            r => panic!("Expected EINVAL, got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn decommit_checked() {
+        let page = page_size();
+        let mut mmap = AnonymousMmap::new_private(page * 2).unwrap();
+        assert!(matches!(
+            mmap.decommit(1, page),
+            Err(AnonymousMmapError::NotPageAligned(1, _))
+        ));
+        assert!(matches!(
+            mmap.decommit(0, 1),
+            Err(AnonymousMmapError::NotPageAligned(1, _))
+        ));
+        assert!(matches!(
+            mmap.decommit(page, page + 1),
+            Err(AnonymousMmapError::OutOfBounds(..))
+        ));
+        assert!(matches!(
+            mmap.decommit(usize::MAX, 2),
+            Err(AnonymousMmapError::OutOfBounds(..))
+        ));
+    }
+
+    #[rstest]
+    #[case(true)]
+    #[case(false)]
+    fn pageout_keeps_contents(#[case] private: bool) {
+        let page = page_size();
+        let mut mmap = match private {
+            true => AnonymousMmap::new_private(page * 4).unwrap(),
+            false => AnonymousMmap::new(page * 4).unwrap(),
+        };
+        mmap.view_mut(..).unwrap().as_slice_mut().fill(0x3C);
+        match mmap.advise_pageout(page, page * 2) {
+            Ok(()) => {}
+            Err(AnonymousMmapError::Unsupported("MADV_PAGEOUT", _)) => return,
+            Err(e) => panic!("MADV_PAGEOUT: {}", e),
+        }
+        // Reclaimed pages are only non-resident with swap to write them to.
+        assert!(mmap.resident_bytes().unwrap() <= page * 4);
+        mmap.advise_cold(0, page * 4).unwrap();
+        let view = mmap.view(..).unwrap();
+        assert!(view.as_slice().iter().all(|b| *b == 0x3C));
+        assert_eq!(mmap.resident_bytes().unwrap(), page * 4);
+    }
+
+    #[rstest]
+    #[case(1, 4096)]
+    #[case(0, 1)]
+    #[case(4096, 8192)]
+    fn cold_and_pageout_checked(#[case] offset: usize, #[case] len: usize) {
+        let mmap = AnonymousMmap::new_private(page_size() * 2).unwrap();
+        assert!(mmap.advise_cold(offset, len).is_err());
+        assert!(mmap.advise_pageout(offset, len).is_err());
+    }
+
+    fn child_sees(mmap: &AnonymousMmap, byte: u8) -> bool {
+        match unsafe { libc::fork() } {
+            0 => {
+                let ok = mmap.view(..).unwrap().as_slice().iter().all(|b| *b == byte);
+                unsafe { libc::_exit(if ok { 0 } else { 1 }) };
+            }
+            pid => {
+                assert!(pid > 0);
+                let mut status = 0;
+                assert_eq!(unsafe { libc::waitpid(pid, &mut status, 0) }, pid);
+                libc::WIFEXITED(status) && libc::WEXITSTATUS(status) == 0
+            }
+        }
+    }
+
+    #[test]
+    fn wipe_on_fork_toggle() {
+        let page = page_size();
+        let mut mmap = AnonymousMmap::new_private(page).unwrap();
+        mmap.view_mut(..).unwrap().as_slice_mut().fill(0x5A);
+
+        mmap.wipe_on_fork(true).unwrap();
+        assert!(child_sees(&mmap, 0));
+        assert!(mmap.view(..).unwrap().as_slice().iter().all(|b| *b == 0x5A));
+
+        mmap.wipe_on_fork(false).unwrap();
+        assert!(child_sees(&mmap, 0x5A));
+    }
+
+    #[test]
+    fn wipe_on_fork_shared_unsupported() {
+        let mmap = AnonymousMmap::new(page_size()).unwrap();
+        assert!(matches!(
+            mmap.wipe_on_fork(true),
+            Err(AnonymousMmapError::Unsupported(..))
+        ));
+    }
+}
@@ -0,0 +1,174 @@
+//! bytes::BufMut writer and frozen bytes::Bytes over [`AnonymousMmap`]
+
+use crate::AnonymousMmap;
+use bytes::buf::UninitSlice;
+use bytes::{BufMut, Bytes};
+use core::mem::ManuallyDrop;
+
+/// [`BufMut`] writing into an [`AnonymousMmap`] from the start through a cursor which can be
+/// frozen into [`Bytes`] without copying.
+#[derive(Debug)]
+pub struct MmapBufMut {
+    map: ManuallyDrop<AnonymousMmap>,
+    written: usize,
+}
+
+// Keeps the mapping alive for as long as any Bytes handle references it.
+struct MmapOwner {
+    map: ManuallyDrop<AnonymousMmap>,
+    written: usize,
+}
+
+impl AsRef<[u8]> for MmapOwner {
+    fn as_ref(&self) -> &[u8] {
+        // SAFETY: [0, written) is within the mapping and initialized, no writer remains.
+        unsafe { core::slice::from_raw_parts(self.map.as_ptr().cast(), self.written) }
+    }
+}
+
+impl Drop for MmapOwner {
+    fn drop(&mut self) {
+        // SAFETY: The last Bytes handle is gone.
+        let map = unsafe { ManuallyDrop::take(&mut self.map) };
+        // Nothing to give the error back to.
+        let _ = unsafe { map.try_drop() };
+    }
+}
+
+impl MmapBufMut {
+    /// Write into the whole mapping which needs to be readable and writable.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the mapping is not writable.
+    #[inline]
+    pub fn new(map: AnonymousMmap) -> Self {
+        map.assert_writable();
+        Self {
+            map: ManuallyDrop::new(map),
+            written: 0,
+        }
+    }
+    /// Bytes written so far
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.written
+    }
+    /// Nothing written so far
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.written == 0
+    }
+    /// The bytes written so far
+    #[inline]
+    pub fn as_slice(&self) -> &[u8] {
+        // SAFETY: [0, written) is within the mapping and initialized.
+        unsafe { core::slice::from_raw_parts(self.map.as_ptr().cast(), self.written) }
+    }
+    /// Freeze the written bytes into [`Bytes`] keeping the mapping alive until the last clone
+    /// or slice of it drops which then unmaps it swallowing any munmap(2) error.
+    #[inline]
+    pub fn freeze(self) -> Bytes {
+        let mut this = ManuallyDrop::new(self);
+        // SAFETY: self is never dropped so the mapping moves into the owner exactly once.
+        let map = unsafe { ManuallyDrop::take(&mut this.map) };
+        Bytes::from_owner(MmapOwner {
+            map: ManuallyDrop::new(map),
+            written: this.written,
+        })
+    }
+}
+
+impl Drop for MmapBufMut {
+    fn drop(&mut self) {
+        // SAFETY: No slices outlive the writer.
+        let map = unsafe { ManuallyDrop::take(&mut self.map) };
+        // Nothing to give the error back to.
+        let _ = unsafe { map.try_drop() };
+    }
+}
+
+// SAFETY: chunk_mut hands out exactly the unwritten [written, len) of the mapping and
+// advance_mut only moves the cursor within it.
+unsafe impl BufMut for MmapBufMut {
+    #[inline]
+    fn remaining_mut(&self) -> usize {
+        self.map.len - self.written
+    }
+    #[inline]
+    unsafe fn advance_mut(&mut self, cnt: usize) {
+        assert!(
+            cnt <= self.remaining_mut(),
+            "advance_mut {} beyond remaining_mut {}",
+            cnt,
+            self.remaining_mut()
+        );
+        self.written += cnt;
+    }
+    #[inline]
+    fn chunk_mut(&mut self) -> &mut UninitSlice {
+        let remaining = self.remaining_mut();
+        // SAFETY: [written, len) is within the mapping and borrowed exclusively.
+        unsafe {
+            let p = self.map.as_ptr_mut().cast::<u8>().add(self.written);
+            UninitSlice::from_raw_parts_mut(p, remaining)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn put_and_freeze() {
+        let mut buf = MmapBufMut::new(AnonymousMmap::new(4096).unwrap());
+        assert_eq!(buf.remaining_mut(), 4096);
+        buf.put_slice(b"hello ");
+        buf.put_u32(0x776f726c);
+        buf.put_u8(b'd');
+        assert_eq!(buf.len(), 11);
+        assert_eq!(buf.remaining_mut(), 4096 - 11);
+        assert_eq!(buf.as_slice(), b"hello world");
+
+        let bytes = buf.freeze();
+        assert_eq!(&bytes[..], b"hello world");
+    }
+
+    #[test]
+    #[should_panic]
+    fn put_beyond_capacity() {
+        let mut buf = MmapBufMut::new(AnonymousMmap::new(4).unwrap());
+        buf.put_slice(b"hello");
+    }
+
+    #[test]
+    fn bytes_dropped_on_other_thread_last() {
+        let mut buf = MmapBufMut::new(AnonymousMmap::new(4096).unwrap());
+        buf.put_slice(b"frozen");
+        let bytes = buf.freeze();
+        let slice = bytes.slice(1..4);
+        let moved = bytes.clone();
+        drop(bytes);
+        std::thread::spawn(move || assert_eq!(&moved[..], b"frozen"))
+            .join()
+            .unwrap();
+        assert_eq!(&slice[..], b"roz");
+    }
+
+    #[test]
+    fn bytes_dropped_here_last() {
+        let mut buf = MmapBufMut::new(AnonymousMmap::new(4096).unwrap());
+        buf.put_slice(b"frozen");
+        let bytes = buf.freeze();
+        let kept = bytes.clone();
+        std::thread::spawn(move || {
+            assert_eq!(&bytes[..], b"frozen");
+            drop(bytes);
+        })
+        .join()
+        .unwrap();
+        assert_eq!(&kept[..], b"frozen");
+    }
+}
@@ -0,0 +1,262 @@
+//! Bump arena over [`AnonymousMmap`] with scoped resets
+
+use crate::{page_size, AnonymousMmap, AnonymousMmapError};
+use core::cell::Cell;
+use core::mem::ManuallyDrop;
+
+/// Error allocating from an [`MmapBump`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BumpError {
+    /// Not enough space left in the arena including the alignment padding
+    OutOfSpace {
+        /// Bytes requested
+        requested: usize,
+        /// Bytes remaining before the alignment padding
+        remaining: usize,
+    },
+}
+
+impl core::fmt::Display for BumpError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::OutOfSpace {
+                requested,
+                remaining,
+            } => write!(
+                f,
+                "Bump out of space: requested {} with {} remaining",
+                requested, remaining
+            ),
+        }
+    }
+}
+
+impl core::error::Error for BumpError {}
+
+/// What [`MmapBump::reset_to`] does with the released range
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResetPolicy {
+    /// Keep the released pages resident for reuse
+    Keep,
+    /// Return the whole released pages to the kernel with MADV_DONTNEED so the RSS drops
+    Decommit,
+}
+
+/// Position of an [`MmapBump`] to [`MmapBump::reset_to`] later
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct BumpMark(usize);
+
+/// Bump arena over a private anonymous mapping handing out allocations from a shared borrow
+/// which are all released at once by [`Self::reset_to`] requiring the exclusive borrow.
+///
+/// Destructors of the allocated values are never run.
+pub struct MmapBump {
+    map: ManuallyDrop<AnonymousMmap>,
+    offset: Cell<usize>,
+    policy: ResetPolicy,
+}
+
+impl core::fmt::Debug for MmapBump {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("MmapBump")
+            .field("map", &*self.map)
+            .field("used", &self.used())
+            .field("policy", &self.policy)
+            .finish()
+    }
+}
+
+impl MmapBump {
+    /// Arena of the given len, the pages only faulted in as allocated.
+    #[inline]
+    pub fn new(len: usize, policy: ResetPolicy) -> Result<Self, AnonymousMmapError> {
+        let map = AnonymousMmap::mmap_with(
+            core::ptr::null_mut(),
+            len,
+            libc::MAP_ANONYMOUS | libc::MAP_PRIVATE,
+        )?;
+        Ok(Self {
+            map: ManuallyDrop::new(map),
+            offset: Cell::new(0),
+            policy,
+        })
+    }
+    /// Bytes handed out including alignment padding
+    #[inline]
+    pub fn used(&self) -> usize {
+        self.offset.get()
+    }
+    /// Bytes left
+    #[inline]
+    pub fn remaining(&self) -> usize {
+        self.map.len - self.offset.get()
+    }
+    /// Bytes in total
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.map.len
+    }
+    #[inline]
+    fn alloc_raw(&self, size: usize, align: usize) -> Result<*mut u8, BumpError> {
+        let base = self.map.start_addr();
+        let offset = self.offset.get();
+        let remaining = self.remaining();
+        let out_of_space = BumpError::OutOfSpace {
+            requested: size,
+            remaining,
+        };
+        let start = (base + offset)
+            .checked_next_multiple_of(align)
+            .ok_or(out_of_space)?
+            - base;
+        let end = start.checked_add(size).ok_or(out_of_space)?;
+        if end > self.map.len {
+            return Err(out_of_space);
+        }
+        self.offset.set(end);
+        // SAFETY: start is within the mapping.
+        Ok(unsafe { self.map.as_ptr_mut().cast::<u8>().add(start) })
+    }
+    /// Move the value into the arena
+    #[inline]
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc<T>(&self, val: T) -> Result<&mut T, BumpError> {
+        let p = self.alloc_raw(size_of::<T>(), align_of::<T>())?.cast::<T>();
+        // SAFETY: p is aligned, within the mapping and handed out only once until reset_to
+        // which requires &mut self outliving all the allocations.
+        unsafe {
+            p.write(val);
+            Ok(&mut *p)
+        }
+    }
+    /// Slice of len copies of val in the arena
+    #[inline]
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_slice_fill_copy<T: Copy>(
+        &self,
+        len: usize,
+        val: T,
+    ) -> Result<&mut [T], BumpError> {
+        let size = size_of::<T>()
+            .checked_mul(len)
+            .ok_or(BumpError::OutOfSpace {
+                requested: usize::MAX,
+                remaining: self.remaining(),
+            })?;
+        let p = self.alloc_raw(size, align_of::<T>())?.cast::<T>();
+        // SAFETY: See alloc, each element is written before the slice is formed.
+        unsafe {
+            for i in 0..len {
+                p.add(i).write(val);
+            }
+            Ok(core::slice::from_raw_parts_mut(p, len))
+        }
+    }
+    /// The current position to reset back to
+    #[inline]
+    pub fn mark(&self) -> BumpMark {
+        BumpMark(self.offset.get())
+    }
+    /// Release everything allocated after the mark. A mark beyond the current position
+    /// e.g. from before an earlier reset further back is ignored.
+    ///
+    /// With [`ResetPolicy::Decommit`] the whole pages released are returned to the kernel and
+    /// read back zero-filled when allocated again.
+    #[inline]
+    pub fn reset_to(&mut self, mark: BumpMark) {
+        let end = self.offset.get();
+        if mark.0 >= end {
+            return;
+        }
+        self.offset.set(mark.0);
+        if self.policy == ResetPolicy::Decommit {
+            let page_size = page_size();
+            let start = mark.0.next_multiple_of(page_size);
+            let end = match end == self.map.len {
+                true => end,
+                false => end / page_size * page_size,
+            };
+            if start < end {
+                // Only advisory, the range is released either way.
+                let _ = self.map.decommit(start, end - start);
+            }
+        }
+    }
+    /// Release everything
+    #[inline]
+    pub fn reset(&mut self) {
+        self.reset_to(BumpMark(0))
+    }
+}
+
+impl Drop for MmapBump {
+    fn drop(&mut self) {
+        // SAFETY: No allocations outlive the shared borrows of the arena.
+        let map = unsafe { ManuallyDrop::take(&mut self.map) };
+        // Nothing to give the error back to.
+        let _ = unsafe { map.try_drop() };
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn alloc_aligned() {
+        let bump = MmapBump::new(4096, ResetPolicy::Keep).unwrap();
+        let a = bump.alloc(1u8).unwrap();
+        let b = bump.alloc(2u64).unwrap();
+        assert_eq!(*a, 1);
+        assert_eq!(*b, 2);
+        assert!((b as *mut u64 as usize).is_multiple_of(align_of::<u64>()));
+        assert_eq!(bump.used(), 16);
+        let s = bump.alloc_slice_fill_copy(3, 7u32).unwrap();
+        assert_eq!(s, &[7, 7, 7]);
+        assert_eq!(bump.used(), 28);
+    }
+
+    #[test]
+    fn out_of_space() {
+        let bump = MmapBump::new(64, ResetPolicy::Keep).unwrap();
+        bump.alloc_slice_fill_copy(60, 0u8).unwrap();
+        assert_eq!(
+            bump.alloc(0u64),
+            Err(BumpError::OutOfSpace {
+                requested: 8,
+                remaining: 4
+            })
+        );
+        assert!(bump.alloc(0u32).is_ok());
+        assert!(bump.alloc_slice_fill_copy(usize::MAX, 0u16).is_err());
+    }
+
+    #[test]
+    fn scoped_reset_reuses() {
+        let mut bump = MmapBump::new(4096, ResetPolicy::Keep).unwrap();
+        bump.alloc(1u32).unwrap();
+        let mark = bump.mark();
+        let first = bump.alloc(0xAAu8).unwrap() as *mut u8;
+        bump.reset_to(mark);
+        assert_eq!(bump.used(), 4);
+        let again = bump.alloc_slice_fill_copy(0, 0u8).unwrap().as_mut_ptr();
+        assert_eq!(again, first);
+        // Kept resident with the old contents.
+        assert_eq!(unsafe { *first }, 0xAA);
+        bump.reset();
+        assert_eq!(bump.remaining(), 4096);
+    }
+
+    #[test]
+    fn decommit_on_reset() {
+        let page = page_size();
+        let mut bump = MmapBump::new(page * 4, ResetPolicy::Decommit).unwrap();
+        let mark = bump.mark();
+        bump.alloc_slice_fill_copy(page * 4, 0xFFu8).unwrap();
+        bump.reset_to(mark);
+        let s = bump.alloc_slice_fill_copy(0, 0u8).unwrap().as_mut_ptr();
+        let released = unsafe { core::slice::from_raw_parts(s, page * 4) };
+        assert!(released.iter().all(|b| *b == 0));
+    }
+}
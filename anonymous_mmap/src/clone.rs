@@ -0,0 +1,77 @@
+//! Deep copies of an [`AnonymousMmap`]
+
+use crate::{AnonymousMmap, AnonymousMmapError};
+
+// Placement and layout flags which are not carried over to a copy.
+const NOT_CLONED_FLAGS: libc::c_int =
+    libc::MAP_FIXED | libc::MAP_FIXED_NOREPLACE | libc::MAP_GROWSDOWN;
+
+impl AnonymousMmap {
+    /// Copy the contents into a new independent anonymous mapping of the same len created with
+    /// the same sharing (MAP_SHARED / MAP_PRIVATE) and MAP_POPULATE flags as this one.
+    ///
+    /// The copy is always read-write and anonymous - memfd backing, guard pages, placement and
+    /// the locking / madvise(2) state of e.g. [`Self::new_secret`] are not carried over.
+    /// A mapping which is not readable can not be copied and is [`AnonymousMmapError::Protected`].
+    /// Ranges unmapped through [`Self::unmap_range`] read back zero-filled in the copy.
+    #[inline]
+    pub fn try_clone(&self) -> Result<AnonymousMmap, AnonymousMmapError> {
+        if !self.is_readable() {
+            return Err(AnonymousMmapError::Protected(self.prot));
+        }
+        let flags = (self.flags | libc::MAP_ANONYMOUS) & !NOT_CLONED_FLAGS;
+        let clone = Self::mmap_with(core::ptr::null_mut(), self.len, flags)?;
+        for (start, end) in self.mapped_ranges(0, self.len) {
+            // SAFETY: Both are distinct mappings of len readable / writable bytes and the range
+            // is still mapped in the original.
+            unsafe {
+                core::ptr::copy_nonoverlapping(
+                    self.addr.as_ptr().cast::<u8>().add(start),
+                    clone.addr.as_ptr().cast::<u8>().add(start),
+                    end - start,
+                )
+            };
+        }
+        Ok(clone)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(AnonymousMmap::new(8192).unwrap())]
+    #[case(AnonymousMmap::new_private(8192).unwrap())]
+    #[case(AnonymousMmap::new_stack(8192).unwrap())]
+    fn clone_independent(#[case] mut original: AnonymousMmap) {
+        original.view_mut(..).unwrap().as_slice_mut().fill(b'A');
+        let mut clone = original.try_clone().unwrap();
+        assert_ne!(clone.start_addr(), original.start_addr());
+        assert_eq!(clone.len, original.len);
+        assert_eq!(
+            clone.flags & libc::MAP_PRIVATE,
+            original.flags & libc::MAP_PRIVATE
+        );
+        assert!(clone
+            .view(..)
+            .unwrap()
+            .as_slice()
+            .iter()
+            .all(|b| *b == b'A'));
+
+        clone.view_mut(..).unwrap().as_slice_mut().fill(b'B');
+        assert!(original
+            .view(..)
+            .unwrap()
+            .as_slice()
+            .iter()
+            .all(|b| *b == b'A'));
+        unsafe {
+            clone.try_drop().unwrap();
+            original.try_drop().unwrap();
+        }
+    }
+}
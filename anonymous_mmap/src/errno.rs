@@ -0,0 +1,300 @@
+//! Classifying the errno behind an [`AnonymousMmapError`]
+
+use crate::AnonymousMmapError;
+
+/// The errno carried by the errors, [`std::io::Error`] with the default `std` feature
+#[cfg(feature = "std")]
+pub type OsError = std::io::Error;
+
+/// The errno carried by the errors, the raw [`Errno`] without the default `std` feature
+#[cfg(not(feature = "std"))]
+pub type OsError = Errno;
+
+/// Raw errno for builds without std, exposing the subset of the [`std::io::Error`] API
+/// used on [`OsError`] so either works the same.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Errno(pub i32);
+
+impl Errno {
+    /// The errno of the last failed call on this thread
+    #[inline]
+    pub fn last_os_error() -> Self {
+        // SAFETY: __errno_location always points to the thread local errno.
+        Self(unsafe { *libc::__errno_location() })
+    }
+    /// Wrap the raw errno
+    #[inline]
+    pub fn from_raw_os_error(errno: i32) -> Self {
+        Self(errno)
+    }
+    /// The raw errno which is always there
+    #[inline]
+    pub fn raw_os_error(&self) -> Option<i32> {
+        Some(self.0)
+    }
+    /// Best-effort description of the errnos the crate commonly sees
+    #[inline]
+    pub fn description(&self) -> &'static str {
+        match self.0 {
+            libc::EPERM => "Operation not permitted",
+            libc::ENOENT => "No such file or directory",
+            libc::ESRCH => "No such process",
+            libc::EINTR => "Interrupted system call",
+            libc::EIO => "Input/output error",
+            libc::EBADF => "Bad file descriptor",
+            libc::EAGAIN => "Resource temporarily unavailable",
+            libc::ENOMEM => "Cannot allocate memory",
+            libc::EACCES => "Permission denied",
+            libc::EFAULT => "Bad address",
+            libc::EBUSY => "Device or resource busy",
+            libc::EEXIST => "File exists",
+            libc::ENODEV => "No such device",
+            libc::EINVAL => "Invalid argument",
+            libc::ENFILE => "Too many open files in system",
+            libc::EMFILE => "Too many open files",
+            libc::ENOSPC => "No space left on device",
+            libc::ESPIPE => "Illegal seek",
+            libc::EPIPE => "Broken pipe",
+            libc::ERANGE => "Numerical result out of range",
+            libc::ENOSYS => "Function not implemented",
+            libc::EOPNOTSUPP => "Operation not supported",
+            _ => "Unknown error",
+        }
+    }
+}
+
+impl core::fmt::Display for Errno {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{} (os error {})", self.description(), self.0)
+    }
+}
+
+impl core::error::Error for Errno {}
+
+/// Coarse errno classes calling for different remediations
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// ENOMEM - out of memory / overcommit / vm.max_map_count or RLIMIT_MEMLOCK for mlock
+    OutOfMemory,
+    /// EPERM / EACCES - RLIMIT_MEMLOCK, missing capability, seals or kernel lockdown
+    PermissionDenied,
+    /// EINVAL - bad len, alignment or flags
+    InvalidArgument,
+    /// EAGAIN - locked pages exceed the limit or the resource is temporarily unavailable
+    TryAgain,
+    /// EEXIST - the requested fixed range is already mapped
+    AlreadyExists,
+    /// Any other errno or an error without one
+    Other,
+}
+
+impl ErrorKind {
+    /// Classify the raw errno
+    #[inline]
+    pub fn from_errno(errno: i32) -> Self {
+        match errno {
+            libc::ENOMEM => Self::OutOfMemory,
+            libc::EPERM | libc::EACCES => Self::PermissionDenied,
+            libc::EINVAL => Self::InvalidArgument,
+            libc::EAGAIN => Self::TryAgain,
+            libc::EEXIST => Self::AlreadyExists,
+            _ => Self::Other,
+        }
+    }
+}
+
+// std::io::Error is not Clone, the errno or otherwise the kind and message carry over.
+#[cfg(feature = "std")]
+#[inline]
+fn clone_os_error(e: &OsError) -> OsError {
+    match e.raw_os_error() {
+        Some(errno) => OsError::from_raw_os_error(errno),
+        None => OsError::new(e.kind(), e.to_string()),
+    }
+}
+
+#[cfg(not(feature = "std"))]
+#[inline]
+fn clone_os_error(e: &OsError) -> OsError {
+    *e
+}
+
+/// Clones every variant as is apart from [`AnonymousMmapError::MunmapFailed`] whose
+/// mapping is uniquely owned and stays with the original, it clones into
+/// [`AnonymousMmapError::MmapFailed`] of the mapping's len with flags 0 and the same errno.
+///
+/// With the std feature the [`OsError`] is rebuilt from its errno, or from its kind and
+/// message for errors without one.
+impl Clone for AnonymousMmapError {
+    fn clone(&self) -> Self {
+        match self {
+            Self::MmapFailed { len, flags, error } => Self::MmapFailed {
+                len: *len,
+                flags: *flags,
+                error: clone_os_error(error),
+            },
+            Self::MunmapFailed(map, e) => Self::MmapFailed {
+                len: map.len(),
+                flags: 0,
+                error: clone_os_error(e),
+            },
+            Self::NotPageAligned(a, b) => Self::NotPageAligned(*a, *b),
+            Self::MprotectFailed(e) => Self::MprotectFailed(clone_os_error(e)),
+            Self::MadviseFailed(e) => Self::MadviseFailed(clone_os_error(e)),
+            Self::OutOfBounds(a, b) => Self::OutOfBounds(*a, *b),
+            Self::SecretFailed(step, e) => Self::SecretFailed(*step, clone_os_error(e)),
+            Self::Unsupported(op, e) => Self::Unsupported(op, clone_os_error(e)),
+            Self::SpliceFailed(e) => Self::SpliceFailed(clone_os_error(e)),
+            Self::NotMemfd => Self::NotMemfd,
+            Self::MemfdFailed(e) => Self::MemfdFailed(clone_os_error(e)),
+            Self::Protected(prot) => Self::Protected(*prot),
+            Self::MremapFailed(e) => Self::MremapFailed(clone_os_error(e)),
+            Self::Unmapped(a, b) => Self::Unmapped(*a, *b),
+            Self::Misaligned(a, b) => Self::Misaligned(*a, *b),
+            Self::UffdFailed(e) => Self::UffdFailed(clone_os_error(e)),
+            Self::ResidencyFailed(e) => Self::ResidencyFailed(clone_os_error(e)),
+            Self::PagemapFailed(e) => Self::PagemapFailed(clone_os_error(e)),
+            Self::Sealed(op) => Self::Sealed(op),
+            Self::Overlapping(a, b) => Self::Overlapping(*a, *b),
+            Self::ReadFailed(e) => Self::ReadFailed(clone_os_error(e)),
+            Self::Denied(op, e) => Self::Denied(op, clone_os_error(e)),
+        }
+    }
+}
+
+impl AnonymousMmapError {
+    /// The [`OsError`] carried if any
+    #[inline]
+    pub fn io_error(&self) -> Option<&OsError> {
+        match self {
+            Self::MmapFailed { error, .. } => Some(error),
+            Self::MunmapFailed(_, e)
+            | Self::MprotectFailed(e)
+            | Self::MadviseFailed(e)
+            | Self::SecretFailed(_, e)
+            | Self::Unsupported(_, e)
+            | Self::SpliceFailed(e)
+            | Self::MemfdFailed(e)
+            | Self::MremapFailed(e)
+            | Self::UffdFailed(e)
+            | Self::ResidencyFailed(e)
+            | Self::PagemapFailed(e)
+            | Self::ReadFailed(e)
+            | Self::Denied(_, e) => Some(e),
+            Self::NotPageAligned(..)
+            | Self::OutOfBounds(..)
+            | Self::Unmapped(..)
+            | Self::Misaligned(..)
+            | Self::NotMemfd
+            | Self::Sealed(..)
+            | Self::Overlapping(..)
+            | Self::Protected(..) => None,
+        }
+    }
+    /// Classify the errno behind the error, [`ErrorKind::Other`] for errors without one.
+    #[inline]
+    pub fn kind(&self) -> ErrorKind {
+        match self.io_error().and_then(|e| e.raw_os_error()) {
+            Some(errno) => ErrorKind::from_errno(errno),
+            None => ErrorKind::Other,
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+
+    use super::*;
+    use crate::AnonymousMmap;
+    use core::error::Error;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(libc::ENOMEM, ErrorKind::OutOfMemory)]
+    #[case(libc::EPERM, ErrorKind::PermissionDenied)]
+    #[case(libc::EINVAL, ErrorKind::InvalidArgument)]
+    #[case(libc::EAGAIN, ErrorKind::TryAgain)]
+    #[case(libc::EEXIST, ErrorKind::AlreadyExists)]
+    #[case(libc::EBADF, ErrorKind::Other)]
+    fn errno_kinds(#[case] errno: i32, #[case] expected: ErrorKind) {
+        assert_eq!(ErrorKind::from_errno(errno), expected);
+    }
+
+    #[test]
+    fn zero_len_invalid() {
+        let err = AnonymousMmap::new(0).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidArgument);
+        let msg = err.to_string();
+        assert!(msg.starts_with("mmap of len 0 with flags 0x"), "{}", msg);
+        let source = err.source().unwrap();
+        assert_eq!(source.to_string(), err.io_error().unwrap().to_string());
+    }
+
+    #[rstest]
+    #[case(libc::EINVAL, "Invalid argument (os error 22)")]
+    #[case(libc::ENOSYS, "Function not implemented (os error 38)")]
+    #[case(4095, "Unknown error (os error 4095)")]
+    fn errno_display(#[case] errno: i32, #[case] expected: &str) {
+        let e = Errno::from_raw_os_error(errno);
+        assert_eq!(e.to_string(), expected);
+        assert_eq!(e.raw_os_error(), Some(errno));
+    }
+
+    #[test]
+    fn huge_len_out_of_memory() {
+        let err = AnonymousMmap::new(usize::MAX & !0xFFF).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::OutOfMemory);
+    }
+
+    #[test]
+    fn without_errno() {
+        let err = AnonymousMmapError::NotMemfd;
+        assert_eq!(err.kind(), ErrorKind::Other);
+        assert!(err.source().is_none());
+    }
+
+    #[rstest]
+    #[case(AnonymousMmap::new(0).unwrap_err())]
+    #[case(AnonymousMmapError::MadviseFailed(OsError::from_raw_os_error(libc::EAGAIN)))]
+    #[case(AnonymousMmapError::Denied("mseal", OsError::from_raw_os_error(libc::EPERM)))]
+    #[case(AnonymousMmapError::OutOfBounds(4096, 1))]
+    fn clone_as_is(#[case] err: AnonymousMmapError) {
+        let cloned = err.clone();
+        assert_eq!(cloned.to_string(), err.to_string());
+        assert_eq!(cloned.kind(), err.kind());
+        assert_eq!(
+            cloned.io_error().and_then(|e| e.raw_os_error()),
+            err.io_error().and_then(|e| e.raw_os_error())
+        );
+    }
+
+    #[test]
+    fn clone_munmap_failed() {
+        let map = AnonymousMmap::new(8192).unwrap();
+        let err = AnonymousMmapError::MunmapFailed(map, OsError::from_raw_os_error(libc::EINVAL));
+        match err.clone() {
+            AnonymousMmapError::MmapFailed { len, flags, error } => {
+                assert_eq!((len, flags), (8192, 0));
+                assert_eq!(error.raw_os_error(), Some(libc::EINVAL));
+            }
+            other => panic!("Expected MmapFailed, got {:?}", other),
+        }
+        let AnonymousMmapError::MunmapFailed(map, _) = err else {
+            panic!("Original keeps the mapping");
+        };
+        unsafe { map.try_drop() }.unwrap();
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn clone_custom_io_error() {
+        let err = AnonymousMmapError::ReadFailed(OsError::new(
+            std::io::ErrorKind::UnexpectedEof,
+            "short",
+        ));
+        let cloned = err.clone();
+        let e = cloned.io_error().unwrap();
+        assert_eq!(e.kind(), std::io::ErrorKind::UnexpectedEof);
+        assert_eq!(e.to_string(), "short");
+    }
+}
@@ -0,0 +1,128 @@
+//! Growing an [`AnonymousMmap`] in place by mapping the following address range
+
+use crate::{accounting, page_size, AnonymousMmap, AnonymousMmapError, OsError};
+
+impl AnonymousMmap {
+    /// Try to grow the mapping by additional bytes without ever moving it by mapping the
+    /// address range right after it with MAP_FIXED_NOREPLACE, unlike [`Self::remap`] which
+    /// may have to move the pages for growing.
+    ///
+    /// Gives Ok(false) leaving the mapping as it is when the following range is occupied.
+    /// On success both kernel mappings are treated as one as they are contiguous, so the new
+    /// bytes are reachable through every view and [`Self::try_drop`] unmaps them along.
+    ///
+    /// memfd-backed mappings are [`AnonymousMmapError::Unsupported`] as the following range
+    /// would need its own part of the memfd.
+    #[inline]
+    pub fn try_extend_adjacent(&mut self, additional: usize) -> Result<bool, AnonymousMmapError> {
+        if self.sealed {
+            return Err(AnonymousMmapError::Sealed("mmap"));
+        }
+        if self.memfd.is_some() {
+            return Err(AnonymousMmapError::Unsupported(
+                "MAP_FIXED_NOREPLACE",
+                OsError::from_raw_os_error(libc::EINVAL),
+            ));
+        }
+        let page_size = page_size();
+        let new_end = self
+            .len
+            .checked_add(additional)
+            .and_then(|l| l.checked_next_multiple_of(page_size))
+            .ok_or(AnonymousMmapError::OutOfBounds(self.len, additional))?;
+        // The kernel already backs the len rounded up to the page size.
+        let mapped_end = self.len.next_multiple_of(page_size);
+        if new_end > mapped_end {
+            let flags = self.flags | libc::MAP_FIXED_NOREPLACE;
+            let map_len = new_end - mapped_end;
+            // SAFETY: One past the end of the kernel mapping.
+            let want = unsafe { self.as_ptr_mut().add(mapped_end) };
+            // SAFETY: MAP_FIXED_NOREPLACE never clobbers an existing mapping.
+            let p = unsafe { libc::mmap(want, map_len, self.prot, flags, -1, 0) };
+            if p == libc::MAP_FAILED {
+                let os_err = OsError::last_os_error();
+                if os_err.raw_os_error() == Some(libc::EEXIST) {
+                    return Ok(false);
+                }
+                return Err(AnonymousMmapError::MmapFailed {
+                    len: map_len,
+                    flags,
+                    error: os_err,
+                });
+            }
+            // Kernels older than 4.17 do not know MAP_FIXED_NOREPLACE and treat the address as a hint.
+            if p != want {
+                // SAFETY: The mapping elsewhere was just created and is not handed out.
+                // Nothing to give the error back to.
+                unsafe { libc::munmap(p, map_len) };
+                return Ok(false);
+            }
+        }
+        accounting::resized(self.len, self.len + additional);
+        self.len += additional;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn extend_into_free_neighbor() {
+        let page = page_size();
+        let (mut head, tail) = AnonymousMmap::new(page * 2)
+            .unwrap()
+            .split_off(page)
+            .unwrap();
+        let start = head.start_addr();
+        // Only a concurrent single page mmap elsewhere could take the freed range in between.
+        unsafe { tail.try_drop().unwrap() };
+        head.fill(1);
+        assert!(head.try_extend_adjacent(page).unwrap());
+        assert_eq!(head.start_addr(), start);
+        assert_eq!(head.len(), page * 2);
+        head.view_mut(page..).unwrap().as_slice_mut().fill(2);
+        let view = head.view(..).unwrap();
+        assert!(view.as_slice()[..page].iter().all(|b| *b == 1));
+        assert!(view.as_slice()[page..].iter().all(|b| *b == 2));
+        unsafe { head.try_drop().unwrap() };
+    }
+
+    #[test]
+    fn extend_into_occupied_neighbor() {
+        let page = page_size();
+        let (mut head, mut tail) = AnonymousMmap::new(page * 2)
+            .unwrap()
+            .split_off(page)
+            .unwrap();
+        tail.fill(3);
+        assert!(!head.try_extend_adjacent(page).unwrap());
+        assert_eq!(head.len(), page);
+        assert!(tail.view(..).unwrap().as_slice().iter().all(|b| *b == 3));
+        unsafe {
+            head.try_drop().unwrap();
+            tail.try_drop().unwrap();
+        }
+    }
+
+    #[test]
+    fn extend_within_last_page() {
+        let page = page_size();
+        let mut mmap = AnonymousMmap::new(page / 2).unwrap();
+        assert!(mmap.try_extend_adjacent(page / 2).unwrap());
+        assert_eq!(mmap.len(), page);
+        mmap.fill(4);
+        unsafe { mmap.try_drop().unwrap() };
+    }
+
+    #[test]
+    fn extend_memfd_unsupported() {
+        let mut mmap = AnonymousMmap::new_memfd(page_size()).unwrap();
+        assert!(matches!(
+            mmap.try_extend_adjacent(page_size()),
+            Err(AnonymousMmapError::Unsupported(..))
+        ));
+    }
+}
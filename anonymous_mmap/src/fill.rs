@@ -0,0 +1,120 @@
+//! Bulk writes over the whole mapping
+
+use crate::AnonymousMmap;
+
+impl AnonymousMmap {
+    /// Zero the whole mapping.
+    ///
+    /// Private anonymous mappings drop the pages with MADV_DONTNEED which the kernel faults back
+    /// in zero-filled on the next touch. Shared and file-backed ones where MADV_DONTNEED would
+    /// keep the contents, as well as locked ones where it is rejected, are zeroed through
+    /// ptr::write_bytes which compiles into memset.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the mapping is not writable.
+    #[inline]
+    pub fn zero(&mut self) {
+        self.assert_writable();
+        let private_anonymous = self.flags & libc::MAP_PRIVATE != 0
+            && self.flags & libc::MAP_ANONYMOUS != 0
+            && self.memfd.is_none();
+        if private_anonymous
+            && self.holes.is_empty()
+            && self.madvise(0, self.len, libc::MADV_DONTNEED).is_ok()
+        {
+            return;
+        }
+        self.fill(0);
+    }
+    /// Fill the whole mapping with the given byte through ptr::write_bytes skipping any ranges
+    /// unmapped through [`Self::unmap_range`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the mapping is not writable.
+    #[inline]
+    pub fn fill(&mut self, byte: u8) {
+        self.assert_writable();
+        for (start, end) in self.mapped_ranges(0, self.len) {
+            // SAFETY: The mapped range is writable and borrowed exclusively.
+            unsafe {
+                core::ptr::write_bytes(
+                    self.addr.as_ptr().cast::<u8>().add(start),
+                    byte,
+                    end - start,
+                )
+            };
+        }
+    }
+    /// Wipe the whole mapping with explicit_bzero(3) which the compiler can not optimize away
+    /// even if the mapping is never read again e.g. for secrets. Unmapped ranges are skipped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the mapping is not writable.
+    #[inline]
+    pub fn wipe(&mut self) {
+        self.assert_writable();
+        for (start, end) in self.mapped_ranges(0, self.len) {
+            // SAFETY: The mapped range is writable and borrowed exclusively.
+            unsafe { libc::explicit_bzero(self.as_ptr_mut().add(start), end - start) };
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(AnonymousMmap::new(128).unwrap())]
+    #[case(AnonymousMmap::new(8192).unwrap())]
+    #[case(AnonymousMmap::new_private(128).unwrap())]
+    #[case(AnonymousMmap::new_private(8192).unwrap())]
+    #[case(AnonymousMmap::new_secret(8192).unwrap())]
+    #[case(AnonymousMmap::new_memfd(8192).unwrap())]
+    fn zeroes(#[case] mut mmap: AnonymousMmap) {
+        mmap.view_mut(..).unwrap().as_slice_mut().fill(0xAB);
+        mmap.zero();
+        assert!(mmap.view(..).unwrap().as_slice().iter().all(|b| *b == 0));
+        unsafe { mmap.try_drop().unwrap() };
+    }
+
+    #[test]
+    fn zero_keeps_private_snapshot_view_zero() {
+        let mut live = AnonymousMmap::new_memfd(8192).unwrap();
+        live.fill(0xAB);
+        let snapshot = live.snapshot_cow().unwrap();
+        // Private over the memfd where MADV_DONTNEED would bring back the memfd contents.
+        live.zero();
+        assert!(live.view(..).unwrap().as_slice().iter().all(|b| *b == 0));
+        assert!(snapshot
+            .view(..)
+            .unwrap()
+            .as_slice()
+            .iter()
+            .all(|b| *b == 0xAB));
+    }
+
+    #[rstest]
+    #[case(0x00)]
+    #[case(0x5A)]
+    #[case(0xFF)]
+    fn fills(#[case] byte: u8) {
+        let mut mmap = AnonymousMmap::new(4096 + 17).unwrap();
+        mmap.fill(byte);
+        assert_eq!(mmap.view(..).unwrap().len(), 4096 + 17);
+        assert!(mmap.view(..).unwrap().as_slice().iter().all(|b| *b == byte));
+    }
+
+    #[test]
+    fn wipes() {
+        let mut mmap = AnonymousMmap::new_private(4096).unwrap();
+        mmap.fill(0x42);
+        mmap.wipe();
+        assert!(mmap.view(..).unwrap().as_slice().iter().all(|b| *b == 0));
+    }
+}
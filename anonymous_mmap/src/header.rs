@@ -0,0 +1,170 @@
+//! Typed header at the start of an [`AnonymousMmap`]
+
+use crate::{AnonymousMmap, AnonymousMmapError};
+use core::sync::atomic::{
+    AtomicI16, AtomicI32, AtomicI64, AtomicI8, AtomicU16, AtomicU32, AtomicU64, AtomicU8,
+    AtomicUsize,
+};
+
+/// Types which can be placed over the start of a mapping through [`AnonymousMmap::map_struct`].
+///
+/// A fresh mapping is zero-filled but a shared one may be written with anything by another
+/// process, hence any bit pattern - not only all zeroes - must be a valid value.
+///
+/// # Safety
+///
+/// Every bit pattern including all zeroes must be a valid value of the type e.g. `#[repr(C)]`
+/// structs of integers and atomics.
+pub unsafe trait MmapHeader {}
+
+macro_rules! mmap_header {
+    ($($t:ty),*) => {
+        $(
+            // SAFETY: Plain integers and atomics are valid for any bit pattern.
+            unsafe impl MmapHeader for $t {}
+        )*
+    };
+}
+
+mmap_header!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize);
+mmap_header!(AtomicU8, AtomicU16, AtomicU32, AtomicU64, AtomicUsize);
+mmap_header!(AtomicI8, AtomicI16, AtomicI32, AtomicI64);
+
+// SAFETY: Arrays of MmapHeader are valid for any bit pattern of the elements.
+unsafe impl<T: MmapHeader, const N: usize> MmapHeader for [T; N] {}
+
+impl AnonymousMmap {
+    /// Treat the start of the mapping as the header T followed by the remaining tail bytes
+    /// e.g. for shared memory protocols with a `#[repr(C)]` header and a data area.
+    ///
+    /// Errors with [`AnonymousMmapError::OutOfBounds`] when T does not fit the mapping,
+    /// [`AnonymousMmapError::Misaligned`] when T needs alignment beyond the page size,
+    /// [`AnonymousMmapError::Protected`] when the mapping is not writable and
+    /// [`AnonymousMmapError::Unmapped`] when part of it was unmapped.
+    ///
+    /// ```
+    /// use anonymous_mmap::{AnonymousMmap, MmapHeader};
+    /// use core::sync::atomic::{AtomicU32, Ordering};
+    ///
+    /// #[repr(C)]
+    /// struct Header {
+    ///     written: AtomicU32,
+    ///     version: u32,
+    /// }
+    ///
+    /// // SAFETY: Integers and atomics only.
+    /// unsafe impl MmapHeader for Header {}
+    ///
+    /// let mut mmap = AnonymousMmap::new(4096).unwrap();
+    /// let (header, data) = mmap.map_struct::<Header>().unwrap();
+    /// data[..5].copy_from_slice(b"hello");
+    /// header.version = 1;
+    /// header.written.store(5, Ordering::Release);
+    /// ```
+    #[inline]
+    pub fn map_struct<T: MmapHeader>(&mut self) -> Result<(&mut T, &mut [u8]), AnonymousMmapError> {
+        let size = size_of::<T>();
+        if size > self.len {
+            return Err(AnonymousMmapError::OutOfBounds(0, size));
+        }
+        if !self.start_addr().is_multiple_of(align_of::<T>()) {
+            return Err(AnonymousMmapError::Misaligned(
+                self.start_addr(),
+                align_of::<T>(),
+            ));
+        }
+        if !self.is_writable() {
+            return Err(AnonymousMmapError::Protected(self.prot));
+        }
+        if self.overlaps_hole(0, self.len) {
+            return Err(AnonymousMmapError::Unmapped(0, self.len));
+        }
+        let p = self.addr.as_ptr().cast::<u8>();
+        // SAFETY: The mapping is aligned for T, large enough, mapped and writable, borrowed
+        // exclusively and any bit pattern is a valid T. The header and the tail are disjoint.
+        unsafe {
+            let header = &mut *p.cast::<T>();
+            let tail = core::slice::from_raw_parts_mut(p.add(size), self.len - size);
+            Ok((header, tail))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use crate::page_size;
+    use core::sync::atomic::Ordering;
+
+    #[repr(C)]
+    struct Header {
+        ready: AtomicU32,
+        len: u32,
+    }
+
+    // SAFETY: Integers and atomics only.
+    unsafe impl MmapHeader for Header {}
+
+    #[repr(C, align(8192))]
+    struct Huge(u8);
+
+    // SAFETY: A single integer.
+    unsafe impl MmapHeader for Huge {}
+
+    #[test]
+    fn header_and_tail_across_fork() {
+        let page = page_size();
+        let mut mmap = AnonymousMmap::new(page).unwrap();
+        match unsafe { libc::fork() } {
+            0 => {
+                let (header, data) = mmap.map_struct::<Header>().unwrap();
+                data[..5].copy_from_slice(b"hello");
+                header.len = 5;
+                header.ready.store(1, Ordering::Release);
+                unsafe { libc::_exit(0) };
+            }
+            pid => {
+                assert!(pid > 0);
+                let mut status = 0;
+                assert_eq!(unsafe { libc::waitpid(pid, &mut status, 0) }, pid);
+                assert!(libc::WIFEXITED(status) && libc::WEXITSTATUS(status) == 0);
+            }
+        }
+        let (header, data) = mmap.map_struct::<Header>().unwrap();
+        assert_eq!(header.ready.load(Ordering::Acquire), 1);
+        assert_eq!(&data[..header.len as usize], b"hello");
+        assert_eq!(data.len(), page - size_of::<Header>());
+        unsafe { mmap.try_drop().unwrap() };
+    }
+
+    #[test]
+    fn header_too_large() {
+        let mut mmap = AnonymousMmap::new(4).unwrap();
+        assert!(matches!(
+            mmap.map_struct::<Header>(),
+            Err(AnonymousMmapError::OutOfBounds(0, 8))
+        ));
+        let (header, data) = mmap.map_struct::<u32>().unwrap();
+        assert_eq!(*header, 0);
+        assert!(data.is_empty());
+        unsafe { mmap.try_drop().unwrap() };
+    }
+
+    #[test]
+    fn header_misaligned_or_protected() {
+        let mut mmap = AnonymousMmap::new(page_size() * 4).unwrap();
+        if !mmap.start_addr().is_multiple_of(align_of::<Huge>()) {
+            assert!(matches!(
+                mmap.map_struct::<Huge>(),
+                Err(AnonymousMmapError::Misaligned(..))
+            ));
+        }
+        mmap.protect_readonly().unwrap();
+        assert!(matches!(
+            mmap.map_struct::<Header>(),
+            Err(AnonymousMmapError::Protected(libc::PROT_READ))
+        ));
+        unsafe { mmap.try_drop().unwrap() };
+    }
+}
@@ -0,0 +1,130 @@
+//! Scatter / gather I/O vectors over the mapping
+
+use crate::{AnonymousMmap, AnonymousMmapError};
+use core::marker::PhantomData;
+use std::io::IoSliceMut;
+
+/// Set of libc::iovec covering the mapping for readv / preadv2 / io_uring buffer registration.
+///
+/// The iovecs mutably borrow the mapping so it can not be dropped or aliased underneath them.
+#[derive(Debug)]
+pub struct MmapIoVecs<'a> {
+    iovecs: Vec<libc::iovec>,
+    _mmap: PhantomData<&'a mut AnonymousMmap>,
+}
+
+impl MmapIoVecs<'_> {
+    /// Provide the iovecs
+    #[inline]
+    pub fn as_slice(&self) -> &[libc::iovec] {
+        &self.iovecs
+    }
+    /// Provide the raw ptr to the first iovec
+    #[inline]
+    pub fn as_ptr(&self) -> *const libc::iovec {
+        self.iovecs.as_ptr()
+    }
+    /// Number of iovecs
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.iovecs.len()
+    }
+    /// Are there no iovecs
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.iovecs.is_empty()
+    }
+}
+
+impl AnonymousMmap {
+    /// Bounds checked IoSliceMut over the given range of the mapping e.g. for read_vectored.
+    #[inline]
+    pub fn as_io_slice_mut(
+        &mut self,
+        offset: usize,
+        len: usize,
+    ) -> Result<IoSliceMut<'_>, AnonymousMmapError> {
+        if !self.is_writable() {
+            return Err(AnonymousMmapError::Protected(self.prot));
+        }
+        let p = self.checked_range(offset, len)?;
+        // SAFETY: The range is within the mapping and the mapping is borrowed exclusively.
+        let slice = unsafe { core::slice::from_raw_parts_mut(p.cast::<u8>(), len) };
+        Ok(IoSliceMut::new(slice))
+    }
+    /// Split the whole mapping into iovecs of chunk_len with the last one holding the remainder.
+    ///
+    /// # Panics
+    ///
+    /// Panics if chunk_len is 0.
+    #[inline]
+    pub fn iovecs_mut(&mut self, chunk_len: usize) -> MmapIoVecs<'_> {
+        assert!(chunk_len != 0, "chunk_len must be non-zero");
+        let base = self.as_ptr_mut().cast::<u8>();
+        let iovecs = (0..self.len)
+            .step_by(chunk_len)
+            .map(|offset| libc::iovec {
+                // SAFETY: offset is within the mapping.
+                iov_base: unsafe { base.add(offset) }.cast(),
+                iov_len: chunk_len.min(self.len - offset),
+            })
+            .collect();
+        MmapIoVecs {
+            iovecs,
+            _mmap: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use rstest::rstest;
+    use std::io::{Read, Write};
+    use std::os::unix::net::UnixStream;
+    use yown_fd::AsRawFd;
+
+    #[rstest]
+    #[case(100, 32, 4)]
+    #[case(128, 32, 4)]
+    #[case(128, 256, 1)]
+    fn iovec_chunks(#[case] len: usize, #[case] chunk_len: usize, #[case] count: usize) {
+        let mut mmap = AnonymousMmap::new(len).unwrap();
+        let start = mmap.start_addr();
+        let iovecs = mmap.iovecs_mut(chunk_len);
+        assert_eq!(iovecs.len(), count);
+        assert_eq!(iovecs.as_slice()[0].iov_base as usize, start);
+        assert_eq!(
+            iovecs.as_slice().iter().map(|v| v.iov_len).sum::<usize>(),
+            len
+        );
+    }
+
+    #[test]
+    fn readv_into_chunks() {
+        let (mut a, b) = UnixStream::pair().unwrap();
+        let data: Vec<u8> = (0..100u8).collect();
+        a.write_all(&data).unwrap();
+
+        let mut mmap = AnonymousMmap::new(100).unwrap();
+        let iovecs = mmap.iovecs_mut(30);
+        let r = unsafe { libc::readv(b.as_raw_fd(), iovecs.as_ptr(), iovecs.len() as i32) };
+        assert_eq!(r, 100);
+        assert_eq!(mmap.view(..).unwrap().as_slice(), &data[..]);
+    }
+
+    #[test]
+    fn read_vectored_io_slice() {
+        let (mut a, mut b) = UnixStream::pair().unwrap();
+        a.write_all(b"hello").unwrap();
+        let mut mmap = AnonymousMmap::new(64).unwrap();
+        assert!(matches!(
+            mmap.as_io_slice_mut(60, 5),
+            Err(AnonymousMmapError::OutOfBounds(60, 5))
+        ));
+        let mut slices = [mmap.as_io_slice_mut(10, 5).unwrap()];
+        assert_eq!(b.read_vectored(&mut slices).unwrap(), 5);
+        assert_eq!(mmap.view(10..15).unwrap().as_slice(), b"hello");
+    }
+}
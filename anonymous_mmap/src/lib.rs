@@ -6,68 +6,467 @@
     unused_qualifications
 )]
 #![doc = include_str!("../README.md")]
+#![cfg_attr(not(feature = "std"), no_std)]
 
 #[cfg(not(target_os = "linux"))]
 compile_error!(
     "Crate anonymous-mmap is Linux specific dependency but is used in non-linux system."
 );
 
+extern crate alloc;
+
+use alloc::vec::Vec;
+
+mod accounting;
+#[cfg(feature = "accounting")]
+pub use accounting::live_mappings;
+#[cfg(feature = "leak_backtraces")]
+pub use accounting::{dump_leaks, write_leaks};
+
+mod advice;
+
+#[cfg(feature = "bytes")]
+mod buf_mut;
+#[cfg(feature = "bytes")]
+pub use buf_mut::MmapBufMut;
+
+mod bump;
+pub use bump::{BumpError, BumpMark, MmapBump, ResetPolicy};
+
+mod clone;
+
+mod errno;
+pub use errno::{Errno, ErrorKind, OsError};
+
+mod extend;
+
+mod fill;
+
+mod header;
+pub use header::MmapHeader;
+
+#[cfg(feature = "std")]
+mod iovec;
+#[cfg(feature = "std")]
+pub use iovec::MmapIoVecs;
+
+mod memfd;
+pub use memfd::SealFlags;
+
+mod numa;
+#[cfg(feature = "std")]
+pub use numa::possible_nodes;
+pub use numa::{MbindFlags, NumaError};
+
+#[cfg(feature = "std")]
+mod page_idle;
+
+#[cfg(feature = "std")]
+mod pkey;
+#[cfg(feature = "std")]
+pub use pkey::{pkey_alloc, pkey_free, pkey_get, pkey_set, PkeyRights};
+
+mod process_vm;
+pub use process_vm::{read_process_memory, write_process_memory, ProcessVmError};
+
+mod protect;
+
+mod raw;
+
+mod read;
+
+mod remap;
+#[cfg(feature = "std")]
+pub use remap::is_range_free;
+
+mod residency;
+#[cfg(feature = "std")]
+pub use residency::SmapsStats;
+
+mod seal;
+
+mod secret;
+pub use secret::SecretStep;
+
+#[cfg(feature = "std")]
+mod soft_dirty;
+
+mod slab;
+pub use slab::{Key, MmapSlab};
+
+mod splice;
+pub use splice::SpliceFlags;
+
+mod split;
+pub use split::SplitError;
+
+mod stack;
+
+mod touch;
+pub use touch::TouchError;
+
+#[cfg(feature = "uffd")]
+mod uffd;
+#[cfg(feature = "uffd")]
+pub use uffd::{UffdEvent, UffdHandle, UffdMode};
+
+mod unmap;
+
+mod vec;
+pub use vec::MmapVec;
+
+mod view;
+pub use view::{MmapView, MmapViewMut};
+
+mod volatile;
+
+mod window;
+pub use window::{RestorePolicy, WriteGuard};
+
+/// System page size as reported by sysconf(_SC_PAGESIZE).
+#[inline]
+pub fn page_size() -> usize {
+    // SAFETY: sysconf has no preconditions.
+    unsafe { libc::sysconf(libc::_SC_PAGESIZE) as usize }
+}
+
 /// Error
 #[derive(Debug)]
 pub enum AnonymousMmapError {
-    /// Call to mmap failed with errno
-    MmapFailed(std::io::Error),
+    /// Call to mmap failed with errno, see [`AnonymousMmapError::kind`]
+    MmapFailed {
+        /// Requested len of the mapping
+        len: usize,
+        /// mmap(2) flags requested
+        flags: libc::c_int,
+        /// The errno
+        error: OsError,
+    },
     /// Call to munmap failed with errno with the non-dropped Self given back.
-    MunmapFailed(AnonymousMmap, std::io::Error),
+    MunmapFailed(AnonymousMmap, OsError),
+    /// Given address or offset was not aligned to the page size
+    NotPageAligned(usize, usize),
+    /// Call to mprotect failed with errno
+    MprotectFailed(OsError),
+    /// Call to madvise failed with errno
+    MadviseFailed(OsError),
+    /// Given offset and len exceed the len of the mapping
+    OutOfBounds(usize, usize),
+    /// Hardening step of a secret mapping failed with errno
+    SecretFailed(SecretStep, OsError),
+    /// The named operation is not supported by the running kernel or for this mapping
+    Unsupported(&'static str, OsError),
+    /// Call to vmsplice failed with errno
+    SpliceFailed(OsError),
+    /// The operation requires a memfd-backed mapping
+    NotMemfd,
+    /// Call to memfd_create, ftruncate, fcntl or fallocate on the memfd failed with errno
+    MemfdFailed(OsError),
+    /// The current PROT_* protection of the mapping does not permit the access
+    Protected(libc::c_int),
+    /// Call to mremap failed with errno
+    MremapFailed(OsError),
+    /// Given offset and len overlap a range already unmapped through [`AnonymousMmap::unmap_range`]
+    Unmapped(usize, usize),
+    /// Given address was not aligned to the required alignment
+    Misaligned(usize, usize),
+    /// Call to userfaultfd(2) ioctls failed with errno
+    UffdFailed(OsError),
+    /// Call to mincore or reading /proc/self/smaps failed with errno
+    ResidencyFailed(OsError),
+    /// Reading /proc/self/pagemap or writing /proc/self/clear_refs failed with errno
+    PagemapFailed(OsError),
+    /// The named operation would change the layout or protection of a mapping sealed
+    /// through [`AnonymousMmap::mseal`]
+    Sealed(&'static str),
+    /// Destination at the first address overlaps the mapping at the second address
+    Overlapping(usize, usize),
+    /// Reading into the mapping failed with errno
+    ReadFailed(OsError),
+    /// The named operation requires privileges the process lacks e.g. CAP_SYS_ADMIN
+    Denied(&'static str, OsError),
 }
 
 impl core::fmt::Display for AnonymousMmapError {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
-            Self::MmapFailed(e) => write!(f, "mmap Failed: {}", e),
+            Self::MmapFailed { len, flags, error } => write!(
+                f,
+                "mmap of len {} with flags {:#x} Failed: {}",
+                len, flags, error
+            ),
             Self::MunmapFailed(tlb, e) => write!(f, "Drop / munmap on {:?} Failed: {}", tlb, e),
+            Self::NotPageAligned(addr, page_size) => {
+                write!(f, "{:#x} is not aligned to page size {}", addr, page_size)
+            }
+            Self::MprotectFailed(e) => write!(f, "mprotect Failed: {}", e),
+            Self::MadviseFailed(e) => write!(f, "madvise Failed: {}", e),
+            Self::OutOfBounds(offset, len) => {
+                write!(f, "Range at {} with len {} out of bounds", offset, len)
+            }
+            Self::SecretFailed(step, e) => write!(f, "Secret {} Failed: {}", step, e),
+            Self::Unsupported(op, e) => write!(f, "{} Unsupported: {}", op, e),
+            Self::SpliceFailed(e) => write!(f, "vmsplice Failed: {}", e),
+            Self::NotMemfd => write!(f, "Mapping is not memfd-backed"),
+            Self::MemfdFailed(e) => write!(f, "memfd Failed: {}", e),
+            Self::Protected(prot) => write!(f, "Access denied by protection {:#x}", prot),
+            Self::MremapFailed(e) => write!(f, "mremap Failed: {}", e),
+            Self::Unmapped(offset, len) => {
+                write!(
+                    f,
+                    "Range at {} with len {} overlaps unmapped range",
+                    offset, len
+                )
+            }
+            Self::Misaligned(addr, align) => {
+                write!(f, "{:#x} is not aligned to {}", addr, align)
+            }
+            Self::UffdFailed(e) => write!(f, "userfaultfd Failed: {}", e),
+            Self::ResidencyFailed(e) => write!(f, "Residency Failed: {}", e),
+            Self::PagemapFailed(e) => write!(f, "pagemap Failed: {}", e),
+            Self::Sealed(op) => write!(f, "{} refused on sealed mapping", op),
+            Self::Overlapping(dest, addr) => {
+                write!(f, "Destination {:#x} overlaps mapping at {:#x}", dest, addr)
+            }
+            Self::ReadFailed(e) => write!(f, "read Failed: {}", e),
+            Self::Denied(op, e) => write!(f, "{} Denied: {}", op, e),
         }
     }
 }
 
-impl core::error::Error for AnonymousMmapError {}
+impl core::error::Error for AnonymousMmapError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        self.io_error().map(|e| e as _)
+    }
+}
 
 /// An anonymous region of memory mapped using `mmap(2)`, not backed by a file
 /// but that is guaranteed to be page-aligned and zero-filled.
-#[derive(Debug)]
 pub struct AnonymousMmap {
     addr: core::ptr::NonNull<libc::c_void>,
     len: usize,
+    // PROT_NONE guard bytes mapped below addr which are unmapped along with the mapping.
+    guard: usize,
+    // memfd backing the mapping from offset 0 if any.
+    memfd: Option<yown_fd::OwnedFd>,
+    // mmap(2) flags the mapping was created with.
+    flags: libc::c_int,
+    // Current PROT_* protection of [addr, addr + len).
+    prot: libc::c_int,
+    // Sorted disjoint [start, end) offsets already unmapped through unmap_range.
+    holes: Vec<(usize, usize)>,
+    // Sealed through mseal so neither the layout nor the protection can change anymore.
+    sealed: bool,
+    // Key of the creation backtrace in the leak registry.
+    #[cfg(feature = "leak_backtraces")]
+    id: u64,
+}
+
+// SAFETY: A mapping is process wide and not tied to the thread which created it - unmapping
+// it from another thread through try_drop(self) is fine as is closing the memfd.
+unsafe impl Send for AnonymousMmap {}
+// SAFETY: Every safe &self method either only reads or hands out raw pointers whereby writing
+// through a raw pointer is unsafe and the caller is responsible for not aliasing another
+// thread's reads or writes, same as std with UnsafeCell::get. Safe mutation through slices
+// (view_mut, zero, ..) requires &mut self.
+unsafe impl Sync for AnonymousMmap {}
+
+impl core::fmt::Debug for AnonymousMmap {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("AnonymousMmap")
+            .field("addr", &format_args!("{:#x}", self.start_addr()))
+            .field("len", &self.len)
+            .finish()
+    }
 }
 
+const DEFAULT_FLAGS: libc::c_int = libc::MAP_ANONYMOUS | libc::MAP_SHARED | libc::MAP_POPULATE;
+
 impl AnonymousMmap {
     /// Construct a new AnonymousMmap with the given len of size.
     #[inline]
     pub fn new(len: usize) -> Result<Self, AnonymousMmapError> {
-        let p = unsafe {
-            libc::mmap(
-                core::ptr::null_mut(),
+        Self::mmap_with(core::ptr::null_mut(), len, DEFAULT_FLAGS)
+    }
+    /// Construct a new AnonymousMmap with the given len of size without MAP_POPULATE so the pages
+    /// are only faulted in on the first touch e.g. after applying a NUMA policy.
+    #[inline]
+    pub fn new_unpopulated(len: usize) -> Result<Self, AnonymousMmapError> {
+        Self::mmap_with(
+            core::ptr::null_mut(),
+            len,
+            libc::MAP_ANONYMOUS | libc::MAP_SHARED,
+        )
+    }
+    /// Construct a new MAP_PRIVATE AnonymousMmap with the given len of size which is not
+    /// shared with the children forked.
+    #[inline]
+    pub fn new_private(len: usize) -> Result<Self, AnonymousMmapError> {
+        Self::mmap_with(
+            core::ptr::null_mut(),
+            len,
+            libc::MAP_ANONYMOUS | libc::MAP_PRIVATE | libc::MAP_POPULATE,
+        )
+    }
+    /// Construct a new AnonymousMmap at exactly the given page-aligned address using
+    /// MAP_FIXED_NOREPLACE which fails with EEXIST instead of clobbering an existing mapping.
+    ///
+    /// Given ASLR randomizes the address space layout, picking your own address is inherently
+    /// fragile and the range may well be occupied on any given run - see [`Self::new_near`]
+    /// for the softer hint based alternative.
+    #[inline]
+    pub fn new_at(addr: usize, len: usize) -> Result<Self, AnonymousMmapError> {
+        let page_size = page_size();
+        if !addr.is_multiple_of(page_size) {
+            return Err(AnonymousMmapError::NotPageAligned(addr, page_size));
+        }
+        let mmap = Self::mmap_with(
+            addr as *mut libc::c_void,
+            len,
+            DEFAULT_FLAGS | libc::MAP_FIXED_NOREPLACE,
+        )?;
+        // Kernels older than 4.17 do not know MAP_FIXED_NOREPLACE and treat the address as a hint.
+        if mmap.start_addr() != addr {
+            // SAFETY: Nothing has been handed out from the mapping.
+            unsafe { mmap.try_drop() }?;
+            return Err(AnonymousMmapError::MmapFailed {
                 len,
-                libc::PROT_READ | libc::PROT_WRITE,
-                libc::MAP_ANONYMOUS | libc::MAP_SHARED | libc::MAP_POPULATE,
-                -1,
-                0,
-            )
-        };
+                flags: DEFAULT_FLAGS | libc::MAP_FIXED_NOREPLACE,
+                error: OsError::from_raw_os_error(libc::EEXIST),
+            });
+        }
+        Ok(mmap)
+    }
+    /// Construct a new AnonymousMmap passing the given address as a hint to the kernel
+    /// which is free to place the mapping elsewhere.
+    #[inline]
+    pub fn new_near(hint: usize, len: usize) -> Result<Self, AnonymousMmapError> {
+        Self::mmap_with(hint as *mut libc::c_void, len, DEFAULT_FLAGS)
+    }
+    #[inline]
+    fn mmap_with(
+        addr: *mut libc::c_void,
+        len: usize,
+        flags: libc::c_int,
+    ) -> Result<Self, AnonymousMmapError> {
+        Self::mmap_raw(addr, len, libc::PROT_READ | libc::PROT_WRITE, flags, -1)
+    }
+    #[inline]
+    fn mmap_fd(
+        len: usize,
+        prot: libc::c_int,
+        flags: libc::c_int,
+        fd: libc::c_int,
+    ) -> Result<Self, AnonymousMmapError> {
+        Self::mmap_raw(core::ptr::null_mut(), len, prot, flags, fd)
+    }
+    #[inline]
+    fn mmap_raw(
+        addr: *mut libc::c_void,
+        len: usize,
+        prot: libc::c_int,
+        flags: libc::c_int,
+        fd: libc::c_int,
+    ) -> Result<Self, AnonymousMmapError> {
+        let p = unsafe { libc::mmap(addr, len, prot, flags, fd, 0) };
 
         if p == libc::MAP_FAILED {
-            let os_err = std::io::Error::last_os_error();
-            return Err(AnonymousMmapError::MmapFailed(os_err));
+            let os_err = OsError::last_os_error();
+            return Err(AnonymousMmapError::MmapFailed {
+                len,
+                flags,
+                error: os_err,
+            });
         }
 
-        Ok(Self {
+        let mut mmap = Self {
             // SAFETY: We've checked the error
             addr: unsafe { core::ptr::NonNull::new_unchecked(p) },
             len,
-        })
+            guard: 0,
+            memfd: None,
+            flags,
+            prot,
+            holes: Vec::new(),
+            sealed: false,
+            #[cfg(feature = "leak_backtraces")]
+            id: 0,
+        };
+        accounting::created(&mut mmap);
+        Ok(mmap)
+    }
+    /// Check the range starts page-aligned and either ends page-aligned or at the end of the mapping
+    /// within the bounds and still mapped before providing the mutable ptr to the start of it.
+    #[inline]
+    pub(crate) fn page_range(
+        &self,
+        offset: usize,
+        len: usize,
+    ) -> Result<*mut libc::c_void, AnonymousMmapError> {
+        self.checked_range(offset, len)?;
+        self.page_bounds(offset, len)
+    }
+    /// Check the range is within the bounds and still mapped before providing the mutable ptr
+    /// to the start of it.
+    #[inline]
+    pub(crate) fn checked_range(
+        &self,
+        offset: usize,
+        len: usize,
+    ) -> Result<*mut libc::c_void, AnonymousMmapError> {
+        let p = self.bounds(offset, len)?;
+        if self.overlaps_hole(offset, offset + len) {
+            return Err(AnonymousMmapError::Unmapped(offset, len));
+        }
+        Ok(p)
+    }
+    /// Page alignment and bounds check of the range regardless of it being mapped.
+    #[inline]
+    pub(crate) fn page_bounds(
+        &self,
+        offset: usize,
+        len: usize,
+    ) -> Result<*mut libc::c_void, AnonymousMmapError> {
+        let p = self.bounds(offset, len)?;
+        let page_size = page_size();
+        if !offset.is_multiple_of(page_size) {
+            return Err(AnonymousMmapError::NotPageAligned(offset, page_size));
+        }
+        let end = offset + len;
+        if end != self.len && !end.is_multiple_of(page_size) {
+            return Err(AnonymousMmapError::NotPageAligned(end, page_size));
+        }
+        Ok(p)
+    }
+    #[inline]
+    fn bounds(&self, offset: usize, len: usize) -> Result<*mut libc::c_void, AnonymousMmapError> {
+        match offset.checked_add(len) {
+            // SAFETY: Within the bounds of the mapping.
+            Some(end) if end <= self.len => Ok(unsafe { self.as_ptr_mut().add(offset) }),
+            _ => Err(AnonymousMmapError::OutOfBounds(offset, len)),
+        }
+    }
+    /// Provide the start address of the mapping
+    #[inline]
+    pub fn start_addr(&self) -> usize {
+        self.addr.as_ptr() as usize
+    }
+    /// Provide the len of the mapping in bytes which the kernel backs rounded up to the page size
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    /// Is the mapping of len 0 which mmap(2) never creates
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
     }
     /// Provide the raw mutable ptr
     /// Same warnigns apply as [`slice::as_mut_ptr`](https://doc.rust-lang.org/std/primitive.slice.html#method.as_mut_ptr).
+    ///
+    /// Given this only takes &self, writing through the ptr while any other reference to the
+    /// mapping - including a shared one on another thread - is reading or writing the same
+    /// bytes is a data race and the caller is responsible for synchronizing the access.
     #[inline]
     pub fn as_ptr_mut(&self) -> *mut libc::c_void {
         self.addr.as_ptr()
@@ -78,29 +477,76 @@ impl AnonymousMmap {
         self.addr.as_ptr()
     }
     /// Provide ptr with an added offset without bounds checking.
+    ///
+    /// # Safety
+    ///
+    /// The offset must be within the mapped len.
     #[inline]
     pub unsafe fn offset_unchecked_as_ptr(&self, offset: u32) -> *const libc::c_void {
         self.as_ptr().add(offset as usize)
     }
     /// Get a mut pointer to the data at the given offset without bounds checking.
+    ///
+    /// # Safety
+    ///
+    /// The offset must be within the mapped len.
     #[inline]
     pub unsafe fn offset_unchecked_as_ptr_mut(&self, offset: u32) -> *mut libc::c_void {
         self.as_ptr_mut().add(offset as usize)
     }
     /// Given Drop may fail, the consumer is responsible manually handling the drop of the construct.
+    ///
+    /// Ranges already unmapped through [`Self::unmap_range`] are not unmapped again as the
+    /// kernel may have placed another mapping there since.
+    ///
+    /// Mappings sealed through [`Self::mseal`] stay mapped until the process exits and are
+    /// [`AnonymousMmapError::Sealed`].
+    ///
+    /// # Safety
+    ///
+    /// No pointers previously handed out may be used after the mapping is gone.
     #[inline]
-    pub unsafe fn try_drop(self) -> Result<(), AnonymousMmapError> {
-        // SAFETY: Construct assumes valid construction and initialization with the given capacity.
-        let p = unsafe { libc::munmap(self.addr.as_ptr(), self.len) };
-        if p != 0 {
-            let os_err = std::io::Error::last_os_error();
-            return Err(AnonymousMmapError::MunmapFailed(self, os_err));
+    pub unsafe fn try_drop(mut self) -> Result<(), AnonymousMmapError> {
+        if self.sealed {
+            return Err(AnonymousMmapError::Sealed("munmap"));
+        }
+        if self.holes.is_empty() {
+            // SAFETY: Construct assumes valid construction and initialization with the given capacity.
+            let p =
+                unsafe { libc::munmap(self.addr.as_ptr().sub(self.guard), self.len + self.guard) };
+            if p != 0 {
+                let os_err = OsError::last_os_error();
+                return Err(AnonymousMmapError::MunmapFailed(self, os_err));
+            }
+            accounting::released(&self);
+            return Ok(());
+        }
+        if self.guard != 0 {
+            // SAFETY: The guard pages below the mapping are ours.
+            let p = unsafe { libc::munmap(self.addr.as_ptr().sub(self.guard), self.guard) };
+            if p != 0 {
+                let os_err = OsError::last_os_error();
+                return Err(AnonymousMmapError::MunmapFailed(self, os_err));
+            }
+            accounting::resized(self.guard, 0);
+            self.guard = 0;
         }
+        let mapped: Vec<(usize, usize)> = self.mapped_ranges(0, self.len).collect();
+        for (start, end) in mapped {
+            // SAFETY: [start, end) is within the mapping and still mapped.
+            let p = unsafe { libc::munmap(self.as_ptr_mut().add(start), end - start) };
+            if p != 0 {
+                let os_err = OsError::last_os_error();
+                return Err(AnonymousMmapError::MunmapFailed(self, os_err));
+            }
+            self.insert_hole(start, end);
+        }
+        accounting::released(&self);
         Ok(())
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "std"))]
 mod test {
 
     use super::*;
@@ -111,4 +557,101 @@ mod test {
     fn choices(#[case] len: usize) {
         AnonymousMmap::new(len).unwrap();
     }
+
+    #[rstest]
+    #[case(1024)]
+    #[case(4096)]
+    #[case(4097)]
+    fn len_as_requested(#[case] len: usize) {
+        let mmap = AnonymousMmap::new(len).unwrap();
+        assert_eq!(mmap.len(), len);
+        assert!(!mmap.is_empty());
+        unsafe { mmap.try_drop().unwrap() };
+    }
+
+    #[test]
+    fn debug_addr_len() {
+        let mmap = AnonymousMmap::new(4096).unwrap();
+        let debug = format!("{:?}", mmap);
+        assert_eq!(
+            debug,
+            format!(
+                "AnonymousMmap {{ addr: {:#x}, len: 4096 }}",
+                mmap.start_addr()
+            )
+        );
+    }
+
+    #[test]
+    fn new_at_fixed_noreplace() {
+        let len = page_size() * 2;
+        let probe = AnonymousMmap::new(len).unwrap();
+        let addr = probe.start_addr();
+        unsafe { probe.try_drop().unwrap() };
+
+        let mmap = AnonymousMmap::new_at(addr, len).unwrap();
+        assert_eq!(mmap.start_addr(), addr);
+        match AnonymousMmap::new_at(addr, len) {
+            Err(e @ AnonymousMmapError::MmapFailed { .. }) => {
+                assert_eq!(e.kind(), ErrorKind::AlreadyExists)
+            }
+            r => panic!("Expected EEXIST, got {:?}", r),
+        }
+        unsafe { mmap.try_drop().unwrap() };
+    }
+
+    #[test]
+    fn new_at_not_page_aligned() {
+        assert!(matches!(
+            AnonymousMmap::new_at(page_size() + 1, 128),
+            Err(AnonymousMmapError::NotPageAligned(..))
+        ));
+    }
+
+    #[test]
+    fn new_near_hint() {
+        let probe = AnonymousMmap::new(128).unwrap();
+        let hint = probe.start_addr();
+        unsafe { probe.try_drop().unwrap() };
+        let mmap = AnonymousMmap::new_near(hint, 128).unwrap();
+        assert!(mmap.start_addr().is_multiple_of(page_size()));
+    }
+
+    #[test]
+    fn send_across_threads() {
+        let mut mmap = AnonymousMmap::new(4096).unwrap();
+        mmap.view_mut(..).unwrap().as_slice_mut().fill(3);
+        let mmap = std::thread::spawn(move || {
+            mmap.view_mut(..).unwrap().as_slice_mut()[0] = 4;
+            mmap
+        })
+        .join()
+        .unwrap();
+        assert_eq!(mmap.view(..1).unwrap().as_slice(), &[4]);
+        unsafe { mmap.try_drop().unwrap() };
+    }
+
+    #[test]
+    fn share_reads_across_threads() {
+        let mut mmap = AnonymousMmap::new(4096).unwrap();
+        mmap.view_mut(..).unwrap().as_slice_mut().fill(5);
+        let shared = std::sync::Arc::new(mmap);
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let shared = shared.clone();
+                std::thread::spawn(move || {
+                    shared.view(..).unwrap().as_slice().iter().all(|b| *b == 5)
+                })
+            })
+            .collect();
+        for handle in handles {
+            assert!(handle.join().unwrap());
+        }
+        let mmap = std::sync::Arc::into_inner(shared).unwrap();
+        std::thread::scope(|s| {
+            s.spawn(|| assert_eq!(mmap.subslice(..2).as_slice(), &[5, 5]));
+            s.spawn(|| assert_eq!(mmap.subslice(4094..).as_slice(), &[5, 5]));
+        });
+        unsafe { mmap.try_drop().unwrap() };
+    }
 }
@@ -0,0 +1,305 @@
+//! memfd-backed mappings
+
+use crate::{AnonymousMmap, AnonymousMmapError, OsError};
+use yown_fd::{AsFd, AsRawFd, BorrowedFd, FromRawFd, OwnedFd, RawFd};
+
+bitflags::bitflags! {
+    /// memfd_create(2) F_ADD_SEALS / F_GET_SEALS seals
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    pub struct SealFlags: libc::c_int {
+        /// No further seals can be added
+        const SEAL_SEAL = libc::F_SEAL_SEAL;
+        /// The memfd can not shrink
+        const SEAL_SHRINK = libc::F_SEAL_SHRINK;
+        /// The memfd can not grow
+        const SEAL_GROW = libc::F_SEAL_GROW;
+        /// The contents can not be written, requires no writable shared mappings to exist
+        const SEAL_WRITE = libc::F_SEAL_WRITE;
+        /// No new writable mappings or writes while the existing mappings stay writable
+        const SEAL_FUTURE_WRITE = libc::F_SEAL_FUTURE_WRITE;
+    }
+}
+
+#[inline]
+fn add_seals(fd: RawFd, seals: SealFlags) -> Result<(), AnonymousMmapError> {
+    // SAFETY: fd is valid.
+    if unsafe { libc::fcntl(fd, libc::F_ADD_SEALS, seals.bits()) } != 0 {
+        return Err(AnonymousMmapError::MemfdFailed(OsError::last_os_error()));
+    }
+    Ok(())
+}
+
+impl AnonymousMmap {
+    /// Construct a new MAP_SHARED mapping of the given len backed by a memfd created with
+    /// MFD_ALLOW_SEALING. The memfd is kept along with the mapping.
+    #[inline]
+    pub fn new_memfd(len: usize) -> Result<Self, AnonymousMmapError> {
+        // SAFETY: The name is a valid C string.
+        let fd = unsafe {
+            libc::memfd_create(
+                c"anonymous-mmap".as_ptr(),
+                libc::MFD_CLOEXEC | libc::MFD_ALLOW_SEALING,
+            )
+        };
+        if fd < 0 {
+            return Err(AnonymousMmapError::MemfdFailed(OsError::last_os_error()));
+        }
+        // SAFETY: fd is valid and ours.
+        let memfd = unsafe { OwnedFd::from_raw_fd(fd) };
+        // SAFETY: memfd is valid.
+        if unsafe { libc::ftruncate(memfd.as_raw_fd(), len as libc::off_t) } != 0 {
+            return Err(AnonymousMmapError::MemfdFailed(OsError::last_os_error()));
+        }
+        let mut mmap = Self::mmap_fd(
+            len,
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_SHARED | libc::MAP_POPULATE,
+            memfd.as_raw_fd(),
+        )?;
+        mmap.memfd = Some(memfd);
+        Ok(mmap)
+    }
+    /// Is the mapping memfd-backed
+    #[inline]
+    pub fn is_memfd(&self) -> bool {
+        self.memfd.is_some()
+    }
+    /// The backing memfd if any e.g. for passing over SCM_RIGHTS after [`Self::seal`].
+    #[inline]
+    pub fn memfd(&self) -> Option<BorrowedFd<'_>> {
+        self.memfd.as_ref().map(|memfd| memfd.as_fd())
+    }
+    #[inline]
+    fn memfd_raw(&self) -> Result<RawFd, AnonymousMmapError> {
+        match &self.memfd {
+            Some(memfd) => Ok(memfd.as_raw_fd()),
+            None => Err(AnonymousMmapError::NotMemfd),
+        }
+    }
+    // Replace the mapping in place keeping the address.
+    #[inline]
+    fn remap_fixed(
+        &mut self,
+        prot: libc::c_int,
+        flags: libc::c_int,
+        fd: RawFd,
+    ) -> Result<(), AnonymousMmapError> {
+        let flags = flags | libc::MAP_FIXED;
+        // SAFETY: The range is our mapping borrowed exclusively which is replaced atomically.
+        let p = unsafe { libc::mmap(self.as_ptr_mut(), self.len, prot, flags, fd, 0) };
+        if p == libc::MAP_FAILED {
+            return Err(AnonymousMmapError::MmapFailed {
+                len: self.len,
+                flags,
+                error: OsError::last_os_error(),
+            });
+        }
+        self.prot = prot;
+        Ok(())
+    }
+    /// Add the seals to the backing memfd through fcntl(2) F_ADD_SEALS e.g. before handing the
+    /// memfd out to less-trusted consumers who then can only map it read-only.
+    ///
+    /// F_SEAL_WRITE is refused by the kernel with EBUSY while any writable shared mapping of the
+    /// memfd exists which includes this one, so with [`SealFlags::SEAL_WRITE`] this mapping is
+    /// first replaced in place with a PROT_NONE placeholder and after sealing mapped back
+    /// MAP_SHARED read-only at the same address. On failure this mapping is restored writable.
+    /// Other mappings of the memfd e.g. by recipients still make the seal fail with EBUSY.
+    ///
+    /// [`AnonymousMmapError::NotMemfd`] if the mapping is not memfd-backed.
+    #[inline]
+    pub fn seal(&mut self, seals: SealFlags) -> Result<(), AnonymousMmapError> {
+        let fd = self.memfd_raw()?;
+        if !seals.contains(SealFlags::SEAL_WRITE) {
+            return add_seals(fd, seals);
+        }
+        let shared_writable = self.flags & libc::MAP_SHARED != 0 && self.is_writable();
+        if shared_writable {
+            self.remap_fixed(libc::PROT_NONE, libc::MAP_PRIVATE | libc::MAP_ANONYMOUS, -1)?;
+        }
+        let sealed = add_seals(fd, seals);
+        let prot = match (&sealed, shared_writable) {
+            (_, false) => return sealed,
+            (Ok(()), true) => libc::PROT_READ,
+            (Err(_), true) => libc::PROT_READ | libc::PROT_WRITE,
+        };
+        self.remap_fixed(prot, libc::MAP_SHARED, fd)?;
+        sealed
+    }
+    /// The seals of the backing memfd through fcntl(2) F_GET_SEALS.
+    ///
+    /// [`AnonymousMmapError::NotMemfd`] if the mapping is not memfd-backed.
+    #[inline]
+    pub fn seals(&self) -> Result<SealFlags, AnonymousMmapError> {
+        let fd = self.memfd_raw()?;
+        // SAFETY: fd is valid.
+        let seals = unsafe { libc::fcntl(fd, libc::F_GET_SEALS) };
+        if seals < 0 {
+            return Err(AnonymousMmapError::MemfdFailed(OsError::last_os_error()));
+        }
+        Ok(SealFlags::from_bits_retain(seals))
+    }
+    /// Take a copy-on-write snapshot of a memfd-backed mapping returning the read-only snapshot
+    /// while this mapping stays writable at the same address.
+    ///
+    /// Mapping the memfd MAP_PRIVATE as the snapshot would not be isolated given the private pages
+    /// not yet copied keep reflecting the writes through the shared mapping. Instead the inverse is
+    /// done:
+    ///
+    /// 1. This mapping is replaced in place with a MAP_PRIVATE mapping of the memfd so any
+    ///    following writes are copied-on-write into private pages never reaching the memfd.
+    /// 2. The memfd is sealed with F_SEAL_WRITE | F_SEAL_SHRINK | F_SEAL_GROW.
+    /// 3. The snapshot maps the sealed memfd MAP_SHARED read-only and takes over the memfd.
+    ///
+    /// Isolated: the writes to this mapping after the snapshot never appear in the snapshot and
+    /// nobody can change the snapshot contents through the memfd.
+    ///
+    /// Not isolated: the bytes written *before* the snapshot are shared with it until this
+    /// mapping overwrites them. This mapping is no longer memfd-backed afterwards so it can be
+    /// snapshot only once and [`AnonymousMmapError::NotMemfd`] is returned on further attempts.
+    #[inline]
+    pub fn snapshot_cow(&mut self) -> Result<AnonymousMmap, AnonymousMmapError> {
+        let fd = match &self.memfd {
+            Some(memfd) => memfd.as_raw_fd(),
+            None => return Err(AnonymousMmapError::NotMemfd),
+        };
+        // SAFETY: The range is our mapping which is replaced atomically keeping the contents.
+        let p = unsafe {
+            libc::mmap(
+                self.as_ptr_mut(),
+                self.len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_PRIVATE | libc::MAP_FIXED,
+                fd,
+                0,
+            )
+        };
+        if p == libc::MAP_FAILED {
+            return Err(AnonymousMmapError::MmapFailed {
+                len: self.len,
+                flags: libc::MAP_PRIVATE | libc::MAP_FIXED,
+                error: OsError::last_os_error(),
+            });
+        }
+        let seals = libc::F_SEAL_WRITE | libc::F_SEAL_SHRINK | libc::F_SEAL_GROW;
+        // SAFETY: fd is valid.
+        if unsafe { libc::fcntl(fd, libc::F_ADD_SEALS, seals) } != 0 {
+            return Err(AnonymousMmapError::MemfdFailed(OsError::last_os_error()));
+        }
+        let mut snapshot = Self::mmap_fd(self.len, libc::PROT_READ, libc::MAP_SHARED, fd)?;
+        snapshot.memfd = self.memfd.take();
+        Ok(snapshot)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn snapshot_isolated_from_later_writes() {
+        let mut live = AnonymousMmap::new_memfd(8192).unwrap();
+        assert!(live.is_memfd());
+        live.view_mut(..).unwrap().as_slice_mut().fill(b'A');
+        let live_addr = live.start_addr();
+
+        let snapshot = live.snapshot_cow().unwrap();
+        assert_eq!(live.start_addr(), live_addr);
+        assert!(!live.is_memfd());
+        assert!(snapshot.is_memfd());
+
+        live.view_mut(4096..).unwrap().as_slice_mut().fill(b'B');
+        assert!(snapshot
+            .view(..)
+            .unwrap()
+            .as_slice()
+            .iter()
+            .all(|b| *b == b'A'));
+        let view = live.view(..).unwrap();
+        assert!(view.as_slice()[..4096].iter().all(|b| *b == b'A'));
+        assert!(view.as_slice()[4096..].iter().all(|b| *b == b'B'));
+
+        // The sealed memfd can not be mapped writable shared anymore.
+        let fd = snapshot.memfd.as_ref().unwrap().as_raw_fd();
+        let r = AnonymousMmap::mmap_fd(
+            8192,
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_SHARED,
+            fd,
+        );
+        assert!(matches!(r, Err(AnonymousMmapError::MmapFailed { .. })));
+    }
+
+    #[test]
+    fn snapshot_requires_memfd() {
+        let mut mmap = AnonymousMmap::new(4096).unwrap();
+        assert!(matches!(
+            mmap.snapshot_cow(),
+            Err(AnonymousMmapError::NotMemfd)
+        ));
+    }
+
+    #[test]
+    fn seal_write_readonly_shared() {
+        let mut mmap = AnonymousMmap::new_memfd(4096).unwrap();
+        mmap.fill(7);
+        let addr = mmap.start_addr();
+        assert_eq!(mmap.seals().unwrap(), SealFlags::empty());
+
+        let seals = SealFlags::SEAL_WRITE | SealFlags::SEAL_SHRINK | SealFlags::SEAL_GROW;
+        mmap.seal(seals).unwrap();
+        assert_eq!(mmap.seals().unwrap(), seals);
+        assert_eq!(mmap.start_addr(), addr);
+        assert!(!mmap.is_writable());
+        assert!(mmap.view(..).unwrap().as_slice().iter().all(|b| *b == 7));
+
+        let fd = mmap.memfd().unwrap().as_raw_fd();
+        let readonly = AnonymousMmap::mmap_fd(4096, libc::PROT_READ, libc::MAP_SHARED, fd).unwrap();
+        assert!(readonly
+            .view(..)
+            .unwrap()
+            .as_slice()
+            .iter()
+            .all(|b| *b == 7));
+        let rw = AnonymousMmap::mmap_fd(
+            4096,
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_SHARED,
+            fd,
+        );
+        match rw {
+            Err(e @ AnonymousMmapError::MmapFailed { .. }) => {
+                assert_eq!(e.kind(), crate::ErrorKind::PermissionDenied)
+            }
+            r => panic!("Expected EPERM, got {:?}", r),
+        }
+        assert_eq!(unsafe { libc::ftruncate(fd, 8192) }, -1);
+        unsafe { readonly.try_drop().unwrap() };
+    }
+
+    #[test]
+    fn seal_seal_refuses_more() {
+        let mut mmap = AnonymousMmap::new_memfd(4096).unwrap();
+        mmap.seal(SealFlags::SEAL_SEAL).unwrap();
+        assert!(matches!(
+            mmap.seal(SealFlags::SEAL_GROW),
+            Err(AnonymousMmapError::MemfdFailed(_))
+        ));
+        // Restored writable after the failed SEAL_WRITE.
+        assert!(mmap.seal(SealFlags::SEAL_WRITE).is_err());
+        assert!(mmap.is_writable());
+        mmap.fill(1);
+    }
+
+    #[test]
+    fn seal_requires_memfd() {
+        let mut mmap = AnonymousMmap::new(4096).unwrap();
+        assert!(matches!(
+            mmap.seal(SealFlags::SEAL_WRITE),
+            Err(AnonymousMmapError::NotMemfd)
+        ));
+        assert!(matches!(mmap.seals(), Err(AnonymousMmapError::NotMemfd)));
+        assert!(mmap.memfd().is_none());
+    }
+}
@@ -0,0 +1,206 @@
+//! NUMA memory policy through mbind(2) and move_pages(2)
+
+use crate::{AnonymousMmap, AnonymousMmapError, OsError};
+use alloc::vec;
+use alloc::vec::Vec;
+
+const NODE_POSSIBLE: &str = "/sys/devices/system/node/possible";
+
+const MPOL_BIND: libc::c_int = 2;
+const MPOL_INTERLEAVE: libc::c_int = 3;
+
+bitflags::bitflags! {
+    /// mbind(2) flags
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    pub struct MbindFlags: libc::c_uint {
+        /// Fail with EIO if existing pages do not follow the policy
+        const STRICT = 1 << 0;
+        /// Move the existing pages of this process to follow the policy
+        const MOVE = 1 << 1;
+        /// Move all existing pages including shared (requires CAP_SYS_NICE)
+        const MOVE_ALL = 1 << 2;
+    }
+}
+
+/// Error applying or querying NUMA policy
+#[derive(Debug)]
+pub enum NumaError {
+    /// The node is not possible on this system as per /sys/devices/system/node/possible
+    InvalidNode(u32),
+    /// No nodes were given
+    NoNodes,
+    /// Reading or parsing /sys/devices/system/node/possible failed
+    NodePossible(OsError),
+    /// Bounds or alignment of the range
+    Range(AnonymousMmapError),
+    /// Call to mbind failed with errno
+    MbindFailed(OsError),
+    /// Call to move_pages failed with errno
+    MovePagesFailed(OsError),
+}
+
+impl core::fmt::Display for NumaError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::InvalidNode(node) => write!(f, "NUMA node {} is not possible", node),
+            Self::NoNodes => write!(f, "No NUMA nodes given"),
+            Self::NodePossible(e) => write!(f, "Reading {} Failed: {}", NODE_POSSIBLE, e),
+            Self::Range(e) => write!(f, "{}", e),
+            Self::MbindFailed(e) => write!(f, "mbind Failed: {}", e),
+            Self::MovePagesFailed(e) => write!(f, "move_pages Failed: {}", e),
+        }
+    }
+}
+
+impl core::error::Error for NumaError {}
+
+// Parse cpulist format e.g. "0", "0-3" or "0,2-3"
+#[cfg(feature = "std")]
+#[inline]
+fn parse_node_list(list: &str) -> Option<Vec<u32>> {
+    let mut nodes = Vec::new();
+    for part in list.trim().split(',').filter(|p| !p.is_empty()) {
+        match part.split_once('-') {
+            Some((lo, hi)) => nodes.extend(lo.parse::<u32>().ok()?..=hi.parse::<u32>().ok()?),
+            None => nodes.push(part.parse().ok()?),
+        }
+    }
+    Some(nodes)
+}
+
+/// Possible NUMA nodes on this system as per /sys/devices/system/node/possible
+#[cfg(feature = "std")]
+#[inline]
+pub fn possible_nodes() -> Result<Vec<u32>, NumaError> {
+    let list = std::fs::read_to_string(NODE_POSSIBLE).map_err(NumaError::NodePossible)?;
+    parse_node_list(&list).ok_or_else(|| {
+        NumaError::NodePossible(std::io::Error::from(std::io::ErrorKind::InvalidData))
+    })
+}
+
+impl AnonymousMmap {
+    #[inline]
+    fn mbind(&self, mode: libc::c_int, nodes: &[u32], flags: MbindFlags) -> Result<(), NumaError> {
+        let max = match nodes.iter().max() {
+            Some(max) => *max as usize,
+            None => return Err(NumaError::NoNodes),
+        };
+        // Without std the kernel is left to refuse impossible nodes with EINVAL.
+        #[cfg(feature = "std")]
+        {
+            let possible = possible_nodes()?;
+            if let Some(node) = nodes.iter().find(|node| !possible.contains(node)) {
+                return Err(NumaError::InvalidNode(*node));
+            }
+        }
+        let bits = libc::c_ulong::BITS as usize;
+        let mut mask: Vec<libc::c_ulong> = vec![0; max / bits + 1];
+        for node in nodes {
+            mask[*node as usize / bits] |= 1 << (*node as usize % bits);
+        }
+        // SAFETY: The range is our mapping and the mask is valid for maxnode bits.
+        let r = unsafe {
+            libc::syscall(
+                libc::SYS_mbind,
+                self.as_ptr_mut(),
+                self.len,
+                mode,
+                mask.as_ptr(),
+                (mask.len() * bits + 1) as libc::c_ulong,
+                flags.bits(),
+            )
+        };
+        if r != 0 {
+            return Err(NumaError::MbindFailed(OsError::last_os_error()));
+        }
+        Ok(())
+    }
+    /// Bind the whole mapping to the given NUMA node through mbind(MPOL_BIND).
+    ///
+    /// Binding is best done before the first touch e.g. with [`Self::new_unpopulated`] given the
+    /// policy only affects future faults unless [`MbindFlags::MOVE`] is requested.
+    #[inline]
+    pub fn bind_to_node(&self, node: u32, flags: MbindFlags) -> Result<(), NumaError> {
+        self.mbind(MPOL_BIND, &[node], flags)
+    }
+    /// Interleave the pages of the whole mapping across the given NUMA nodes through
+    /// mbind(MPOL_INTERLEAVE). Same as [`Self::bind_to_node`] regarding already populated pages.
+    #[inline]
+    pub fn interleave(&self, nodes: &[u32], flags: MbindFlags) -> Result<(), NumaError> {
+        self.mbind(MPOL_INTERLEAVE, nodes, flags)
+    }
+    /// NUMA node the page at the given offset resides on through move_pages(2) or None
+    /// if the page has not been faulted in yet.
+    #[inline]
+    pub fn numa_node_of_page(&self, offset: usize) -> Result<Option<u32>, NumaError> {
+        let p = self.checked_range(offset, 1).map_err(NumaError::Range)?;
+        let pages = [p];
+        let mut status: [libc::c_int; 1] = [0];
+        // SAFETY: pages and status are valid for count 1 and null nodes only queries.
+        let r = unsafe {
+            libc::syscall(
+                libc::SYS_move_pages,
+                0,
+                1 as libc::c_ulong,
+                pages.as_ptr(),
+                core::ptr::null::<libc::c_int>(),
+                status.as_mut_ptr(),
+                0,
+            )
+        };
+        if r != 0 {
+            return Err(NumaError::MovePagesFailed(OsError::last_os_error()));
+        }
+        match status[0] {
+            node if node >= 0 => Ok(Some(node as u32)),
+            e if e == -libc::ENOENT => Ok(None),
+            e => Err(NumaError::MovePagesFailed(OsError::from_raw_os_error(-e))),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+
+    use super::*;
+    use crate::page_size;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("0\n", vec![0])]
+    #[case("0-3", vec![0, 1, 2, 3])]
+    #[case("0,2-3", vec![0, 2, 3])]
+    fn node_lists(#[case] list: &str, #[case] nodes: Vec<u32>) {
+        assert_eq!(parse_node_list(list), Some(nodes));
+    }
+
+    #[test]
+    fn bind_before_first_touch() {
+        let page = page_size();
+        let mut mmap = AnonymousMmap::new_unpopulated(page * 4).unwrap();
+        let node = possible_nodes().unwrap()[0];
+        mmap.bind_to_node(node, MbindFlags::empty()).unwrap();
+        assert_eq!(mmap.numa_node_of_page(0).unwrap(), None);
+        mmap.view_mut(..).unwrap().as_slice_mut().fill(1);
+        assert_eq!(mmap.numa_node_of_page(page).unwrap(), Some(node));
+    }
+
+    #[test]
+    fn interleave_and_invalid() {
+        let mmap = AnonymousMmap::new_unpopulated(page_size()).unwrap();
+        let nodes = possible_nodes().unwrap();
+        mmap.interleave(&nodes, MbindFlags::MOVE).unwrap();
+        assert!(matches!(
+            mmap.bind_to_node(9999, MbindFlags::empty()),
+            Err(NumaError::InvalidNode(9999))
+        ));
+        assert!(matches!(
+            mmap.interleave(&[], MbindFlags::empty()),
+            Err(NumaError::NoNodes)
+        ));
+        assert!(matches!(
+            mmap.numa_node_of_page(page_size()),
+            Err(NumaError::Range(_))
+        ));
+    }
+}
@@ -0,0 +1,162 @@
+//! /sys/kernel/mm/page_idle/bitmap idle page tracking of the pages of an [`AnonymousMmap`]
+
+use crate::soft_dirty::PM_PRESENT;
+use crate::{AnonymousMmap, AnonymousMmapError};
+use std::os::unix::fs::FileExt;
+
+const PAGE_IDLE_BITMAP: &str = "/sys/kernel/mm/page_idle/bitmap";
+// Bits 0-54 of a present pagemap entry are the PFN, zeroed without CAP_SYS_ADMIN.
+const PM_PFN_MASK: u64 = (1 << 55) - 1;
+
+// Missing bitmap is a kernel without CONFIG_IDLE_PAGE_TRACKING and the bitmap is root only.
+fn idle_error(e: std::io::Error) -> AnonymousMmapError {
+    match e.raw_os_error() {
+        Some(libc::ENOENT) => AnonymousMmapError::Unsupported("page_idle", e),
+        Some(libc::EPERM | libc::EACCES) => AnonymousMmapError::Denied("page_idle", e),
+        _ => AnonymousMmapError::PagemapFailed(e),
+    }
+}
+
+impl AnonymousMmap {
+    /// Mark every present page of the mapping idle in the idle page bitmap so that
+    /// [`Self::idle_pages`] reports the pages not accessed from now on.
+    ///
+    /// Only the bits of the mapping's own PFNs are set, other pages of the system are left
+    /// as is. Pages not yet faulted in have no PFN and are skipped.
+    ///
+    /// Translating to PFNs through /proc/self/pagemap and the bitmap itself require
+    /// CAP_SYS_ADMIN / root, otherwise [`AnonymousMmapError::Denied`]. Kernels without
+    /// CONFIG_IDLE_PAGE_TRACKING are [`AnonymousMmapError::Unsupported`].
+    #[inline]
+    pub fn mark_idle(&self) -> Result<(), AnonymousMmapError> {
+        let bitmap = std::fs::OpenOptions::new()
+            .write(true)
+            .open(PAGE_IDLE_BITMAP)
+            .map_err(idle_error)?;
+        // Zero bits are ignored by the kernel so only ours are set in each word written.
+        let mut word: Option<(u64, u64)> = None;
+        for (_, pfn) in self.present_pfns()? {
+            match &mut word {
+                Some((at, bits)) if *at == pfn / 64 => *bits |= 1 << (pfn % 64),
+                _ => {
+                    if let Some((at, bits)) = word {
+                        write_word(&bitmap, at, bits)?;
+                    }
+                    word = Some((pfn / 64, 1 << (pfn % 64)));
+                }
+            }
+        }
+        match word {
+            Some((at, bits)) => write_word(&bitmap, at, bits),
+            None => Ok(()),
+        }
+    }
+    /// Indices of the pages of the mapping not accessed since the last [`Self::mark_idle`]
+    /// as per the idle page bitmap, e.g. to hand to [`Self::advise_pageout`].
+    ///
+    /// The indices are in base pages. Pages not present, e.g. reclaimed or never faulted in,
+    /// are not reported. Same privileges as [`Self::mark_idle`] are needed.
+    #[inline]
+    pub fn idle_pages(&self) -> Result<Vec<usize>, AnonymousMmapError> {
+        let bitmap = std::fs::File::open(PAGE_IDLE_BITMAP).map_err(idle_error)?;
+        let mut word: Option<(u64, u64)> = None;
+        let mut idle = Vec::new();
+        for (index, pfn) in self.present_pfns()? {
+            let bits = match word {
+                Some((at, bits)) if at == pfn / 64 => bits,
+                _ => {
+                    let mut bytes = [0u8; 8];
+                    bitmap
+                        .read_exact_at(&mut bytes, pfn / 64 * 8)
+                        .map_err(idle_error)?;
+                    let bits = u64::from_ne_bytes(bytes);
+                    word = Some((pfn / 64, bits));
+                    bits
+                }
+            };
+            if bits & (1 << (pfn % 64)) != 0 {
+                idle.push(index);
+            }
+        }
+        Ok(idle)
+    }
+    // Page index and PFN of each present page through /proc/self/pagemap.
+    fn present_pfns(&self) -> Result<Vec<(usize, u64)>, AnonymousMmapError> {
+        let mut pfns = Vec::new();
+        let mut hidden = false;
+        self.pagemap_entries(|index, entry| match entry & PM_PFN_MASK {
+            _ if entry & PM_PRESENT == 0 => {}
+            0 => hidden = true,
+            pfn => pfns.push((index, pfn)),
+        })?;
+        if hidden {
+            return Err(AnonymousMmapError::Denied(
+                "pagemap PFN",
+                std::io::Error::from_raw_os_error(libc::EPERM),
+            ));
+        }
+        Ok(pfns)
+    }
+}
+
+#[inline]
+fn write_word(bitmap: &std::fs::File, at: u64, bits: u64) -> Result<(), AnonymousMmapError> {
+    bitmap
+        .write_all_at(&bits.to_ne_bytes(), at * 8)
+        .map_err(idle_error)
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use crate::page_size;
+    use rstest::rstest;
+
+    // Idle page tracking needs root and CONFIG_IDLE_PAGE_TRACKING.
+    fn marked_or_skip(mmap: &AnonymousMmap) -> bool {
+        match mmap.mark_idle() {
+            Ok(()) => true,
+            Err(AnonymousMmapError::Unsupported("page_idle", _))
+            | Err(AnonymousMmapError::Denied(..)) => false,
+            Err(e) => panic!("mark_idle: {}", e),
+        }
+    }
+
+    #[rstest]
+    #[case(AnonymousMmap::new(page_size() * 8).unwrap(), vec![2, 5])]
+    #[case(AnonymousMmap::new_private(page_size() * 8).unwrap(), vec![0, 7])]
+    fn touched_pages_not_idle(#[case] mut mmap: AnonymousMmap, #[case] touched: Vec<usize>) {
+        let page = page_size();
+        mmap.fill(1);
+        if marked_or_skip(&mmap) {
+            assert_eq!(mmap.idle_pages().unwrap(), (0..8).collect::<Vec<_>>());
+            for index in &touched {
+                mmap.write_volatile_at(index * page, 2u8).unwrap();
+            }
+            let idle = mmap.idle_pages().unwrap();
+            assert!(touched.iter().all(|index| !idle.contains(index)));
+            assert_eq!(idle.len(), 8 - touched.len());
+        }
+        unsafe { mmap.try_drop().unwrap() };
+    }
+
+    #[test]
+    fn not_present_not_idle() {
+        let mmap = AnonymousMmap::new_unpopulated(page_size() * 4).unwrap();
+        if marked_or_skip(&mmap) {
+            assert_eq!(mmap.idle_pages().unwrap(), Vec::<usize>::new());
+        }
+    }
+
+    #[rstest]
+    #[case(libc::ENOENT, "Unsupported")]
+    #[case(libc::EACCES, "Denied")]
+    #[case(libc::EPERM, "Denied")]
+    #[case(libc::EIO, "PagemapFailed")]
+    fn idle_errors(#[case] errno: i32, #[case] expected: &str) {
+        let err = idle_error(std::io::Error::from_raw_os_error(errno));
+        assert!(format!("{:?}", err).starts_with(expected), "{:?}", err);
+        assert_eq!(err.io_error().unwrap().raw_os_error(), Some(errno));
+    }
+}
@@ -0,0 +1,155 @@
+//! pkey_mprotect(2) based memory protection keys of an [`AnonymousMmap`]
+
+use crate::{AnonymousMmap, AnonymousMmapError};
+
+pub use ypkeys::{pkey_alloc, pkey_free, pkey_get, pkey_set, PkeyRights};
+
+impl AnonymousMmap {
+    /// Tag the whole mapping with pkey from [`pkey_alloc`] through pkey_mprotect(2) keeping
+    /// the current protection and set the rights of the calling thread to the key.
+    ///
+    /// Access through the key is then switched per thread with [`pkey_set`] without any
+    /// syscall while other threads keep the default rights of the key. Kernels or CPUs
+    /// without protection keys are [`AnonymousMmapError::Unsupported`].
+    #[inline]
+    pub fn assign_pkey(
+        &self,
+        pkey: libc::c_int,
+        initial_rights: PkeyRights,
+    ) -> Result<(), AnonymousMmapError> {
+        for (start, end) in self.mapped_ranges(0, self.len) {
+            // SAFETY: [start, end) is within the mapping and still mapped.
+            unsafe { self.pkey_mprotect(start, end - start, pkey)? };
+        }
+        set_rights(pkey, initial_rights)
+    }
+    /// Tag the given page-aligned range with pkey like [`Self::assign_pkey`].
+    #[inline]
+    pub fn assign_pkey_range(
+        &self,
+        offset: usize,
+        len: usize,
+        pkey: libc::c_int,
+        initial_rights: PkeyRights,
+    ) -> Result<(), AnonymousMmapError> {
+        self.page_range(offset, len)?;
+        // SAFETY: The range is checked to be within the mapping and still mapped.
+        unsafe { self.pkey_mprotect(offset, len, pkey)? };
+        set_rights(pkey, initial_rights)
+    }
+    #[inline]
+    unsafe fn pkey_mprotect(
+        &self,
+        offset: usize,
+        len: usize,
+        pkey: libc::c_int,
+    ) -> Result<(), AnonymousMmapError> {
+        if self.sealed {
+            return Err(AnonymousMmapError::Sealed("pkey_mprotect"));
+        }
+        // SAFETY: The caller guarantees the range is within the mapping.
+        let addr = unsafe { self.as_ptr_mut().add(offset) };
+        // SAFETY: The mapping is ours and the protection stays as it is.
+        match unsafe { ypkeys::pkey_mprotect(addr, len, self.prot, pkey) } {
+            Ok(()) => Ok(()),
+            Err(e) if matches!(e.raw_os_error(), Some(libc::ENOSYS | libc::EINVAL)) => {
+                Err(AnonymousMmapError::Unsupported("pkey_mprotect", e))
+            }
+            Err(e) => Err(AnonymousMmapError::MprotectFailed(e)),
+        }
+    }
+}
+
+#[inline]
+fn set_rights(pkey: libc::c_int, rights: PkeyRights) -> Result<(), AnonymousMmapError> {
+    pkey_set(pkey, rights).map_err(|e| AnonymousMmapError::Unsupported("pkey_set", e))
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use crate::page_size;
+
+    // Machines without protection keys fail the allocation in which case there's nothing to test.
+    fn alloc_or_skip() -> Option<libc::c_int> {
+        match pkey_alloc(PkeyRights::empty()) {
+            Ok(pkey) => Some(pkey),
+            Err(e)
+                if matches!(
+                    e.raw_os_error(),
+                    Some(libc::EINVAL | libc::ENOSPC | libc::ENOSYS)
+                ) =>
+            {
+                None
+            }
+            Err(e) => panic!("pkey_alloc: {}", e),
+        }
+    }
+
+    #[test]
+    fn disabled_access_faults_in_child() {
+        let Some(pkey) = alloc_or_skip() else {
+            return;
+        };
+        let mut mmap = AnonymousMmap::new(page_size() * 2).unwrap();
+        mmap.fill(7);
+        mmap.assign_pkey(pkey, PkeyRights::empty()).unwrap();
+        assert_eq!(mmap.read_volatile_at::<u8>(0).unwrap(), 7);
+        match unsafe { libc::fork() } {
+            0 => {
+                if pkey_set(pkey, PkeyRights::DISABLE_ACCESS).is_err() {
+                    unsafe { libc::_exit(1) };
+                }
+                let _ = mmap.read_volatile_at::<u8>(page_size());
+                unsafe { libc::_exit(0) };
+            }
+            pid => {
+                assert!(pid > 0);
+                let mut status = 0;
+                assert_eq!(unsafe { libc::waitpid(pid, &mut status, 0) }, pid);
+                assert!(libc::WIFSIGNALED(status));
+                assert_eq!(libc::WTERMSIG(status), libc::SIGSEGV);
+            }
+        }
+        assert_eq!(mmap.read_volatile_at::<u8>(page_size()).unwrap(), 7);
+        unsafe { mmap.try_drop().unwrap() };
+        pkey_free(pkey).unwrap();
+    }
+
+    #[test]
+    fn disabled_write_keeps_reads() {
+        let Some(pkey) = alloc_or_skip() else {
+            return;
+        };
+        let mut mmap = AnonymousMmap::new(page_size() * 2).unwrap();
+        mmap.fill(3);
+        mmap.assign_pkey_range(page_size(), page_size(), pkey, PkeyRights::DISABLE_WRITE)
+            .unwrap();
+        assert_eq!(mmap.read_volatile_at::<u8>(page_size()).unwrap(), 3);
+        mmap.write_volatile_at::<u8>(0, 4).unwrap();
+        pkey_set(pkey, PkeyRights::empty()).unwrap();
+        mmap.write_volatile_at::<u8>(page_size(), 5).unwrap();
+        assert_eq!(mmap.read_volatile_at::<u8>(page_size()).unwrap(), 5);
+        unsafe { mmap.try_drop().unwrap() };
+        pkey_free(pkey).unwrap();
+    }
+
+    #[test]
+    fn range_not_page_aligned() {
+        let mmap = AnonymousMmap::new(page_size() * 2).unwrap();
+        assert!(matches!(
+            mmap.assign_pkey_range(1, page_size(), 1, PkeyRights::empty()),
+            Err(AnonymousMmapError::NotPageAligned(1, _))
+        ));
+    }
+
+    #[test]
+    fn unallocated_key_unsupported() {
+        let mmap = AnonymousMmap::new(page_size()).unwrap();
+        assert!(matches!(
+            mmap.assign_pkey(15, PkeyRights::empty()),
+            Err(AnonymousMmapError::Unsupported("pkey_mprotect", _))
+        ));
+    }
+}
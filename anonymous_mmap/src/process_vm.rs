@@ -0,0 +1,270 @@
+//! Cross-process memory access through process_vm_readv(2) / process_vm_writev(2)
+
+use crate::{page_size, OsError};
+use alloc::vec::Vec;
+
+/// Error accessing the memory of another process
+#[derive(Debug)]
+pub enum ProcessVmError {
+    /// ESRCH - No such process
+    NoSuchProcess(libc::pid_t),
+    /// EPERM - The caller may not ptrace the process e.g. due to kernel.yama.ptrace_scope
+    PermissionDenied(libc::pid_t),
+    /// EFAULT - The remote address range is not mapped in the process with the bytes
+    /// successfully transferred before the fault.
+    BadAddress {
+        /// Remote address where the transfer faulted
+        remote_addr: usize,
+        /// Bytes successfully transferred before the fault
+        transferred: usize,
+    },
+    /// Call to process_vm_readv / process_vm_writev failed with any other errno
+    Failed(OsError),
+}
+
+impl core::fmt::Display for ProcessVmError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::NoSuchProcess(pid) => write!(f, "No such process {}", pid),
+            Self::PermissionDenied(pid) => write!(f, "ptrace scope denies access to {}", pid),
+            Self::BadAddress {
+                remote_addr,
+                transferred,
+            } => write!(
+                f,
+                "Bad remote address {:#x} after {} bytes",
+                remote_addr, transferred
+            ),
+            Self::Failed(e) => write!(f, "process_vm Failed: {}", e),
+        }
+    }
+}
+
+impl core::error::Error for ProcessVmError {}
+
+type ProcessVmFn = unsafe extern "C" fn(
+    libc::pid_t,
+    *const libc::iovec,
+    libc::c_ulong,
+    *const libc::iovec,
+    libc::c_ulong,
+    libc::c_ulong,
+) -> libc::ssize_t;
+
+#[inline]
+fn iov_max() -> usize {
+    // SAFETY: sysconf has no preconditions.
+    match unsafe { libc::sysconf(libc::_SC_IOV_MAX) } {
+        n if n > 0 => n as usize,
+        _ => 1024,
+    }
+}
+
+// Partial transfers happen at the granularity of the remote iovecs so the remote range is split
+// at page boundaries letting the transfer progress up to the faulting page.
+#[inline]
+fn transfer(
+    pid: libc::pid_t,
+    remote_addr: usize,
+    local: *mut u8,
+    len: usize,
+    f: ProcessVmFn,
+) -> Result<usize, ProcessVmError> {
+    let page_size = page_size();
+    let iov_max = iov_max();
+    let mut remote_iovs: Vec<libc::iovec> = Vec::with_capacity(iov_max);
+    let mut done = 0;
+    while done < len {
+        remote_iovs.clear();
+        let mut batch = 0;
+        while done + batch < len && remote_iovs.len() < iov_max {
+            let at = remote_addr + done + batch;
+            let chunk = (page_size - at % page_size).min(len - done - batch);
+            remote_iovs.push(libc::iovec {
+                iov_base: at as *mut libc::c_void,
+                iov_len: chunk,
+            });
+            batch += chunk;
+        }
+        let local_iov = libc::iovec {
+            // SAFETY: done < len within the local buffer.
+            iov_base: unsafe { local.add(done) }.cast(),
+            iov_len: batch,
+        };
+        // SAFETY: The iovecs are valid for the duration of the call and the local is ours.
+        let r = unsafe {
+            f(
+                pid,
+                &local_iov,
+                1,
+                remote_iovs.as_ptr(),
+                remote_iovs.len() as libc::c_ulong,
+                0,
+            )
+        };
+        if r < 0 {
+            let os_err = OsError::last_os_error();
+            return Err(match os_err.raw_os_error() {
+                Some(libc::ESRCH) => ProcessVmError::NoSuchProcess(pid),
+                Some(libc::EPERM) => ProcessVmError::PermissionDenied(pid),
+                Some(libc::EFAULT) => ProcessVmError::BadAddress {
+                    remote_addr: remote_addr + done,
+                    transferred: done,
+                },
+                _ => ProcessVmError::Failed(os_err),
+            });
+        }
+        if r == 0 {
+            return Err(ProcessVmError::BadAddress {
+                remote_addr: remote_addr + done,
+                transferred: done,
+            });
+        }
+        done += r as usize;
+    }
+    Ok(done)
+}
+
+/// Read the memory of the process at the remote address into local looping over partial
+/// transfers. Returns the bytes read which is local.len() on success.
+#[inline]
+pub fn read_process_memory(
+    pid: libc::pid_t,
+    remote_addr: usize,
+    local: &mut [u8],
+) -> Result<usize, ProcessVmError> {
+    transfer(
+        pid,
+        remote_addr,
+        local.as_mut_ptr(),
+        local.len(),
+        libc::process_vm_readv,
+    )
+}
+
+/// Write the data into the memory of the process at the remote address looping over partial
+/// transfers. Returns the bytes written which is data.len() on success.
+#[inline]
+pub fn write_process_memory(
+    pid: libc::pid_t,
+    remote_addr: usize,
+    data: &[u8],
+) -> Result<usize, ProcessVmError> {
+    // process_vm_writev only reads from the local iovec.
+    transfer(
+        pid,
+        remote_addr,
+        data.as_ptr() as *mut u8,
+        data.len(),
+        libc::process_vm_writev,
+    )
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+
+    use super::*;
+    use crate::AnonymousMmap;
+
+    struct Child(libc::pid_t);
+
+    impl Drop for Child {
+        fn drop(&mut self) {
+            unsafe {
+                libc::kill(self.0, libc::SIGKILL);
+                libc::waitpid(self.0, core::ptr::null_mut(), 0);
+            }
+        }
+    }
+
+    // Fork a child which fills its copy of the private mapping with the byte and then waits.
+    fn fork_filling(mmap: &AnonymousMmap, byte: u8) -> Child {
+        let mut fds = [0; 2];
+        assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+        match unsafe { libc::fork() } {
+            0 => unsafe {
+                core::ptr::write_bytes(mmap.as_ptr_mut().cast::<u8>(), byte, mmap.len);
+                libc::write(fds[1], [1u8].as_ptr().cast(), 1);
+                loop {
+                    libc::pause();
+                }
+            },
+            pid => {
+                assert!(pid > 0);
+                let mut ready = [0u8];
+                assert_eq!(
+                    unsafe { libc::read(fds[0], ready.as_mut_ptr().cast(), 1) },
+                    1
+                );
+                unsafe {
+                    libc::close(fds[0]);
+                    libc::close(fds[1]);
+                }
+                Child(pid)
+            }
+        }
+    }
+
+    #[test]
+    fn read_write_forked_child() {
+        let len = page_size() * 3;
+        let mmap = AnonymousMmap::new_private(len).unwrap();
+        let child = fork_filling(&mmap, 0xC1);
+
+        let mut local = vec![0u8; len];
+        assert_eq!(
+            read_process_memory(child.0, mmap.start_addr(), &mut local).unwrap(),
+            len
+        );
+        assert!(local.iter().all(|b| *b == 0xC1));
+        assert!(mmap.view(..).unwrap().as_slice().iter().all(|b| *b == 0));
+
+        let data = vec![0x7Eu8; len - 100];
+        assert_eq!(
+            write_process_memory(child.0, mmap.start_addr() + 50, &data).unwrap(),
+            len - 100
+        );
+        read_process_memory(child.0, mmap.start_addr(), &mut local).unwrap();
+        assert!(local[..50].iter().all(|b| *b == 0xC1));
+        assert!(local[50..len - 50].iter().all(|b| *b == 0x7E));
+        assert!(local[len - 50..].iter().all(|b| *b == 0xC1));
+        assert!(mmap.view(..).unwrap().as_slice().iter().all(|b| *b == 0));
+    }
+
+    #[test]
+    fn bad_address_partial() {
+        let page = page_size();
+        let mmap = AnonymousMmap::new_private(page * 2).unwrap();
+        let (head, tail) = mmap.split_off(page).unwrap();
+        let tail_addr = tail.start_addr();
+        unsafe { tail.try_drop().unwrap() };
+
+        let mut local = vec![0u8; page * 2];
+        match read_process_memory(unsafe { libc::getpid() }, head.start_addr(), &mut local) {
+            Err(ProcessVmError::BadAddress {
+                remote_addr,
+                transferred,
+            }) => {
+                assert_eq!(remote_addr, tail_addr);
+                assert_eq!(transferred, page);
+            }
+            r => panic!("Expected BadAddress, got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn no_such_process() {
+        let pid = match unsafe { libc::fork() } {
+            0 => unsafe { libc::_exit(0) },
+            pid => {
+                unsafe { libc::waitpid(pid, core::ptr::null_mut(), 0) };
+                pid
+            }
+        };
+        let mut local = [0u8; 8];
+        assert!(matches!(
+            read_process_memory(pid, 0x1000, &mut local),
+            Err(ProcessVmError::NoSuchProcess(p)) if p == pid
+        ));
+    }
+}
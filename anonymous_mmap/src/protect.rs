@@ -0,0 +1,111 @@
+//! mprotect(2) based protection of the whole mapping
+
+use crate::{AnonymousMmap, AnonymousMmapError, OsError};
+
+impl AnonymousMmap {
+    #[inline]
+    fn mprotect(&mut self, prot: libc::c_int) -> Result<(), AnonymousMmapError> {
+        if self.sealed {
+            return Err(AnonymousMmapError::Sealed("mprotect"));
+        }
+        for (start, end) in self.mapped_ranges(0, self.len) {
+            // SAFETY: The mapping is ours and borrowed exclusively so no views exist.
+            if unsafe { libc::mprotect(self.as_ptr_mut().add(start), end - start, prot) } != 0 {
+                let os_err = OsError::last_os_error();
+                return Err(AnonymousMmapError::MprotectFailed(os_err));
+            }
+        }
+        self.prot = prot;
+        Ok(())
+    }
+    /// Make the whole mapping read-only with PROT_READ.
+    /// [`Self::view_mut`] and the bulk writes are refused until [`Self::protect_readwrite`].
+    #[inline]
+    pub fn protect_readonly(&mut self) -> Result<(), AnonymousMmapError> {
+        self.mprotect(libc::PROT_READ)
+    }
+    /// Make the whole mapping inaccessible with PROT_NONE.
+    /// Any view is refused until [`Self::protect_readonly`] or [`Self::protect_readwrite`].
+    #[inline]
+    pub fn protect_none(&mut self) -> Result<(), AnonymousMmapError> {
+        self.mprotect(libc::PROT_NONE)
+    }
+    /// Restore the full PROT_READ | PROT_WRITE access of the whole mapping.
+    ///
+    /// Fails with EACCES e.g. for a read-only snapshot of a sealed memfd.
+    #[inline]
+    pub fn protect_readwrite(&mut self) -> Result<(), AnonymousMmapError> {
+        self.mprotect(libc::PROT_READ | libc::PROT_WRITE)
+    }
+    /// The mapping is currently PROT_READ
+    #[inline]
+    pub fn is_readable(&self) -> bool {
+        self.prot & libc::PROT_READ != 0
+    }
+    /// The mapping is currently PROT_READ | PROT_WRITE
+    #[inline]
+    pub fn is_writable(&self) -> bool {
+        self.prot & (libc::PROT_READ | libc::PROT_WRITE) == libc::PROT_READ | libc::PROT_WRITE
+    }
+    #[inline]
+    pub(crate) fn assert_writable(&self) {
+        assert!(
+            self.is_writable(),
+            "mapping protection {:#x} is not writable",
+            self.prot
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use crate::ErrorKind;
+
+    #[test]
+    fn protect_cycle() {
+        let mut mmap = AnonymousMmap::new(8192).unwrap();
+        mmap.fill(9);
+
+        mmap.protect_none().unwrap();
+        assert!(!mmap.is_readable() && !mmap.is_writable());
+        assert!(mmap.view(..).is_none());
+        assert!(mmap.view_mut(..).is_none());
+        assert!(matches!(
+            mmap.try_clone(),
+            Err(AnonymousMmapError::Protected(libc::PROT_NONE))
+        ));
+
+        mmap.protect_readonly().unwrap();
+        assert!(mmap.is_readable() && !mmap.is_writable());
+        assert!(mmap.view(..).unwrap().as_slice().iter().all(|b| *b == 9));
+        assert!(mmap.view_mut(..).is_none());
+        #[cfg(feature = "std")]
+        assert!(mmap.as_io_slice_mut(0, 1).is_err());
+
+        mmap.protect_readwrite().unwrap();
+        assert!(mmap.is_writable());
+        mmap.view_mut(..1).unwrap().as_slice_mut()[0] = 10;
+        assert_eq!(mmap.view(..1).unwrap().as_slice(), &[10]);
+        unsafe { mmap.try_drop().unwrap() };
+    }
+
+    #[test]
+    #[should_panic(expected = "not writable")]
+    fn fill_readonly_panics() {
+        let mut mmap = AnonymousMmap::new(4096).unwrap();
+        mmap.protect_readonly().unwrap();
+        mmap.fill(1);
+    }
+
+    #[test]
+    fn sealed_snapshot_stays_readonly() {
+        let mut live = AnonymousMmap::new_memfd(4096).unwrap();
+        let mut snapshot = live.snapshot_cow().unwrap();
+        assert!(snapshot.is_readable() && !snapshot.is_writable());
+        assert!(snapshot.view_mut(..).is_none());
+        let err = snapshot.protect_readwrite().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+    }
+}
@@ -0,0 +1,121 @@
+//! Dismantling an [`AnonymousMmap`] into and from raw parts
+
+use crate::{accounting, page_size, AnonymousMmap, AnonymousMmapError};
+use alloc::vec::Vec;
+
+impl AnonymousMmap {
+    /// Give up the ownership of the mapping without unmapping it returning the whole of it as
+    /// a slice living for the rest of the program unless unmapped manually with munmap(2).
+    ///
+    /// Any guard pages below the mapping stay mapped and the memfd backing if any is closed.
+    /// A mapping which is not readable and writable can not be handed out as a mut slice and
+    /// is [`AnonymousMmapError::Protected`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if part of the mapping was unmapped through [`Self::unmap_range`].
+    #[inline]
+    pub fn leak(self) -> Result<&'static mut [u8], AnonymousMmapError> {
+        assert!(
+            self.holes.is_empty(),
+            "can not leak a partially unmapped mapping"
+        );
+        if !self.is_writable() {
+            return Err(AnonymousMmapError::Protected(self.prot));
+        }
+        let (ptr, len) = self.into_raw();
+        // SAFETY: The mapping is never unmapped through us again and is len bytes read-write.
+        Ok(unsafe { core::slice::from_raw_parts_mut(ptr.cast::<u8>(), len) })
+    }
+    /// Give up the ownership of the mapping without unmapping it returning the start ptr and len
+    /// e.g. for handing the buffer over to a C library or later [`Self::from_raw`].
+    ///
+    /// Any guard pages below the mapping stay mapped and the memfd backing if any is closed.
+    /// Ranges unmapped through [`Self::unmap_range`] are not recorded in the raw parts.
+    #[inline]
+    pub fn into_raw(self) -> (*mut libc::c_void, usize) {
+        accounting::released(&self);
+        (self.addr.as_ptr(), self.len)
+    }
+    /// Reconstitute the AnonymousMmap from the raw parts e.g. for a later [`Self::try_drop`].
+    ///
+    /// The mapping is assumed MAP_SHARED which is what [`Self::try_clone`] would create.
+    ///
+    /// # Safety
+    ///
+    /// ptr and len must describe a single PROT_READ | PROT_WRITE mmap(2) mapping e.g. from
+    /// [`Self::into_raw`] or [`Self::leak`] which nobody else owns or unmaps. ptr must be
+    /// non-null and page-aligned. The protection is not queried but assumed read-write, parts
+    /// given e.g. PROT_READ through mprotect(2) after [`Self::into_raw`] have to be restored
+    /// first.
+    #[inline]
+    pub unsafe fn from_raw(ptr: *mut libc::c_void, len: usize) -> AnonymousMmap {
+        debug_assert!((ptr as usize).is_multiple_of(page_size()));
+        let mut mmap = AnonymousMmap {
+            // SAFETY: The caller guarantees ptr is a mapping.
+            addr: unsafe { core::ptr::NonNull::new_unchecked(ptr) },
+            len,
+            guard: 0,
+            memfd: None,
+            flags: libc::MAP_ANONYMOUS | libc::MAP_SHARED,
+            prot: libc::PROT_READ | libc::PROT_WRITE,
+            holes: Vec::new(),
+            sealed: false,
+            #[cfg(feature = "leak_backtraces")]
+            id: 0,
+        };
+        accounting::created(&mut mmap);
+        mmap
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn into_from_raw_roundtrip() {
+        let mut mmap = AnonymousMmap::new(8192).unwrap();
+        mmap.view_mut(..).unwrap().as_slice_mut().fill(7);
+        let addr = mmap.start_addr();
+        let (ptr, len) = mmap.into_raw();
+        assert_eq!((ptr as usize, len), (addr, 8192));
+
+        let mmap = unsafe { AnonymousMmap::from_raw(ptr, len) };
+        assert_eq!(mmap.start_addr(), addr);
+        assert!(mmap.view(..).unwrap().as_slice().iter().all(|b| *b == 7));
+        unsafe { mmap.try_drop().unwrap() };
+    }
+
+    #[test]
+    fn leak_then_munmap() {
+        let mmap = AnonymousMmap::new(4096).unwrap();
+        let leaked = mmap.leak().unwrap();
+        leaked.fill(1);
+        assert_eq!(leaked.len(), 4096);
+        let (ptr, len) = (leaked.as_mut_ptr(), leaked.len());
+        assert_eq!(unsafe { libc::munmap(ptr.cast(), len) }, 0);
+    }
+
+    #[test]
+    fn leak_readonly_protected() {
+        let mut mmap = AnonymousMmap::new(4096).unwrap();
+        mmap.protect_readonly().unwrap();
+        let addr = mmap.start_addr();
+        assert!(matches!(
+            mmap.leak(),
+            Err(AnonymousMmapError::Protected(libc::PROT_READ))
+        ));
+        assert_eq!(unsafe { libc::munmap(addr as *mut libc::c_void, 4096) }, 0);
+    }
+
+    #[test]
+    #[should_panic]
+    #[cfg(debug_assertions)]
+    fn from_raw_not_page_aligned() {
+        let mmap = AnonymousMmap::new(4096).unwrap();
+        let (ptr, _) = mmap.into_raw();
+        let _ = unsafe { AnonymousMmap::from_raw(ptr.cast::<u8>().add(1).cast(), 1) };
+    }
+}
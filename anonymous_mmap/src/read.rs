@@ -0,0 +1,163 @@
+//! Filling a new [`AnonymousMmap`] from a reader or a file
+
+use crate::{AnonymousMmap, AnonymousMmapError, OsError};
+use yown_fd::{AsRawFd, BorrowedFd};
+
+impl AnonymousMmap {
+    /// Map len bytes and read into them from r until EOF or the mapping is full, returning
+    /// the mapping along the bytes read. The rest past a short input stays zero-filled.
+    ///
+    /// Reads interrupted with EINTR are retried. Any other read error unmaps the mapping
+    /// and is [`AnonymousMmapError::ReadFailed`].
+    #[cfg(feature = "std")]
+    #[inline]
+    pub fn from_reader(
+        mut r: impl std::io::Read,
+        len: usize,
+    ) -> Result<(Self, usize), AnonymousMmapError> {
+        let mmap = Self::new(len)?;
+        let p = mmap.addr.as_ptr().cast::<u8>();
+        // SAFETY: The new mapping is writable and borrowed exclusively.
+        let buf = unsafe { core::slice::from_raw_parts_mut(p, len) };
+        let mut filled = 0;
+        while filled < len {
+            match r.read(&mut buf[filled..]) {
+                Ok(0) => break,
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(fail_unmapped(mmap, e)),
+            }
+        }
+        Ok((mmap, filled))
+    }
+    /// Like [`Self::from_reader`] through pread(2) from the given file offset on which
+    /// leaves the file position of fd untouched as opposed to read(2).
+    #[inline]
+    pub fn from_file_pread(
+        fd: BorrowedFd<'_>,
+        offset: u64,
+        len: usize,
+    ) -> Result<(Self, usize), AnonymousMmapError> {
+        let mmap = Self::new(len)?;
+        let p = mmap.addr.as_ptr().cast::<u8>();
+        let mut filled = 0;
+        while filled < len {
+            let pos = match offset.checked_add(filled as u64).map(libc::off_t::try_from) {
+                Some(Ok(pos)) => pos,
+                _ => {
+                    let e = OsError::from_raw_os_error(libc::EINVAL);
+                    return Err(fail_unmapped(mmap, e));
+                }
+            };
+            // SAFETY: [filled, len) is within the new mapping which is borrowed exclusively.
+            let buf = unsafe { p.add(filled) }.cast();
+            // SAFETY: buf is valid for len - filled bytes.
+            let r = unsafe { libc::pread(fd.as_raw_fd(), buf, len - filled, pos) };
+            match r {
+                0 => break,
+                r if r > 0 => filled += r as usize,
+                _ => {
+                    let os_err = OsError::last_os_error();
+                    if os_err.raw_os_error() == Some(libc::EINTR) {
+                        continue;
+                    }
+                    return Err(fail_unmapped(mmap, os_err));
+                }
+            }
+        }
+        Ok((mmap, filled))
+    }
+}
+
+#[inline]
+fn fail_unmapped(mmap: AnonymousMmap, e: OsError) -> AnonymousMmapError {
+    // SAFETY: Nothing has been handed out from the mapping.
+    // The read error is what matters, nothing to give the munmap error back to.
+    let _ = unsafe { mmap.try_drop() };
+    AnonymousMmapError::ReadFailed(e)
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+
+    use super::*;
+    use crate::page_size;
+    use rstest::rstest;
+    use std::io::{Seek, Write};
+    use yown_fd::AsFd;
+
+    struct FailAfter(usize);
+
+    impl std::io::Read for FailAfter {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            if self.0 == 0 {
+                return Err(OsError::from_raw_os_error(libc::EIO));
+            }
+            let n = buf.len().min(self.0).min(100);
+            buf[..n].fill(1);
+            self.0 -= n;
+            Ok(n)
+        }
+    }
+
+    #[rstest]
+    #[case(0, 4096)]
+    #[case(100, 4096)]
+    #[case(4096, 4096)]
+    #[case(10000, 4096)]
+    fn from_slice_reader(#[case] input: usize, #[case] len: usize) {
+        let data: Vec<u8> = (0..input).map(|i| i as u8).collect();
+        let (mmap, read) = AnonymousMmap::from_reader(data.as_slice(), len).unwrap();
+        assert_eq!(read, input.min(len));
+        let view = mmap.view(..).unwrap();
+        assert_eq!(&view.as_slice()[..read], &data[..read]);
+        assert!(view.as_slice()[read..].iter().all(|b| *b == 0));
+        unsafe { mmap.try_drop().unwrap() };
+    }
+
+    #[test]
+    fn reader_error_midway() {
+        let err = AnonymousMmap::from_reader(FailAfter(250), page_size()).unwrap_err();
+        let AnonymousMmapError::ReadFailed(e) = err else {
+            panic!("{}", err);
+        };
+        assert_eq!(e.raw_os_error(), Some(libc::EIO));
+    }
+
+    #[rstest]
+    #[case(0, 8192, 8192)]
+    #[case(4096, 8192, 4096)]
+    #[case(8192, 4096, 0)]
+    fn pread_keeps_position(#[case] offset: u64, #[case] len: usize, #[case] expected: usize) {
+        let path = std::env::temp_dir().join(format!(
+            "anonymous-mmap-pread-{}-{}",
+            std::process::id(),
+            offset
+        ));
+        let mut file = std::fs::File::create_new(&path).unwrap();
+        let data: Vec<u8> = (0..8192).map(|i| (i / 16) as u8).collect();
+        file.write_all(&data).unwrap();
+        let r = AnonymousMmap::from_file_pread(file.as_fd(), offset, len);
+        let pos = file.stream_position().unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let (mmap, read) = r.unwrap();
+        assert_eq!(read, expected);
+        assert_eq!(pos, 8192);
+        let start = offset as usize;
+        assert_eq!(
+            mmap.view(..read).unwrap().as_slice(),
+            &data[start..start + read]
+        );
+        unsafe { mmap.try_drop().unwrap() };
+    }
+
+    #[test]
+    fn pread_write_only_fd() {
+        let file = std::fs::File::create("/dev/null").unwrap();
+        let err = AnonymousMmap::from_file_pread(file.as_fd(), 0, 4096).unwrap_err();
+        let AnonymousMmapError::ReadFailed(e) = err else {
+            panic!("{}", err);
+        };
+        assert_eq!(e.raw_os_error(), Some(libc::EBADF));
+    }
+}
@@ -0,0 +1,209 @@
+//! mremap(2) based resizing and relocation of the mapping
+
+use crate::{accounting, page_size, AnonymousMmap, AnonymousMmapError, OsError};
+
+/// Is [addr, addr + len) free of any mapping of the process according to /proc/self/maps,
+/// e.g. as a dry-run before [`AnonymousMmap::relocate_to`] which discards whatever is there.
+///
+/// This is only a snapshot and any other thread may map into the range right after.
+#[cfg(feature = "std")]
+#[inline]
+pub fn is_range_free(addr: usize, len: usize) -> std::io::Result<bool> {
+    let maps = std::fs::read_to_string("/proc/self/maps")?;
+    let end = addr.saturating_add(len);
+    Ok(!maps.lines().any(|line| {
+        let range = line
+            .split_whitespace()
+            .next()
+            .and_then(|r| r.split_once('-'));
+        match range.map(|(s, e)| (usize::from_str_radix(s, 16), usize::from_str_radix(e, 16))) {
+            Some((Ok(vma_start), Ok(vma_end))) => vma_start < end && addr < vma_end,
+            _ => false,
+        }
+    }))
+}
+
+impl AnonymousMmap {
+    /// Resize the mapping to new_len through mremap(2) without copying the contents - the page
+    /// tables are moved instead when the mapping can not grow in place and may_move allows it.
+    ///
+    /// Growing pages read back zero-filled. Without may_move growing fails with ENOMEM when
+    /// the following address range is occupied, shrinking never moves.
+    ///
+    /// Mappings with guard pages, memfd backing or ranges unmapped through
+    /// [`Self::unmap_range`] can not be resized and are
+    /// [`AnonymousMmapError::Unsupported`].
+    ///
+    /// # Safety
+    ///
+    /// No pointers previously handed out may be used after the mapping moved or shrunk.
+    #[inline]
+    pub unsafe fn remap(
+        &mut self,
+        new_len: usize,
+        may_move: bool,
+    ) -> Result<(), AnonymousMmapError> {
+        if self.sealed {
+            return Err(AnonymousMmapError::Sealed("mremap"));
+        }
+        if self.guard != 0 || self.memfd.is_some() || !self.holes.is_empty() {
+            return Err(AnonymousMmapError::Unsupported(
+                "mremap",
+                OsError::from_raw_os_error(libc::EINVAL),
+            ));
+        }
+        let flags = match may_move {
+            true => libc::MREMAP_MAYMOVE,
+            false => 0,
+        };
+        // SAFETY: The mapping is ours and borrowed exclusively.
+        let p = unsafe { libc::mremap(self.as_ptr_mut(), self.len, new_len, flags) };
+        if p == libc::MAP_FAILED {
+            let os_err = OsError::last_os_error();
+            return Err(AnonymousMmapError::MremapFailed(os_err));
+        }
+        // SAFETY: We've checked the error
+        self.addr = unsafe { core::ptr::NonNull::new_unchecked(p) };
+        accounting::resized(self.len, new_len);
+        self.len = new_len;
+        Ok(())
+    }
+    /// Move the whole mapping to the given page-aligned address through mremap(2) with
+    /// MREMAP_FIXED | MREMAP_MAYMOVE without copying the contents, e.g. for a deterministic
+    /// address space layout.
+    ///
+    /// Any existing mapping in the destination range is atomically replaced and its contents
+    /// discarded - check with [`is_range_free`] first when the range is not already owned.
+    /// A destination overlapping the mapping itself is [`AnonymousMmapError::Overlapping`].
+    ///
+    /// Mappings with guard pages or ranges unmapped through [`Self::unmap_range`] can not be
+    /// relocated and are [`AnonymousMmapError::Unsupported`].
+    ///
+    /// # Safety
+    ///
+    /// No pointers previously handed out may be used after the mapping moved and nothing
+    /// may still be using a mapping in the destination range.
+    #[inline]
+    pub unsafe fn relocate_to(&mut self, new_addr: usize) -> Result<(), AnonymousMmapError> {
+        if self.sealed {
+            return Err(AnonymousMmapError::Sealed("mremap"));
+        }
+        if self.guard != 0 || !self.holes.is_empty() {
+            return Err(AnonymousMmapError::Unsupported(
+                "mremap",
+                OsError::from_raw_os_error(libc::EINVAL),
+            ));
+        }
+        let page_size = page_size();
+        if !new_addr.is_multiple_of(page_size) {
+            return Err(AnonymousMmapError::NotPageAligned(new_addr, page_size));
+        }
+        let start = self.start_addr();
+        let mapped_len = self.len.next_multiple_of(page_size);
+        match new_addr.checked_add(mapped_len) {
+            Some(new_end) if new_end <= start || start + mapped_len <= new_addr => {}
+            _ => return Err(AnonymousMmapError::Overlapping(new_addr, start)),
+        }
+        // SAFETY: The mapping is ours and borrowed exclusively while the caller guarantees
+        //         the destination is not in use.
+        let p = unsafe {
+            libc::mremap(
+                self.as_ptr_mut(),
+                self.len,
+                self.len,
+                libc::MREMAP_FIXED | libc::MREMAP_MAYMOVE,
+                new_addr as *mut libc::c_void,
+            )
+        };
+        if p == libc::MAP_FAILED {
+            let os_err = OsError::last_os_error();
+            return Err(AnonymousMmapError::MremapFailed(os_err));
+        }
+        // SAFETY: We've checked the error
+        self.addr = unsafe { core::ptr::NonNull::new_unchecked(p) };
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+
+    use super::*;
+    use rstest::rstest;
+
+    #[test]
+    fn grow_keeps_contents() {
+        let page = page_size();
+        let mut mmap = AnonymousMmap::new_private(page).unwrap();
+        mmap.fill(3);
+        unsafe { mmap.remap(page * 4, true).unwrap() };
+        let view = mmap.view(..).unwrap();
+        assert_eq!(view.len(), page * 4);
+        assert!(view.as_slice()[..page].iter().all(|b| *b == 3));
+        assert!(view.as_slice()[page..].iter().all(|b| *b == 0));
+        unsafe { mmap.try_drop().unwrap() };
+    }
+
+    #[test]
+    fn shrink_in_place() {
+        let page = page_size();
+        let mut mmap = AnonymousMmap::new(page * 4).unwrap();
+        let addr = mmap.start_addr();
+        unsafe { mmap.remap(page, false).unwrap() };
+        assert_eq!((mmap.start_addr(), mmap.len), (addr, page));
+        unsafe { mmap.try_drop().unwrap() };
+    }
+
+    #[test]
+    fn stack_unsupported() {
+        let mut stack = AnonymousMmap::new_stack(page_size()).unwrap();
+        assert!(matches!(
+            unsafe { stack.remap(page_size() * 2, true) },
+            Err(AnonymousMmapError::Unsupported("mremap", _))
+        ));
+    }
+
+    #[test]
+    fn relocate_over_owned_mapping() {
+        let page = page_size();
+        let mut mmap = AnonymousMmap::new(page * 2).unwrap();
+        mmap.fill(5);
+        let old = AnonymousMmap::new(page * 2).unwrap();
+        // The destination is replaced by the relocation so it must not be unmapped after.
+        let (dest, _) = old.into_raw();
+        let dest = dest as usize;
+        assert!(!is_range_free(dest, page * 2).unwrap());
+        unsafe { mmap.relocate_to(dest).unwrap() };
+        assert_eq!(mmap.start_addr(), dest);
+        assert!(mmap.view(..).unwrap().as_slice().iter().all(|b| *b == 5));
+        unsafe { mmap.try_drop().unwrap() };
+    }
+
+    #[rstest]
+    #[case(0)]
+    #[case(1)]
+    #[case(2)]
+    fn relocate_overlapping(#[case] pages: usize) {
+        let page = page_size();
+        let mut mmap = AnonymousMmap::new(page * 3).unwrap();
+        let start = mmap.start_addr();
+        let dest = start - page + pages * page;
+        assert!(matches!(
+            unsafe { mmap.relocate_to(dest) },
+            Err(AnonymousMmapError::Overlapping(d, s)) if d == dest && s == start
+        ));
+        assert!(matches!(
+            unsafe { mmap.relocate_to(start + 1) },
+            Err(AnonymousMmapError::NotPageAligned(..))
+        ));
+        unsafe { mmap.try_drop().unwrap() };
+    }
+
+    #[test]
+    fn null_page_is_free() {
+        assert!(is_range_free(0, page_size()).unwrap());
+        let mmap = AnonymousMmap::new(page_size()).unwrap();
+        assert!(!is_range_free(mmap.start_addr() + 1, 1).unwrap());
+        unsafe { mmap.try_drop().unwrap() };
+    }
+}
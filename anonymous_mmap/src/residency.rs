@@ -0,0 +1,175 @@
+//! mincore(2) and /proc/self/smaps based residency of an [`AnonymousMmap`]
+
+use crate::{page_size, AnonymousMmap, AnonymousMmapError, OsError};
+use alloc::vec::Vec;
+
+/// Memory accounting of the mapping from /proc/self/smaps in bytes
+#[cfg(feature = "std")]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct SmapsStats {
+    /// Resident set size
+    pub rss: usize,
+    /// Proportional set size with the pages shared with other processes divided among them
+    pub pss: usize,
+    /// Resident in transparent huge pages
+    pub anon_huge_pages: usize,
+    /// Swapped out
+    pub swap: usize,
+    /// Locked in memory
+    pub locked: usize,
+}
+
+#[cfg(feature = "std")]
+impl SmapsStats {
+    #[inline]
+    fn add_field(&mut self, name: &str, kb: usize) {
+        let field = match name {
+            "Rss" => &mut self.rss,
+            "Pss" => &mut self.pss,
+            "AnonHugePages" => &mut self.anon_huge_pages,
+            "Swap" => &mut self.swap,
+            "Locked" => &mut self.locked,
+            _ => return,
+        };
+        *field += kb * 1024;
+    }
+}
+
+// Sum the fields of every VMA overlapping [start, end) as the kernel may have split the
+// mapping into several.
+#[cfg(feature = "std")]
+fn parse_smaps(smaps: &str, start: usize, end: usize) -> SmapsStats {
+    let mut stats = SmapsStats::default();
+    let mut within = false;
+    for line in smaps.lines() {
+        let mut parts = line.split_whitespace();
+        let first = match parts.next() {
+            Some(first) => first,
+            None => continue,
+        };
+        if let Some(name) = first.strip_suffix(':') {
+            if within {
+                if let Some(Ok(kb)) = parts.next().map(str::parse::<usize>) {
+                    stats.add_field(name, kb);
+                }
+            }
+            continue;
+        }
+        // VMA header of start-end perms offset dev inode path.
+        within = match first
+            .split_once('-')
+            .map(|(s, e)| (usize::from_str_radix(s, 16), usize::from_str_radix(e, 16)))
+        {
+            Some((Ok(vma_start), Ok(vma_end))) => vma_start < end && start < vma_end,
+            _ => false,
+        };
+    }
+    stats
+}
+
+impl AnonymousMmap {
+    /// Bytes of the mapping currently resident in memory through mincore(2) in page granularity.
+    /// Ranges unmapped through [`Self::unmap_range`] are not resident.
+    #[inline]
+    pub fn resident_bytes(&self) -> Result<usize, AnonymousMmapError> {
+        let page = page_size();
+        let mut resident = 0;
+        let mut vec = Vec::new();
+        for (start, end) in self.mapped_ranges(0, self.len) {
+            let len = end - start;
+            vec.clear();
+            vec.resize(len.div_ceil(page), 0u8);
+            // SAFETY: The range is mapped and vec holds a byte per page of it.
+            let r = unsafe { libc::mincore(self.as_ptr_mut().add(start), len, vec.as_mut_ptr()) };
+            if r != 0 {
+                let os_err = OsError::last_os_error();
+                return Err(AnonymousMmapError::ResidencyFailed(os_err));
+            }
+            resident += vec.iter().filter(|v| *v & 1 != 0).count() * page;
+        }
+        Ok(resident)
+    }
+    /// Rss, Pss, AnonHugePages, Swap and Locked of the mapping summed over the VMAs of its
+    /// address range in /proc/self/smaps.
+    ///
+    /// A VMA the kernel merged with an adjacent mapping of the same flags is counted as a whole.
+    #[cfg(feature = "std")]
+    #[inline]
+    pub fn smaps_stats(&self) -> Result<SmapsStats, AnonymousMmapError> {
+        let smaps = std::fs::read_to_string("/proc/self/smaps")
+            .map_err(AnonymousMmapError::ResidencyFailed)?;
+        let start = self.start_addr();
+        Ok(parse_smaps(&smaps, start, start + self.len))
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+
+    use super::*;
+    use rstest::rstest;
+
+    const SMAPS: &str = "\
+1000-3000 rw-s 00000000 00:01 42                         /dev/zero (deleted)
+Size:                  8 kB
+Rss:                   4 kB
+Pss:                   2 kB
+Swap:                  0 kB
+Locked:                4 kB
+VmFlags: rd wr sh mr mw me ms sd
+3000-4000 rw-s 00002000 00:01 42                         /dev/zero (deleted)
+Rss:                   4 kB
+Pss:                   4 kB
+AnonHugePages:         0 kB
+Swap:                  4 kB
+VmFlags: rd wr sh mr mw me ms dc sd
+4000-5000 rw-p 00000000 00:00 0
+Rss:                   4 kB
+";
+
+    #[rstest]
+    #[case(0x1000, 0x4000, SmapsStats { rss: 8192, pss: 6144, anon_huge_pages: 0, swap: 4096, locked: 4096 })]
+    #[case(0x3000, 0x3800, SmapsStats { rss: 4096, pss: 4096, anon_huge_pages: 0, swap: 4096, locked: 0 })]
+    #[case(0x5000, 0x6000, SmapsStats::default())]
+    fn parse_overlapping_vmas(
+        #[case] start: usize,
+        #[case] end: usize,
+        #[case] expected: SmapsStats,
+    ) {
+        assert_eq!(parse_smaps(SMAPS, start, end), expected);
+    }
+
+    #[test]
+    fn half_resident() {
+        const PAGES: usize = 64;
+        let page = page_size();
+        let mut mmap = AnonymousMmap::new_unpopulated(page * PAGES).unwrap();
+        assert_eq!(mmap.resident_bytes().unwrap(), 0);
+        {
+            let mut view = mmap.view_mut(..).unwrap();
+            for index in 0..PAGES / 2 {
+                view.as_slice_mut()[index * page] = 1;
+            }
+        }
+        let resident = mmap.resident_bytes().unwrap();
+        assert!(resident >= page * PAGES / 2 && resident < page * PAGES);
+
+        // Split into three VMAs which are all summed up.
+        mmap.madvise(page * 8, page * 8, libc::MADV_DONTFORK)
+            .unwrap();
+        let stats = mmap.smaps_stats().unwrap();
+        assert_eq!(stats.rss, resident);
+        assert!(stats.pss <= stats.rss);
+        unsafe { mmap.try_drop().unwrap() };
+    }
+
+    #[test]
+    fn populated_resident() {
+        let page = page_size();
+        let mut mmap = AnonymousMmap::new(page * 4).unwrap();
+        assert_eq!(mmap.resident_bytes().unwrap(), page * 4);
+        unsafe { mmap.unmap_range(0, page).unwrap() };
+        assert_eq!(mmap.resident_bytes().unwrap(), page * 3);
+        unsafe { mmap.try_drop().unwrap() };
+    }
+}
@@ -0,0 +1,117 @@
+//! mseal(2) based sealing of the mapping layout
+
+use crate::{AnonymousMmap, AnonymousMmapError, OsError};
+
+impl AnonymousMmap {
+    /// Seal the whole mapping including any guard pages through mseal(2) so the kernel
+    /// refuses any later mprotect, munmap, mremap or mmap over it, e.g. after initializing
+    /// the data of a hardened build so it can not be made executable or replaced.
+    ///
+    /// The seal is permanent and the mapping stays until the process exits. The seal is
+    /// tracked so [`Self::protect_readonly`] and friends, [`Self::remap`],
+    /// [`Self::unmap_range`] and [`Self::try_drop`] fail with
+    /// [`AnonymousMmapError::Sealed`] up front instead of a bare EPERM. Not to be mixed up
+    /// with the memfd seals of [`Self::seal`].
+    ///
+    /// Kernels before 6.10 or 32 bit kernels are [`AnonymousMmapError::Unsupported`].
+    #[inline]
+    pub fn mseal(&mut self) -> Result<(), AnonymousMmapError> {
+        if self.guard != 0 {
+            // SAFETY: The guard pages below the mapping are ours.
+            let guard = unsafe { self.as_ptr_mut().sub(self.guard) };
+            mseal(guard, self.guard)?;
+        }
+        for (start, end) in self.mapped_ranges(0, self.len) {
+            // SAFETY: [start, end) is within the mapping and still mapped.
+            mseal(unsafe { self.as_ptr_mut().add(start) }, end - start)?;
+        }
+        self.sealed = true;
+        Ok(())
+    }
+    /// The mapping is sealed through [`Self::mseal`]
+    #[inline]
+    pub fn is_msealed(&self) -> bool {
+        self.sealed
+    }
+}
+
+#[inline]
+fn mseal(addr: *mut libc::c_void, len: usize) -> Result<(), AnonymousMmapError> {
+    // SAFETY: Only changes what the kernel permits on the range.
+    if unsafe { libc::syscall(libc::SYS_mseal, addr, len, 0) } != 0 {
+        let os_err = OsError::last_os_error();
+        return match os_err.raw_os_error() {
+            Some(libc::ENOSYS) => Err(AnonymousMmapError::Unsupported("mseal", os_err)),
+            _ => Err(AnonymousMmapError::MprotectFailed(os_err)),
+        };
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use crate::page_size;
+
+    // Kernels without mseal have nothing to test.
+    fn sealed_or_skip(mmap: &mut AnonymousMmap) -> bool {
+        match mmap.mseal() {
+            Ok(()) => true,
+            Err(AnonymousMmapError::Unsupported("mseal", _)) => false,
+            Err(e) => panic!("mseal: {}", e),
+        }
+    }
+
+    #[test]
+    fn sealed_refuses_layout_changes() {
+        let page = page_size();
+        let mut mmap = AnonymousMmap::new_private(page * 2).unwrap();
+        mmap.fill(1);
+        if !sealed_or_skip(&mut mmap) {
+            return;
+        }
+        assert!(mmap.is_msealed());
+        assert!(matches!(
+            mmap.protect_readonly(),
+            Err(AnonymousMmapError::Sealed("mprotect"))
+        ));
+        assert!(matches!(
+            unsafe { mmap.remap(page * 4, true) },
+            Err(AnonymousMmapError::Sealed("mremap"))
+        ));
+        assert!(matches!(
+            unsafe { mmap.unmap_range(0, page) },
+            Err(AnonymousMmapError::Sealed("munmap"))
+        ));
+        // The kernel refuses it as well.
+        let r = unsafe { libc::mprotect(mmap.as_ptr_mut(), page, libc::PROT_READ) };
+        assert_eq!(r, -1);
+        assert_eq!(OsError::last_os_error().raw_os_error(), Some(libc::EPERM));
+        // Contents stay accessible.
+        mmap.fill(2);
+        assert!(mmap.view(..).unwrap().as_slice().iter().all(|b| *b == 2));
+        assert!(matches!(
+            unsafe { mmap.try_drop() },
+            Err(AnonymousMmapError::Sealed("munmap"))
+        ));
+    }
+
+    #[test]
+    fn sealed_stack_guard() {
+        let mut stack = AnonymousMmap::new_stack(page_size() * 2).unwrap();
+        if !sealed_or_skip(&mut stack) {
+            return;
+        }
+        let guard = (stack.start_addr() - page_size()) as *mut libc::c_void;
+        let r = unsafe { libc::mprotect(guard, page_size(), libc::PROT_READ) };
+        assert_eq!(r, -1);
+    }
+
+    #[test]
+    fn unsealed_by_default() {
+        let mmap = AnonymousMmap::new(page_size()).unwrap();
+        assert!(!mmap.is_msealed());
+        unsafe { mmap.try_drop().unwrap() };
+    }
+}
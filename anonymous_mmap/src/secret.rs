@@ -0,0 +1,136 @@
+//! Hardened mappings for storing secrets
+
+use crate::{AnonymousMmap, AnonymousMmapError, OsError};
+#[cfg(feature = "memfd_secret")]
+use yown_fd::{AsRawFd, FromRawFd, OwnedFd};
+
+/// Hardening step of [`AnonymousMmap::new_secret`] that failed
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SecretStep {
+    /// mlock(2) keeping the pages out of swap
+    Mlock,
+    /// MADV_WIPEONFORK keeping the contents out of forked children
+    WipeOnFork,
+    /// MADV_DONTDUMP keeping the contents out of core dumps
+    DontDump,
+    /// memfd_secret(2) / ftruncate(2) creating the secret memory backing
+    MemfdSecret,
+}
+
+impl core::fmt::Display for SecretStep {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Mlock => write!(f, "mlock"),
+            Self::WipeOnFork => write!(f, "MADV_WIPEONFORK"),
+            Self::DontDump => write!(f, "MADV_DONTDUMP"),
+            Self::MemfdSecret => write!(f, "memfd_secret"),
+        }
+    }
+}
+
+impl AnonymousMmap {
+    /// Construct a new MAP_PRIVATE mapping for secrets which is mlocked and applied with
+    /// MADV_WIPEONFORK and MADV_DONTDUMP so the contents are kept out of swap, forked
+    /// children and core dumps.
+    ///
+    /// If any of the hardening steps fail the mapping is unmapped and the step is returned.
+    /// Use [`Self::wipe`] before dropping the mapping.
+    #[inline]
+    pub fn new_secret(len: usize) -> Result<Self, AnonymousMmapError> {
+        let mmap = Self::new_private(len)?;
+        match mmap.harden_secret() {
+            Ok(()) => Ok(mmap),
+            Err((step, os_err)) => {
+                // SAFETY: Nothing has been handed out from the mapping.
+                unsafe { mmap.try_drop() }?;
+                Err(AnonymousMmapError::SecretFailed(step, os_err))
+            }
+        }
+    }
+    #[inline]
+    fn harden_secret(&self) -> Result<(), (SecretStep, OsError)> {
+        // SAFETY: The whole range is our mapping.
+        unsafe {
+            if libc::mlock(self.as_ptr(), self.len) != 0 {
+                return Err((SecretStep::Mlock, OsError::last_os_error()));
+            }
+            if libc::madvise(self.as_ptr_mut(), self.len, libc::MADV_WIPEONFORK) != 0 {
+                return Err((SecretStep::WipeOnFork, OsError::last_os_error()));
+            }
+            if libc::madvise(self.as_ptr_mut(), self.len, libc::MADV_DONTDUMP) != 0 {
+                return Err((SecretStep::DontDump, OsError::last_os_error()));
+            }
+        }
+        Ok(())
+    }
+    /// Construct a new mapping backed by memfd_secret(2) (Linux 5.14+) which removes the pages
+    /// from the kernel direct map so they are not accessible even to the kernel itself.
+    ///
+    /// Requires secretmem to be enabled in the running kernel, otherwise fails with the
+    /// [`SecretStep::MemfdSecret`] step.
+    #[cfg(feature = "memfd_secret")]
+    #[inline]
+    pub fn new_secret_memfd(len: usize) -> Result<Self, AnonymousMmapError> {
+        // SAFETY: memfd_secret takes only the flags.
+        let fd = unsafe { libc::syscall(libc::SYS_memfd_secret, libc::O_CLOEXEC) } as libc::c_int;
+        if fd < 0 {
+            let os_err = OsError::last_os_error();
+            return Err(AnonymousMmapError::SecretFailed(
+                SecretStep::MemfdSecret,
+                os_err,
+            ));
+        }
+        // SAFETY: fd is valid and closed after mapping given the mapping holds the reference.
+        let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+        // SAFETY: fd is valid.
+        if unsafe { libc::ftruncate(fd.as_raw_fd(), len as libc::off_t) } != 0 {
+            let os_err = OsError::last_os_error();
+            return Err(AnonymousMmapError::SecretFailed(
+                SecretStep::MemfdSecret,
+                os_err,
+            ));
+        }
+        Self::mmap_fd(
+            len,
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_SHARED,
+            fd.as_raw_fd(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn secret_wipe() {
+        let mut mmap = AnonymousMmap::new_secret(4096).unwrap();
+        mmap.view_mut(..).unwrap().as_slice_mut().fill(0x42);
+        mmap.wipe();
+        assert!(mmap.view(..).unwrap().as_slice().iter().all(|b| *b == 0));
+        unsafe { mmap.try_drop().unwrap() };
+    }
+
+    #[cfg(feature = "memfd_secret")]
+    #[test]
+    fn secret_memfd() {
+        match AnonymousMmap::new_secret_memfd(4096) {
+            Ok(mut mmap) => {
+                mmap.view_mut(..).unwrap().as_slice_mut().fill(0x42);
+                mmap.wipe();
+                assert!(mmap.view(..).unwrap().as_slice().iter().all(|b| *b == 0));
+                unsafe { mmap.try_drop().unwrap() };
+            }
+            // secretmem not enabled in the running kernel
+            Err(AnonymousMmapError::SecretFailed(SecretStep::MemfdSecret, e)) => {
+                assert!(matches!(
+                    e.raw_os_error(),
+                    Some(libc::ENOSYS | libc::EINVAL)
+                ))
+            }
+            Err(e) => panic!("{}", e),
+        }
+    }
+}
@@ -0,0 +1,265 @@
+//! Fixed capacity slab of T over [`AnonymousMmap`]
+
+use crate::{page_size, AnonymousMmap, AnonymousMmapError};
+use core::marker::PhantomData;
+use core::mem::{ManuallyDrop, MaybeUninit};
+
+const FREE_END: u32 = u32::MAX;
+
+// Odd generation means occupied, even vacant.
+struct Slot<T> {
+    generation: u32,
+    next_free: u32,
+    value: MaybeUninit<T>,
+}
+
+impl<T> Slot<T> {
+    #[inline]
+    fn is_occupied(&self) -> bool {
+        self.generation & 1 == 1
+    }
+}
+
+/// Generation tagged key of an [`MmapSlab`] slot.
+///
+/// A key of a removed value never matches the slot reused afterwards, until the generation
+/// wraps around after 2^31 reuses of the same slot.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Key {
+    index: u32,
+    generation: u32,
+}
+
+impl Key {
+    /// Slot index of the key
+    #[inline]
+    pub fn index(&self) -> usize {
+        self.index as usize
+    }
+}
+
+/// Fixed capacity slab carving a private anonymous mapping into slots of T kept on a free-list.
+///
+/// The slots never move so the address of a value stays the same from insert until remove.
+/// The pages are only faulted in as the slots are first used.
+pub struct MmapSlab<T> {
+    map: ManuallyDrop<AnonymousMmap>,
+    capacity: u32,
+    len: u32,
+    // Slots below are initialized, above never touched.
+    used: u32,
+    free_head: u32,
+    _t: PhantomData<T>,
+}
+
+impl<T> core::fmt::Debug for MmapSlab<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("MmapSlab")
+            .field("map", &*self.map)
+            .field("capacity", &self.capacity)
+            .field("len", &self.len)
+            .finish()
+    }
+}
+
+impl<T> MmapSlab<T> {
+    /// Slab of the given capacity of slots which must be non-zero and below u32::MAX.
+    #[inline]
+    pub fn with_capacity(capacity: usize) -> Result<Self, AnonymousMmapError> {
+        assert!(
+            align_of::<Slot<T>>() <= page_size(),
+            "T aligned beyond the page size"
+        );
+        if capacity == 0 || capacity >= FREE_END as usize {
+            return Err(AnonymousMmapError::OutOfBounds(capacity, FREE_END as usize));
+        }
+        let len = capacity
+            .checked_mul(size_of::<Slot<T>>())
+            .ok_or(AnonymousMmapError::OutOfBounds(capacity, FREE_END as usize))?;
+        let map = AnonymousMmap::mmap_with(
+            core::ptr::null_mut(),
+            len,
+            libc::MAP_ANONYMOUS | libc::MAP_PRIVATE,
+        )?;
+        Ok(Self {
+            map: ManuallyDrop::new(map),
+            capacity: capacity as u32,
+            len: 0,
+            used: 0,
+            free_head: FREE_END,
+            _t: PhantomData,
+        })
+    }
+    /// Slots in total
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.capacity as usize
+    }
+    /// Occupied slots
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+    /// No occupied slots
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    #[inline]
+    fn slot_ptr(&self, index: u32) -> *mut Slot<T> {
+        // SAFETY: Callers keep index below capacity.
+        unsafe { self.map.as_ptr_mut().cast::<Slot<T>>().add(index as usize) }
+    }
+    #[inline]
+    fn slot(&self, key: Key) -> Option<&Slot<T>> {
+        if key.index >= self.used {
+            return None;
+        }
+        // SAFETY: Slots below used are initialized.
+        let slot = unsafe { &*self.slot_ptr(key.index) };
+        match slot.generation == key.generation && slot.is_occupied() {
+            true => Some(slot),
+            false => None,
+        }
+    }
+    /// Insert the value returning its key or the value back when the slab is full.
+    #[inline]
+    pub fn insert(&mut self, value: T) -> Result<Key, T> {
+        let index = match self.free_head {
+            FREE_END if self.used == self.capacity => return Err(value),
+            FREE_END => {
+                let index = self.used;
+                // SAFETY: index is below capacity and the slot was never touched.
+                unsafe {
+                    self.slot_ptr(index).write(Slot {
+                        generation: 0,
+                        next_free: FREE_END,
+                        value: MaybeUninit::uninit(),
+                    })
+                };
+                self.used += 1;
+                index
+            }
+            index => index,
+        };
+        // SAFETY: index is below used so initialized and vacant, the slab is borrowed exclusively.
+        let slot = unsafe { &mut *self.slot_ptr(index) };
+        if index == self.free_head {
+            self.free_head = slot.next_free;
+        }
+        slot.generation = slot.generation.wrapping_add(1);
+        slot.value.write(value);
+        self.len += 1;
+        Ok(Key {
+            index,
+            generation: slot.generation,
+        })
+    }
+    /// The value of the key, None if removed
+    #[inline]
+    pub fn get(&self, key: Key) -> Option<&T> {
+        // SAFETY: Occupied slots hold an initialized value.
+        self.slot(key)
+            .map(|slot| unsafe { slot.value.assume_init_ref() })
+    }
+    /// The value of the key mutably, None if removed
+    #[inline]
+    pub fn get_mut(&mut self, key: Key) -> Option<&mut T> {
+        self.slot(key)?;
+        // SAFETY: Checked occupied and the slab is borrowed exclusively.
+        let slot = unsafe { &mut *self.slot_ptr(key.index) };
+        Some(unsafe { slot.value.assume_init_mut() })
+    }
+    /// Remove the value of the key putting the slot on the free-list, None if already removed
+    #[inline]
+    pub fn remove(&mut self, key: Key) -> Option<T> {
+        self.slot(key)?;
+        // SAFETY: Checked occupied and the slab is borrowed exclusively.
+        let slot = unsafe { &mut *self.slot_ptr(key.index) };
+        slot.generation = slot.generation.wrapping_add(1);
+        slot.next_free = self.free_head;
+        self.free_head = key.index;
+        self.len -= 1;
+        // SAFETY: The slot was occupied and is now marked vacant so the value is moved out once.
+        Some(unsafe { slot.value.assume_init_read() })
+    }
+}
+
+impl<T> Drop for MmapSlab<T> {
+    fn drop(&mut self) {
+        for index in 0..self.used {
+            // SAFETY: Slots below used are initialized and the slab is going away.
+            let slot = unsafe { &mut *self.slot_ptr(index) };
+            if slot.is_occupied() {
+                unsafe { slot.value.assume_init_drop() };
+            }
+        }
+        // SAFETY: No references outlive the slab.
+        let map = unsafe { ManuallyDrop::take(&mut self.map) };
+        // Nothing to give the error back to.
+        let _ = unsafe { map.try_drop() };
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+
+    use super::*;
+    use std::rc::Rc;
+
+    #[test]
+    fn insert_get_remove() {
+        let mut slab = MmapSlab::<String>::with_capacity(4).unwrap();
+        let a = slab.insert("a".to_string()).unwrap();
+        let b = slab.insert("b".to_string()).unwrap();
+        assert_eq!(slab.len(), 2);
+        assert_eq!(slab.get(a).map(String::as_str), Some("a"));
+        slab.get_mut(b).unwrap().push('!');
+        assert_eq!(slab.get(b).map(String::as_str), Some("b!"));
+
+        let addr = slab.get(a).unwrap() as *const String;
+        assert_eq!(slab.remove(b).as_deref(), Some("b!"));
+        assert_eq!(slab.remove(b), None);
+        let c = slab.insert("c".to_string()).unwrap();
+        assert_eq!(slab.get(a).unwrap() as *const String, addr);
+        // c reuses the slot of b with a new generation.
+        assert_eq!(c.index(), b.index());
+        assert_ne!(c, b);
+        assert_eq!(slab.get(b), None);
+        assert!(slab.get_mut(b).is_none());
+        assert_eq!(slab.get(c).map(String::as_str), Some("c"));
+    }
+
+    #[test]
+    fn full_gives_value_back() {
+        let mut slab = MmapSlab::<u64>::with_capacity(2).unwrap();
+        slab.insert(1).unwrap();
+        let k = slab.insert(2).unwrap();
+        assert_eq!(slab.insert(3), Err(3));
+        slab.remove(k).unwrap();
+        assert!(slab.insert(3).is_ok());
+        assert_eq!(slab.capacity(), 2);
+    }
+
+    #[test]
+    fn drop_runs_destructors() {
+        let counter = Rc::new(());
+        {
+            let mut slab = MmapSlab::with_capacity(8).unwrap();
+            let keys: Vec<Key> = (0..5)
+                .map(|_| slab.insert(counter.clone()).unwrap())
+                .collect();
+            drop(slab.remove(keys[1]));
+            assert_eq!(Rc::strong_count(&counter), 5);
+        }
+        assert_eq!(Rc::strong_count(&counter), 1);
+    }
+
+    #[test]
+    fn zero_capacity() {
+        assert!(matches!(
+            MmapSlab::<u8>::with_capacity(0),
+            Err(AnonymousMmapError::OutOfBounds(0, _))
+        ));
+    }
+}
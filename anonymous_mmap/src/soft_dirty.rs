@@ -0,0 +1,185 @@
+//! /proc/self/pagemap soft-dirty tracking of the pages written in an [`AnonymousMmap`]
+
+use crate::{page_size, AnonymousMmap, AnonymousMmapError, OsError};
+use std::os::unix::fs::FileExt;
+
+// Bit 55 of a pagemap entry is set when the page was written since the last clear_refs.
+const PM_SOFT_DIRTY: u64 = 1 << 55;
+// Bit 63 of a pagemap entry is set when the page is present in memory.
+pub(crate) const PM_PRESENT: u64 = 1 << 63;
+// Entries read per pread(2) of the pagemap.
+const PAGEMAP_CHUNK: usize = 512;
+
+// The VmFlags line of the smaps VMA containing addr.
+fn vm_flags_of(smaps: &str, addr: usize) -> Option<&str> {
+    let mut within = false;
+    for line in smaps.lines() {
+        if let Some(flags) = line.strip_prefix("VmFlags:") {
+            if within {
+                return Some(flags);
+            }
+            continue;
+        }
+        let range = line
+            .split_whitespace()
+            .next()
+            .and_then(|r| r.split_once('-'));
+        if let Some((start, end)) = range {
+            if let (Ok(start), Ok(end)) = (
+                usize::from_str_radix(start, 16),
+                usize::from_str_radix(end, 16),
+            ) {
+                within = start <= addr && addr < end;
+            }
+        }
+    }
+    None
+}
+
+impl AnonymousMmap {
+    /// Clear the soft-dirty bits by writing 4 to /proc/self/clear_refs so that
+    /// [`Self::dirty_page_indices`] reports the pages written from now on.
+    ///
+    /// clear_refs is process wide - every mapping of the process is reset, not only one, and
+    /// the next write to each page takes a minor fault to set the bit again.
+    ///
+    /// Kernels without CONFIG_MEM_SOFT_DIRTY accept the write but never set the bit, which is
+    /// detected through the sd VmFlag of a probe mapping and [`AnonymousMmapError::Unsupported`].
+    #[inline]
+    pub fn clear_soft_dirty() -> Result<(), AnonymousMmapError> {
+        let probe = AnonymousMmap::new_unpopulated(page_size())?;
+        let probe_addr = probe.start_addr();
+        let smaps = std::fs::read_to_string("/proc/self/smaps");
+        // SAFETY: Nothing has been handed out from the probe.
+        unsafe { probe.try_drop()? };
+        let smaps = smaps.map_err(AnonymousMmapError::PagemapFailed)?;
+        // New VMAs are always soft-dirty when the kernel tracks it.
+        if !vm_flags_of(&smaps, probe_addr).is_some_and(|flags| flags.contains(" sd")) {
+            return Err(AnonymousMmapError::Unsupported(
+                "soft-dirty",
+                OsError::from_raw_os_error(libc::EOPNOTSUPP),
+            ));
+        }
+        std::fs::write("/proc/self/clear_refs", "4").map_err(AnonymousMmapError::PagemapFailed)
+    }
+    /// Indices of the pages of the mapping written since the last [`Self::clear_soft_dirty`]
+    /// from the soft-dirty bit of their /proc/self/pagemap entry, read in chunks.
+    ///
+    /// The indices are in base pages - a write into a transparent huge page marks all of its
+    /// e.g. 512 base pages dirty. Pages never touched since the mapping was created are clean
+    /// but freshly populated ones count as written.
+    #[inline]
+    pub fn dirty_page_indices(&self) -> Result<Vec<usize>, AnonymousMmapError> {
+        self.pagemap_indices(PM_SOFT_DIRTY)
+    }
+    // Indices of the pages with any of the mask bits set in their pagemap entry.
+    fn pagemap_indices(&self, mask: u64) -> Result<Vec<usize>, AnonymousMmapError> {
+        let mut indices = Vec::new();
+        self.pagemap_entries(|index, entry| {
+            if entry & mask != 0 {
+                indices.push(index);
+            }
+        })?;
+        Ok(indices)
+    }
+    // Walk the pagemap entries of the mapped pages along their page index.
+    pub(crate) fn pagemap_entries(
+        &self,
+        mut f: impl FnMut(usize, u64),
+    ) -> Result<(), AnonymousMmapError> {
+        let pagemap =
+            std::fs::File::open("/proc/self/pagemap").map_err(AnonymousMmapError::PagemapFailed)?;
+        let page = page_size();
+        let first_page = self.start_addr() / page;
+        let mut entries = [0u8; PAGEMAP_CHUNK * 8];
+        for (start, end) in self.mapped_ranges(0, self.len) {
+            let (mut index, end_index) = (start / page, end.div_ceil(page));
+            while index < end_index {
+                let count = (end_index - index).min(PAGEMAP_CHUNK);
+                let buf = &mut entries[..count * 8];
+                pagemap
+                    .read_exact_at(buf, ((first_page + index) * 8) as u64)
+                    .map_err(AnonymousMmapError::PagemapFailed)?;
+                for (i, entry) in buf.chunks_exact(8).enumerate() {
+                    let mut bytes = [0u8; 8];
+                    bytes.copy_from_slice(entry);
+                    f(index + i, u64::from_ne_bytes(bytes));
+                }
+                index += count;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use rstest::rstest;
+
+    // Kernels without CONFIG_MEM_SOFT_DIRTY can not track.
+    fn clear_or_unsupported() -> bool {
+        match AnonymousMmap::clear_soft_dirty() {
+            Ok(()) => true,
+            Err(AnonymousMmapError::Unsupported("soft-dirty", _)) => false,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    #[rstest]
+    #[case(AnonymousMmap::new(page_size() * 16).unwrap(), vec![3, 10])]
+    #[case(AnonymousMmap::new_private(page_size() * 16).unwrap(), vec![0, 15])]
+    #[case(AnonymousMmap::new(page_size() * (PAGEMAP_CHUNK + 8)).unwrap(), vec![1, PAGEMAP_CHUNK + 2])]
+    fn dirty_written_pages(#[case] mut mmap: AnonymousMmap, #[case] written: Vec<usize>) {
+        let page = page_size();
+        if clear_or_unsupported() {
+            assert_eq!(mmap.dirty_page_indices().unwrap(), Vec::<usize>::new());
+            for index in &written {
+                mmap.write_volatile_at(index * page + 8, 1u64).unwrap();
+            }
+            assert_eq!(mmap.dirty_page_indices().unwrap(), written);
+        }
+        unsafe { mmap.try_drop().unwrap() };
+    }
+
+    #[test]
+    fn dirty_skips_unmapped() {
+        let page = page_size();
+        let mut mmap = AnonymousMmap::new(page * 8).unwrap();
+        unsafe { mmap.unmap_range(0, page * 2).unwrap() };
+        if clear_or_unsupported() {
+            mmap.write_volatile_at(page * 5, 1u64).unwrap();
+            assert_eq!(mmap.dirty_page_indices().unwrap(), vec![5]);
+        }
+        unsafe { mmap.try_drop().unwrap() };
+    }
+
+    #[rstest]
+    #[case(16, vec![3, 10])]
+    #[case(PAGEMAP_CHUNK + 8, vec![1, PAGEMAP_CHUNK - 1, PAGEMAP_CHUNK, PAGEMAP_CHUNK + 7])]
+    fn present_pages_across_chunks(#[case] pages: usize, #[case] touched: Vec<usize>) {
+        let page = page_size();
+        let mut mmap = AnonymousMmap::new_unpopulated(page * pages).unwrap();
+        for index in &touched {
+            mmap.write_volatile_at(index * page, 1u8).unwrap();
+        }
+        assert_eq!(mmap.pagemap_indices(PM_PRESENT).unwrap(), touched);
+        unsafe { mmap.try_drop().unwrap() };
+    }
+
+    #[rstest]
+    #[case(0x1000, Some(" rd wr sh mr mw me ms sd"))]
+    #[case(0x3fff, Some(" rd wr mr mw me"))]
+    #[case(0x4000, None)]
+    fn vm_flags(#[case] addr: usize, #[case] expected: Option<&str>) {
+        let smaps = "\
+1000-3000 rw-s 00000000 00:01 42                         /dev/zero (deleted)
+Rss:                   4 kB
+VmFlags: rd wr sh mr mw me ms sd
+3000-4000 rw-p 00000000 00:00 0
+VmFlags: rd wr mr mw me
+";
+        assert_eq!(vm_flags_of(smaps, addr), expected);
+    }
+}
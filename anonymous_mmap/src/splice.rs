@@ -0,0 +1,117 @@
+//! vmsplice(2) of the mapping contents into a pipe
+
+use crate::{AnonymousMmap, AnonymousMmapError, OsError};
+use yown_fd::{AsRawFd, BorrowedFd};
+
+bitflags::bitflags! {
+    /// vmsplice(2) flags
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    pub struct SpliceFlags: libc::c_uint {
+        /// Gift the pages to the kernel. The gifted pages must not be modified afterwards
+        /// as the pipe reader may observe the changes - only use on pages never touched again.
+        const GIFT = libc::SPLICE_F_GIFT;
+        /// Do not block on the pipe - partial writes return the bytes written so far.
+        const NONBLOCK = libc::SPLICE_F_NONBLOCK;
+        /// More data will be coming in a subsequent splice.
+        const MORE = libc::SPLICE_F_MORE;
+    }
+}
+
+impl AnonymousMmap {
+    /// Splice the given range of the mapping into the pipe without copying, looping over partial
+    /// writes until the whole range is in. Returns the bytes written which on a non-blocking
+    /// pipe are the bytes written until EAGAIN or Err if none could be written.
+    ///
+    /// Without [`SpliceFlags::GIFT`] the pipe references the pages so modifying the range before
+    /// the reader consumed it is visible to the reader.
+    #[inline]
+    pub fn vmsplice_to(
+        &self,
+        pipe: BorrowedFd<'_>,
+        offset: usize,
+        len: usize,
+        flags: SpliceFlags,
+    ) -> Result<usize, AnonymousMmapError> {
+        let p = self.checked_range(offset, len)?.cast::<u8>();
+        let mut written = 0;
+        while written < len {
+            let iov = libc::iovec {
+                // SAFETY: written < len within the checked range.
+                iov_base: unsafe { p.add(written) }.cast(),
+                iov_len: len - written,
+            };
+            // SAFETY: iov is valid for the duration of the call.
+            let r = unsafe { libc::vmsplice(pipe.as_raw_fd(), &iov, 1, flags.bits()) };
+            if r < 0 {
+                let os_err = OsError::last_os_error();
+                match os_err.raw_os_error() {
+                    Some(libc::EINTR) => continue,
+                    Some(libc::EAGAIN) if written > 0 => break,
+                    _ => return Err(AnonymousMmapError::SpliceFailed(os_err)),
+                }
+            }
+            written += r as usize;
+        }
+        Ok(written)
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+
+    use super::*;
+    use std::fs::File;
+    use std::io::Read;
+    use yown_fd::{AsFd, FromRawFd};
+
+    fn pipe(flags: libc::c_int) -> (File, File) {
+        let mut fds = [0; 2];
+        assert_eq!(unsafe { libc::pipe2(fds.as_mut_ptr(), flags) }, 0);
+        unsafe { (File::from_raw_fd(fds[0]), File::from_raw_fd(fds[1])) }
+    }
+
+    #[test]
+    fn vmsplice_loopback_1mb() {
+        const LEN: usize = 1024 * 1024;
+        let mut mmap = AnonymousMmap::new(LEN).unwrap();
+        for (i, b) in mmap
+            .view_mut(..)
+            .unwrap()
+            .as_slice_mut()
+            .iter_mut()
+            .enumerate()
+        {
+            *b = (i % 251) as u8;
+        }
+        let (mut rx, tx) = pipe(libc::O_CLOEXEC);
+        let reader = std::thread::spawn(move || {
+            let mut buf = Vec::with_capacity(LEN);
+            rx.read_to_end(&mut buf).unwrap();
+            buf
+        });
+        let written = mmap
+            .vmsplice_to(tx.as_fd(), 0, LEN, SpliceFlags::empty())
+            .unwrap();
+        assert_eq!(written, LEN);
+        drop(tx);
+        let buf = reader.join().unwrap();
+        assert_eq!(&buf[..], mmap.view(..).unwrap().as_slice());
+    }
+
+    #[test]
+    fn vmsplice_nonblocking_partial() {
+        const LEN: usize = 1024 * 1024;
+        let mmap = AnonymousMmap::new(LEN).unwrap();
+        let (_rx, tx) = pipe(libc::O_CLOEXEC | libc::O_NONBLOCK);
+        let written = mmap
+            .vmsplice_to(tx.as_fd(), 0, LEN, SpliceFlags::NONBLOCK)
+            .unwrap();
+        assert!(written > 0 && written < LEN);
+        match mmap.vmsplice_to(tx.as_fd(), 0, LEN, SpliceFlags::NONBLOCK) {
+            Err(AnonymousMmapError::SpliceFailed(e)) => {
+                assert_eq!(e.raw_os_error(), Some(libc::EAGAIN))
+            }
+            r => panic!("Expected EAGAIN, got {:?}", r),
+        }
+    }
+}
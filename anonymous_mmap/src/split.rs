@@ -0,0 +1,140 @@
+//! Splitting an [`AnonymousMmap`] into independently owned mappings
+
+use crate::{accounting, page_size, AnonymousMmap};
+
+/// Error splitting a mapping
+#[derive(Debug)]
+pub enum SplitError {
+    /// The split point was not aligned to the page size
+    NotPageAligned {
+        /// Requested split point
+        at: usize,
+        /// System page size
+        page_size: usize,
+    },
+    /// The split point would leave either of the halves empty
+    OutOfBounds {
+        /// Requested split point
+        at: usize,
+        /// Len of the mapping
+        len: usize,
+    },
+}
+
+impl core::fmt::Display for SplitError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::NotPageAligned { at, page_size } => {
+                write!(
+                    f,
+                    "Split at {} is not aligned to page size {}",
+                    at, page_size
+                )
+            }
+            Self::OutOfBounds { at, len } => {
+                write!(f, "Split at {} is out of bounds of mapping len {}", at, len)
+            }
+        }
+    }
+}
+
+impl core::error::Error for SplitError {}
+
+impl AnonymousMmap {
+    /// Split the mapping at the given page-aligned offset into two independently owned mappings
+    /// where the first is [0, at) and the second [at, len). Both need to be dropped separately
+    /// as munmap(2) can unmap any page-aligned part of the mapping.
+    ///
+    /// On error Self is given back untouched. Only the first keeps the memfd backing if any.
+    #[inline]
+    pub fn split_off(
+        self,
+        at: usize,
+    ) -> Result<(AnonymousMmap, AnonymousMmap), (Self, SplitError)> {
+        let page_size = page_size();
+        if !at.is_multiple_of(page_size) {
+            return Err((self, SplitError::NotPageAligned { at, page_size }));
+        }
+        if at == 0 || at >= self.len {
+            let len = self.len;
+            return Err((self, SplitError::OutOfBounds { at, len }));
+        }
+        // SAFETY: at is within the mapping and the original is consumed.
+        let tail_addr = unsafe { self.addr.add(at) };
+        let head_holes = self
+            .holes
+            .iter()
+            .filter(|(start, _)| *start < at)
+            .map(|(start, end)| (*start, (*end).min(at)))
+            .collect();
+        let tail_holes = self
+            .holes
+            .iter()
+            .filter(|(_, end)| *end > at)
+            .map(|(start, end)| ((*start).max(at) - at, *end - at))
+            .collect();
+        let mut tail = AnonymousMmap {
+            addr: tail_addr,
+            len: self.len - at,
+            guard: 0,
+            memfd: None,
+            flags: self.flags,
+            prot: self.prot,
+            holes: tail_holes,
+            sealed: self.sealed,
+            #[cfg(feature = "leak_backtraces")]
+            id: 0,
+        };
+        accounting::resized(self.len, at);
+        accounting::created(&mut tail);
+        let head = AnonymousMmap {
+            addr: self.addr,
+            len: at,
+            guard: self.guard,
+            memfd: self.memfd,
+            flags: self.flags,
+            prot: self.prot,
+            holes: head_holes,
+            sealed: self.sealed,
+            #[cfg(feature = "leak_backtraces")]
+            id: self.id,
+        };
+        Ok((head, tail))
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn split_and_drop_both() {
+        let page = page_size();
+        let mmap = AnonymousMmap::new(page * 3).unwrap();
+        let (head, tail) = mmap.split_off(page).unwrap();
+        assert_eq!(head.len, page);
+        assert_eq!(tail.len, page * 2);
+        assert_eq!(
+            unsafe { head.offset_unchecked_as_ptr(page as u32) },
+            tail.as_ptr()
+        );
+        unsafe {
+            *(head.as_ptr_mut() as *mut u8) = 1;
+            *(tail.as_ptr_mut() as *mut u8) = 2;
+            head.try_drop().unwrap();
+            assert_eq!(*(tail.as_ptr() as *const u8), 2);
+            tail.try_drop().unwrap();
+        }
+    }
+
+    #[test]
+    fn split_not_page_aligned() {
+        let page = page_size();
+        let mmap = AnonymousMmap::new(page * 2).unwrap();
+        let (mmap, err) = mmap.split_off(page + 1).unwrap_err();
+        assert!(matches!(err, SplitError::NotPageAligned { .. }));
+        let (_mmap, err) = mmap.split_off(page * 2).unwrap_err();
+        assert!(matches!(err, SplitError::OutOfBounds { .. }));
+    }
+}
@@ -0,0 +1,82 @@
+//! Stack allocations with a guard page
+
+use crate::{page_size, AnonymousMmap, AnonymousMmapError, OsError};
+
+const STACK_FLAGS: libc::c_int =
+    libc::MAP_ANONYMOUS | libc::MAP_PRIVATE | libc::MAP_STACK | libc::MAP_GROWSDOWN;
+
+impl AnonymousMmap {
+    /// Construct a new stack of the given len rounded up to the page size using
+    /// MAP_STACK | MAP_GROWSDOWN with a PROT_NONE guard page installed at the low end
+    /// so that an overflow faults instead of silently corrupting the adjacent memory.
+    ///
+    /// The guard page is not part of the usable len but is unmapped along with the stack.
+    /// Use [`Self::stack_top`] to get the high address to give to clone / makecontext style consumers.
+    #[inline]
+    pub fn new_stack(len: usize) -> Result<Self, AnonymousMmapError> {
+        let page_size = page_size();
+        let len = len.next_multiple_of(page_size);
+        let mut mmap = Self::mmap_with(core::ptr::null_mut(), len + page_size, STACK_FLAGS)?;
+
+        // SAFETY: The guard page is the first page of the mapping we just created.
+        let p = unsafe { libc::mprotect(mmap.as_ptr_mut(), page_size, libc::PROT_NONE) };
+        if p != 0 {
+            let os_err = OsError::last_os_error();
+            // SAFETY: Nothing has been handed out from the mapping.
+            unsafe { mmap.try_drop() }?;
+            return Err(AnonymousMmapError::MprotectFailed(os_err));
+        }
+
+        // SAFETY: The mapping is len + page_size.
+        mmap.addr = unsafe { mmap.addr.add(page_size) };
+        mmap.len = len;
+        mmap.guard = page_size;
+        Ok(mmap)
+    }
+    /// Provide the high end address of the mapping where the downwards growing stack starts.
+    #[inline]
+    pub fn stack_top(&self) -> *mut libc::c_void {
+        // SAFETY: One past the end of the mapping.
+        unsafe { self.as_ptr_mut().add(self.len) }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn stack_top_aligned() {
+        let page = page_size();
+        let stack = AnonymousMmap::new_stack(page * 4 - 1).unwrap();
+        let top = stack.stack_top() as usize;
+        assert!(top.is_multiple_of(page));
+        assert_eq!(top, stack.start_addr() + page * 4);
+        unsafe {
+            *(stack.stack_top() as *mut u8).sub(1) = 0xAB;
+            *(stack.as_ptr_mut() as *mut u8) = 0xCD;
+            stack.try_drop().unwrap();
+        }
+    }
+
+    #[test]
+    fn guard_page_faults() {
+        let stack = AnonymousMmap::new_stack(page_size()).unwrap();
+        let guard = (stack.start_addr() - 1) as *mut u8;
+        match unsafe { libc::fork() } {
+            0 => unsafe {
+                core::ptr::write_volatile(guard, 1);
+                libc::_exit(0);
+            },
+            pid => {
+                assert!(pid > 0);
+                let mut status = 0;
+                assert_eq!(unsafe { libc::waitpid(pid, &mut status, 0) }, pid);
+                assert!(libc::WIFSIGNALED(status));
+                assert_eq!(libc::WTERMSIG(status), libc::SIGSEGV);
+            }
+        }
+        unsafe { stack.try_drop().unwrap() };
+    }
+}
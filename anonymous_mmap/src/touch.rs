@@ -0,0 +1,176 @@
+//! Populating every page of an [`AnonymousMmap`] up front with the failure reported instead
+//! of a SIGBUS on the first write
+
+use crate::{page_size, AnonymousMmap, AnonymousMmapError, OsError};
+use alloc::vec::Vec;
+
+// Pages populated per madvise(2) so a failure only needs the last chunk walked page-wise.
+const POPULATE_CHUNK: usize = 512;
+
+/// Error populating a mapping through [`AnonymousMmap::touch_all`]
+#[derive(Debug)]
+pub struct TouchError {
+    /// Pages of the mapping populated before the failure, counting from the start and
+    /// skipping ranges unmapped through [`AnonymousMmap::unmap_range`]
+    pub populated: usize,
+    /// Why populating the next page failed
+    pub error: AnonymousMmapError,
+}
+
+impl core::fmt::Display for TouchError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "Populating after {} pages Failed: {}",
+            self.populated, self.error
+        )
+    }
+}
+
+impl core::error::Error for TouchError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+impl AnonymousMmap {
+    /// Fault in every page of the mapping for writing, e.g. to find out up front whether a
+    /// MAP_NORESERVE mapping can be backed rather than through SIGBUS on some later write.
+    ///
+    /// Prefers MADV_POPULATE_WRITE (kernel 5.14) where running out of memory comes back as
+    /// [`AnonymousMmapError::MadviseFailed`] with ENOMEM along the pages populated so far to
+    /// shrink and retry. Older kernels rejecting the advice with EINVAL fall back to writing
+    /// each page back its own first byte which can not report anything: a page the kernel
+    /// can not back kills the process with SIGBUS or invokes the OOM killer.
+    ///
+    /// The contents are kept. A mapping which is not writable is
+    /// [`AnonymousMmapError::Protected`] with nothing populated.
+    #[inline]
+    pub fn touch_all(&mut self) -> Result<(), TouchError> {
+        if !self.is_writable() {
+            return Err(TouchError {
+                populated: 0,
+                error: AnonymousMmapError::Protected(self.prot),
+            });
+        }
+        let page = page_size();
+        let ranges: Vec<(usize, usize)> = self.mapped_ranges(0, self.len).collect();
+        let mut populated = 0;
+        for (start, end) in ranges.iter().copied() {
+            let mut at = start;
+            while at < end {
+                let len = (end - at).min(POPULATE_CHUNK * page);
+                match self.populate_write(at, len) {
+                    Ok(()) => {
+                        populated += len.div_ceil(page);
+                        at += len;
+                    }
+                    Err(e) if populated == 0 && e.raw_os_error() == Some(libc::EINVAL) => {
+                        self.touch_walk(&ranges);
+                        return Ok(());
+                    }
+                    Err(e) => {
+                        // Find how far the failed chunk got one page at a time.
+                        while at < end && self.populate_write(at, page.min(end - at)).is_ok() {
+                            populated += 1;
+                            at += page;
+                        }
+                        return Err(TouchError {
+                            populated,
+                            error: AnonymousMmapError::MadviseFailed(e),
+                        });
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+    #[inline]
+    fn populate_write(&self, offset: usize, len: usize) -> Result<(), OsError> {
+        // SAFETY: [offset, offset + len) is within a mapped range of the mapping.
+        let p = unsafe { self.as_ptr_mut().add(offset) };
+        // SAFETY: Populating only faults in pages and leaves the contents as is.
+        match unsafe { libc::madvise(p, len, libc::MADV_POPULATE_WRITE) } {
+            0 => Ok(()),
+            _ => Err(OsError::last_os_error()),
+        }
+    }
+    // Write fault each page keeping its first byte.
+    #[inline]
+    fn touch_walk(&mut self, ranges: &[(usize, usize)]) {
+        let page = page_size();
+        let base = self.addr.as_ptr().cast::<u8>();
+        for (start, end) in ranges {
+            for offset in (*start..*end).step_by(page) {
+                // SAFETY: offset is within a mapped writable range borrowed exclusively.
+                unsafe {
+                    let p = base.add(offset);
+                    p.write_volatile(p.read_volatile());
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(1)]
+    #[case(POPULATE_CHUNK)]
+    #[case(POPULATE_CHUNK + 3)]
+    fn touch_all_resident(#[case] pages: usize) {
+        let page = page_size();
+        let mut mmap = AnonymousMmap::new_unpopulated(page * pages).unwrap();
+        mmap.write_volatile_at(page * (pages - 1), 0xA5u8).unwrap();
+        mmap.touch_all().unwrap();
+        assert_eq!(mmap.resident_bytes().unwrap(), page * pages);
+        let view = mmap.view(..).unwrap();
+        assert_eq!(view.as_slice()[page * (pages - 1)], 0xA5);
+        assert!(view.as_slice()[..page * (pages - 1)]
+            .iter()
+            .all(|b| *b == 0));
+        unsafe { mmap.try_drop().unwrap() };
+    }
+
+    #[test]
+    fn touch_walk_fallback_keeps_contents() {
+        let page = page_size();
+        let mut mmap = AnonymousMmap::new_unpopulated(page * 4).unwrap();
+        unsafe { mmap.unmap_range(page, page).unwrap() };
+        mmap.write_volatile_at(0, 9u8).unwrap();
+        mmap.write_volatile_at(page * 2 + 1, 7u8).unwrap();
+        let ranges: Vec<(usize, usize)> = mmap.mapped_ranges(0, mmap.len).collect();
+        mmap.touch_walk(&ranges);
+        assert_eq!(mmap.resident_bytes().unwrap(), page * 3);
+        assert_eq!(mmap.read_volatile_at::<u8>(0).unwrap(), 9);
+        assert_eq!(mmap.read_volatile_at::<u8>(page * 2 + 1).unwrap(), 7);
+        unsafe { mmap.try_drop().unwrap() };
+    }
+
+    #[test]
+    fn touch_all_skips_unmapped() {
+        let page = page_size();
+        let mut mmap = AnonymousMmap::new_unpopulated(page * 6).unwrap();
+        unsafe { mmap.unmap_range(page * 2, page * 2).unwrap() };
+        mmap.touch_all().unwrap();
+        assert_eq!(mmap.resident_bytes().unwrap(), page * 4);
+        unsafe { mmap.try_drop().unwrap() };
+    }
+
+    #[test]
+    fn touch_all_readonly() {
+        let mut mmap = AnonymousMmap::new_unpopulated(page_size()).unwrap();
+        mmap.protect_readonly().unwrap();
+        let err = mmap.touch_all().unwrap_err();
+        assert_eq!(err.populated, 0);
+        assert!(matches!(
+            err.error,
+            AnonymousMmapError::Protected(libc::PROT_READ)
+        ));
+        assert_eq!(mmap.resident_bytes().unwrap(), 0);
+    }
+}
@@ -0,0 +1,425 @@
+//! userfaultfd(2) demand paging of an [`AnonymousMmap`]
+
+use crate::{page_size, AnonymousMmap, AnonymousMmapError, OsError};
+use core::marker::PhantomData;
+use yown_fd::{AsRawFd, FromRawFd, OwnedFd};
+
+// linux/userfaultfd.h which libc does not carry.
+const UFFD_API: u64 = 0xAA;
+const UFFD_USER_MODE_ONLY: libc::c_int = 1;
+const UFFD_FEATURE_PAGEFAULT_FLAG_WP: u64 = 1 << 0;
+const UFFD_FEATURE_WP_HUGETLBFS_SHMEM: u64 = 1 << 12;
+const UFFDIO_REGISTER_MODE_MISSING: u64 = 1 << 0;
+const UFFDIO_REGISTER_MODE_WP: u64 = 1 << 1;
+const UFFDIO_WRITEPROTECT_MODE_WP: u64 = 1 << 0;
+const UFFD_EVENT_PAGEFAULT: u8 = 0x12;
+const UFFD_PAGEFAULT_FLAG_WRITE: u64 = 1 << 0;
+const UFFD_PAGEFAULT_FLAG_WP: u64 = 1 << 1;
+// struct uffd_msg is packed into 32 bytes.
+const UFFD_MSG_LEN: usize = 32;
+
+#[repr(C)]
+struct UffdioApi {
+    api: u64,
+    features: u64,
+    ioctls: u64,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct UffdioRange {
+    start: u64,
+    len: u64,
+}
+
+#[repr(C)]
+struct UffdioRegister {
+    range: UffdioRange,
+    mode: u64,
+    ioctls: u64,
+}
+
+#[repr(C)]
+struct UffdioCopy {
+    dst: u64,
+    src: u64,
+    len: u64,
+    mode: u64,
+    copy: i64,
+}
+
+#[repr(C)]
+struct UffdioZeropage {
+    range: UffdioRange,
+    mode: u64,
+    zeropage: i64,
+}
+
+#[repr(C)]
+struct UffdioWriteprotect {
+    range: UffdioRange,
+    mode: u64,
+}
+
+#[cfg(not(any(
+    target_arch = "powerpc",
+    target_arch = "powerpc64",
+    target_arch = "mips",
+    target_arch = "mips64",
+    target_arch = "sparc",
+    target_arch = "sparc64"
+)))]
+mod ioc {
+    pub(super) const READ: u64 = 2;
+    pub(super) const WRITE: u64 = 1;
+    pub(super) const DIR_SHIFT: u64 = 30;
+}
+
+#[cfg(any(
+    target_arch = "powerpc",
+    target_arch = "powerpc64",
+    target_arch = "mips",
+    target_arch = "mips64",
+    target_arch = "sparc",
+    target_arch = "sparc64"
+))]
+mod ioc {
+    pub(super) const READ: u64 = 2;
+    pub(super) const WRITE: u64 = 4;
+    pub(super) const DIR_SHIFT: u64 = 29;
+}
+
+// _IOC(dir, 0xAA, nr, size) of the UFFDIO ioctls.
+const fn uffdio<T>(dir: u64, nr: u64) -> u64 {
+    dir << ioc::DIR_SHIFT | (size_of::<T>() as u64) << 16 | UFFD_API << 8 | nr
+}
+
+const UFFDIO_API: u64 = uffdio::<UffdioApi>(ioc::READ | ioc::WRITE, 0x3F);
+const UFFDIO_REGISTER: u64 = uffdio::<UffdioRegister>(ioc::READ | ioc::WRITE, 0x00);
+const UFFDIO_COPY: u64 = uffdio::<UffdioCopy>(ioc::READ | ioc::WRITE, 0x03);
+const UFFDIO_ZEROPAGE: u64 = uffdio::<UffdioZeropage>(ioc::READ | ioc::WRITE, 0x04);
+const UFFDIO_WRITEPROTECT: u64 = uffdio::<UffdioWriteprotect>(ioc::READ | ioc::WRITE, 0x06);
+
+/// Which faults of the registered mapping are delivered through the [`UffdHandle`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum UffdMode {
+    /// Faults on pages not yet populated
+    Missing,
+    /// Faults on pages not yet populated and writes to pages write-protected through
+    /// [`UffdHandle::write_protect`] (Linux 5.19+ for the shared anonymous mappings)
+    MissingWriteProtect,
+}
+
+/// Event read from the userfaultfd
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum UffdEvent {
+    /// The faulting thread is blocked until the page at addr is resolved
+    PageFault {
+        /// Page-aligned address of the fault
+        addr: usize,
+        /// The fault was a write
+        write: bool,
+        /// The fault was a write to a write-protected page
+        write_protect: bool,
+    },
+    /// Other event of the given UFFD_EVENT_* type
+    Other(u8),
+}
+
+/// userfaultfd(2) registered over an [`AnonymousMmap`] which can not outlive the mapping.
+/// Dropping the handle closes the userfaultfd unregistering the mapping and waking any
+/// thread blocked on a fault.
+#[derive(Debug)]
+pub struct UffdHandle<'a> {
+    uffd: OwnedFd,
+    start: usize,
+    len: usize,
+    _mmap: PhantomData<&'a AnonymousMmap>,
+}
+
+#[inline]
+fn uffd_ioctl<T>(fd: libc::c_int, request: u64, arg: &mut T) -> Result<(), AnonymousMmapError> {
+    // SAFETY: arg is the struct the UFFDIO request reads and writes.
+    if unsafe { libc::ioctl(fd, request as libc::Ioctl, arg as *mut T) } != 0 {
+        return Err(AnonymousMmapError::UffdFailed(OsError::last_os_error()));
+    }
+    Ok(())
+}
+
+impl AnonymousMmap {
+    /// Create a userfaultfd(2) and register the whole mapping with it for the given mode so
+    /// the page faults are delivered through [`UffdHandle::read_event`] e.g. to a thread
+    /// fetching the pages from elsewhere.
+    ///
+    /// Only pages not yet populated fault, so the mapping should be created without
+    /// MAP_POPULATE e.g. through [`Self::new_unpopulated`]. Without CAP_SYS_PTRACE or
+    /// vm.unprivileged_userfaultfd only the faults from user mode are handled (Linux 5.11+)
+    /// - a kernel access e.g. read(2) into a missing page fails with EFAULT instead.
+    ///
+    /// Kernels without userfaultfd or the mode are [`AnonymousMmapError::Unsupported`].
+    #[inline]
+    pub fn register_userfaultfd(
+        &self,
+        mode: UffdMode,
+    ) -> Result<UffdHandle<'_>, AnonymousMmapError> {
+        let flags = libc::O_CLOEXEC;
+        // SAFETY: userfaultfd takes only the flags.
+        let mut fd = unsafe { libc::syscall(libc::SYS_userfaultfd, flags) };
+        if fd < 0 && OsError::last_os_error().raw_os_error() == Some(libc::EPERM) {
+            // SAFETY: userfaultfd takes only the flags.
+            fd = unsafe { libc::syscall(libc::SYS_userfaultfd, flags | UFFD_USER_MODE_ONLY) };
+        }
+        if fd < 0 {
+            let os_err = OsError::last_os_error();
+            return Err(AnonymousMmapError::Unsupported("userfaultfd", os_err));
+        }
+        // SAFETY: fd is valid and ours.
+        let uffd = unsafe { OwnedFd::from_raw_fd(fd as libc::c_int) };
+
+        let (features, register_mode) = match mode {
+            UffdMode::Missing => (0, UFFDIO_REGISTER_MODE_MISSING),
+            UffdMode::MissingWriteProtect => {
+                let features = match self.flags & libc::MAP_SHARED != 0 {
+                    true => UFFD_FEATURE_PAGEFAULT_FLAG_WP | UFFD_FEATURE_WP_HUGETLBFS_SHMEM,
+                    false => UFFD_FEATURE_PAGEFAULT_FLAG_WP,
+                };
+                (
+                    features,
+                    UFFDIO_REGISTER_MODE_MISSING | UFFDIO_REGISTER_MODE_WP,
+                )
+            }
+        };
+        let mut api = UffdioApi {
+            api: UFFD_API,
+            features,
+            ioctls: 0,
+        };
+        match uffd_ioctl(uffd.as_raw_fd(), UFFDIO_API, &mut api) {
+            Err(AnonymousMmapError::UffdFailed(e)) => {
+                return Err(AnonymousMmapError::Unsupported("UFFDIO_API", e));
+            }
+            r => r?,
+        }
+
+        let page = page_size();
+        let len = self.len.div_ceil(page) * page;
+        let mut register = UffdioRegister {
+            range: UffdioRange {
+                start: self.start_addr() as u64,
+                len: len as u64,
+            },
+            mode: register_mode,
+            ioctls: 0,
+        };
+        match uffd_ioctl(uffd.as_raw_fd(), UFFDIO_REGISTER, &mut register) {
+            Err(AnonymousMmapError::UffdFailed(e)) if e.raw_os_error() == Some(libc::EINVAL) => {
+                return Err(AnonymousMmapError::Unsupported("UFFDIO_REGISTER", e));
+            }
+            r => r?,
+        }
+        Ok(UffdHandle {
+            uffd,
+            start: self.start_addr(),
+            len,
+            _mmap: PhantomData,
+        })
+    }
+}
+
+impl UffdHandle<'_> {
+    #[inline]
+    fn page_range(&self, addr: usize, len: usize) -> Result<UffdioRange, AnonymousMmapError> {
+        let page = page_size();
+        if !addr.is_multiple_of(page) {
+            return Err(AnonymousMmapError::NotPageAligned(addr, page));
+        }
+        if !len.is_multiple_of(page) {
+            return Err(AnonymousMmapError::NotPageAligned(len, page));
+        }
+        match addr
+            .checked_sub(self.start)
+            .and_then(|o| o.checked_add(len))
+        {
+            Some(end) if addr >= self.start && end <= self.len => Ok(UffdioRange {
+                start: addr as u64,
+                len: len as u64,
+            }),
+            _ => Err(AnonymousMmapError::OutOfBounds(
+                addr.wrapping_sub(self.start),
+                len,
+            )),
+        }
+    }
+    /// Block until the next event e.g. a page fault of another thread.
+    #[inline]
+    pub fn read_event(&self) -> Result<UffdEvent, AnonymousMmapError> {
+        let mut msg = [0u8; UFFD_MSG_LEN];
+        loop {
+            // SAFETY: msg is valid for writes of UFFD_MSG_LEN bytes.
+            let r =
+                unsafe { libc::read(self.uffd.as_raw_fd(), msg.as_mut_ptr().cast(), UFFD_MSG_LEN) };
+            if r == UFFD_MSG_LEN as isize {
+                break;
+            }
+            let os_err = OsError::last_os_error();
+            if r < 0 && os_err.raw_os_error() == Some(libc::EINTR) {
+                continue;
+            }
+            return Err(AnonymousMmapError::UffdFailed(os_err));
+        }
+        let u64_at = |at: usize| {
+            let mut bytes = [0u8; 8];
+            bytes.copy_from_slice(&msg[at..at + 8]);
+            u64::from_ne_bytes(bytes)
+        };
+        // struct uffd_msg: event, reserved, then the pagefault flags and address.
+        Ok(match msg[0] {
+            UFFD_EVENT_PAGEFAULT => {
+                let flags = u64_at(8);
+                UffdEvent::PageFault {
+                    addr: (u64_at(16) as usize) & !(page_size() - 1),
+                    write: flags & UFFD_PAGEFAULT_FLAG_WRITE != 0,
+                    write_protect: flags & UFFD_PAGEFAULT_FLAG_WP != 0,
+                }
+            }
+            event => UffdEvent::Other(event),
+        })
+    }
+    /// Resolve the missing pages at page_addr by atomically copying src into them through
+    /// UFFDIO_COPY and wake the faulting threads. src len needs to be a multiple of the page size.
+    ///
+    /// Pages already populated fail with EEXIST.
+    #[inline]
+    pub fn copy_into(&self, page_addr: usize, src: &[u8]) -> Result<(), AnonymousMmapError> {
+        let range = self.page_range(page_addr, src.len())?;
+        let mut copy = UffdioCopy {
+            dst: range.start,
+            src: src.as_ptr() as u64,
+            len: range.len,
+            mode: 0,
+            copy: 0,
+        };
+        uffd_ioctl(self.uffd.as_raw_fd(), UFFDIO_COPY, &mut copy)
+    }
+    /// Resolve the missing page at page_addr with a zero-filled page through UFFDIO_ZEROPAGE and
+    /// wake the faulting threads.
+    #[inline]
+    pub fn zero_page(&self, page_addr: usize) -> Result<(), AnonymousMmapError> {
+        let range = self.page_range(page_addr, page_size())?;
+        let mut zeropage = UffdioZeropage {
+            range,
+            mode: 0,
+            zeropage: 0,
+        };
+        uffd_ioctl(self.uffd.as_raw_fd(), UFFDIO_ZEROPAGE, &mut zeropage)
+    }
+    /// Write-protect or unprotect the populated pages of the range through UFFDIO_WRITEPROTECT
+    /// which requires [`UffdMode::MissingWriteProtect`]. Unprotecting wakes the faulting threads.
+    #[inline]
+    pub fn write_protect(
+        &self,
+        page_addr: usize,
+        len: usize,
+        protect: bool,
+    ) -> Result<(), AnonymousMmapError> {
+        let range = self.page_range(page_addr, len)?;
+        let mut wp = UffdioWriteprotect {
+            range,
+            mode: match protect {
+                true => UFFDIO_WRITEPROTECT_MODE_WP,
+                false => 0,
+            },
+        };
+        uffd_ioctl(self.uffd.as_raw_fd(), UFFDIO_WRITEPROTECT, &mut wp)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn ioctl_numbers() {
+        assert_eq!(size_of::<UffdioApi>(), 24);
+        assert_eq!(size_of::<UffdioRegister>(), 32);
+        assert_eq!(size_of::<UffdioCopy>(), 40);
+        assert_eq!(size_of::<UffdioZeropage>(), 32);
+        #[cfg(target_arch = "x86_64")]
+        {
+            assert_eq!(UFFDIO_API, 0xc018_aa3f);
+            assert_eq!(UFFDIO_REGISTER, 0xc020_aa00);
+            assert_eq!(UFFDIO_COPY, 0xc028_aa03);
+            assert_eq!(UFFDIO_ZEROPAGE, 0xc020_aa04);
+            assert_eq!(UFFDIO_WRITEPROTECT, 0xc018_aa06);
+        }
+    }
+
+    #[test]
+    fn serve_faults_from_thread() {
+        const PAGES: usize = 4;
+        let page = page_size();
+        let mmap = AnonymousMmap::new_unpopulated(page * PAGES).unwrap();
+        let handle = match mmap.register_userfaultfd(UffdMode::Missing) {
+            Ok(handle) => handle,
+            // userfaultfd not available in the running kernel
+            Err(AnonymousMmapError::Unsupported(_, e)) => {
+                assert!(matches!(
+                    e.raw_os_error(),
+                    Some(libc::ENOSYS | libc::EPERM | libc::EINVAL)
+                ));
+                return;
+            }
+            Err(e) => panic!("{}", e),
+        };
+        let start = mmap.start_addr();
+
+        std::thread::scope(|s| {
+            s.spawn(|| {
+                let mut served = Vec::new();
+                for _ in 0..PAGES {
+                    let addr = match handle.read_event().unwrap() {
+                        UffdEvent::PageFault { addr, write, .. } => {
+                            assert!(!write);
+                            addr
+                        }
+                        other => panic!("unexpected {:?}", other),
+                    };
+                    let index = (addr - start) / page;
+                    match index % 2 {
+                        0 => handle
+                            .copy_into(addr, &vec![index as u8 + 1; page])
+                            .unwrap(),
+                        _ => handle.zero_page(addr).unwrap(),
+                    }
+                    served.push(index);
+                }
+                assert_eq!(served, (0..PAGES).collect::<Vec<_>>());
+            });
+            let view = mmap.view(..).unwrap();
+            for index in 0..PAGES {
+                let expected = match index % 2 {
+                    0 => index as u8 + 1,
+                    _ => 0,
+                };
+                let bytes = &view.as_slice()[index * page..(index + 1) * page];
+                assert!(bytes.iter().all(|b| *b == expected));
+            }
+        });
+
+        assert!(matches!(
+            handle.zero_page(start + 1),
+            Err(AnonymousMmapError::NotPageAligned(..))
+        ));
+        assert!(matches!(
+            handle.zero_page(start + page * PAGES),
+            Err(AnonymousMmapError::OutOfBounds(..))
+        ));
+        assert!(matches!(
+            handle.copy_into(start, &vec![0; page]),
+            Err(AnonymousMmapError::UffdFailed(e)) if e.raw_os_error() == Some(libc::EEXIST)
+        ));
+        drop(handle);
+        unsafe { mmap.try_drop().unwrap() };
+    }
+}
@@ -0,0 +1,287 @@
+//! Releasing ranges in the middle of an [`AnonymousMmap`]
+
+use crate::{AnonymousMmap, AnonymousMmapError, OsError};
+use alloc::vec::Vec;
+use yown_fd::AsRawFd;
+
+impl AnonymousMmap {
+    /// Unmap the given page-aligned range through munmap(2) splitting the mapping in the
+    /// kernel while the rest stays mapped e.g. for releasing the consumed head of a log.
+    ///
+    /// The range is remembered so [`Self::try_drop`] only unmaps what is still mapped and
+    /// any view or bulk access touching it is refused. Parts of the range already
+    /// unmapped are skipped. memfd-backed mappings are [`AnonymousMmapError::Unsupported`]
+    /// as they are remapped as a whole - see [`Self::punch_hole`] for those.
+    ///
+    /// # Safety
+    ///
+    /// No pointers previously handed out into the range may be used after it is unmapped.
+    #[inline]
+    pub unsafe fn unmap_range(
+        &mut self,
+        offset: usize,
+        len: usize,
+    ) -> Result<(), AnonymousMmapError> {
+        if self.sealed {
+            return Err(AnonymousMmapError::Sealed("munmap"));
+        }
+        if self.memfd.is_some() {
+            return Err(AnonymousMmapError::Unsupported(
+                "munmap",
+                OsError::from_raw_os_error(libc::EINVAL),
+            ));
+        }
+        self.page_bounds(offset, len)?;
+        let mapped: Vec<(usize, usize)> = self.mapped_ranges(offset, offset + len).collect();
+        for (start, end) in mapped {
+            // SAFETY: [start, end) is within the mapping and still mapped.
+            let p = unsafe { libc::munmap(self.as_ptr_mut().add(start), end - start) };
+            if p != 0 {
+                let os_err = OsError::last_os_error();
+                return Err(AnonymousMmapError::Unsupported("munmap", os_err));
+            }
+            self.insert_hole(start, end);
+        }
+        Ok(())
+    }
+    /// Release the backing pages of the given page-aligned range from the memfd through
+    /// fallocate(FALLOC_FL_PUNCH_HOLE) while the address range stays valid and reads back
+    /// zero-filled.
+    ///
+    /// Only memfd-backed mappings have a backing to punch, others are
+    /// [`AnonymousMmapError::NotMemfd`].
+    ///
+    /// Zeroing changes the bytes under any view, so the mapping is borrowed exclusively as
+    /// with [`Self::decommit`].
+    #[inline]
+    pub fn punch_hole(&mut self, offset: usize, len: usize) -> Result<(), AnonymousMmapError> {
+        let fd = match &self.memfd {
+            Some(memfd) => memfd.as_raw_fd(),
+            None => return Err(AnonymousMmapError::NotMemfd),
+        };
+        self.page_range(offset, len)?;
+        // SAFETY: fd is valid and the range is within the memfd.
+        let r = unsafe {
+            libc::fallocate(
+                fd,
+                libc::FALLOC_FL_PUNCH_HOLE | libc::FALLOC_FL_KEEP_SIZE,
+                offset as libc::off_t,
+                len as libc::off_t,
+            )
+        };
+        if r != 0 {
+            return Err(AnonymousMmapError::MemfdFailed(OsError::last_os_error()));
+        }
+        Ok(())
+    }
+    /// Does [start, end) overlap any range unmapped through [`Self::unmap_range`]
+    #[inline]
+    pub(crate) fn overlaps_hole(&self, start: usize, end: usize) -> bool {
+        self.holes.iter().any(|(s, e)| *s < end && start < *e)
+    }
+    /// The still mapped [start, end) parts within the given range in ascending order.
+    #[inline]
+    pub(crate) fn mapped_ranges(
+        &self,
+        start: usize,
+        end: usize,
+    ) -> impl Iterator<Item = (usize, usize)> + '_ {
+        let mut cursor = start;
+        let mut holes = self.holes.iter();
+        core::iter::from_fn(move || {
+            while cursor < end {
+                let (s, e) = match holes.next() {
+                    Some(hole) => *hole,
+                    None => (end, end),
+                };
+                let mapped = (cursor, s.clamp(cursor, end));
+                cursor = cursor.max(e);
+                if mapped.0 < mapped.1 {
+                    return Some(mapped);
+                }
+            }
+            None
+        })
+    }
+    // Keep the holes sorted and disjoint merging the adjacent ones.
+    #[inline]
+    pub(crate) fn insert_hole(&mut self, start: usize, end: usize) {
+        let at = self.holes.partition_point(|(s, _)| *s < start);
+        self.holes.insert(at, (start, end));
+        let mut merged: Vec<(usize, usize)> = Vec::with_capacity(self.holes.len());
+        for (s, e) in self.holes.drain(..) {
+            match merged.last_mut() {
+                Some(last) if s <= last.1 => last.1 = last.1.max(e),
+                _ => merged.push((s, e)),
+            }
+        }
+        self.holes = merged;
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+
+    use super::*;
+    use crate::page_size;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(vec![], (0, 8), vec![(0, 8)])]
+    #[case(vec![(2, 4)], (0, 8), vec![(0, 2), (4, 8)])]
+    #[case(vec![(0, 2), (6, 8)], (0, 8), vec![(2, 6)])]
+    #[case(vec![(0, 2), (4, 6)], (3, 8), vec![(3, 4), (6, 8)])]
+    #[case(vec![(0, 8)], (0, 8), vec![])]
+    fn mapped_ranges_between_holes(
+        #[case] holes: Vec<(usize, usize)>,
+        #[case] range: (usize, usize),
+        #[case] expected: Vec<(usize, usize)>,
+    ) {
+        let mut mmap = AnonymousMmap::new(8).unwrap();
+        mmap.holes = holes;
+        let mapped: Vec<_> = mmap.mapped_ranges(range.0, range.1).collect();
+        assert_eq!(mapped, expected);
+        mmap.holes.clear();
+        unsafe { mmap.try_drop().unwrap() };
+    }
+
+    #[rstest]
+    #[case(vec![(4, 6)], vec![(0, 2), (4, 6)])]
+    #[case(vec![(2, 4)], vec![(0, 4)])]
+    #[case(vec![(1, 3), (3, 5)], vec![(0, 5)])]
+    fn holes_merge(#[case] inserts: Vec<(usize, usize)>, #[case] expected: Vec<(usize, usize)>) {
+        let mut mmap = AnonymousMmap::new(8).unwrap();
+        mmap.insert_hole(0, 2);
+        for (start, end) in inserts {
+            mmap.insert_hole(start, end);
+        }
+        assert_eq!(mmap.holes, expected);
+        mmap.holes.clear();
+        unsafe { mmap.try_drop().unwrap() };
+    }
+
+    #[test]
+    fn drop_after_unmap_middle_keeps_reused_range() {
+        let page = page_size();
+        let mut mmap = AnonymousMmap::new_private(page * 4).unwrap();
+        mmap.fill(7);
+        let hole = mmap.start_addr() + page;
+        unsafe { mmap.unmap_range(page, page * 2).unwrap() };
+
+        assert!(mmap.view(page..page * 2).is_none());
+        assert!(mmap.view(..).is_none());
+        assert!(mmap
+            .view(..page)
+            .unwrap()
+            .as_slice()
+            .iter()
+            .all(|b| *b == 7));
+        assert!(mmap
+            .view(page * 3..)
+            .unwrap()
+            .as_slice()
+            .iter()
+            .all(|b| *b == 7));
+        assert!(matches!(
+            mmap.decommit(page, page),
+            Err(AnonymousMmapError::Unmapped(..))
+        ));
+        mmap.fill(9);
+        mmap.protect_readonly().unwrap();
+        mmap.protect_readwrite().unwrap();
+
+        // Someone else takes over the unmapped range which try_drop must leave alone.
+        let mut reused = AnonymousMmap::new_at(hole, page).unwrap();
+        unsafe { mmap.try_drop().unwrap() };
+        reused.fill(1);
+        assert!(reused.view(..).unwrap().as_slice().iter().all(|b| *b == 1));
+        unsafe { reused.try_drop().unwrap() };
+    }
+
+    #[test]
+    fn unmap_head_and_overlapping_again() {
+        let page = page_size();
+        let mut mmap = AnonymousMmap::new_private(page * 4).unwrap();
+        unsafe { mmap.unmap_range(0, page).unwrap() };
+        unsafe { mmap.unmap_range(0, page * 2).unwrap() };
+        assert_eq!(mmap.holes, vec![(0, page * 2)]);
+        let clone = mmap.try_clone().unwrap();
+        assert!(clone.view(..).unwrap().as_slice().iter().all(|b| *b == 0));
+        unsafe { clone.try_drop().unwrap() };
+        unsafe { mmap.try_drop().unwrap() };
+    }
+
+    #[test]
+    fn drop_after_unmap_everything() {
+        let page = page_size();
+        let mut mmap = AnonymousMmap::new(page * 2).unwrap();
+        unsafe { mmap.unmap_range(0, page * 2).unwrap() };
+        unsafe { mmap.try_drop().unwrap() };
+    }
+
+    #[test]
+    fn drop_stack_after_unmap() {
+        let page = page_size();
+        let mut stack = AnonymousMmap::new_stack(page * 2).unwrap();
+        unsafe { stack.unmap_range(page, page).unwrap() };
+        unsafe { stack.try_drop().unwrap() };
+    }
+
+    #[test]
+    fn split_after_unmap() {
+        let page = page_size();
+        let mut mmap = AnonymousMmap::new(page * 4).unwrap();
+        unsafe { mmap.unmap_range(page, page * 2).unwrap() };
+        let (head, tail) = mmap.split_off(page * 2).unwrap();
+        assert_eq!(head.holes, vec![(page, page * 2)]);
+        assert_eq!(tail.holes, vec![(0, page)]);
+        unsafe { head.try_drop().unwrap() };
+        unsafe { tail.try_drop().unwrap() };
+    }
+
+    #[rstest]
+    #[case(1, 4096, AnonymousMmapError::NotPageAligned(1, 4096))]
+    #[case(0, 4097, AnonymousMmapError::NotPageAligned(4097, 4096))]
+    #[case(4096, 8192, AnonymousMmapError::OutOfBounds(4096, 8192))]
+    fn unmap_range_checked(
+        #[case] offset: usize,
+        #[case] len: usize,
+        #[case] expected: AnonymousMmapError,
+    ) {
+        let mut mmap = AnonymousMmap::new(8192).unwrap();
+        let err = unsafe { mmap.unmap_range(offset, len).unwrap_err() };
+        assert_eq!(format!("{}", err), format!("{}", expected));
+        unsafe { mmap.try_drop().unwrap() };
+    }
+
+    #[test]
+    fn punch_hole_memfd() {
+        let page = page_size();
+        let mut mmap = AnonymousMmap::new_memfd(page * 3).unwrap();
+        mmap.fill(5);
+        mmap.punch_hole(page, page).unwrap();
+        let view = mmap.view(..).unwrap();
+        assert!(view.as_slice()[..page].iter().all(|b| *b == 5));
+        assert!(view.as_slice()[page..page * 2].iter().all(|b| *b == 0));
+        assert!(view.as_slice()[page * 2..].iter().all(|b| *b == 5));
+        assert!(matches!(
+            unsafe { mmap.unmap_range(0, page) },
+            Err(AnonymousMmapError::Unsupported("munmap", _))
+        ));
+        assert!(matches!(
+            mmap.punch_hole(1, page),
+            Err(AnonymousMmapError::NotPageAligned(..))
+        ));
+        unsafe { mmap.try_drop().unwrap() };
+    }
+
+    #[test]
+    fn punch_hole_not_memfd() {
+        let mut mmap = AnonymousMmap::new(page_size()).unwrap();
+        assert!(matches!(
+            mmap.punch_hole(0, page_size()),
+            Err(AnonymousMmapError::NotMemfd)
+        ));
+        unsafe { mmap.try_drop().unwrap() };
+    }
+}
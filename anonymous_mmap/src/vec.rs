@@ -0,0 +1,255 @@
+//! Growable byte vector over [`AnonymousMmap`]
+
+use crate::{page_size, AnonymousMmap, AnonymousMmapError, OsError};
+
+const VEC_FLAGS: libc::c_int = libc::MAP_ANONYMOUS | libc::MAP_PRIVATE;
+
+/// Growable byte vector over a private anonymous mapping which grows by doubling through
+/// mremap(2) MREMAP_MAYMOVE moving the page tables instead of copying the contents.
+///
+/// The pages are only faulted in when written so the spare capacity costs address space only.
+/// Growth may move the base address - use [`Self::try_reserve_in_place`] where the address
+/// must stay put, failing rather than moving.
+#[derive(Debug, Default)]
+pub struct MmapVec {
+    map: Option<AnonymousMmap>,
+    len: usize,
+}
+
+#[inline]
+fn round_to_page(len: usize) -> Option<usize> {
+    let page_size = page_size();
+    len.checked_add(page_size - 1).map(|l| l & !(page_size - 1))
+}
+
+#[inline]
+fn capacity_overflow() -> AnonymousMmapError {
+    AnonymousMmapError::MremapFailed(OsError::from_raw_os_error(libc::ENOMEM))
+}
+
+impl MmapVec {
+    /// Empty vector without a mapping
+    #[inline]
+    pub fn new() -> Self {
+        Self { map: None, len: 0 }
+    }
+    /// Empty vector with at least the given capacity rounded up to the page size
+    #[inline]
+    pub fn with_capacity(capacity: usize) -> Result<Self, AnonymousMmapError> {
+        let mut vec = Self::new();
+        vec.reserve(capacity)?;
+        Ok(vec)
+    }
+    /// Bytes pushed
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    /// Nothing pushed
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    /// Bytes mapped
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.map.as_ref().map_or(0, |map| map.len)
+    }
+    /// The pushed bytes
+    #[inline]
+    pub fn as_slice(&self) -> &[u8] {
+        match &self.map {
+            // SAFETY: [0, len) is within the mapping and initialized.
+            Some(map) => unsafe { core::slice::from_raw_parts(map.as_ptr().cast(), self.len) },
+            None => &[],
+        }
+    }
+    /// The pushed bytes mutably
+    #[inline]
+    pub fn as_mut_slice(&mut self) -> &mut [u8] {
+        match &mut self.map {
+            // SAFETY: [0, len) is within the mapping, initialized and borrowed exclusively.
+            Some(map) => unsafe {
+                core::slice::from_raw_parts_mut(map.as_ptr_mut().cast(), self.len)
+            },
+            None => &mut [],
+        }
+    }
+    /// Append the bytes growing the capacity by doubling when needed.
+    #[inline]
+    pub fn push_slice(&mut self, bytes: &[u8]) -> Result<(), AnonymousMmapError> {
+        self.reserve(bytes.len())?;
+        if let Some(map) = &mut self.map {
+            // SAFETY: reserve made room for bytes after len.
+            unsafe {
+                core::ptr::copy_nonoverlapping(
+                    bytes.as_ptr(),
+                    map.as_ptr_mut().cast::<u8>().add(self.len),
+                    bytes.len(),
+                )
+            };
+        }
+        self.len += bytes.len();
+        Ok(())
+    }
+    /// Make room for at least additional more bytes doubling the capacity, moving if needed.
+    #[inline]
+    pub fn reserve(&mut self, additional: usize) -> Result<(), AnonymousMmapError> {
+        self.grow(additional, true)
+    }
+    /// Make room for at least additional more bytes growing the mapping in place only and
+    /// failing with [`AnonymousMmapError::MremapFailed`] ENOMEM if the next range is occupied.
+    #[inline]
+    pub fn try_reserve_in_place(&mut self, additional: usize) -> Result<(), AnonymousMmapError> {
+        self.grow(additional, false)
+    }
+    #[inline]
+    fn grow(&mut self, additional: usize, may_move: bool) -> Result<(), AnonymousMmapError> {
+        let required = self
+            .len
+            .checked_add(additional)
+            .ok_or_else(capacity_overflow)?;
+        let capacity = self.capacity();
+        if required <= capacity {
+            return Ok(());
+        }
+        let doubled = match may_move {
+            true => required.max(capacity.saturating_mul(2)),
+            false => required,
+        };
+        let new_capacity = round_to_page(doubled).ok_or_else(capacity_overflow)?;
+        match &mut self.map {
+            // SAFETY: No pointers into the mapping outlive the &mut self borrow.
+            Some(map) => unsafe { map.remap(new_capacity, may_move) },
+            None => {
+                self.map = Some(AnonymousMmap::mmap_with(
+                    core::ptr::null_mut(),
+                    new_capacity,
+                    VEC_FLAGS,
+                )?);
+                Ok(())
+            }
+        }
+    }
+    /// Shrink the capacity to the len rounded up to the page size, unmapping all if empty.
+    /// A failing munmap(2) keeps the mapping and is [`AnonymousMmapError::Unsupported`].
+    #[inline]
+    pub fn shrink_to_fit(&mut self) -> Result<(), AnonymousMmapError> {
+        if self.len == 0 {
+            if let Some(map) = self.map.take() {
+                // SAFETY: No pointers into the mapping outlive the &mut self borrow.
+                match unsafe { map.try_drop() } {
+                    Err(AnonymousMmapError::MunmapFailed(map, e)) => {
+                        // Keep owning the mapping which is still there.
+                        self.map = Some(map);
+                        return Err(AnonymousMmapError::Unsupported("munmap", e));
+                    }
+                    r => r?,
+                }
+            }
+            return Ok(());
+        }
+        let new_capacity = round_to_page(self.len).ok_or_else(capacity_overflow)?;
+        match &mut self.map {
+            // SAFETY: Shrinking keeps [0, len) in place.
+            Some(map) if new_capacity < map.len => unsafe { map.remap(new_capacity, false) },
+            _ => Ok(()),
+        }
+    }
+    /// Truncate to empty returning the pages to the kernel with MADV_DONTNEED while keeping
+    /// the capacity mapped so the next pushes fault in fresh zero-filled pages.
+    #[inline]
+    pub fn clear(&mut self) -> Result<(), AnonymousMmapError> {
+        self.len = 0;
+        match &mut self.map {
+            Some(map) => map.decommit(0, map.len),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for MmapVec {
+    fn drop(&mut self) {
+        if let Some(map) = self.map.take() {
+            // SAFETY: No slices outlive the vector. Nothing to give the error back to.
+            let _ = unsafe { map.try_drop() };
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+
+    use super::*;
+
+    const MIB: usize = 1024 * 1024;
+
+    #[test]
+    fn append_100mb() {
+        let mut vec = MmapVec::new();
+        let mut chunk = vec![0u8; MIB];
+        let mut capacities = vec![];
+        for i in 0..100u8 {
+            chunk.fill(i);
+            vec.push_slice(&chunk).unwrap();
+            if capacities.last() != Some(&vec.capacity()) {
+                capacities.push(vec.capacity());
+            }
+        }
+        assert_eq!(vec.len(), 100 * MIB);
+        // Doubling: 1, 2, 4, .. 128 MiB
+        assert_eq!(capacities, (0..8).map(|s| MIB << s).collect::<Vec<_>>());
+        for (i, chunk) in vec.as_slice().chunks(MIB).enumerate() {
+            assert!(chunk.iter().all(|b| *b == i as u8), "chunk {}", i);
+        }
+    }
+
+    #[test]
+    fn reserve_in_place_keeps_base() {
+        let mut vec = MmapVec::with_capacity(page_size()).unwrap();
+        vec.push_slice(b"journal").unwrap();
+        let base = vec.as_slice().as_ptr();
+        match vec.try_reserve_in_place(page_size() * 16) {
+            Ok(()) => {
+                assert_eq!(vec.as_slice().as_ptr(), base);
+                assert!(vec.capacity() >= page_size() * 17);
+            }
+            Err(e) => {
+                assert_eq!(e.kind(), crate::ErrorKind::OutOfMemory);
+                assert_eq!(vec.capacity(), page_size());
+            }
+        }
+        assert_eq!(vec.as_slice(), b"journal");
+    }
+
+    #[test]
+    fn shrink_and_clear() {
+        let mut vec = MmapVec::with_capacity(page_size() * 8).unwrap();
+        vec.push_slice(&[1; 10]).unwrap();
+        vec.shrink_to_fit().unwrap();
+        assert_eq!(vec.capacity(), page_size());
+        assert_eq!(vec.as_slice(), &[1; 10]);
+
+        vec.clear().unwrap();
+        assert!(vec.is_empty());
+        assert_eq!(vec.capacity(), page_size());
+        vec.reserve(1).unwrap();
+        // Decommitted pages read back zero-filled.
+        assert_eq!(
+            unsafe { *vec.map.as_ref().unwrap().as_ptr().cast::<u8>() },
+            0
+        );
+
+        vec.shrink_to_fit().unwrap();
+        assert_eq!(vec.capacity(), 0);
+        assert_eq!(vec.as_slice(), &[] as &[u8]);
+    }
+
+    #[test]
+    fn mutate_in_place() {
+        let mut vec = MmapVec::new();
+        vec.push_slice(b"abc").unwrap();
+        vec.as_mut_slice()[1] = b'B';
+        assert_eq!(vec.as_slice(), b"aBc");
+    }
+}
@@ -0,0 +1,367 @@
+//! Borrowed views into an [`AnonymousMmap`]
+
+use crate::AnonymousMmap;
+use core::ops::{Bound, RangeBounds};
+
+/// Resolve a range against the given len returning (start, end) if within bounds.
+#[inline]
+pub(crate) fn resolve_range<R: RangeBounds<usize>>(range: R, len: usize) -> Option<(usize, usize)> {
+    let start = match range.start_bound() {
+        Bound::Included(s) => *s,
+        Bound::Excluded(s) => s.checked_add(1)?,
+        Bound::Unbounded => 0,
+    };
+    let end = match range.end_bound() {
+        Bound::Included(e) => e.checked_add(1)?,
+        Bound::Excluded(e) => *e,
+        Bound::Unbounded => len,
+    };
+    if start > end || end > len {
+        return None;
+    }
+    Some((start, end))
+}
+
+/// Immutable borrowed window into an [`AnonymousMmap`] which can not outlive the mapping.
+#[derive(Debug, Clone, Copy)]
+pub struct MmapView<'a> {
+    slice: &'a [u8],
+}
+
+impl<'a> MmapView<'a> {
+    /// Provide the raw ptr to the start of the view
+    #[inline]
+    pub fn as_ptr(&self) -> *const u8 {
+        self.slice.as_ptr()
+    }
+    /// As bytes slice tied to the lifetime of the mapping.
+    #[inline]
+    pub fn as_slice(&self) -> &'a [u8] {
+        self.slice
+    }
+    /// Provide the len of the view
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.slice.len()
+    }
+    /// Is the view empty
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.slice.is_empty()
+    }
+}
+
+impl<'a> MmapView<'a> {
+    /// Narrow the view further into the given range within the view.
+    #[inline]
+    pub fn subview<R: RangeBounds<usize>>(&self, range: R) -> Option<MmapView<'a>> {
+        let (start, end) = resolve_range(range, self.slice.len())?;
+        Some(MmapView {
+            slice: &self.slice[start..end],
+        })
+    }
+}
+
+/// Mutable borrowed window into an [`AnonymousMmap`] which can not outlive the mapping.
+/// Disjointness is enforced through the exclusive borrow of the mapping.
+#[derive(Debug)]
+pub struct MmapViewMut<'a> {
+    slice: &'a mut [u8],
+}
+
+impl<'a> MmapViewMut<'a> {
+    /// Provide the raw ptr to the start of the view
+    #[inline]
+    pub fn as_ptr(&self) -> *const u8 {
+        self.slice.as_ptr()
+    }
+    /// Provide the raw mutable ptr to the start of the view
+    #[inline]
+    pub fn as_mut_ptr(&mut self) -> *mut u8 {
+        self.slice.as_mut_ptr()
+    }
+    /// As bytes slice
+    #[inline]
+    pub fn as_slice(&self) -> &[u8] {
+        self.slice
+    }
+    /// As mut bytes slice
+    #[inline]
+    pub fn as_slice_mut(&mut self) -> &mut [u8] {
+        self.slice
+    }
+    /// Provide the len of the view
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.slice.len()
+    }
+    /// Is the view empty
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.slice.is_empty()
+    }
+    /// Narrow the view further into the given range within the view.
+    #[inline]
+    pub fn subview<R: RangeBounds<usize>>(self, range: R) -> Option<MmapViewMut<'a>> {
+        let (start, end) = resolve_range(range, self.slice.len())?;
+        Some(MmapViewMut {
+            slice: &mut self.slice[start..end],
+        })
+    }
+    /// Split the view into two disjoint mutable views at the given offset within the view.
+    #[inline]
+    pub fn split_at(self, at: usize) -> Option<(MmapViewMut<'a>, MmapViewMut<'a>)> {
+        if at > self.slice.len() {
+            return None;
+        }
+        let (head, tail) = self.slice.split_at_mut(at);
+        Some((MmapViewMut { slice: head }, MmapViewMut { slice: tail }))
+    }
+}
+
+impl AnonymousMmap {
+    /// Borrowed view into the given range of the mapping without transferring the ownership.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is out of bounds of the mapping, overlaps an unmapped range or the
+    /// mapping is not readable.
+    #[inline]
+    pub fn subslice<R: RangeBounds<usize>>(&self, range: R) -> MmapView<'_> {
+        match self.view(range) {
+            Some(v) => v,
+            None => panic!(
+                "range out of bounds of the mapping len {}, unmapped or not readable",
+                self.len
+            ),
+        }
+    }
+    /// Bounds checked borrowed view into the given range of the mapping.
+    /// None if out of bounds, overlapping an unmapped range or the mapping is not readable.
+    #[inline]
+    pub fn view<R: RangeBounds<usize>>(&self, range: R) -> Option<MmapView<'_>> {
+        if !self.is_readable() {
+            return None;
+        }
+        let (start, end) = resolve_range(range, self.len)?;
+        if self.overlaps_hole(start, end) {
+            return None;
+        }
+        // SAFETY: The range is within the mapping which is initialized (zero-filled) on construction.
+        let slice = unsafe {
+            core::slice::from_raw_parts(self.addr.as_ptr().cast::<u8>().add(start), end - start)
+        };
+        Some(MmapView { slice })
+    }
+    /// Bounds checked mutable borrowed view into the given range of the mapping.
+    /// None if out of bounds, overlapping an unmapped range or the mapping is not readable and
+    /// writable.
+    #[inline]
+    pub fn view_mut<R: RangeBounds<usize>>(&mut self, range: R) -> Option<MmapViewMut<'_>> {
+        if !self.is_writable() {
+            return None;
+        }
+        let (start, end) = resolve_range(range, self.len)?;
+        if self.overlaps_hole(start, end) {
+            return None;
+        }
+        // SAFETY: The range is within the mapping and the mapping is borrowed exclusively.
+        let slice = unsafe {
+            core::slice::from_raw_parts_mut(self.addr.as_ptr().cast::<u8>().add(start), end - start)
+        };
+        Some(MmapViewMut { slice })
+    }
+    /// Safe counterpart of [`Self::offset_unchecked_as_ptr`] borrowing len bytes at offset.
+    /// None if offset + len overflows or is beyond the mapping, and as with [`Self::view`].
+    #[inline]
+    pub fn offset_slice(&self, offset: usize, len: usize) -> Option<&[u8]> {
+        let end = offset.checked_add(len)?;
+        Some(self.view(offset..end)?.slice)
+    }
+    /// Mutable [`Self::offset_slice`], None also if the mapping is not writable.
+    #[inline]
+    pub fn offset_slice_mut(&mut self, offset: usize, len: usize) -> Option<&mut [u8]> {
+        let end = offset.checked_add(len)?;
+        Some(self.view_mut(offset..end)?.slice)
+    }
+    /// Interpret the whole mapping as a slice of T.
+    ///
+    /// None if the mapping is not aligned to align_of::<T>(), the len is not a multiple of
+    /// size_of::<T>() or part of it is unmapped.
+    ///
+    /// # Safety
+    ///
+    /// The contents of the mapping must be valid bit patterns for T and the mapping readable.
+    #[inline]
+    pub unsafe fn as_typed_slice<T>(&self) -> Option<&[T]> {
+        let (ptr, count) = self.typed_parts::<T>()?;
+        Some(core::slice::from_raw_parts(ptr, count))
+    }
+    /// Interpret the whole mapping as a mut slice of T.
+    ///
+    /// None if the mapping is not aligned to align_of::<T>(), the len is not a multiple of
+    /// size_of::<T>() or part of it is unmapped.
+    ///
+    /// # Safety
+    ///
+    /// The contents of the mapping must be valid bit patterns for T and the mapping writable.
+    #[inline]
+    pub unsafe fn as_typed_slice_mut<T>(&mut self) -> Option<&mut [T]> {
+        let (ptr, count) = self.typed_parts::<T>()?;
+        Some(core::slice::from_raw_parts_mut(ptr, count))
+    }
+    #[inline]
+    fn typed_parts<T>(&self) -> Option<(*mut T, usize)> {
+        let ptr = self.addr.as_ptr().cast::<T>();
+        if size_of::<T>() == 0
+            || !ptr.is_aligned()
+            || !self.len.is_multiple_of(size_of::<T>())
+            || !self.holes.is_empty()
+        {
+            return None;
+        }
+        Some((ptr, self.len / size_of::<T>()))
+    }
+    /// Two disjoint mutable views [0, at) and [at, len) of the mapping at once.
+    #[inline]
+    pub fn split_view_mut(&mut self, at: usize) -> Option<(MmapViewMut<'_>, MmapViewMut<'_>)> {
+        self.view_mut(..)?.split_at(at)
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(.., 0, 128)]
+    #[case(16..32, 16, 16)]
+    #[case(..=63, 0, 64)]
+    #[case(128.., 128, 0)]
+    fn subslices<R: RangeBounds<usize>>(
+        #[case] range: R,
+        #[case] start: usize,
+        #[case] len: usize,
+    ) {
+        let mmap = AnonymousMmap::new(128).unwrap();
+        let view = mmap.subslice(range);
+        assert_eq!(view.len(), len);
+        assert_eq!(
+            view.as_ptr(),
+            unsafe { mmap.offset_unchecked_as_ptr(start as u32) } as *const u8
+        );
+        assert!(view.as_slice().iter().all(|b| *b == 0));
+    }
+
+    #[rstest]
+    #[case(0, 0, Some(0..0))]
+    #[case(0, 4096, Some(0..4096))]
+    #[case(100, 28, Some(100..128))]
+    #[case(4095, 1, Some(4095..4096))]
+    #[case(4096, 0, Some(4096..4096))]
+    #[case(4095, 2, None)]
+    #[case(4097, 0, None)]
+    #[case(1, usize::MAX, None)]
+    fn offset_slices(
+        #[case] offset: usize,
+        #[case] len: usize,
+        #[case] expected: Option<core::ops::Range<usize>>,
+    ) {
+        let mut mmap = AnonymousMmap::new(4096).unwrap();
+        for (i, b) in mmap
+            .offset_slice_mut(0, 4096)
+            .unwrap()
+            .iter_mut()
+            .enumerate()
+        {
+            *b = i as u8;
+        }
+        let sequence: Vec<u8> = (0..4096).map(|i| i as u8).collect();
+        let expected = expected.map(|range| &sequence[range]);
+        assert_eq!(mmap.offset_slice(offset, len), expected);
+        assert_eq!(mmap.offset_slice_mut(offset, len).as_deref(), expected);
+        unsafe { mmap.try_drop().unwrap() };
+    }
+
+    #[test]
+    fn offset_slice_protected() {
+        let mut mmap = AnonymousMmap::new(4096).unwrap();
+        mmap.offset_slice_mut(8, 1).unwrap()[0] = 5;
+        mmap.protect_readonly().unwrap();
+        assert_eq!(mmap.offset_slice(8, 1), Some(&[5u8][..]));
+        assert!(mmap.offset_slice_mut(8, 1).is_none());
+    }
+
+    #[test]
+    #[should_panic]
+    fn subslice_out_of_bounds() {
+        let mmap = AnonymousMmap::new(128).unwrap();
+        mmap.subslice(64..129);
+    }
+
+    #[rstest]
+    #[case(0..129)]
+    #[case(129..)]
+    #[case(..=128)]
+    fn views_out_of_bounds<R: RangeBounds<usize> + Clone>(#[case] range: R) {
+        let mut mmap = AnonymousMmap::new(128).unwrap();
+        assert!(mmap.view(range.clone()).is_none());
+        assert!(mmap.view_mut(range).is_none());
+    }
+
+    #[test]
+    fn subviews() {
+        let mut mmap = AnonymousMmap::new(128).unwrap();
+        {
+            let mut view = mmap.view_mut(32..64).unwrap().subview(8..16).unwrap();
+            assert_eq!(view.len(), 8);
+            view.as_slice_mut().fill(0xAB);
+        }
+        let view = mmap.view(32..).unwrap();
+        let sub = view.subview(8..16).unwrap();
+        assert!(sub.as_slice().iter().all(|b| *b == 0xAB));
+        assert!(view.subview(..=96).is_none());
+        assert_eq!(view.as_slice()[7], 0);
+        assert_eq!(view.as_slice()[16], 0);
+    }
+
+    #[test]
+    fn split_views_mut() {
+        let mut mmap = AnonymousMmap::new(128).unwrap();
+        assert!(mmap.split_view_mut(129).is_none());
+        let (mut head, mut tail) = mmap.split_view_mut(64).unwrap();
+        assert_eq!(head.len(), 64);
+        assert_eq!(tail.len(), 64);
+        head.as_slice_mut().fill(1);
+        tail.as_slice_mut().fill(2);
+        let view = mmap.view(..).unwrap();
+        assert!(view.as_slice()[..64].iter().all(|b| *b == 1));
+        assert!(view.as_slice()[64..].iter().all(|b| *b == 2));
+    }
+
+    #[test]
+    fn typed_slices() {
+        let mut mmap = AnonymousMmap::new(64).unwrap();
+        let words = unsafe { mmap.as_typed_slice_mut::<u64>() }.unwrap();
+        assert_eq!(words.len(), 8);
+        for (i, w) in words.iter_mut().enumerate() {
+            *w = i as u64 * 0x0101_0101;
+        }
+        let words = unsafe { mmap.as_typed_slice::<u64>() }.unwrap();
+        for (i, w) in words.iter().enumerate() {
+            assert_eq!(*w, i as u64 * 0x0101_0101);
+        }
+    }
+
+    #[test]
+    fn typed_slice_len_not_multiple() {
+        let mmap = AnonymousMmap::new(60).unwrap();
+        assert!(unsafe { mmap.as_typed_slice::<u64>() }.is_none());
+        assert_eq!(
+            unsafe { mmap.as_typed_slice::<u32>() }.map(|s| s.len()),
+            Some(15)
+        );
+    }
+}
@@ -0,0 +1,159 @@
+//! Bounds checked volatile and unaligned typed access at offsets of the mapping
+
+use crate::{AnonymousMmap, AnonymousMmapError, MmapHeader};
+
+impl AnonymousMmap {
+    #[inline]
+    fn typed_at<T>(
+        &self,
+        offset: usize,
+        aligned: bool,
+        prot: libc::c_int,
+    ) -> Result<*mut T, AnonymousMmapError> {
+        if self.prot & prot != prot {
+            return Err(AnonymousMmapError::Protected(self.prot));
+        }
+        let p = self.checked_range(offset, size_of::<T>())?.cast::<T>();
+        if aligned && !p.is_aligned() {
+            return Err(AnonymousMmapError::Misaligned(p as usize, align_of::<T>()));
+        }
+        Ok(p)
+    }
+    /// Volatile read of T at the given offset which needs to be aligned for T.
+    ///
+    /// Volatile only keeps the compiler from eliding, merging or reordering the access with
+    /// other volatile ones e.g. for device registers. It is neither atomic nor does it order
+    /// any other memory access, so a writer on another thread or process may be observed torn
+    /// or out of order. Synchronizing with other processes needs atomics with Acquire / Release
+    /// e.g. an AtomicU32 ready flag in a [`Self::map_struct`] header, not volatile.
+    ///
+    /// Errors with [`AnonymousMmapError::OutOfBounds`], [`AnonymousMmapError::Misaligned`],
+    /// [`AnonymousMmapError::Unmapped`] or [`AnonymousMmapError::Protected`] if not readable.
+    #[inline]
+    pub fn read_volatile_at<T: MmapHeader + Copy>(
+        &self,
+        offset: usize,
+    ) -> Result<T, AnonymousMmapError> {
+        let p = self.typed_at::<T>(offset, true, libc::PROT_READ)?;
+        // SAFETY: p is within the mapping, readable, aligned and any bit pattern is a valid T.
+        Ok(unsafe { p.read_volatile() })
+    }
+    /// Volatile write of T at the given offset which needs to be aligned for T - see
+    /// [`Self::read_volatile_at`] on volatile versus atomics.
+    ///
+    /// Errors with [`AnonymousMmapError::OutOfBounds`], [`AnonymousMmapError::Misaligned`],
+    /// [`AnonymousMmapError::Unmapped`] or [`AnonymousMmapError::Protected`] if not writable.
+    #[inline]
+    pub fn write_volatile_at<T: Copy>(
+        &mut self,
+        offset: usize,
+        val: T,
+    ) -> Result<(), AnonymousMmapError> {
+        let p = self.typed_at::<T>(offset, true, libc::PROT_READ | libc::PROT_WRITE)?;
+        // SAFETY: p is within the mapping, writable, aligned and borrowed exclusively.
+        unsafe { p.write_volatile(val) };
+        Ok(())
+    }
+    /// Read of T at the given offset regardless of its alignment e.g. for packed wire formats.
+    ///
+    /// Errors with [`AnonymousMmapError::OutOfBounds`], [`AnonymousMmapError::Unmapped`] or
+    /// [`AnonymousMmapError::Protected`] if not readable.
+    #[inline]
+    pub fn read_unaligned_at<T: MmapHeader + Copy>(
+        &self,
+        offset: usize,
+    ) -> Result<T, AnonymousMmapError> {
+        let p = self.typed_at::<T>(offset, false, libc::PROT_READ)?;
+        // SAFETY: p is within the mapping, readable and any bit pattern is a valid T.
+        Ok(unsafe { p.read_unaligned() })
+    }
+    /// Write of T at the given offset regardless of its alignment.
+    ///
+    /// Errors with [`AnonymousMmapError::OutOfBounds`], [`AnonymousMmapError::Unmapped`] or
+    /// [`AnonymousMmapError::Protected`] if not writable.
+    #[inline]
+    pub fn write_unaligned_at<T: Copy>(
+        &mut self,
+        offset: usize,
+        val: T,
+    ) -> Result<(), AnonymousMmapError> {
+        let p = self.typed_at::<T>(offset, false, libc::PROT_READ | libc::PROT_WRITE)?;
+        // SAFETY: p is within the mapping, writable and borrowed exclusively.
+        unsafe { p.write_unaligned(val) };
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(0)]
+    #[case(8)]
+    #[case(4088)]
+    fn volatile_roundtrip(#[case] offset: usize) {
+        let mut mmap = AnonymousMmap::new(4096).unwrap();
+        mmap.write_volatile_at(offset, 0xC0FF_EE00_u64).unwrap();
+        assert_eq!(mmap.read_volatile_at::<u64>(offset).unwrap(), 0xC0FF_EE00);
+        assert_eq!(mmap.read_unaligned_at::<u64>(offset).unwrap(), 0xC0FF_EE00);
+        unsafe { mmap.try_drop().unwrap() };
+    }
+
+    #[rstest]
+    #[case(1)]
+    #[case(4087)]
+    fn unaligned_roundtrip(#[case] offset: usize) {
+        let mut mmap = AnonymousMmap::new(4096).unwrap();
+        mmap.write_unaligned_at(offset, 0x0102_0304_0506_0708_u64)
+            .unwrap();
+        assert_eq!(
+            mmap.read_unaligned_at::<u64>(offset).unwrap(),
+            0x0102_0304_0506_0708
+        );
+        assert!(matches!(
+            mmap.read_volatile_at::<u64>(offset),
+            Err(AnonymousMmapError::Misaligned(_, 8))
+        ));
+        assert!(matches!(
+            mmap.write_volatile_at(offset, 0u64),
+            Err(AnonymousMmapError::Misaligned(_, 8))
+        ));
+        unsafe { mmap.try_drop().unwrap() };
+    }
+
+    #[rstest]
+    #[case(4089)]
+    #[case(usize::MAX)]
+    fn out_of_bounds(#[case] offset: usize) {
+        let mut mmap = AnonymousMmap::new(4096).unwrap();
+        assert!(matches!(
+            mmap.read_unaligned_at::<u64>(offset.max(4092)),
+            Err(AnonymousMmapError::OutOfBounds(..))
+        ));
+        assert!(matches!(
+            mmap.write_unaligned_at(offset, 0u64),
+            Err(AnonymousMmapError::OutOfBounds(..))
+        ));
+        unsafe { mmap.try_drop().unwrap() };
+    }
+
+    #[test]
+    fn protected() {
+        let mut mmap = AnonymousMmap::new(4096).unwrap();
+        mmap.protect_readonly().unwrap();
+        assert_eq!(mmap.read_volatile_at::<u32>(0).unwrap(), 0);
+        assert!(matches!(
+            mmap.write_volatile_at(0, 1u32),
+            Err(AnonymousMmapError::Protected(libc::PROT_READ))
+        ));
+        mmap.protect_none().unwrap();
+        assert!(matches!(
+            mmap.read_unaligned_at::<u32>(0),
+            Err(AnonymousMmapError::Protected(libc::PROT_NONE))
+        ));
+        unsafe { mmap.try_drop().unwrap() };
+    }
+}
@@ -0,0 +1,210 @@
+//! RAII write windows over a read-only [`AnonymousMmap`]
+
+use crate::view::resolve_range;
+use crate::{AnonymousMmap, AnonymousMmapError, OsError};
+use core::ops::{Deref, DerefMut, RangeBounds};
+
+/// What dropping a [`WriteGuard`] does when restoring the protection fails
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RestorePolicy {
+    /// Ignore the failure leaving the window writable
+    BestEffort,
+    /// Panic unless already unwinding
+    Panic,
+}
+
+/// Writable window into a range of an [`AnonymousMmap`] from [`AnonymousMmap::write_window`]
+/// which restores the protection of the mapping when dropped.
+///
+/// Only one window can be outstanding at a time as it borrows the mapping exclusively.
+#[derive(Debug)]
+pub struct WriteGuard<'a> {
+    mmap: &'a mut AnonymousMmap,
+    offset: usize,
+    len: usize,
+    policy: RestorePolicy,
+    restored: bool,
+}
+
+impl AnonymousMmap {
+    /// Make the given page-aligned range PROT_READ | PROT_WRITE until the returned guard is
+    /// dropped, restoring the protection of the mapping e.g. PROT_READ of a mapping kept
+    /// read-only through [`Self::protect_readonly`] in steady state.
+    ///
+    /// A failure to restore in Drop is ignored, see [`Self::write_window_with`] to panic
+    /// instead or [`WriteGuard::restore`] to handle it.
+    #[inline]
+    pub fn write_window<R: RangeBounds<usize>>(
+        &mut self,
+        range: R,
+    ) -> Result<WriteGuard<'_>, AnonymousMmapError> {
+        self.write_window_with(range, RestorePolicy::BestEffort)
+    }
+    /// Like [`Self::write_window`] with the given [`RestorePolicy`] for Drop.
+    #[inline]
+    pub fn write_window_with<R: RangeBounds<usize>>(
+        &mut self,
+        range: R,
+        policy: RestorePolicy,
+    ) -> Result<WriteGuard<'_>, AnonymousMmapError> {
+        if self.sealed {
+            return Err(AnonymousMmapError::Sealed("mprotect"));
+        }
+        let bounds = (range.start_bound().cloned(), range.end_bound().cloned());
+        let (start, end) = match resolve_range(bounds, self.len) {
+            Some(bounds) => bounds,
+            None => {
+                let (start, end) = resolve_range(bounds, usize::MAX).unwrap_or((0, usize::MAX));
+                return Err(AnonymousMmapError::OutOfBounds(
+                    start,
+                    end.saturating_sub(start),
+                ));
+            }
+        };
+        let p = self.page_range(start, end - start)?;
+        // SAFETY: The range is checked to be within the mapping which is borrowed exclusively.
+        if unsafe { libc::mprotect(p, end - start, libc::PROT_READ | libc::PROT_WRITE) } != 0 {
+            return Err(AnonymousMmapError::MprotectFailed(OsError::last_os_error()));
+        }
+        Ok(WriteGuard {
+            mmap: self,
+            offset: start,
+            len: end - start,
+            policy,
+            restored: false,
+        })
+    }
+}
+
+impl WriteGuard<'_> {
+    /// Restore the protection of the mapping over the window now, surfacing the error
+    /// Drop would otherwise handle as per the [`RestorePolicy`].
+    #[inline]
+    pub fn restore(mut self) -> Result<(), AnonymousMmapError> {
+        self.restored = true;
+        self.mprotect_back()
+    }
+    #[inline]
+    fn mprotect_back(&self) -> Result<(), AnonymousMmapError> {
+        // SAFETY: The window is within the mapping and the slice borrow ends with the guard.
+        let p = unsafe { self.mmap.as_ptr_mut().add(self.offset) };
+        // SAFETY: Only the protection of our own window changes.
+        if unsafe { libc::mprotect(p, self.len, self.mmap.prot) } != 0 {
+            return Err(AnonymousMmapError::MprotectFailed(OsError::last_os_error()));
+        }
+        Ok(())
+    }
+}
+
+impl Deref for WriteGuard<'_> {
+    type Target = [u8];
+    #[inline]
+    fn deref(&self) -> &[u8] {
+        // SAFETY: The window is within the mapping, readable and borrowed exclusively.
+        unsafe {
+            let p = self.mmap.addr.as_ptr().cast::<u8>().add(self.offset);
+            core::slice::from_raw_parts(p, self.len)
+        }
+    }
+}
+
+impl DerefMut for WriteGuard<'_> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut [u8] {
+        // SAFETY: The window is within the mapping, writable and borrowed exclusively.
+        unsafe {
+            let p = self.mmap.addr.as_ptr().cast::<u8>().add(self.offset);
+            core::slice::from_raw_parts_mut(p, self.len)
+        }
+    }
+}
+
+impl Drop for WriteGuard<'_> {
+    fn drop(&mut self) {
+        if self.restored {
+            return;
+        }
+        if let Err(e) = self.mprotect_back() {
+            if self.policy == RestorePolicy::Panic {
+                #[cfg(feature = "std")]
+                if std::thread::panicking() {
+                    return;
+                }
+                panic!("restoring write window protection: {}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use crate::page_size;
+    use rstest::rstest;
+
+    // Write through the raw pointer in a fork child to see whether the page is writable.
+    fn child_can_write(mmap: &AnonymousMmap, offset: usize) -> bool {
+        match unsafe { libc::fork() } {
+            0 => {
+                unsafe { mmap.as_ptr_mut().cast::<u8>().add(offset).write_volatile(1) };
+                unsafe { libc::_exit(0) };
+            }
+            pid => {
+                assert!(pid > 0);
+                let mut status = 0;
+                assert_eq!(unsafe { libc::waitpid(pid, &mut status, 0) }, pid);
+                libc::WIFEXITED(status) && libc::WEXITSTATUS(status) == 0
+            }
+        }
+    }
+
+    #[test]
+    fn window_writes_then_readonly() {
+        let page = page_size();
+        let mut mmap = AnonymousMmap::new(page * 4).unwrap();
+        mmap.protect_readonly().unwrap();
+        {
+            let mut window = mmap.write_window(page..page * 3).unwrap();
+            assert_eq!(window.len(), page * 2);
+            window.fill(0x42);
+        }
+        assert!(!mmap.is_writable());
+        assert!(!child_can_write(&mmap, page));
+        let view = mmap.view(..).unwrap();
+        assert!(view.as_slice()[..page].iter().all(|b| *b == 0));
+        assert!(view.as_slice()[page..page * 3].iter().all(|b| *b == 0x42));
+        assert!(view.as_slice()[page * 3..].iter().all(|b| *b == 0));
+    }
+
+    #[test]
+    fn window_restore_explicit() {
+        let page = page_size();
+        let mut mmap = AnonymousMmap::new(page * 2).unwrap();
+        mmap.protect_readonly().unwrap();
+        let mut window = mmap.write_window_with(.., RestorePolicy::Panic).unwrap();
+        window[0] = 7;
+        window.restore().unwrap();
+        assert!(!child_can_write(&mmap, 0));
+        assert_eq!(mmap.view(..1).unwrap().as_slice(), &[7]);
+    }
+
+    #[rstest]
+    #[case(1..4096)]
+    #[case(0..1)]
+    #[case(4096..16384)]
+    #[case(16384..16385)]
+    fn window_checked(#[case] range: core::ops::Range<usize>) {
+        let mut mmap = AnonymousMmap::new(page_size() * 2).unwrap();
+        mmap.protect_readonly().unwrap();
+        let r = mmap.write_window(range);
+        assert!(
+            matches!(
+                r,
+                Err(AnonymousMmapError::NotPageAligned(..) | AnonymousMmapError::OutOfBounds(..))
+            ),
+            "{:?}",
+            r
+        );
+    }
+}
@@ -0,0 +1,127 @@
+//! Arena [`Allocator`] over [`HugePageBytes`] on nightly
+
+use crate::HugePageBytes;
+use core::alloc::{AllocError, Allocator, Layout};
+use core::cell::Cell;
+use core::ptr::NonNull;
+
+/// Bump allocation of sub-regions out of the capacity of the exclusively borrowed
+/// [`HugePageBytes`] where deallocation is a no-op and the space is only given back through
+/// [`Self::reset`].
+///
+/// The exclusive borrow keeps the slices of [`HugePageBytes::page_slice`] from being used
+/// over the regions handed out while the arena lives.
+#[derive(Debug)]
+pub struct HugePageArena<'a> {
+    hp: &'a mut HugePageBytes,
+    cursor: Cell<usize>,
+}
+
+impl<'a> HugePageArena<'a> {
+    /// Arena bump allocating from the start of hp
+    #[inline]
+    pub fn new(hp: &'a mut HugePageBytes) -> Self {
+        Self {
+            hp,
+            cursor: Cell::new(0),
+        }
+    }
+    /// Give back everything bump allocated at once, the exclusive borrow making sure none
+    /// of the allocations is still live.
+    #[inline]
+    pub fn reset(&mut self) {
+        self.cursor.set(0);
+    }
+    /// Bytes bump allocated since the last [`Self::reset`] including alignment padding
+    #[inline]
+    pub fn allocated(&self) -> usize {
+        self.cursor.get()
+    }
+    /// Capacity of the underlying [`HugePageBytes`]
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.hp.capacity()
+    }
+}
+
+unsafe impl Allocator for HugePageArena<'_> {
+    #[inline]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let base = self.hp.addr as usize;
+        let at = base.checked_add(self.cursor.get()).ok_or(AllocError)?;
+        let start = at
+            .checked_next_multiple_of(layout.align())
+            .ok_or(AllocError)?
+            - base;
+        let end = start.checked_add(layout.size()).ok_or(AllocError)?;
+        if end > self.capacity() {
+            return Err(AllocError);
+        }
+        self.cursor.set(end);
+        // SAFETY: [start, end) is within the capacity and not handed out since the last reset.
+        let p = unsafe { NonNull::new_unchecked(self.hp.addr.add(start)) };
+        Ok(NonNull::slice_from_raw_parts(p, layout.size()))
+    }
+    #[inline]
+    unsafe fn deallocate(&self, _ptr: NonNull<u8>, _layout: Layout) {}
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use crate::HugePageChoice;
+    use rstest::rstest;
+
+    #[test]
+    fn boxed_within_mapping() {
+        let mut hp = HugePageBytes::new(HugePageChoice::HUGE_2MB).unwrap();
+        let range = hp.addr as usize..hp.addr as usize + hp.capacity();
+        let mut arena = HugePageArena::new(&mut hp);
+        {
+            let a = Box::new_in([7u8; 64], &arena);
+            let b = Box::new_in([9u8; 64], &arena);
+            for p in [a.as_ptr() as usize, b.as_ptr() as usize] {
+                assert!(range.contains(&p));
+                assert!(range.contains(&(p + 63)));
+            }
+            assert_eq!(b.as_ptr() as usize - a.as_ptr() as usize, 64);
+            assert!(a.iter().all(|x| *x == 7));
+            assert!(b.iter().all(|x| *x == 9));
+            assert_eq!(arena.allocated(), 128);
+        }
+        arena.reset();
+        assert_eq!(arena.allocated(), 0);
+        let c = Box::new_in(1u64, &arena);
+        assert_eq!(&*c as *const u64 as usize, range.start);
+        drop(c);
+        hp.try_drop().unwrap();
+    }
+
+    #[rstest]
+    #[case(&[(1, 1), (8, 8)], 16)]
+    #[case(&[(3, 1), (1, 4096)], 4097)]
+    #[case(&[(0, 64), (1, 1)], 1)]
+    fn aligned_bumps(#[case] layouts: &[(usize, usize)], #[case] allocated: usize) {
+        let mut hp = HugePageBytes::new(HugePageChoice::HUGE_2MB).unwrap();
+        let arena = HugePageArena::new(&mut hp);
+        for (size, align) in layouts {
+            let layout = Layout::from_size_align(*size, *align).unwrap();
+            let p = arena.allocate(layout).unwrap();
+            assert_eq!(p.len(), *size);
+            assert!((p.as_ptr() as *mut u8 as usize).is_multiple_of(*align));
+        }
+        assert_eq!(arena.allocated(), allocated);
+        hp.try_drop().unwrap();
+    }
+
+    #[test]
+    fn exhausted() {
+        let mut hp = HugePageBytes::new(HugePageChoice::HUGE_2MB).unwrap();
+        let arena = HugePageArena::new(&mut hp);
+        let all = Layout::from_size_align(arena.capacity(), 1).unwrap();
+        assert!(arena.allocate(all).is_ok());
+        assert!(arena.allocate(Layout::new::<u8>()).is_err());
+        hp.try_drop().unwrap();
+    }
+}
@@ -6,6 +6,7 @@
     unused_qualifications
 )]
 #![doc = include_str!("../README.md")]
+#![cfg_attr(feature = "nightly", feature(allocator_api))]
 
 // /sys/kernel/mm/hugepages/hugepages-
 // hugepages-1 048 576kB/  hugepages-2048kB/     hugepages-32768kB/    hugepages-64kB/
@@ -13,6 +14,11 @@
 #[cfg(not(target_os = "linux"))]
 compile_error!("Crate hugepages is Linux specific dependency but is used in non-linux system.");
 
+#[cfg(feature = "nightly")]
+mod allocator;
+#[cfg(feature = "nightly")]
+pub use allocator::HugePageArena;
+
 /// Error
 #[derive(Debug)]
 pub enum HugePageBytesError {
@@ -20,6 +26,11 @@ pub enum HugePageBytesError {
     MmapFailed(std::io::Error),
     /// Call to munmap failed with errno with the non-dropped Self given back.
     MunmapFailed(HugePageBytes, std::io::Error),
+    /// The choice is not supported on the current architecture,
+    /// see [`HugePageChoice::is_supported_on_current_arch`]
+    Unsupported(HugePageChoice),
+    /// The given number of pages of the choice overflows the address space
+    TooLarge(HugePageChoice, usize),
 }
 
 impl core::fmt::Display for HugePageBytesError {
@@ -27,18 +38,58 @@ impl core::fmt::Display for HugePageBytesError {
         match self {
             Self::MmapFailed(e) => write!(f, "mmap Failed: {}", e),
             Self::MunmapFailed(tlb, e) => write!(f, "Drop / munmap on {:?} Failed: {}", tlb, e),
+            Self::Unsupported(choice) => write!(
+                f,
+                "{} huge pages are not supported on this architecture",
+                choice.human_size()
+            ),
+            Self::TooLarge(choice, pages) => write!(
+                f,
+                "{} {} huge pages overflow the address space",
+                pages,
+                choice.human_size()
+            ),
         }
     }
 }
 
 impl core::error::Error for HugePageBytesError {}
 
+/// Clones [`HugePageBytesError::MunmapFailed`] into [`HugePageBytesError::MmapFailed`] of
+/// the same errno as the mapping is uniquely owned and stays with the original.
+///
+/// std::io::Error is not Clone so it is rebuilt from its errno, or from its kind and message
+/// for errors without one.
+impl Clone for HugePageBytesError {
+    fn clone(&self) -> Self {
+        let clone_io = |e: &std::io::Error| match e.raw_os_error() {
+            Some(errno) => std::io::Error::from_raw_os_error(errno),
+            None => std::io::Error::new(e.kind(), e.to_string()),
+        };
+        match self {
+            Self::MmapFailed(e) | Self::MunmapFailed(_, e) => Self::MmapFailed(clone_io(e)),
+            Self::Unsupported(choice) => Self::Unsupported(*choice),
+            Self::TooLarge(choice, pages) => Self::TooLarge(*choice, *pages),
+        }
+    }
+}
+
 /// HugePage Bytes constructs large size continuous byteslices through mmap() using Linux HugeTLB feature.
 /// See https://www.kernel.org/doc/Documentation/admin-guide/mm/hugetlbpage.rst
-#[derive(Debug)]
 pub struct HugePageBytes {
     addr: *mut u8,
     tlb_choice: HugePageChoice,
+    len: usize,
+}
+
+impl core::fmt::Debug for HugePageBytes {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("HugePageBytes")
+            .field("addr", &format_args!("{:#x}", self.addr as usize))
+            .field("size", &self.tlb_choice.human_size())
+            .field("capacity", &self.capacity())
+            .finish()
+    }
 }
 
 /// It is the responsibility of the application to understand which sizes are both configured and supported in the kernel.
@@ -84,8 +135,52 @@ impl HugePageChoice {
             Self::HUGE_2GB => libc::MAP_HUGE_2GB,
             #[cfg(not(target_pointer_width = "32"))]
             Self::HUGE_16GB => libc::MAP_HUGE_16GB,
+            #[cfg(target_pointer_width = "32")]
+            Self::HUGE_2GB | Self::HUGE_16GB => self.unsupported_on_32bit(),
+        }
+    }
+    /// Human readable size of one page of this choice e.g. "2 MiB"
+    #[inline]
+    pub fn human_size(&self) -> &'static str {
+        match self {
+            Self::HUGE_64KB => "64 KiB",
+            Self::HUGE_512KB => "512 KiB",
+            Self::HUGE_1MB => "1 MiB",
+            Self::HUGE_2MB => "2 MiB",
+            Self::HUGE_8MB => "8 MiB",
+            Self::HUGE_16MB => "16 MiB",
+            Self::HUGE_32MB => "32 MiB",
+            Self::HUGE_256MB => "256 MiB",
+            Self::HUGE_512MB => "512 MiB",
+            Self::HUGE_1GB => "1 GiB",
+            Self::HUGE_2GB => "2 GiB",
+            Self::HUGE_16GB => "16 GiB",
         }
     }
+    /// Can the choice be mapped on the current architecture at all - 2 GiB and 16 GiB pages do
+    /// not fit the 32 bit address space. Whether the size is configured in the running kernel
+    /// is still up to the application.
+    #[inline]
+    pub fn is_supported_on_current_arch(self) -> bool {
+        !(cfg!(target_pointer_width = "32") && matches!(self, Self::HUGE_2GB | Self::HUGE_16GB))
+    }
+    #[cfg(target_pointer_width = "32")]
+    #[inline]
+    fn unsupported_on_32bit(&self) -> ! {
+        panic!(
+            "HugePageChoice {:?} is not supported on 32 bit targets, check is_supported_on_current_arch",
+            self
+        )
+    }
+    /// Size of one page of this choice in bytes
+    ///
+    /// # Panics
+    ///
+    /// Panics if the choice is not [`Self::is_supported_on_current_arch`].
+    #[inline]
+    pub fn size_bytes(&self) -> usize {
+        self.as_libc_usize()
+    }
     #[inline]
     fn as_libc_usize(&self) -> usize {
         match self {
@@ -103,6 +198,8 @@ impl HugePageChoice {
             Self::HUGE_2GB => 2_147_483_648,
             #[cfg(not(target_pointer_width = "32"))]
             Self::HUGE_16GB => 17_179_869_184,
+            #[cfg(target_pointer_width = "32")]
+            Self::HUGE_2GB | Self::HUGE_16GB => self.unsupported_on_32bit(),
         }
     }
 }
@@ -111,12 +208,32 @@ impl HugePageBytes {
     /// The range of HugeTLB page sixes can be discovered from /sys/kernel/mm/hugepages.
     /// It is the responsibility of the application to know which sizes are supported on
     /// the running system.  See mmap(2) man page for details.
+    ///
+    /// Choices not [`HugePageChoice::is_supported_on_current_arch`] are
+    /// [`HugePageBytesError::Unsupported`].
     #[inline]
     pub fn new(tlb_choice: HugePageChoice) -> Result<Self, HugePageBytesError> {
+        Self::new_n_pages(tlb_choice, 1)
+    }
+    /// Same as [`Self::new`] but allocates n continuous pages of the given choice.
+    ///
+    /// More pages than fit the address space are [`HugePageBytesError::TooLarge`].
+    #[inline]
+    pub fn new_n_pages(
+        tlb_choice: HugePageChoice,
+        pages: usize,
+    ) -> Result<Self, HugePageBytesError> {
+        if !tlb_choice.is_supported_on_current_arch() {
+            return Err(HugePageBytesError::Unsupported(tlb_choice));
+        }
+        let len = tlb_choice
+            .as_libc_usize()
+            .checked_mul(pages)
+            .ok_or(HugePageBytesError::TooLarge(tlb_choice, pages))?;
         let p = unsafe {
             libc::mmap(
                 core::ptr::null_mut(),
-                tlb_choice.as_libc_usize(),
+                len,
                 libc::PROT_READ | libc::PROT_WRITE,
                 libc::MAP_PRIVATE
                     | libc::MAP_ANONYMOUS
@@ -136,12 +253,48 @@ impl HugePageBytes {
         Ok(Self {
             addr: p as *mut u8,
             tlb_choice,
+            len,
         })
     }
     /// Provide the capacity
     #[inline]
     pub fn capacity(&self) -> usize {
-        self.tlb_choice.as_libc_usize()
+        self.len
+    }
+    /// Size of a single huge page in bytes
+    #[inline]
+    pub fn page_size(&self) -> usize {
+        self.tlb_choice.size_bytes()
+    }
+    /// Number of huge pages allocated
+    #[inline]
+    pub fn page_count(&self) -> usize {
+        self.capacity() / self.page_size()
+    }
+    /// Bytes slice of exactly one huge page at the given index
+    #[inline]
+    pub fn page_slice(&self, index: usize) -> Option<&[u8]> {
+        if index >= self.page_count() {
+            return None;
+        }
+        // SAFETY: The page is within the allocated capacity.
+        Some(unsafe {
+            core::slice::from_raw_parts(self.addr.add(index * self.page_size()), self.page_size())
+        })
+    }
+    /// Mut bytes slice of exactly one huge page at the given index
+    #[inline]
+    pub fn page_slice_mut(&mut self, index: usize) -> Option<&mut [u8]> {
+        if index >= self.page_count() {
+            return None;
+        }
+        // SAFETY: The page is within the allocated capacity.
+        Some(unsafe {
+            core::slice::from_raw_parts_mut(
+                self.addr.add(index * self.page_size()),
+                self.page_size(),
+            )
+        })
     }
     /// As a mut bytes slice.
     #[inline]
@@ -149,6 +302,12 @@ impl HugePageBytes {
         // SAFETY: Construct assumes valid construction and initialization with the given capacity.
         unsafe { core::slice::from_raw_parts_mut(self.as_mut_ptr(), self.capacity()) }
     }
+    /// Zero the whole capacity through ptr::write_bytes which compiles into memset.
+    #[inline]
+    pub fn zero(&mut self) {
+        // SAFETY: The whole capacity is writable and borrowed exclusively.
+        unsafe { core::ptr::write_bytes(self.addr, 0, self.capacity()) };
+    }
     /// Provide the raw unsafe mutable ptr of the allocated TLB.
     /// Same warnigns apply as [`slice::as_mut_ptr`](https://doc.rust-lang.org/std/primitive.slice.html#method.as_mut_ptr).    
     #[inline]
@@ -159,12 +318,7 @@ impl HugePageBytes {
     #[inline]
     pub fn try_drop(self) -> Result<(), HugePageBytesError> {
         // SAFETY: Construct assumes valid construction and initialization with the given capacity.
-        let p = unsafe {
-            libc::munmap(
-                self.addr as *mut libc::c_void,
-                self.tlb_choice.as_libc_usize(),
-            )
-        };
+        let p = unsafe { libc::munmap(self.addr as *mut libc::c_void, self.capacity()) };
         if p != 0 {
             let os_err = std::io::Error::last_os_error();
             return Err(HugePageBytesError::MunmapFailed(self, os_err));
@@ -195,4 +349,79 @@ mod test {
     fn choices(#[case] tlb_choice: HugePageChoice) {
         HugePageBytes::new(tlb_choice).unwrap();
     }
+
+    #[rstest]
+    #[case(HugePageChoice::HUGE_2MB, true)]
+    #[case(HugePageChoice::HUGE_1GB, true)]
+    #[case(HugePageChoice::HUGE_2GB, cfg!(not(target_pointer_width = "32")))]
+    #[case(HugePageChoice::HUGE_16GB, cfg!(not(target_pointer_width = "32")))]
+    fn supported_on_current_arch(#[case] tlb_choice: HugePageChoice, #[case] expected: bool) {
+        assert_eq!(tlb_choice.is_supported_on_current_arch(), expected);
+    }
+
+    #[test]
+    fn pages_independent() {
+        let mut hp = HugePageBytes::new_n_pages(HugePageChoice::HUGE_2MB, 2).unwrap();
+        assert_eq!(hp.page_size(), 2_097_152);
+        assert_eq!(hp.page_count(), 2);
+        assert_eq!(hp.capacity(), 2 * 2_097_152);
+        hp.page_slice_mut(0).unwrap().fill(0xAB);
+        assert!(hp.page_slice(1).unwrap().iter().all(|b| *b == 0));
+        assert!(hp.page_slice(0).unwrap().iter().all(|b| *b == 0xAB));
+        assert!(hp.page_slice(2).is_none());
+        assert!(hp.page_slice_mut(2).is_none());
+        hp.try_drop().unwrap();
+    }
+
+    #[test]
+    fn too_many_pages() {
+        match HugePageBytes::new_n_pages(HugePageChoice::HUGE_2MB, usize::MAX) {
+            Err(HugePageBytesError::TooLarge(choice, usize::MAX)) => {
+                assert_eq!(choice.size_bytes(), 2_097_152)
+            }
+            other => panic!("Expected TooLarge, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn zeroes() {
+        let mut hp = HugePageBytes::new(HugePageChoice::HUGE_2MB).unwrap();
+        hp.as_slice_mut().fill(0xAB);
+        hp.zero();
+        assert!(hp.as_slice_mut().iter().all(|b| *b == 0));
+        hp.try_drop().unwrap();
+    }
+
+    #[test]
+    fn debug_addr_size() {
+        let hp = HugePageBytes::new(HugePageChoice::HUGE_2MB).unwrap();
+        let debug = format!("{:?}", hp);
+        assert!(debug.contains("addr: 0x"));
+        assert!(debug.contains(r#"size: "2 MiB""#));
+        assert!(debug.contains("capacity: 2097152"));
+        hp.try_drop().unwrap();
+    }
+
+    #[rstest]
+    #[case(HugePageBytesError::MmapFailed(std::io::Error::from_raw_os_error(libc::ENOMEM)))]
+    #[case(HugePageBytesError::Unsupported(HugePageChoice::HUGE_16GB))]
+    #[case(HugePageBytesError::TooLarge(HugePageChoice::HUGE_1GB, usize::MAX))]
+    fn clone_as_is(#[case] err: HugePageBytesError) {
+        assert_eq!(err.clone().to_string(), err.to_string());
+    }
+
+    #[test]
+    fn clone_munmap_failed() {
+        let hp = HugePageBytes::new(HugePageChoice::HUGE_2MB).unwrap();
+        let err =
+            HugePageBytesError::MunmapFailed(hp, std::io::Error::from_raw_os_error(libc::EINVAL));
+        match err.clone() {
+            HugePageBytesError::MmapFailed(e) => assert_eq!(e.raw_os_error(), Some(libc::EINVAL)),
+            other => panic!("Expected MmapFailed, got {:?}", other),
+        }
+        let HugePageBytesError::MunmapFailed(hp, _) = err else {
+            panic!("Original keeps the mapping");
+        };
+        hp.try_drop().unwrap();
+    }
 }
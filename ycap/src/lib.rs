@@ -0,0 +1,311 @@
+#![warn(
+    clippy::unwrap_used,
+    missing_docs,
+    rust_2018_idioms,
+    unused_lifetimes,
+    unused_qualifications
+)]
+#![doc = include_str!("../README.md")]
+
+#[cfg(not(target_os = "linux"))]
+compile_error!("Crate ycap is Linux specific dependency but is used in non-linux system.");
+
+use std::io;
+
+// _LINUX_CAPABILITY_VERSION_3 with the 64 bit sets split over two data structs.
+const LINUX_CAPABILITY_VERSION_3: u32 = 0x2008_0522;
+
+/// The kernel struct __user_cap_header_struct from linux/capability.h which libc does not
+/// carry.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct CapUserHeader {
+    version: u32,
+    pid: libc::c_int,
+}
+
+/// The kernel struct __user_cap_data_struct from linux/capability.h which libc does not carry.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct CapUserData {
+    effective: u32,
+    permitted: u32,
+    inheritable: u32,
+}
+
+/// Linux capability from linux/capability.h
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[repr(u32)]
+pub enum Capability {
+    /// CAP_CHOWN: Change file ownership
+    Chown = 0,
+    /// CAP_DAC_OVERRIDE: Bypass file read, write and execute permission checks
+    DacOverride = 1,
+    /// CAP_DAC_READ_SEARCH: Bypass file read and directory read and execute permission checks
+    DacReadSearch = 2,
+    /// CAP_FOWNER: Bypass permission checks requiring the file owner
+    Fowner = 3,
+    /// CAP_FSETID: Keep set-user-ID and set-group-ID bits on file modification
+    Fsetid = 4,
+    /// CAP_KILL: Bypass permission checks for sending signals
+    Kill = 5,
+    /// CAP_SETGID: Manipulate process GIDs
+    Setgid = 6,
+    /// CAP_SETUID: Manipulate process UIDs
+    Setuid = 7,
+    /// CAP_SETPCAP: Manipulate the capability bounding set and other processes capabilities
+    Setpcap = 8,
+    /// CAP_LINUX_IMMUTABLE: Set the immutable and append-only file attributes
+    LinuxImmutable = 9,
+    /// CAP_NET_BIND_SERVICE: Bind to ports below 1024
+    NetBindService = 10,
+    /// CAP_NET_BROADCAST: Make socket broadcasts and listen to multicasts
+    NetBroadcast = 11,
+    /// CAP_NET_ADMIN: Network administration e.g. interfaces, routing and firewalling
+    NetAdmin = 12,
+    /// CAP_NET_RAW: Use RAW and PACKET sockets
+    NetRaw = 13,
+    /// CAP_IPC_LOCK: Lock memory through mlock(2) and mmap(2) MAP_LOCKED
+    IpcLock = 14,
+    /// CAP_IPC_OWNER: Bypass permission checks on System V IPC objects
+    IpcOwner = 15,
+    /// CAP_SYS_MODULE: Load and unload kernel modules
+    SysModule = 16,
+    /// CAP_SYS_RAWIO: Raw I/O port access
+    SysRawio = 17,
+    /// CAP_SYS_CHROOT: Use chroot(2)
+    SysChroot = 18,
+    /// CAP_SYS_PTRACE: Trace arbitrary processes through ptrace(2)
+    SysPtrace = 19,
+    /// CAP_SYS_PACCT: Use acct(2)
+    SysPacct = 20,
+    /// CAP_SYS_ADMIN: System administration e.g. mount(2), sethostname(2) and setns(2)
+    SysAdmin = 21,
+    /// CAP_SYS_BOOT: Use reboot(2) and kexec_load(2)
+    SysBoot = 22,
+    /// CAP_SYS_NICE: Raise the nice value and set real-time scheduling of any process
+    SysNice = 23,
+    /// CAP_SYS_RESOURCE: Override resource limits
+    SysResource = 24,
+    /// CAP_SYS_TIME: Set the system clock
+    SysTime = 25,
+    /// CAP_SYS_TTY_CONFIG: Use vhangup(2) and privileged tty ioctls
+    SysTtyConfig = 26,
+    /// CAP_MKNOD: Create special files through mknod(2)
+    Mknod = 27,
+    /// CAP_LEASE: Establish leases on arbitrary files
+    Lease = 28,
+    /// CAP_AUDIT_WRITE: Write records to the kernel audit log
+    AuditWrite = 29,
+    /// CAP_AUDIT_CONTROL: Configure kernel auditing
+    AuditControl = 30,
+    /// CAP_SETFCAP: Set file capabilities
+    Setfcap = 31,
+    /// CAP_MAC_OVERRIDE: Override Mandatory Access Control
+    MacOverride = 32,
+    /// CAP_MAC_ADMIN: Configure Mandatory Access Control
+    MacAdmin = 33,
+    /// CAP_SYSLOG: Privileged syslog(2) operations
+    Syslog = 34,
+    /// CAP_WAKE_ALARM: Trigger wake up of the system
+    WakeAlarm = 35,
+    /// CAP_BLOCK_SUSPEND: Block system suspend
+    BlockSuspend = 36,
+    /// CAP_AUDIT_READ: Read the audit log through a multicast netlink socket
+    AuditRead = 37,
+    /// CAP_PERFMON: Privileged performance monitoring
+    Perfmon = 38,
+    /// CAP_BPF: Privileged BPF operations
+    Bpf = 39,
+    /// CAP_CHECKPOINT_RESTORE: Checkpoint / restore operations
+    CheckpointRestore = 40,
+}
+
+impl Capability {
+    #[inline]
+    fn index_mask(self) -> (usize, u32) {
+        let bit = self as u32;
+        ((bit / 32) as usize, 1 << (bit % 32))
+    }
+}
+
+/// The effective, permitted and inheritable capability sets of a thread
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CapState {
+    header: CapUserHeader,
+    data: [CapUserData; 2],
+}
+
+macro_rules! cap_set {
+    ($field:ident, $has:ident, $set:ident, $clear:ident, $bits:ident, $name:literal) => {
+        #[doc = concat!("Is the capability in the ", $name, " set")]
+        #[inline]
+        pub fn $has(&self, cap: Capability) -> bool {
+            let (i, mask) = cap.index_mask();
+            self.data[i].$field & mask != 0
+        }
+        #[doc = concat!("Raise the capability in the ", $name, " set")]
+        #[inline]
+        pub fn $set(&mut self, cap: Capability) -> &mut Self {
+            let (i, mask) = cap.index_mask();
+            self.data[i].$field |= mask;
+            self
+        }
+        #[doc = concat!("Drop the capability from the ", $name, " set")]
+        #[inline]
+        pub fn $clear(&mut self, cap: Capability) -> &mut Self {
+            let (i, mask) = cap.index_mask();
+            self.data[i].$field &= !mask;
+            self
+        }
+        #[doc = concat!("The ", $name, " set as the 64 bit mask of /proc/pid/status")]
+        #[inline]
+        pub fn $bits(&self) -> u64 {
+            (self.data[1].$field as u64) << 32 | self.data[0].$field as u64
+        }
+    };
+}
+
+impl CapState {
+    /// Empty sets for the calling thread e.g. for dropping everything through [`capset`].
+    #[inline]
+    pub fn empty() -> Self {
+        Self {
+            header: CapUserHeader {
+                version: LINUX_CAPABILITY_VERSION_3,
+                pid: 0,
+            },
+            data: [CapUserData::default(); 2],
+        }
+    }
+    /// Is the capability in the effective set
+    #[inline]
+    pub fn has(&self, cap: Capability) -> bool {
+        self.has_effective(cap)
+    }
+    cap_set!(
+        effective,
+        has_effective,
+        set_effective,
+        clear_effective,
+        effective_bits,
+        "effective"
+    );
+    cap_set!(
+        permitted,
+        has_permitted,
+        set_permitted,
+        clear_permitted,
+        permitted_bits,
+        "permitted"
+    );
+    cap_set!(
+        inheritable,
+        has_inheritable,
+        set_inheritable,
+        clear_inheritable,
+        inheritable_bits,
+        "inheritable"
+    );
+}
+
+/// Capability sets of the given thread through capget(2) where pid 0 is the calling thread.
+#[inline]
+pub fn capget(pid: libc::pid_t) -> io::Result<CapState> {
+    let mut state = CapState::empty();
+    state.header.pid = pid;
+    // SAFETY: header and the two data structs are valid for the duration of the call.
+    let r = unsafe {
+        libc::syscall(
+            libc::SYS_capget,
+            &mut state.header as *mut CapUserHeader,
+            state.data.as_mut_ptr(),
+        )
+    };
+    match r {
+        0 => Ok(state),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+/// Set the capability sets of the calling thread through capset(2).
+///
+/// Only the calling thread is changed - other threads of the process keep theirs.
+/// Raising a capability not in the permitted set fails with EPERM.
+#[inline]
+pub fn capset(state: &CapState) -> io::Result<()> {
+    // SAFETY: header and the two data structs are only read for the duration of the call.
+    let r = unsafe {
+        libc::syscall(
+            libc::SYS_capset,
+            &state.header as *const CapUserHeader,
+            state.data.as_ptr(),
+        )
+    };
+    match r {
+        0 => Ok(()),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use rstest::rstest;
+
+    fn proc_status_mask(field: &str) -> u64 {
+        let status = std::fs::read_to_string("/proc/thread-self/status").unwrap();
+        let line = status.lines().find_map(|l| l.strip_prefix(field)).unwrap();
+        u64::from_str_radix(line.trim(), 16).unwrap()
+    }
+
+    #[test]
+    fn capget_matches_proc_status() {
+        let state = capget(0).unwrap();
+        assert_eq!(state.effective_bits(), proc_status_mask("CapEff:"));
+        assert_eq!(state.permitted_bits(), proc_status_mask("CapPrm:"));
+        assert_eq!(state.inheritable_bits(), proc_status_mask("CapInh:"));
+        assert_eq!(
+            state.has(Capability::SysAdmin),
+            proc_status_mask("CapEff:") & 1 << 21 != 0
+        );
+    }
+
+    #[rstest]
+    #[case(Capability::Chown)]
+    #[case(Capability::Setfcap)]
+    #[case(Capability::MacOverride)]
+    #[case(Capability::CheckpointRestore)]
+    fn set_and_clear(#[case] cap: Capability) {
+        let mut state = CapState::empty();
+        state.set_effective(cap).set_inheritable(cap);
+        assert!(state.has(cap));
+        assert!(!state.has_permitted(cap));
+        assert!(state.has_inheritable(cap));
+        assert_eq!(state.effective_bits(), 1 << cap as u32);
+        state.clear_effective(cap);
+        assert!(!state.has(cap));
+        assert_eq!(state.effective_bits(), 0);
+    }
+
+    #[test]
+    fn capset_drops_effective_on_own_thread() {
+        std::thread::spawn(|| {
+            let mut state = capget(0).unwrap();
+            state.clear_effective(Capability::NetBindService);
+            capset(&state).unwrap();
+            let after = capget(0).unwrap();
+            assert!(!after.has(Capability::NetBindService));
+            assert_eq!(after.permitted_bits(), state.permitted_bits());
+        })
+        .join()
+        .unwrap();
+    }
+
+    #[test]
+    fn capget_no_such_thread() {
+        let err = capget(libc::pid_t::MAX).unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(libc::ESRCH));
+    }
+}
@@ -0,0 +1,269 @@
+#![warn(
+    clippy::unwrap_used,
+    missing_docs,
+    rust_2018_idioms,
+    unused_lifetimes,
+    unused_qualifications
+)]
+#![doc = include_str!("../README.md")]
+
+#[cfg(not(target_os = "linux"))]
+compile_error!("Crate ycgroup is Linux specific dependency but is used in non-linux system.");
+
+use std::io;
+use std::path::PathBuf;
+
+const CGROUP_ROOT: &str = "/sys/fs/cgroup";
+const PROC_SELF_CGROUP: &str = "/proc/self/cgroup";
+
+/// cgroup hierarchy the controllers of the current process live in
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CgroupVersion {
+    /// Legacy per-controller hierarchies including the hybrid layout
+    V1,
+    /// Unified hierarchy
+    V2,
+}
+
+/// Detect the cgroup version from /proc/self/cgroup where any hierarchy other than the
+/// unified 0:: one means the controllers are on v1.
+#[inline]
+pub fn detect_cgroup_version() -> io::Result<CgroupVersion> {
+    let content = std::fs::read_to_string(PROC_SELF_CGROUP)?;
+    version_of(&content)
+}
+
+/// Memory limit in bytes of the current cgroup from memory.max (v2) or
+/// memory.limit_in_bytes (v1) where None means unlimited.
+#[inline]
+pub fn read_memory_limit() -> io::Result<Option<u64>> {
+    match detect_cgroup_version()? {
+        CgroupVersion::V2 => match read_controller_file(CgroupVersion::V2, "memory", "memory.max")?
+        {
+            Some(v) => parse_max(&v),
+            // The root cgroup has no limit files.
+            None => Ok(None),
+        },
+        CgroupVersion::V1 => {
+            match read_controller_file(CgroupVersion::V1, "memory", "memory.limit_in_bytes")? {
+                Some(v) => {
+                    let limit = parse_u64(&v)?;
+                    // Unlimited is reported as the largest page multiple within i64.
+                    let page = page_size();
+                    match limit >= (i64::MAX as u64 / page) * page {
+                        true => Ok(None),
+                        false => Ok(Some(limit)),
+                    }
+                }
+                None => Ok(None),
+            }
+        }
+    }
+}
+
+/// Memory currently charged in bytes to the cgroup from memory.current (v2) or
+/// memory.usage_in_bytes (v1).
+#[inline]
+pub fn read_memory_current() -> io::Result<u64> {
+    let version = detect_cgroup_version()?;
+    let file = match version {
+        CgroupVersion::V2 => "memory.current",
+        CgroupVersion::V1 => "memory.usage_in_bytes",
+    };
+    match read_controller_file(version, "memory", file)? {
+        Some(v) => parse_u64(&v),
+        None => Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("cgroup has no {}", file),
+        )),
+    }
+}
+
+/// CPU bandwidth limit of the cgroup as (quota, period) in microseconds from cpu.max (v2) or
+/// cpu.cfs_quota_us and cpu.cfs_period_us (v1) where None means unlimited.
+#[inline]
+pub fn read_cpu_quota() -> io::Result<Option<(u64, u64)>> {
+    match detect_cgroup_version()? {
+        CgroupVersion::V2 => match read_controller_file(CgroupVersion::V2, "cpu", "cpu.max")? {
+            Some(v) => parse_cpu_max(&v),
+            None => Ok(None),
+        },
+        CgroupVersion::V1 => {
+            let quota = match read_controller_file(CgroupVersion::V1, "cpu", "cpu.cfs_quota_us")? {
+                Some(q) => q,
+                None => return Ok(None),
+            };
+            // Unlimited is reported as -1.
+            if quota.trim() == "-1" {
+                return Ok(None);
+            }
+            let quota = parse_u64(&quota)?;
+            match read_controller_file(CgroupVersion::V1, "cpu", "cpu.cfs_period_us")? {
+                Some(period) => Ok(Some((quota, parse_u64(&period)?))),
+                None => Ok(None),
+            }
+        }
+    }
+}
+
+#[inline]
+fn page_size() -> u64 {
+    // SAFETY: FFI, no invariants.
+    match unsafe { libc::sysconf(libc::_SC_PAGESIZE) } {
+        p if p > 0 => p as u64,
+        _ => 4096,
+    }
+}
+
+fn invalid_data(what: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("Malformed cgroup value {:?}", what),
+    )
+}
+
+fn version_of(proc_self_cgroup: &str) -> io::Result<CgroupVersion> {
+    let mut unified = false;
+    for line in proc_self_cgroup.lines() {
+        match line.split_once(':') {
+            Some(("0", _)) => unified = true,
+            Some(_) => return Ok(CgroupVersion::V1),
+            None => {}
+        }
+    }
+    match unified {
+        true => Ok(CgroupVersion::V2),
+        false => Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "No cgroup hierarchy in /proc/self/cgroup",
+        )),
+    }
+}
+
+// cgroup path of the process for the controller within its hierarchy from /proc/self/cgroup
+// lines of hierarchy-ID:controller-list:cgroup-path.
+fn cgroup_path_of<'c>(
+    proc_self_cgroup: &'c str,
+    version: CgroupVersion,
+    controller: &str,
+) -> Option<&'c str> {
+    proc_self_cgroup.lines().find_map(|line| {
+        let mut parts = line.splitn(3, ':');
+        let (id, controllers, path) = (parts.next()?, parts.next()?, parts.next()?);
+        let matches = match version {
+            CgroupVersion::V2 => id == "0",
+            CgroupVersion::V1 => id != "0" && controllers.split(',').any(|c| c == controller),
+        };
+        matches.then_some(path)
+    })
+}
+
+// Reads the file from the cgroup of the process falling back to the hierarchy root when the
+// cgroup is not visible in this mount namespace. Ok(None) when the file does not exist.
+fn read_controller_file(
+    version: CgroupVersion,
+    controller: &str,
+    file: &str,
+) -> io::Result<Option<String>> {
+    let content = std::fs::read_to_string(PROC_SELF_CGROUP)?;
+    let mut root = PathBuf::from(CGROUP_ROOT);
+    if version == CgroupVersion::V1 {
+        root.push(controller);
+    }
+    let own = cgroup_path_of(&content, version, controller)
+        .map(|p| root.join(p.trim_start_matches('/')))
+        .filter(|p| p.is_dir());
+    let dir = own.unwrap_or(root);
+    match std::fs::read_to_string(dir.join(file)) {
+        Ok(v) => Ok(Some(v)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+fn parse_u64(v: &str) -> io::Result<u64> {
+    let v = v.trim();
+    v.parse().map_err(|_| invalid_data(v))
+}
+
+fn parse_max(v: &str) -> io::Result<Option<u64>> {
+    match v.trim() {
+        "max" => Ok(None),
+        n => parse_u64(n).map(Some),
+    }
+}
+
+fn parse_cpu_max(v: &str) -> io::Result<Option<(u64, u64)>> {
+    let mut parts = v.split_whitespace();
+    let (quota, period) = match (parts.next(), parts.next(), parts.next()) {
+        (Some(q), Some(p), None) => (q, p),
+        _ => return Err(invalid_data(v)),
+    };
+    let period = parse_u64(period)?;
+    match parse_max(quota)? {
+        Some(quota) => Ok(Some((quota, period))),
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("0::/user.slice\n", CgroupVersion::V2)]
+    #[case("1:name=systemd:/\n0::/\n", CgroupVersion::V1)]
+    #[case("4:memory:/a\n1:cpu:/\n", CgroupVersion::V1)]
+    fn versions(#[case] content: &str, #[case] expected: CgroupVersion) {
+        assert_eq!(version_of(content).unwrap(), expected);
+    }
+
+    #[test]
+    fn version_of_empty() {
+        assert_eq!(version_of("").unwrap_err().kind(), io::ErrorKind::NotFound);
+    }
+
+    #[rstest]
+    #[case(CgroupVersion::V2, "memory", Some("/user.slice"))]
+    #[case(CgroupVersion::V1, "memory", Some("/a"))]
+    #[case(CgroupVersion::V1, "cpu", Some("/"))]
+    #[case(CgroupVersion::V1, "pids", None)]
+    fn paths(
+        #[case] version: CgroupVersion,
+        #[case] controller: &str,
+        #[case] expected: Option<&str>,
+    ) {
+        let content = "4:memory:/a\n2:cpu,cpuacct:/\n0::/user.slice\n";
+        assert_eq!(cgroup_path_of(content, version, controller), expected);
+    }
+
+    #[rstest]
+    #[case("max\n", Some(None))]
+    #[case("1073741824\n", Some(Some(1073741824)))]
+    #[case("lots\n", None)]
+    fn memory_max(#[case] v: &str, #[case] expected: Option<Option<u64>>) {
+        assert_eq!(parse_max(v).ok(), expected);
+    }
+
+    #[rstest]
+    #[case("max 100000\n", Some(None))]
+    #[case("50000 100000\n", Some(Some((50000, 100000))))]
+    #[case("50000\n", None)]
+    #[case("50000 100000 1\n", None)]
+    fn cpu_max(#[case] v: &str, #[case] expected: Option<Option<(u64, u64)>>) {
+        assert_eq!(parse_cpu_max(v).ok(), expected);
+    }
+
+    #[test]
+    fn read_all() {
+        let _version = detect_cgroup_version().unwrap();
+        let _limit = read_memory_limit().unwrap();
+        let _current = read_memory_current().unwrap();
+        if let Some((quota, period)) = read_cpu_quota().unwrap() {
+            assert!(quota > 0);
+            assert!(period > 0);
+        }
+    }
+}
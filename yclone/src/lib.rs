@@ -0,0 +1,428 @@
+#![warn(
+    clippy::unwrap_used,
+    missing_docs,
+    rust_2018_idioms,
+    unused_lifetimes,
+    unused_qualifications
+)]
+#![doc = include_str!("../README.md")]
+
+#[cfg(not(target_os = "linux"))]
+compile_error!("Crate yclone is Linux specific dependency but is used in non-linux system.");
+
+use core::marker::PhantomData;
+use std::io;
+use yown_fd::RawFd;
+
+bitflags::bitflags! {
+    /// clone3(2) flags, the exit signal is given separately through
+    /// [`CloneArgs::exit_signal`]
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    pub struct CloneFlags: u64 {
+        /// Share the address space
+        const CLONE_VM = libc::CLONE_VM as u32 as u64;
+        /// Share the filesystem information e.g. the working directory
+        const CLONE_FS = libc::CLONE_FS as u32 as u64;
+        /// Share the file descriptor table
+        const CLONE_FILES = libc::CLONE_FILES as u32 as u64;
+        /// Share the signal handlers, requires CLONE_VM
+        const CLONE_SIGHAND = libc::CLONE_SIGHAND as u32 as u64;
+        /// Store a pidfd of the child through [`CloneArgs::pidfd`]
+        const CLONE_PIDFD = libc::CLONE_PIDFD as u32 as u64;
+        /// Suspend the parent until the child exits or execs
+        const CLONE_VFORK = libc::CLONE_VFORK as u32 as u64;
+        /// Same parent as the caller
+        const CLONE_PARENT = libc::CLONE_PARENT as u32 as u64;
+        /// Same thread group as the caller, requires CLONE_SIGHAND
+        const CLONE_THREAD = libc::CLONE_THREAD as u32 as u64;
+        /// Share System V semaphore adjustments
+        const CLONE_SYSVSEM = libc::CLONE_SYSVSEM as u32 as u64;
+        /// Set the TLS of the child to [`CloneArgs::tls`]
+        const CLONE_SETTLS = libc::CLONE_SETTLS as u32 as u64;
+        /// Share the I/O context
+        const CLONE_IO = libc::CLONE_IO as u32 as u64;
+        /// New mount namespace
+        const CLONE_NEWNS = libc::CLONE_NEWNS as u32 as u64;
+        /// New cgroup namespace
+        const CLONE_NEWCGROUP = libc::CLONE_NEWCGROUP as u32 as u64;
+        /// New UTS namespace
+        const CLONE_NEWUTS = libc::CLONE_NEWUTS as u32 as u64;
+        /// New IPC namespace
+        const CLONE_NEWIPC = libc::CLONE_NEWIPC as u32 as u64;
+        /// New user namespace
+        const CLONE_NEWUSER = libc::CLONE_NEWUSER as u32 as u64;
+        /// New PID namespace
+        const CLONE_NEWPID = libc::CLONE_NEWPID as u32 as u64;
+        /// New network namespace
+        const CLONE_NEWNET = libc::CLONE_NEWNET as u32 as u64;
+    }
+}
+
+// struct clone_args of the kernel up to CLONE_ARGS_SIZE_VER0 as libc lacks it on most
+// targets.
+#[repr(C, align(8))]
+#[derive(Debug, Default)]
+struct RawCloneArgs {
+    flags: u64,
+    pidfd: u64,
+    child_tid: u64,
+    parent_tid: u64,
+    exit_signal: u64,
+    stack: u64,
+    stack_size: u64,
+    tls: u64,
+}
+
+/// Arguments to [`clone3`] built up from [`CloneArgs::new`], borrowing where the kernel
+/// stores the pidfd.
+#[derive(Debug)]
+pub struct CloneArgs<'a> {
+    raw: RawCloneArgs,
+    _pidfd: PhantomData<&'a mut RawFd>,
+}
+
+impl Default for CloneArgs<'_> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> CloneArgs<'a> {
+    /// No flags, SIGCHLD as the exit signal and no stack of its own like fork(2)
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            raw: RawCloneArgs {
+                exit_signal: libc::SIGCHLD as u64,
+                ..Default::default()
+            },
+            _pidfd: PhantomData,
+        }
+    }
+    /// Set the flags replacing any set before except CLONE_PIDFD through [`Self::pidfd`]
+    #[inline]
+    pub fn flags(mut self, flags: CloneFlags) -> Self {
+        let pidfd = self.raw.flags & CloneFlags::CLONE_PIDFD.bits();
+        self.raw.flags = flags.bits() | pidfd;
+        self
+    }
+    /// Signal sent to the parent when the child exits, 0 for none
+    #[inline]
+    pub fn exit_signal(mut self, signal: libc::c_int) -> Self {
+        self.raw.exit_signal = signal as u64;
+        self
+    }
+    /// Have the kernel store a pidfd of the child to pidfd setting CLONE_PIDFD
+    #[inline]
+    pub fn pidfd(mut self, pidfd: &'a mut RawFd) -> Self {
+        self.raw.pidfd = pidfd as *mut RawFd as u64;
+        self.raw.flags |= CloneFlags::CLONE_PIDFD.bits();
+        self
+    }
+    /// Lowest address of the stack of the child, the kernel starts it at stack + stack_size
+    #[inline]
+    pub fn stack(mut self, stack: *mut libc::c_void) -> Self {
+        self.raw.stack = stack as u64;
+        self
+    }
+    /// Size of the stack given through [`Self::stack`]
+    #[inline]
+    pub fn stack_size(mut self, stack_size: usize) -> Self {
+        self.raw.stack_size = stack_size as u64;
+        self
+    }
+    /// Thread pointer of the child with [`CloneFlags::CLONE_SETTLS`]
+    #[inline]
+    pub fn tls(mut self, tls: usize) -> Self {
+        self.raw.tls = tls as u64;
+        self
+    }
+}
+
+// clone(2) takes the stack pointer itself and the exit signal in the low byte of the flags.
+#[inline]
+fn legacy_flags(args: &CloneArgs<'_>) -> libc::c_ulong {
+    (args.raw.flags | args.raw.exit_signal) as libc::c_ulong
+}
+
+#[inline]
+fn legacy_stack_top(args: &CloneArgs<'_>) -> usize {
+    match args.raw.stack {
+        0 => 0,
+        stack => (stack + args.raw.stack_size) as usize,
+    }
+}
+
+#[inline]
+fn check(r: libc::c_long) -> io::Result<libc::pid_t> {
+    match r {
+        -1 => Err(io::Error::last_os_error()),
+        pid => Ok(pid as libc::pid_t),
+    }
+}
+
+/// Create a child through clone3(2) returning its pid to the parent and 0 to the child like
+/// fork(2), falling back to clone(2) on kernels before 5.3 returning ENOSYS.
+///
+/// # Safety
+///
+/// The child continues from the return on the stack given through [`CloneArgs::stack`] or
+/// otherwise the caller's. With a stack of its own the child returns into an empty stack so
+/// only [`clone3_fn`] can make use of one. Without, CLONE_VM has the child share the very
+/// stack frames of the parent which is only sound with CLONE_VFORK and the child doing no
+/// more than exiting through _exit(2) or exec.
+#[inline]
+pub unsafe fn clone3(args: &CloneArgs<'_>) -> io::Result<libc::pid_t> {
+    // SAFETY: args is a valid clone_args of its size with the rest up to the caller.
+    let r = unsafe {
+        libc::syscall(
+            libc::SYS_clone3,
+            &args.raw as *const RawCloneArgs,
+            size_of::<RawCloneArgs>(),
+        )
+    };
+    match check(r) {
+        Err(e) if e.raw_os_error() == Some(libc::ENOSYS) => {
+            // SAFETY: As above with the clone(2) argument order of the architecture.
+            #[cfg(any(target_arch = "x86_64", target_arch = "x86"))]
+            let r = unsafe {
+                libc::syscall(
+                    libc::SYS_clone,
+                    legacy_flags(args),
+                    legacy_stack_top(args),
+                    args.raw.pidfd as usize,
+                    args.raw.child_tid as usize,
+                    args.raw.tls as usize,
+                )
+            };
+            // SAFETY: As above with the clone(2) argument order of the architecture.
+            #[cfg(not(any(target_arch = "x86_64", target_arch = "x86")))]
+            let r = unsafe {
+                libc::syscall(
+                    libc::SYS_clone,
+                    legacy_flags(args),
+                    legacy_stack_top(args),
+                    args.raw.pidfd as usize,
+                    args.raw.tls as usize,
+                    args.raw.child_tid as usize,
+                )
+            };
+            check(r)
+        }
+        r => r,
+    }
+}
+
+/// Create a child through clone3(2) which runs f(arg) on the stack given through
+/// [`CloneArgs::stack`] and exits with its return value, returning the pid of the child,
+/// falling back to clone(2) on kernels before 5.3 returning ENOSYS.
+///
+/// The top of the stack is aligned down to 16 bytes. Only on x86_64 and aarch64.
+///
+/// # Safety
+///
+/// The stack has to stay valid until the child exits. Without [`CloneFlags::CLONE_SETTLS`]
+/// the child shares the thread pointer of the caller so thread locals and errno of the
+/// calling thread are shared, f should stick to atomics and raw syscalls. f must not
+/// unwind.
+#[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+#[inline]
+pub unsafe fn clone3_fn(
+    args: &CloneArgs<'_>,
+    f: extern "C" fn(*mut libc::c_void) -> libc::c_int,
+    arg: *mut libc::c_void,
+) -> io::Result<libc::pid_t> {
+    if args.raw.stack == 0 || args.raw.stack_size == 0 {
+        return Err(io::Error::from(io::ErrorKind::InvalidInput));
+    }
+    // SAFETY: The child branch never returns into Rust and runs only f on its own stack.
+    let r = unsafe { raw_clone3_fn(&args.raw, f, arg) };
+    match check(r) {
+        Err(e) if e.raw_os_error() == Some(libc::ENOSYS) => {
+            // SAFETY: glibc / musl clone(3) runs f on the stack top in the same way.
+            let r = unsafe {
+                libc::clone(
+                    f,
+                    legacy_stack_top(args) as *mut libc::c_void,
+                    legacy_flags(args) as libc::c_int,
+                    arg,
+                    args.raw.pidfd as usize as *mut RawFd,
+                    args.raw.tls as usize as *mut libc::c_void,
+                    args.raw.child_tid as usize as *mut libc::pid_t,
+                )
+            };
+            check(r as libc::c_long)
+        }
+        r => r,
+    }
+}
+
+// The child returns from the syscall on the new stack where there is no Rust frame to
+// return to so it calls f and exits right away.
+#[cfg(target_arch = "x86_64")]
+#[inline]
+unsafe fn raw_clone3_fn(
+    raw: &RawCloneArgs,
+    f: extern "C" fn(*mut libc::c_void) -> libc::c_int,
+    arg: *mut libc::c_void,
+) -> libc::c_long {
+    let r: libc::c_long;
+    // SAFETY: Up to the caller. syscall only clobbers rcx and r11 besides rax.
+    unsafe {
+        core::arch::asm!(
+            "syscall",
+            "test rax, rax",
+            "jnz 2f",
+            "and rsp, -16",
+            "mov rdi, r13",
+            "call r12",
+            "mov edi, eax",
+            "mov rax, r14",
+            "syscall",
+            "ud2",
+            "2:",
+            inlateout("rax") libc::SYS_clone3 => r,
+            in("rdi") raw as *const RawCloneArgs,
+            in("rsi") size_of::<RawCloneArgs>(),
+            in("r12") f,
+            in("r13") arg,
+            in("r14") libc::SYS_exit,
+            lateout("rcx") _,
+            lateout("r11") _,
+            options(nostack),
+        );
+    }
+    // Negative errno from the raw syscall.
+    match r {
+        e if e < 0 => {
+            // SAFETY: errno of the calling thread.
+            unsafe { *libc::__errno_location() = -e as libc::c_int };
+            -1
+        }
+        pid => pid,
+    }
+}
+
+// The child returns from the syscall on the new stack where there is no Rust frame to
+// return to so it calls f and exits right away.
+#[cfg(target_arch = "aarch64")]
+#[inline]
+unsafe fn raw_clone3_fn(
+    raw: &RawCloneArgs,
+    f: extern "C" fn(*mut libc::c_void) -> libc::c_int,
+    arg: *mut libc::c_void,
+) -> libc::c_long {
+    let r: libc::c_long;
+    // SAFETY: Up to the caller. svc only clobbers x0.
+    unsafe {
+        core::arch::asm!(
+            "svc 0",
+            "cbnz x0, 2f",
+            "mov x12, sp",
+            "and x12, x12, #-16",
+            "mov sp, x12",
+            "mov x0, x10",
+            "blr x9",
+            "mov x8, x11",
+            "svc 0",
+            "brk 0",
+            "2:",
+            inlateout("x0") raw as *const RawCloneArgs => r,
+            in("x1") size_of::<RawCloneArgs>(),
+            in("x8") libc::SYS_clone3,
+            in("x9") f,
+            in("x10") arg,
+            in("x11") libc::SYS_exit,
+            lateout("x12") _,
+            options(nostack),
+        );
+    }
+    // Negative errno from the raw syscall.
+    match r {
+        e if e < 0 => {
+            // SAFETY: errno of the calling thread.
+            unsafe { *libc::__errno_location() = -e as libc::c_int };
+            -1
+        }
+        pid => pid,
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use core::sync::atomic::{AtomicU32, Ordering};
+    use rstest::rstest;
+
+    fn wait_exit_code(pid: libc::pid_t) -> libc::c_int {
+        let mut status = 0;
+        assert_eq!(unsafe { libc::waitpid(pid, &mut status, 0) }, pid);
+        assert!(libc::WIFEXITED(status));
+        libc::WEXITSTATUS(status)
+    }
+
+    extern "C" fn store_42(arg: *mut libc::c_void) -> libc::c_int {
+        let shared = unsafe { &*(arg as *const AtomicU32) };
+        shared.store(42, Ordering::SeqCst);
+        7
+    }
+
+    #[rstest]
+    #[case(CloneFlags::CLONE_VM)]
+    #[case(CloneFlags::CLONE_VM | CloneFlags::CLONE_VFORK)]
+    #[case(CloneFlags::CLONE_VM | CloneFlags::CLONE_FS | CloneFlags::CLONE_FILES)]
+    fn shared_vm_own_stack(#[case] flags: CloneFlags) {
+        let shared = AtomicU32::new(0);
+        let mut stack = vec![0u128; 4096];
+        let args = CloneArgs::new()
+            .flags(flags)
+            .stack(stack.as_mut_ptr().cast())
+            .stack_size(stack.len() * size_of::<u128>());
+        let arg = &shared as *const AtomicU32 as *mut libc::c_void;
+        let pid = unsafe { clone3_fn(&args, store_42, arg) }.unwrap();
+        assert!(pid > 0);
+        assert_eq!(wait_exit_code(pid), 7);
+        assert_eq!(shared.load(Ordering::SeqCst), 42);
+    }
+
+    #[test]
+    fn fork_like() {
+        let shared = AtomicU32::new(0);
+        let mut pidfd: RawFd = -1;
+        let args = CloneArgs::new().pidfd(&mut pidfd);
+        match unsafe { clone3(&args) }.unwrap() {
+            0 => {
+                // Own copy of the address space.
+                shared.store(42, Ordering::SeqCst);
+                unsafe { libc::_exit(3) };
+            }
+            pid => {
+                assert!(pidfd >= 0);
+                assert_eq!(wait_exit_code(pid), 3);
+                assert_eq!(shared.load(Ordering::SeqCst), 0);
+                assert_eq!(unsafe { libc::close(pidfd) }, 0);
+            }
+        }
+    }
+
+    #[test]
+    fn no_stack_rejected() {
+        let err = unsafe { clone3_fn(&CloneArgs::new(), store_42, core::ptr::null_mut()) };
+        assert_eq!(err.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn flags_keep_pidfd() {
+        let mut pidfd: RawFd = -1;
+        let args = CloneArgs::new()
+            .pidfd(&mut pidfd)
+            .flags(CloneFlags::CLONE_VM);
+        assert_eq!(
+            args.raw.flags,
+            (CloneFlags::CLONE_VM | CloneFlags::CLONE_PIDFD).bits()
+        );
+        assert_eq!(legacy_flags(&args) & 0xff, libc::SIGCHLD as libc::c_ulong);
+    }
+}
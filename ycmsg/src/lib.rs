@@ -0,0 +1,273 @@
+#![warn(
+    clippy::unwrap_used,
+    missing_docs,
+    rust_2018_idioms,
+    unused_lifetimes,
+    unused_qualifications
+)]
+#![doc = include_str!("../README.md")]
+
+#[cfg(not(target_os = "linux"))]
+compile_error!("Crate ycmsg is Linux specific dependency but is used in non-linux system.");
+
+/// Types which can be carried as control message data.
+///
+/// # Safety
+///
+/// The type must have no padding bytes and every bit pattern must be a valid value.
+pub unsafe trait CmsgData: Copy {}
+
+macro_rules! cmsg_data {
+    ($($t:ty),*) => {
+        $(
+            // SAFETY: Plain integers / C structs of integers without padding.
+            unsafe impl CmsgData for $t {}
+        )*
+    };
+}
+
+cmsg_data!(u8, u16, u32, u64, i8, i16, i32, i64);
+cmsg_data!(libc::ucred, libc::in_pktinfo, libc::in6_pktinfo);
+#[cfg(target_pointer_width = "64")]
+cmsg_data!(libc::timeval, libc::timespec);
+
+// SAFETY: Arrays of CmsgData have no padding between the elements.
+unsafe impl<T: CmsgData, const N: usize> CmsgData for [T; N] {}
+
+/// Builder of a control message chain for msghdr msg_control
+#[derive(Clone, Debug, Default)]
+pub struct CmsgBuilder(Vec<u8>);
+
+impl CmsgBuilder {
+    /// Empty chain
+    #[inline]
+    pub fn new() -> Self {
+        Self(Vec::new())
+    }
+    /// Append a control message of the given level and type carrying val
+    /// e.g. SOL_SOCKET, SCM_RIGHTS and [RawFd; N].
+    #[inline]
+    pub fn push<T: CmsgData>(&mut self, level: i32, type_: i32, val: T) {
+        let data_len = size_of::<T>() as libc::c_uint;
+        let start = self.0.len();
+        // SAFETY: CMSG_SPACE and CMSG_LEN are arithmetic only.
+        let (space, cmsg_len) = unsafe { (libc::CMSG_SPACE(data_len), libc::CMSG_LEN(data_len)) };
+        self.0.resize(start + space as usize, 0);
+        // SAFETY: zeroed cmsghdr is valid.
+        let mut hdr: libc::cmsghdr = unsafe { core::mem::zeroed() };
+        hdr.cmsg_len = cmsg_len as _;
+        hdr.cmsg_level = level;
+        hdr.cmsg_type = type_;
+        let hdr_ptr = self.0[start..].as_mut_ptr().cast::<libc::cmsghdr>();
+        // SAFETY: start..start + space was just reserved for the header and the data which may
+        // be unaligned as Vec<u8> does not promise the cmsghdr alignment.
+        unsafe {
+            hdr_ptr.write_unaligned(hdr);
+            libc::CMSG_DATA(hdr_ptr).cast::<T>().write_unaligned(val);
+        }
+    }
+    /// The chain to pass as msg_control / msg_controllen
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+    /// Bytes of the chain
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+    /// Nothing pushed
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// A control message borrowed from the parsed buffer
+#[derive(Clone, Copy, Debug)]
+pub struct Cmsg<'a> {
+    level: i32,
+    type_: i32,
+    data: &'a [u8],
+}
+
+impl<'a> Cmsg<'a> {
+    /// cmsg_level e.g. SOL_SOCKET
+    #[inline]
+    pub fn level(&self) -> i32 {
+        self.level
+    }
+    /// cmsg_type e.g. SCM_RIGHTS
+    #[inline]
+    pub fn type_(&self) -> i32 {
+        self.type_
+    }
+    /// The raw data bytes
+    #[inline]
+    pub fn data_bytes(&self) -> &'a [u8] {
+        self.data
+    }
+    /// The data as T if it is large enough and the data is aligned for T.
+    #[inline]
+    pub fn data<T: CmsgData>(&self) -> Option<&'a T> {
+        let ptr = self.data.as_ptr();
+        if self.data.len() < size_of::<T>() || !(ptr as usize).is_multiple_of(align_of::<T>()) {
+            return None;
+        }
+        // SAFETY: Checked the len and alignment and CmsgData is valid for any bytes.
+        Some(unsafe { &*ptr.cast::<T>() })
+    }
+}
+
+/// Parser of a control message chain e.g. filled by recvmsg(2) into msg_control
+#[derive(Clone, Copy, Debug)]
+pub struct CmsgParser<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> CmsgParser<'a> {
+    /// Parse the chain in buf which must be aligned to cmsghdr, None otherwise.
+    #[inline]
+    pub fn new(buf: &'a [u8]) -> Option<Self> {
+        match (buf.as_ptr() as usize).is_multiple_of(align_of::<libc::cmsghdr>()) {
+            true => Some(Self { buf }),
+            false => None,
+        }
+    }
+    /// Iterate the control messages stopping at the first one truncated or malformed.
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = Cmsg<'a>> + 'a {
+        // SAFETY: zeroed msghdr is valid.
+        let mut mhdr: libc::msghdr = unsafe { core::mem::zeroed() };
+        mhdr.msg_control = self.buf.as_ptr() as *mut libc::c_void;
+        mhdr.msg_controllen = self.buf.len() as _;
+        let buf = self.buf;
+        // SAFETY: mhdr is valid and points to buf.
+        let mut cur = unsafe { libc::CMSG_FIRSTHDR(&mhdr) };
+        core::iter::from_fn(move || {
+            if cur.is_null() {
+                return None;
+            }
+            // SAFETY: FIRSTHDR / NXTHDR only return an aligned header fully within buf.
+            let hdr = unsafe { &*cur };
+            let start = cur as usize - buf.as_ptr() as usize;
+            // SAFETY: CMSG_LEN and CMSG_DATA are arithmetic only.
+            let data_start = unsafe { libc::CMSG_DATA(cur) } as usize - buf.as_ptr() as usize;
+            let header_len = unsafe { libc::CMSG_LEN(0) } as usize;
+            let cmsg_len = hdr.cmsg_len as usize;
+            if cmsg_len < header_len || start + cmsg_len > buf.len() {
+                cur = core::ptr::null_mut();
+                return None;
+            }
+            let cmsg = Cmsg {
+                level: hdr.cmsg_level,
+                type_: hdr.cmsg_type,
+                data: &buf[data_start..start + cmsg_len],
+            };
+            // SAFETY: cur is within buf described by mhdr.
+            cur = unsafe { libc::CMSG_NXTHDR(&mhdr, cur) };
+            Some(cmsg)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use std::fs::File;
+    use std::os::unix::net::UnixDatagram;
+    use yown_fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+
+    fn aligned(bytes: &[u8]) -> Vec<u64> {
+        let mut words = vec![0u64; bytes.len().div_ceil(8)];
+        // SAFETY: words has at least bytes.len() bytes.
+        unsafe {
+            core::ptr::copy_nonoverlapping(bytes.as_ptr(), words.as_mut_ptr().cast(), bytes.len())
+        };
+        words
+    }
+
+    fn as_bytes(words: &[u64], len: usize) -> &[u8] {
+        unsafe { core::slice::from_raw_parts(words.as_ptr().cast(), len) }
+    }
+
+    #[test]
+    fn scm_rights_roundtrip() {
+        let file = File::open("/dev/null").unwrap();
+        let fd: RawFd = file.as_raw_fd();
+        let mut builder = CmsgBuilder::new();
+        builder.push(libc::SOL_SOCKET, libc::SCM_RIGHTS, fd);
+        builder.push(libc::SOL_SOCKET, libc::SCM_RIGHTS, [fd, fd]);
+        assert_eq!(
+            builder.len(),
+            unsafe { libc::CMSG_SPACE(4) + libc::CMSG_SPACE(8) } as usize
+        );
+
+        let words = aligned(builder.as_bytes());
+        let parser = CmsgParser::new(as_bytes(&words, builder.len())).unwrap();
+        let cmsgs: Vec<Cmsg<'_>> = parser.iter().collect();
+        assert_eq!(cmsgs.len(), 2);
+        assert_eq!(cmsgs[0].level(), libc::SOL_SOCKET);
+        assert_eq!(cmsgs[0].type_(), libc::SCM_RIGHTS);
+        assert_eq!(cmsgs[0].data::<RawFd>(), Some(&fd));
+        assert_eq!(cmsgs[0].data::<[RawFd; 2]>(), None);
+        assert_eq!(cmsgs[1].data::<[RawFd; 2]>(), Some(&[fd, fd]));
+    }
+
+    #[test]
+    fn truncated_stops() {
+        let mut builder = CmsgBuilder::new();
+        builder.push(libc::SOL_SOCKET, libc::SCM_RIGHTS, 1i32);
+        builder.push(libc::SOL_SOCKET, libc::SCM_RIGHTS, 2i32);
+        let words = aligned(builder.as_bytes());
+        let parser = CmsgParser::new(as_bytes(&words, builder.len() - 4)).unwrap();
+        assert_eq!(parser.iter().count(), 1);
+        let parser = CmsgParser::new(as_bytes(&words, 4)).unwrap();
+        assert_eq!(parser.iter().count(), 0);
+    }
+
+    #[test]
+    fn not_aligned() {
+        let words = [0u64; 4];
+        assert!(CmsgParser::new(&as_bytes(&words, 32)[1..]).is_none());
+    }
+
+    #[test]
+    fn sendmsg_recvmsg_passes_fd() {
+        let (a, b) = UnixDatagram::pair().unwrap();
+        let file = File::open("/dev/null").unwrap();
+        let mut builder = CmsgBuilder::new();
+        builder.push(libc::SOL_SOCKET, libc::SCM_RIGHTS, file.as_raw_fd());
+
+        let mut payload = [7u8];
+        let mut iov = libc::iovec {
+            iov_base: payload.as_mut_ptr().cast(),
+            iov_len: 1,
+        };
+        let mut mhdr: libc::msghdr = unsafe { core::mem::zeroed() };
+        mhdr.msg_iov = &mut iov;
+        mhdr.msg_iovlen = 1;
+        mhdr.msg_control = builder.as_bytes().as_ptr() as *mut libc::c_void;
+        mhdr.msg_controllen = builder.len() as _;
+        assert_eq!(unsafe { libc::sendmsg(a.as_raw_fd(), &mhdr, 0) }, 1);
+
+        let mut control = [0u64; 8];
+        mhdr.msg_control = control.as_mut_ptr().cast();
+        mhdr.msg_controllen = size_of_val(&control) as _;
+        assert_eq!(unsafe { libc::recvmsg(b.as_raw_fd(), &mut mhdr, 0) }, 1);
+        let parser = CmsgParser::new(as_bytes(&control, mhdr.msg_controllen as usize)).unwrap();
+        let cmsg = parser.iter().next().unwrap();
+        assert_eq!(cmsg.type_(), libc::SCM_RIGHTS);
+        let received = *cmsg.data::<RawFd>().unwrap();
+        assert_ne!(received, file.as_raw_fd());
+        let received = unsafe { OwnedFd::from_raw_fd(received) };
+        let mut st_a: libc::stat = unsafe { core::mem::zeroed() };
+        let mut st_b: libc::stat = unsafe { core::mem::zeroed() };
+        unsafe {
+            assert_eq!(libc::fstat(file.as_raw_fd(), &mut st_a), 0);
+            assert_eq!(libc::fstat(received.as_raw_fd(), &mut st_b), 0);
+        }
+        assert_eq!((st_a.st_dev, st_a.st_ino), (st_b.st_dev, st_b.st_ino));
+    }
+}
@@ -0,0 +1,268 @@
+#![warn(
+    clippy::unwrap_used,
+    missing_docs,
+    rust_2018_idioms,
+    unused_lifetimes,
+    unused_qualifications
+)]
+#![doc = include_str!("../README.md")]
+
+#[cfg(not(target_os = "linux"))]
+compile_error!("Crate yepoll is Linux specific dependency but is used in non-linux system.");
+
+use std::collections::HashMap;
+use std::io;
+use yown_fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+
+bitflags::bitflags! {
+    /// epoll_event events
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    pub struct EpollEvents: u32 {
+        /// Available for read
+        const EPOLLIN = libc::EPOLLIN as u32;
+        /// Available for write
+        const EPOLLOUT = libc::EPOLLOUT as u32;
+        /// Exceptional condition e.g. out-of-band data
+        const EPOLLPRI = libc::EPOLLPRI as u32;
+        /// Error condition
+        const EPOLLERR = libc::EPOLLERR as u32;
+        /// Hang up
+        const EPOLLHUP = libc::EPOLLHUP as u32;
+        /// Stream peer closed or shut down writing
+        const EPOLLRDHUP = libc::EPOLLRDHUP as u32;
+        /// Edge-triggered
+        const EPOLLET = libc::EPOLLET as u32;
+        /// Disable after one event until re-armed
+        const EPOLLONESHOT = libc::EPOLLONESHOT as u32;
+    }
+}
+
+/// Max events picked up by a single [`EpollDispatcher::run_once`]
+const MAX_EVENTS: usize = 64;
+
+struct Registration {
+    events: EpollEvents,
+    handler: Box<dyn FnMut(EpollEvents)>,
+}
+
+/// Dispatches epoll events to the handlers registered per fd and re-arms EPOLLONESHOT fds.
+pub struct EpollDispatcher {
+    epfd: OwnedFd,
+    handlers: HashMap<RawFd, Registration>,
+}
+
+impl core::fmt::Debug for EpollDispatcher {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("EpollDispatcher")
+            .field("epfd", &self.epfd)
+            .field("fds", &self.handlers.keys())
+            .finish()
+    }
+}
+
+impl EpollDispatcher {
+    /// Create the epoll instance through epoll_create1(EPOLL_CLOEXEC)
+    #[inline]
+    pub fn new() -> io::Result<Self> {
+        // SAFETY: Only takes the flags.
+        let fd = unsafe { libc::epoll_create1(libc::EPOLL_CLOEXEC) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Self {
+            // SAFETY: fd is valid and ours.
+            epfd: unsafe { OwnedFd::from_raw_fd(fd) },
+            handlers: HashMap::new(),
+        })
+    }
+    #[inline]
+    fn ctl(&self, op: libc::c_int, fd: RawFd, events: EpollEvents) -> io::Result<()> {
+        let mut ev = libc::epoll_event {
+            events: events.bits(),
+            u64: fd as u64,
+        };
+        // SAFETY: ev is valid for the duration of the call.
+        if unsafe { libc::epoll_ctl(self.epfd.as_raw_fd(), op, fd, &mut ev) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+    /// Register the fd with the given events and the handler invoked with the ready events.
+    #[inline]
+    pub fn register(
+        &mut self,
+        fd: RawFd,
+        events: EpollEvents,
+        handler: impl FnMut(EpollEvents) + 'static,
+    ) -> io::Result<()> {
+        self.ctl(libc::EPOLL_CTL_ADD, fd, events)?;
+        self.handlers.insert(
+            fd,
+            Registration {
+                events,
+                handler: Box::new(handler),
+            },
+        );
+        Ok(())
+    }
+    /// Deregister the fd and drop its handler.
+    ///
+    /// The handler is dropped even when EPOLL_CTL_DEL fails e.g. with EBADF for an fd closed
+    /// since, so a later fd of the same number never reaches it.
+    #[inline]
+    pub fn deregister(&mut self, fd: RawFd) -> io::Result<()> {
+        self.handlers.remove(&fd);
+        self.ctl(libc::EPOLL_CTL_DEL, fd, EpollEvents::empty())
+    }
+    /// Wait up to timeout_ms (-1 blocks) through epoll_wait, dispatch the ready events to the
+    /// handlers and re-arm the EPOLLONESHOT registrations. Returns the number of events dispatched.
+    ///
+    /// All the ready events are dispatched before any is re-armed. A registration whose fd a
+    /// handler closed fails to re-arm with EBADF or ENOENT and is dropped in place of failing.
+    #[inline]
+    pub fn run_once(&mut self, timeout_ms: i32) -> io::Result<usize> {
+        let mut events = [libc::epoll_event { events: 0, u64: 0 }; MAX_EVENTS];
+        // SAFETY: events is valid for MAX_EVENTS.
+        let n = unsafe {
+            libc::epoll_wait(
+                self.epfd.as_raw_fd(),
+                events.as_mut_ptr(),
+                MAX_EVENTS as libc::c_int,
+                timeout_ms,
+            )
+        };
+        if n < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let n = n as usize;
+        for ev in &events[..n] {
+            let fd = ev.u64 as RawFd;
+            if let Some(reg) = self.handlers.get_mut(&fd) {
+                (reg.handler)(EpollEvents::from_bits_retain(ev.events));
+            }
+        }
+        for ev in &events[..n] {
+            let fd = ev.u64 as RawFd;
+            let rearm = match self.handlers.get(&fd) {
+                Some(reg) if reg.events.contains(EpollEvents::EPOLLONESHOT) => reg.events,
+                _ => continue,
+            };
+            match self.ctl(libc::EPOLL_CTL_MOD, fd, rearm) {
+                Ok(()) => {}
+                Err(e) if matches!(e.raw_os_error(), Some(libc::EBADF | libc::ENOENT)) => {
+                    self.handlers.remove(&fd);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    fn timerfd(nsec: libc::c_long) -> OwnedFd {
+        let fd = unsafe { libc::timerfd_create(libc::CLOCK_MONOTONIC, libc::TFD_CLOEXEC) };
+        assert!(fd >= 0);
+        let spec = libc::itimerspec {
+            it_interval: libc::timespec {
+                tv_sec: 0,
+                tv_nsec: 0,
+            },
+            it_value: libc::timespec {
+                tv_sec: 0,
+                tv_nsec: nsec,
+            },
+        };
+        assert_eq!(
+            unsafe { libc::timerfd_settime(fd, 0, &spec, core::ptr::null_mut()) },
+            0
+        );
+        unsafe { OwnedFd::from_raw_fd(fd) }
+    }
+
+    #[test]
+    fn timer_oneshot_invoked_once() {
+        let timer = timerfd(1_000_000);
+        let raw = timer.as_raw_fd();
+        let invoked = Rc::new(Cell::new(0));
+        let counter = invoked.clone();
+
+        let mut dispatcher = EpollDispatcher::new().unwrap();
+        dispatcher
+            .register(
+                raw,
+                EpollEvents::EPOLLIN | EpollEvents::EPOLLONESHOT,
+                move |ev| {
+                    assert!(ev.contains(EpollEvents::EPOLLIN));
+                    let mut expirations = 0u64;
+                    let r =
+                        unsafe { libc::read(raw, core::ptr::addr_of_mut!(expirations).cast(), 8) };
+                    assert_eq!(r, 8);
+                    counter.set(counter.get() + 1);
+                },
+            )
+            .unwrap();
+
+        assert_eq!(dispatcher.run_once(1000).unwrap(), 1);
+        assert_eq!(invoked.get(), 1);
+        // Re-armed but the non-periodic timer does not expire again
+        assert_eq!(dispatcher.run_once(10).unwrap(), 0);
+        assert_eq!(invoked.get(), 1);
+
+        dispatcher.deregister(raw).unwrap();
+        assert!(dispatcher.deregister(raw).is_err());
+    }
+
+    #[test]
+    fn deregister_closed_fd() {
+        let timer = timerfd(1_000_000);
+        let raw = timer.as_raw_fd();
+        let mut dispatcher = EpollDispatcher::new().unwrap();
+        dispatcher
+            .register(raw, EpollEvents::EPOLLIN, |_| {})
+            .unwrap();
+        drop(timer);
+        let err = dispatcher.deregister(raw).unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(libc::EBADF));
+        assert!(!dispatcher.handlers.contains_key(&raw));
+    }
+
+    #[test]
+    fn handler_closing_own_fd() {
+        let first = timerfd(1_000_000);
+        let second = timerfd(2_000_000);
+        let (first_raw, second_raw) = (first.as_raw_fd(), second.as_raw_fd());
+        // Both timers expire before the wait so both are ready at once, first in line.
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        let invoked = Rc::new(Cell::new(0));
+
+        let mut dispatcher = EpollDispatcher::new().unwrap();
+        let oneshot = EpollEvents::EPOLLIN | EpollEvents::EPOLLONESHOT;
+        let counter = invoked.clone();
+        let mut first = Some(first);
+        dispatcher
+            .register(first_raw, oneshot, move |_| {
+                drop(first.take());
+                counter.set(counter.get() + 1);
+            })
+            .unwrap();
+        let counter = invoked.clone();
+        dispatcher
+            .register(second_raw, oneshot, move |_| {
+                counter.set(counter.get() + 1);
+            })
+            .unwrap();
+
+        assert_eq!(dispatcher.run_once(1000).unwrap(), 2);
+        assert_eq!(invoked.get(), 2);
+        assert!(dispatcher.deregister(first_raw).is_err());
+        dispatcher.deregister(second_raw).unwrap();
+        drop(second);
+    }
+}
@@ -0,0 +1,177 @@
+#![warn(
+    clippy::unwrap_used,
+    missing_docs,
+    rust_2018_idioms,
+    unused_lifetimes,
+    unused_qualifications
+)]
+#![doc = include_str!("../README.md")]
+
+#[cfg(not(target_os = "linux"))]
+compile_error!("Crate yexec is Linux specific dependency but is used in non-linux system.");
+
+use std::ffi::CStr;
+use std::io;
+use yown_fd::RawFd;
+
+bitflags::bitflags! {
+    /// execveat(2) flags
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    pub struct ExecFlags: libc::c_int {
+        /// Execute the file dirfd refers to itself when path is empty
+        const AT_EMPTY_PATH = libc::AT_EMPTY_PATH;
+        /// Fail with ELOOP if path is a symlink
+        const AT_SYMLINK_NOFOLLOW = libc::AT_SYMLINK_NOFOLLOW;
+    }
+}
+
+// NULL terminated pointer array borrowing the given CStrs.
+#[inline]
+fn c_array(strs: &[&CStr]) -> Vec<*const libc::c_char> {
+    strs.iter()
+        .map(|s| s.as_ptr())
+        .chain(core::iter::once(core::ptr::null()))
+        .collect()
+}
+
+/// Replace the process image with file through execvpe(3) searching PATH when file has no
+/// slash, returning only the error since success does not return.
+///
+/// args is the whole argv including the conventional program name as the first. Building
+/// the argv and envp arrays allocates, which a child of a multi-threaded parent has to
+/// keep in mind.
+#[inline]
+pub fn execvpe(file: &CStr, args: &[&CStr], env: &[&CStr]) -> io::Error {
+    let argv = c_array(args);
+    let envp = c_array(env);
+    // SAFETY: file and the arrays are NUL / NULL terminated and outlive the call.
+    unsafe { libc::execvpe(file.as_ptr(), argv.as_ptr(), envp.as_ptr()) };
+    io::Error::last_os_error()
+}
+
+/// Replace the process image with path through execveat(2) where a relative path is
+/// resolved against dirfd or the current working directory with None, returning only the
+/// error since success does not return.
+///
+/// With [`ExecFlags::AT_EMPTY_PATH`] and an empty path the file dirfd refers to is executed.
+#[inline]
+pub fn execveat(
+    dirfd: Option<RawFd>,
+    path: &CStr,
+    args: &[&CStr],
+    env: &[&CStr],
+    flags: ExecFlags,
+) -> io::Error {
+    let dirfd = dirfd.unwrap_or(libc::AT_FDCWD);
+    let argv = c_array(args);
+    let envp = c_array(env);
+    // SAFETY: path and the arrays are NUL / NULL terminated and outlive the call. Raw
+    // syscall as not every libc has the wrapper.
+    unsafe {
+        libc::syscall(
+            libc::SYS_execveat,
+            dirfd,
+            path.as_ptr(),
+            argv.as_ptr(),
+            envp.as_ptr(),
+            flags.bits(),
+        )
+    };
+    io::Error::last_os_error()
+}
+
+/// Replace the process image with the file fd refers to through fexecve(3), returning
+/// only the error since success does not return.
+///
+/// An O_CLOEXEC fd of a script fails with ENOENT as the interpreter can not open it.
+#[inline]
+pub fn fexecve(fd: RawFd, args: &[&CStr], env: &[&CStr]) -> io::Error {
+    let argv = c_array(args);
+    let envp = c_array(env);
+    // SAFETY: The arrays are NULL terminated and outlive the call.
+    unsafe { libc::fexecve(fd, argv.as_ptr(), envp.as_ptr()) };
+    io::Error::last_os_error()
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use rstest::rstest;
+    use std::fs::File;
+    use yfork::fork_then;
+    use yown_fd::AsRawFd;
+
+    fn wait_exit_code(pid: libc::pid_t) -> libc::c_int {
+        let mut status = 0;
+        assert_eq!(unsafe { libc::waitpid(pid, &mut status, 0) }, pid);
+        assert!(libc::WIFEXITED(status));
+        libc::WEXITSTATUS(status)
+    }
+
+    // Exit 127 like a shell when the exec in the child failed.
+    fn exec_in_child(exec: impl FnOnce() -> io::Error) -> libc::c_int {
+        // SAFETY: The child only execs or exits.
+        let pid = unsafe {
+            fork_then(|| {
+                let _ = exec();
+                libc::_exit(127)
+            })
+        }
+        .unwrap();
+        wait_exit_code(pid)
+    }
+
+    #[rstest]
+    #[case(c"true")]
+    #[case(c"/bin/true")]
+    fn execvpe_true(#[case] file: &CStr) {
+        assert_eq!(exec_in_child(|| execvpe(file, &[], &[])), 0);
+    }
+
+    #[test]
+    fn execveat_true() {
+        let bin = File::open("/bin").unwrap();
+        let code = exec_in_child(|| {
+            execveat(
+                Some(bin.as_raw_fd()),
+                c"true",
+                &[c"true"],
+                &[],
+                ExecFlags::empty(),
+            )
+        });
+        assert_eq!(code, 0);
+        let code = exec_in_child(|| execveat(None, c"/bin/true", &[], &[], ExecFlags::empty()));
+        assert_eq!(code, 0);
+    }
+
+    #[test]
+    fn execveat_empty_path() {
+        let true_bin = File::open("/bin/true").unwrap();
+        let code = exec_in_child(|| {
+            execveat(
+                Some(true_bin.as_raw_fd()),
+                c"",
+                &[],
+                &[],
+                ExecFlags::AT_EMPTY_PATH,
+            )
+        });
+        assert_eq!(code, 0);
+    }
+
+    #[test]
+    fn fexecve_true() {
+        let true_bin = File::open("/bin/true").unwrap();
+        assert_eq!(exec_in_child(|| fexecve(true_bin.as_raw_fd(), &[], &[])), 0);
+    }
+
+    #[test]
+    fn exec_missing_returns_error() {
+        let e = execvpe(c"/nonexistent/yexec", &[], &[]);
+        assert_eq!(e.raw_os_error(), Some(libc::ENOENT));
+        let e = execveat(None, c"/nonexistent/yexec", &[], &[], ExecFlags::empty());
+        assert_eq!(e.raw_os_error(), Some(libc::ENOENT));
+    }
+}
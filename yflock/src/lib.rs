@@ -0,0 +1,268 @@
+#![warn(
+    clippy::unwrap_used,
+    missing_docs,
+    rust_2018_idioms,
+    unused_lifetimes,
+    unused_qualifications
+)]
+#![doc = include_str!("../README.md")]
+
+#[cfg(not(target_os = "linux"))]
+compile_error!("Crate yflock is Linux specific dependency but is used in non-linux system.");
+
+use std::io;
+use yown_fd::RawFd;
+
+/// flock(2) operation
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FlockOp {
+    /// LOCK_SH blocking until acquired
+    SharedLock,
+    /// LOCK_EX blocking until acquired
+    ExclusiveLock,
+    /// LOCK_UN
+    Unlock,
+    /// LOCK_SH | LOCK_NB failing with EWOULDBLOCK if held exclusively elsewhere
+    SharedLockNb,
+    /// LOCK_EX | LOCK_NB failing with EWOULDBLOCK if held elsewhere
+    ExclusiveLockNb,
+}
+
+impl FlockOp {
+    #[inline]
+    fn as_c(self) -> libc::c_int {
+        match self {
+            Self::SharedLock => libc::LOCK_SH,
+            Self::ExclusiveLock => libc::LOCK_EX,
+            Self::Unlock => libc::LOCK_UN,
+            Self::SharedLockNb => libc::LOCK_SH | libc::LOCK_NB,
+            Self::ExclusiveLockNb => libc::LOCK_EX | libc::LOCK_NB,
+        }
+    }
+}
+
+/// Whole-file advisory lock through flock(2) held by the open file description.
+/// Interrupted blocking waits are retried.
+#[inline]
+pub fn flock(fd: RawFd, op: FlockOp) -> io::Result<()> {
+    loop {
+        // SAFETY: Only takes the fd and the op.
+        if unsafe { libc::flock(fd, op.as_c()) } == 0 {
+            return Ok(());
+        }
+        let err = io::Error::last_os_error();
+        if err.kind() != io::ErrorKind::Interrupted {
+            return Err(err);
+        }
+    }
+}
+
+/// Open file description record lock over libc::flock for [`ofd_setlk`] / [`ofd_getlk`]
+#[derive(Clone, Copy)]
+pub struct OfdLock(libc::flock);
+
+impl core::fmt::Debug for OfdLock {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("OfdLock")
+            .field("l_type", &self.l_type())
+            .field("l_whence", &self.l_whence())
+            .field("l_start", &self.l_start())
+            .field("l_len", &self.l_len())
+            .finish()
+    }
+}
+
+impl OfdLock {
+    /// Lock of l_type F_RDLCK / F_WRLCK / F_UNLCK over l_len bytes (0 meaning to the end of file)
+    /// from l_start relative to l_whence SEEK_SET / SEEK_CUR / SEEK_END.
+    #[inline]
+    pub fn new(
+        l_type: libc::c_short,
+        l_whence: libc::c_short,
+        l_start: libc::off_t,
+        l_len: libc::off_t,
+    ) -> Self {
+        // SAFETY: flock is valid when zeroed which includes the l_pid 0 required for OFD locks.
+        let mut lock: libc::flock = unsafe { core::mem::zeroed() };
+        lock.l_type = l_type;
+        lock.l_whence = l_whence;
+        lock.l_start = l_start;
+        lock.l_len = l_len;
+        Self(lock)
+    }
+    /// Shared F_RDLCK over [start, start + len) from the start of file
+    #[inline]
+    pub fn read(start: libc::off_t, len: libc::off_t) -> Self {
+        Self::new(
+            libc::F_RDLCK as libc::c_short,
+            libc::SEEK_SET as libc::c_short,
+            start,
+            len,
+        )
+    }
+    /// Exclusive F_WRLCK over [start, start + len) from the start of file
+    #[inline]
+    pub fn write(start: libc::off_t, len: libc::off_t) -> Self {
+        Self::new(
+            libc::F_WRLCK as libc::c_short,
+            libc::SEEK_SET as libc::c_short,
+            start,
+            len,
+        )
+    }
+    /// F_UNLCK over [start, start + len) from the start of file
+    #[inline]
+    pub fn unlock(start: libc::off_t, len: libc::off_t) -> Self {
+        Self::new(
+            libc::F_UNLCK as libc::c_short,
+            libc::SEEK_SET as libc::c_short,
+            start,
+            len,
+        )
+    }
+    /// F_RDLCK / F_WRLCK / F_UNLCK
+    #[inline]
+    pub fn l_type(&self) -> libc::c_short {
+        self.0.l_type
+    }
+    /// SEEK_SET / SEEK_CUR / SEEK_END
+    #[inline]
+    pub fn l_whence(&self) -> libc::c_short {
+        self.0.l_whence
+    }
+    /// Start offset relative to l_whence
+    #[inline]
+    pub fn l_start(&self) -> libc::off_t {
+        self.0.l_start
+    }
+    /// Len of the range with 0 meaning to the end of file
+    #[inline]
+    pub fn l_len(&self) -> libc::off_t {
+        self.0.l_len
+    }
+    /// The lock is F_UNLCK e.g. no conflicting lock was found by [`ofd_getlk`]
+    #[inline]
+    pub fn is_unlocked(&self) -> bool {
+        self.0.l_type == libc::F_UNLCK as libc::c_short
+    }
+    /// libc::flock
+    #[inline]
+    pub fn as_libc(&self) -> &libc::flock {
+        &self.0
+    }
+}
+
+/// Acquire or release the record lock without blocking through fcntl(2) F_OFD_SETLK.
+/// A conflicting lock held through another open file description is EAGAIN.
+#[inline]
+pub fn ofd_setlk(fd: RawFd, lock: &OfdLock) -> io::Result<()> {
+    // SAFETY: lock is valid for the duration of the call.
+    match unsafe { libc::fcntl(fd, libc::F_OFD_SETLK, &lock.0 as *const libc::flock) } {
+        0 => Ok(()),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+/// Test whether the lock could be placed through fcntl(2) F_OFD_GETLK returning the first
+/// conflicting lock or the lock with l_type F_UNLCK if none conflicts.
+#[inline]
+pub fn ofd_getlk(fd: RawFd, lock: OfdLock) -> io::Result<OfdLock> {
+    let mut lock = lock;
+    // SAFETY: lock is valid for the duration of the call.
+    match unsafe { libc::fcntl(fd, libc::F_OFD_GETLK, &mut lock.0 as *mut libc::flock) } {
+        0 => Ok(lock),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use std::fs::{File, OpenOptions};
+    use std::path::PathBuf;
+    use yown_fd::AsRawFd;
+
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("yflock-{}-{}", std::process::id(), name));
+            File::create(&path).unwrap();
+            Self(path)
+        }
+        fn open(&self) -> File {
+            OpenOptions::new()
+                .read(true)
+                .write(true)
+                .open(&self.0)
+                .unwrap()
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn flock_exclusive_across_threads() {
+        let tmp = TempFile::new("flock");
+        let holder = tmp.open();
+        flock(holder.as_raw_fd(), FlockOp::ExclusiveLock).unwrap();
+
+        let other = tmp.open();
+        let other = std::thread::spawn(move || {
+            let err = flock(other.as_raw_fd(), FlockOp::ExclusiveLockNb).unwrap_err();
+            assert_eq!(err.raw_os_error(), Some(libc::EWOULDBLOCK));
+            let err = flock(other.as_raw_fd(), FlockOp::SharedLockNb).unwrap_err();
+            assert_eq!(err.raw_os_error(), Some(libc::EWOULDBLOCK));
+            other
+        })
+        .join()
+        .unwrap();
+
+        flock(holder.as_raw_fd(), FlockOp::Unlock).unwrap();
+        std::thread::spawn(move || flock(other.as_raw_fd(), FlockOp::ExclusiveLockNb).unwrap())
+            .join()
+            .unwrap();
+    }
+
+    #[test]
+    fn flock_shared_coexist() {
+        let tmp = TempFile::new("shared");
+        let a = tmp.open();
+        let b = tmp.open();
+        flock(a.as_raw_fd(), FlockOp::SharedLockNb).unwrap();
+        flock(b.as_raw_fd(), FlockOp::SharedLockNb).unwrap();
+        assert!(flock(b.as_raw_fd(), FlockOp::ExclusiveLockNb).is_err());
+    }
+
+    #[test]
+    fn ofd_record_locks() {
+        let tmp = TempFile::new("ofd");
+        let holder = tmp.open();
+        ofd_setlk(holder.as_raw_fd(), &OfdLock::write(0, 10)).unwrap();
+
+        let other = tmp.open();
+        let other = std::thread::spawn(move || {
+            let err = ofd_setlk(other.as_raw_fd(), &OfdLock::write(5, 10)).unwrap_err();
+            assert_eq!(err.raw_os_error(), Some(libc::EWOULDBLOCK));
+            // Disjoint range is fine.
+            ofd_setlk(other.as_raw_fd(), &OfdLock::write(10, 10)).unwrap();
+            let conflict = ofd_getlk(other.as_raw_fd(), OfdLock::read(0, 1)).unwrap();
+            assert_eq!(conflict.l_type(), libc::F_WRLCK as libc::c_short);
+            assert_eq!((conflict.l_start(), conflict.l_len()), (0, 10));
+            other
+        })
+        .join()
+        .unwrap();
+
+        ofd_setlk(holder.as_raw_fd(), &OfdLock::unlock(0, 10)).unwrap();
+        assert!(ofd_getlk(other.as_raw_fd(), OfdLock::write(0, 10))
+            .unwrap()
+            .is_unlocked());
+        ofd_setlk(other.as_raw_fd(), &OfdLock::write(0, 10)).unwrap();
+    }
+}
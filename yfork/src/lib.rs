@@ -0,0 +1,140 @@
+#![warn(
+    clippy::unwrap_used,
+    missing_docs,
+    rust_2018_idioms,
+    unused_lifetimes,
+    unused_qualifications
+)]
+#![doc = include_str!("../README.md")]
+
+#[cfg(not(target_os = "linux"))]
+compile_error!("Crate yfork is Linux specific dependency but is used in non-linux system.");
+
+use std::convert::Infallible;
+use std::io;
+
+/// Which side of [`fork`] the caller is on
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ForkResult {
+    /// The original process with the pid of the new child
+    Parent {
+        /// Pid of the child to wait for
+        child_pid: libc::pid_t,
+    },
+    /// The new child process
+    Child,
+}
+
+/// Duplicate the calling process through fork(2).
+///
+/// Only the calling thread is duplicated.
+///
+/// # Safety
+///
+/// In a multi-threaded process any lock another thread held at the time e.g. of the
+/// allocator stays locked in the child, so until it execs or exits the child may only make
+/// async-signal-safe calls which excludes allocating, formatting and panicking.
+#[inline]
+pub unsafe fn fork() -> io::Result<ForkResult> {
+    // SAFETY: The caller upholds the restrictions on the child.
+    match unsafe { libc::fork() } {
+        -1 => Err(io::Error::last_os_error()),
+        0 => Ok(ForkResult::Child),
+        child_pid => Ok(ForkResult::Parent { child_pid }),
+    }
+}
+
+// Exits the child should child_fn unwind regardless so it never returns into the
+// duplicated stack of the caller.
+struct ExitOnUnwind;
+
+impl Drop for ExitOnUnwind {
+    fn drop(&mut self) {
+        // SAFETY: _exit is async-signal-safe and skips the atexit handlers of the parent.
+        unsafe { libc::_exit(127) }
+    }
+}
+
+/// [`fork`] running child_fn in the child which has to exit or exec, returning the pid of
+/// the child to the parent.
+///
+/// child_fn returns [`Infallible`] in place of the still unstable never type so it can only
+/// diverge e.g. through _exit(2).
+///
+/// # Safety
+///
+/// The same restrictions on the child apply as with [`fork`], child_fn must not panic.
+#[inline]
+pub unsafe fn fork_then<F: FnOnce() -> Infallible>(child_fn: F) -> io::Result<libc::pid_t> {
+    // SAFETY: The caller upholds the restrictions on the child.
+    match unsafe { fork() }? {
+        ForkResult::Parent { child_pid } => Ok(child_pid),
+        // Nothing follows the uninhabited return value.
+        #[allow(unreachable_code)]
+        ForkResult::Child => {
+            let _exit = ExitOnUnwind;
+            match child_fn() {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use std::fs::File;
+    use std::io::{Read, Write};
+    use yown_fd::FromRawFd;
+
+    fn pipe() -> (File, File) {
+        let mut fds = [0; 2];
+        assert_eq!(unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) }, 0);
+        unsafe { (File::from_raw_fd(fds[0]), File::from_raw_fd(fds[1])) }
+    }
+
+    fn wait_exit_code(pid: libc::pid_t) -> libc::c_int {
+        let mut status = 0;
+        assert_eq!(unsafe { libc::waitpid(pid, &mut status, 0) }, pid);
+        assert!(libc::WIFEXITED(status));
+        libc::WEXITSTATUS(status)
+    }
+
+    #[test]
+    fn fork_child_writes_pipe() {
+        let (mut rx, mut tx) = pipe();
+        match unsafe { fork() }.unwrap() {
+            ForkResult::Child => {
+                let code = match tx.write_all(b"y") {
+                    Ok(()) => 0,
+                    Err(_) => 1,
+                };
+                unsafe { libc::_exit(code) };
+            }
+            ForkResult::Parent { child_pid } => {
+                drop(tx);
+                assert!(child_pid > 0);
+                let mut buf = Vec::new();
+                rx.read_to_end(&mut buf).unwrap();
+                assert_eq!(buf, b"y");
+                assert_eq!(wait_exit_code(child_pid), 0);
+            }
+        }
+    }
+
+    #[test]
+    fn fork_then_exit_code() {
+        let (mut rx, mut tx) = pipe();
+        let pid = unsafe {
+            fork_then(move || {
+                let pid = libc::getpid();
+                let _ = tx.write_all(&pid.to_ne_bytes());
+                libc::_exit(42)
+            })
+        }
+        .unwrap();
+        let mut buf = [0u8; 4];
+        rx.read_exact(&mut buf).unwrap();
+        assert_eq!(libc::pid_t::from_ne_bytes(buf), pid);
+        assert_eq!(wait_exit_code(pid), 42);
+    }
+}
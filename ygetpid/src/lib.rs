@@ -0,0 +1,109 @@
+#![warn(
+    clippy::unwrap_used,
+    missing_docs,
+    rust_2018_idioms,
+    unused_lifetimes,
+    unused_qualifications
+)]
+#![doc = include_str!("../README.md")]
+
+#[cfg(not(target_os = "linux"))]
+compile_error!("Crate ygetpid is Linux specific dependency but is used in non-linux system.");
+
+/// Process id of the caller through getpid(2) as seen in its PID namespace
+#[inline]
+pub fn getpid() -> libc::pid_t {
+    // SAFETY: getpid always succeeds.
+    unsafe { libc::getpid() }
+}
+
+/// Process id of the parent through getppid(2), 0 when the parent is outside the PID
+/// namespace
+#[inline]
+pub fn getppid() -> libc::pid_t {
+    // SAFETY: getppid always succeeds.
+    unsafe { libc::getppid() }
+}
+
+/// Thread id of the calling thread through gettid(2) which equals [`getpid`] on the main
+/// thread only
+#[inline]
+pub fn gettid() -> libc::pid_t {
+    // SAFETY: gettid always succeeds. Through the syscall as the glibc wrapper only exists
+    //         since 2.30.
+    unsafe { libc::syscall(libc::SYS_gettid) as libc::pid_t }
+}
+
+/// Real user id through getuid(2)
+#[inline]
+pub fn getuid() -> libc::uid_t {
+    // SAFETY: getuid always succeeds.
+    unsafe { libc::getuid() }
+}
+
+/// Effective user id through geteuid(2) used for the permission checks
+#[inline]
+pub fn geteuid() -> libc::uid_t {
+    // SAFETY: geteuid always succeeds.
+    unsafe { libc::geteuid() }
+}
+
+/// Real group id through getgid(2)
+#[inline]
+pub fn getgid() -> libc::gid_t {
+    // SAFETY: getgid always succeeds.
+    unsafe { libc::getgid() }
+}
+
+/// Effective group id through getegid(2) used for the permission checks
+#[inline]
+pub fn getegid() -> libc::gid_t {
+    // SAFETY: getegid always succeeds.
+    unsafe { libc::getegid() }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    // The status fields are the real, effective, saved and filesystem ids.
+    fn proc_status_ids(field: &str) -> Vec<u32> {
+        let status = std::fs::read_to_string("/proc/self/status").unwrap();
+        let line = status
+            .lines()
+            .find_map(|line| line.strip_prefix(field))
+            .unwrap();
+        line.split_whitespace()
+            .map(|id| id.parse().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn pids_stable() {
+        assert_eq!(getpid(), getpid());
+        assert_eq!(getpid(), std::process::id() as libc::pid_t);
+        assert_eq!(
+            getppid(),
+            std::os::unix::process::parent_id() as libc::pid_t
+        );
+    }
+
+    #[test]
+    fn tid_per_thread() {
+        let tid = gettid();
+        assert!(tid > 0);
+        assert_eq!(tid, gettid());
+        let other = std::thread::spawn(gettid).join().unwrap();
+        assert_ne!(other, tid);
+        assert_eq!(getpid(), std::thread::spawn(getpid).join().unwrap());
+    }
+
+    #[test]
+    fn ids_match_proc_status() {
+        let uids = proc_status_ids("Uid:");
+        assert_eq!((getuid(), geteuid()), (uids[0], uids[1]));
+        let gids = proc_status_ids("Gid:");
+        assert_eq!((getgid(), getegid()), (gids[0], gids[1]));
+    }
+}
@@ -0,0 +1,106 @@
+#![warn(
+    clippy::unwrap_used,
+    missing_docs,
+    rust_2018_idioms,
+    unused_lifetimes,
+    unused_qualifications
+)]
+#![doc = include_str!("../README.md")]
+
+#[cfg(not(target_os = "linux"))]
+compile_error!("Crate yhostname is Linux specific dependency but is used in non-linux system.");
+
+use std::ffi::{OsStr, OsString};
+use std::io;
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+
+/// Maximum hostname len in bytes excluding the NUL terminator
+pub const HOST_NAME_MAX: usize = 64;
+
+/// Hostname of the UTS namespace through gethostname(2)
+#[inline]
+pub fn gethostname() -> io::Result<OsString> {
+    let mut buf = vec![0u8; HOST_NAME_MAX + 1];
+    // SAFETY: buf is valid for writes of its len.
+    if unsafe { libc::gethostname(buf.as_mut_ptr().cast(), buf.len()) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let len = buf.iter().position(|b| *b == 0).unwrap_or(buf.len());
+    buf.truncate(len);
+    Ok(OsString::from_vec(buf))
+}
+
+/// Set the hostname of the UTS namespace through sethostname(2) e.g. after unsharing
+/// CLONE_NEWUTS in a container. Requires CAP_SYS_ADMIN in the namespace.
+///
+/// Names longer than [`HOST_NAME_MAX`] or with a NUL are [`io::ErrorKind::InvalidInput`].
+#[inline]
+pub fn sethostname(name: &OsStr) -> io::Result<()> {
+    let name = name.as_bytes();
+    if name.len() > HOST_NAME_MAX || name.contains(&0) {
+        return Err(io::Error::from(io::ErrorKind::InvalidInput));
+    }
+    // SAFETY: name is valid for reads of its len which needs no NUL terminator.
+    match unsafe { libc::sethostname(name.as_ptr().cast(), name.len()) } {
+        0 => Ok(()),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use rstest::rstest;
+
+    #[test]
+    fn gethostname_matches_uts() {
+        let name = gethostname().unwrap();
+        assert!(!name.is_empty());
+        let uts = std::fs::read_to_string("/proc/sys/kernel/hostname").unwrap();
+        assert_eq!(name, OsStr::new(uts.trim_end()));
+    }
+
+    // Whether the name set and the original restored read back as such.
+    fn set_and_restore() -> io::Result<bool> {
+        let original = gethostname()?;
+        sethostname(OsStr::new("yhostname-test"))?;
+        let set = gethostname()?;
+        sethostname(&original)?;
+        let restored = gethostname()?;
+        Ok(set == "yhostname-test" && restored == original)
+    }
+
+    // Set and restore in a fork child of its own UTS namespace to leave the system alone.
+    #[test]
+    fn sethostname_in_new_uts() {
+        match unsafe { libc::fork() } {
+            0 => {
+                let code = match unsafe { libc::unshare(libc::CLONE_NEWUTS) } {
+                    // Without CAP_SYS_ADMIN there is nothing to test.
+                    r if r != 0 => 0,
+                    // Exiting in place of panicking keeps the unwind out of the duplicated harness.
+                    _ => match set_and_restore() {
+                        Ok(true) => 0,
+                        Ok(false) | Err(_) => 1,
+                    },
+                };
+                unsafe { libc::_exit(code) };
+            }
+            pid => {
+                assert!(pid > 0);
+                let mut status = 0;
+                assert_eq!(unsafe { libc::waitpid(pid, &mut status, 0) }, pid);
+                assert!(libc::WIFEXITED(status) && libc::WEXITSTATUS(status) == 0);
+            }
+        }
+    }
+
+    #[rstest]
+    #[case(&[b'a'; HOST_NAME_MAX + 1])]
+    #[case(b"nul\0byte")]
+    fn sethostname_invalid(#[case] name: &[u8]) {
+        let err = sethostname(OsStr::from_bytes(name)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+}
@@ -0,0 +1,116 @@
+#![warn(
+    clippy::unwrap_used,
+    missing_docs,
+    rust_2018_idioms,
+    unused_lifetimes,
+    unused_qualifications
+)]
+#![doc = include_str!("../README.md")]
+
+#[cfg(not(target_os = "linux"))]
+compile_error!("Crate yioctl is Linux specific dependency but is used in non-linux system.");
+
+use std::io;
+use yown_fd::RawFd;
+
+#[inline]
+fn check(r: libc::c_int) -> io::Result<i32> {
+    match r {
+        r if r < 0 => Err(io::Error::last_os_error()),
+        r => Ok(r),
+    }
+}
+
+/// ioctl(2) taking no argument e.g. FIOCLEX returning the non-negative result.
+///
+/// The request is truncated to the libc request type which is an int on musl.
+#[inline]
+pub fn ioctl_none(fd: RawFd, request: u64) -> io::Result<i32> {
+    // SAFETY: No memory is passed, a request expecting a pointer gets null and fails with EFAULT.
+    check(unsafe { libc::ioctl(fd, request as libc::Ioctl, 0) })
+}
+
+/// ioctl(2) the kernel writes the T through e.g. FIONREAD returning the non-negative result.
+///
+/// # Safety
+///
+/// The request must expect a pointer to T and any bit pattern the kernel writes must be
+/// valid for T.
+#[inline]
+pub unsafe fn ioctl_read<T>(fd: RawFd, request: u64, value: &mut T) -> io::Result<i32> {
+    // SAFETY: value is valid for writes of T which the caller guarantees the request expects.
+    check(unsafe { libc::ioctl(fd, request as libc::Ioctl, value as *mut T) })
+}
+
+/// ioctl(2) the kernel reads the T from e.g. FIONBIO returning the non-negative result.
+///
+/// # Safety
+///
+/// The request must expect a pointer to T which it only reads.
+#[inline]
+pub unsafe fn ioctl_write<T>(fd: RawFd, request: u64, value: &T) -> io::Result<i32> {
+    // SAFETY: value is valid for reads of T which the caller guarantees the request expects.
+    check(unsafe { libc::ioctl(fd, request as libc::Ioctl, value as *const T) })
+}
+
+/// ioctl(2) taking the int by value e.g. TCSBRK returning the non-negative result - see
+/// [`ioctl_write`] for the requests taking a pointer to int.
+#[inline]
+pub fn ioctl_write_int(fd: RawFd, request: u64, value: i32) -> io::Result<i32> {
+    // SAFETY: No memory is passed, a request expecting a pointer fails with EFAULT at worst.
+    check(unsafe { libc::ioctl(fd, request as libc::Ioctl, value) })
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use rstest::rstest;
+    use std::fs::File;
+    use std::io::Write;
+    use yown_fd::{AsRawFd, FromRawFd};
+
+    fn pipe() -> (File, File) {
+        let mut fds = [0; 2];
+        assert_eq!(unsafe { libc::pipe2(fds.as_mut_ptr(), 0) }, 0);
+        unsafe { (File::from_raw_fd(fds[0]), File::from_raw_fd(fds[1])) }
+    }
+
+    #[rstest]
+    #[case(0)]
+    #[case(1)]
+    #[case(4096)]
+    fn fionread_pipe(#[case] len: usize) {
+        let (rx, mut tx) = pipe();
+        tx.write_all(&vec![7u8; len]).unwrap();
+        let mut available: libc::c_int = -1;
+        let r = unsafe { ioctl_read(rx.as_raw_fd(), libc::FIONREAD as _, &mut available) };
+        assert_eq!(r.unwrap(), 0);
+        assert_eq!(available as usize, len);
+    }
+
+    #[test]
+    fn fioclex_pipe() {
+        let (rx, _tx) = pipe();
+        assert_eq!(ioctl_none(rx.as_raw_fd(), libc::FIOCLEX as _).unwrap(), 0);
+        let fd_flags = unsafe { libc::fcntl(rx.as_raw_fd(), libc::F_GETFD) };
+        assert_eq!(fd_flags & libc::FD_CLOEXEC, libc::FD_CLOEXEC);
+    }
+
+    #[test]
+    fn fionbio_pipe() {
+        let (rx, _tx) = pipe();
+        let on: libc::c_int = 1;
+        let r = unsafe { ioctl_write(rx.as_raw_fd(), libc::FIONBIO as _, &on) };
+        assert_eq!(r.unwrap(), 0);
+        let fl = unsafe { libc::fcntl(rx.as_raw_fd(), libc::F_GETFL) };
+        assert_eq!(fl & libc::O_NONBLOCK, libc::O_NONBLOCK);
+    }
+
+    #[test]
+    fn tcsbrk_not_a_tty() {
+        let (rx, _tx) = pipe();
+        let err = ioctl_write_int(rx.as_raw_fd(), libc::TCSBRK as _, 1).unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(libc::ENOTTY));
+    }
+}
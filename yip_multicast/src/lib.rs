@@ -0,0 +1,145 @@
+#![warn(
+    clippy::unwrap_used,
+    missing_docs,
+    rust_2018_idioms,
+    unused_lifetimes,
+    unused_qualifications
+)]
+#![doc = include_str!("../README.md")]
+
+#[cfg(not(target_os = "linux"))]
+compile_error!("Crate yip_multicast is Linux specific dependency but is used in non-linux system.");
+
+use std::io;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use yown_fd::RawFd;
+
+#[inline]
+fn setsockopt<T>(fd: RawFd, level: libc::c_int, name: libc::c_int, value: &T) -> io::Result<()> {
+    // SAFETY: value is valid for size_of::<T>() for the duration of the call.
+    let r = unsafe {
+        libc::setsockopt(
+            fd,
+            level,
+            name,
+            (value as *const T).cast::<libc::c_void>(),
+            size_of::<T>() as libc::socklen_t,
+        )
+    };
+    match r {
+        0 => Ok(()),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+#[inline]
+fn ip_mreq(group: Ipv4Addr, iface: Ipv4Addr) -> libc::ip_mreq {
+    libc::ip_mreq {
+        imr_multiaddr: libc::in_addr {
+            s_addr: u32::from(group).to_be(),
+        },
+        imr_interface: libc::in_addr {
+            s_addr: u32::from(iface).to_be(),
+        },
+    }
+}
+
+#[inline]
+fn ipv6_mreq(group: Ipv6Addr, iface_index: u32) -> libc::ipv6_mreq {
+    libc::ipv6_mreq {
+        ipv6mr_multiaddr: libc::in6_addr {
+            s6_addr: group.octets(),
+        },
+        ipv6mr_interface: iface_index as _,
+    }
+}
+
+/// Join the IPv4 multicast group on the interface with the given local address through
+/// IP_ADD_MEMBERSHIP. [`Ipv4Addr::UNSPECIFIED`] lets the kernel pick the interface.
+#[inline]
+pub fn join_multicast_v4(fd: RawFd, group: Ipv4Addr, iface: Ipv4Addr) -> io::Result<()> {
+    setsockopt(
+        fd,
+        libc::IPPROTO_IP,
+        libc::IP_ADD_MEMBERSHIP,
+        &ip_mreq(group, iface),
+    )
+}
+
+/// Leave the IPv4 multicast group on the interface with the given local address through
+/// IP_DROP_MEMBERSHIP.
+#[inline]
+pub fn leave_multicast_v4(fd: RawFd, group: Ipv4Addr, iface: Ipv4Addr) -> io::Result<()> {
+    setsockopt(
+        fd,
+        libc::IPPROTO_IP,
+        libc::IP_DROP_MEMBERSHIP,
+        &ip_mreq(group, iface),
+    )
+}
+
+/// Join the IPv6 multicast group on the interface with the given index through
+/// IPV6_ADD_MEMBERSHIP. Index 0 lets the kernel pick the interface.
+#[inline]
+pub fn join_multicast_v6(fd: RawFd, group: Ipv6Addr, iface_index: u32) -> io::Result<()> {
+    setsockopt(
+        fd,
+        libc::IPPROTO_IPV6,
+        libc::IPV6_ADD_MEMBERSHIP,
+        &ipv6_mreq(group, iface_index),
+    )
+}
+
+/// Leave the IPv6 multicast group on the interface with the given index through
+/// IPV6_DROP_MEMBERSHIP.
+#[inline]
+pub fn leave_multicast_v6(fd: RawFd, group: Ipv6Addr, iface_index: u32) -> io::Result<()> {
+    setsockopt(
+        fd,
+        libc::IPPROTO_IPV6,
+        libc::IPV6_DROP_MEMBERSHIP,
+        &ipv6_mreq(group, iface_index),
+    )
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use std::net::UdpSocket;
+    use yown_fd::AsRawFd;
+
+    #[test]
+    fn v4_join_leave() {
+        let socket = UdpSocket::bind("0.0.0.0:0").unwrap();
+        let fd = socket.as_raw_fd();
+        let group = Ipv4Addr::new(239, 255, 0, 1);
+        join_multicast_v4(fd, group, Ipv4Addr::LOCALHOST).unwrap();
+        let again = join_multicast_v4(fd, group, Ipv4Addr::LOCALHOST).unwrap_err();
+        assert_eq!(again.raw_os_error(), Some(libc::EADDRINUSE));
+        leave_multicast_v4(fd, group, Ipv4Addr::LOCALHOST).unwrap();
+        let again = leave_multicast_v4(fd, group, Ipv4Addr::LOCALHOST).unwrap_err();
+        assert_eq!(again.raw_os_error(), Some(libc::EADDRNOTAVAIL));
+    }
+
+    #[test]
+    fn v4_not_multicast() {
+        let socket = UdpSocket::bind("0.0.0.0:0").unwrap();
+        let err = join_multicast_v4(socket.as_raw_fd(), Ipv4Addr::LOCALHOST, Ipv4Addr::LOCALHOST)
+            .unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(libc::EINVAL));
+    }
+
+    #[test]
+    fn v6_join_leave() {
+        let socket = UdpSocket::bind("[::]:0").unwrap();
+        let fd = socket.as_raw_fd();
+        let group = "ff15::1234".parse().unwrap();
+        let lo = unsafe { libc::if_nametoindex(c"lo".as_ptr()) };
+        join_multicast_v6(fd, group, lo).unwrap();
+        let again = join_multicast_v6(fd, group, lo).unwrap_err();
+        assert_eq!(again.raw_os_error(), Some(libc::EADDRINUSE));
+        leave_multicast_v6(fd, group, lo).unwrap();
+        assert!(leave_multicast_v6(fd, group, lo).is_err());
+    }
+}
@@ -0,0 +1,117 @@
+#![warn(
+    clippy::unwrap_used,
+    missing_docs,
+    rust_2018_idioms,
+    unused_lifetimes,
+    unused_qualifications
+)]
+#![doc = include_str!("../README.md")]
+
+#[cfg(not(target_os = "linux"))]
+compile_error!("Crate ymkdir is Linux specific dependency but is used in non-linux system.");
+
+use std::ffi::{CString, OsString};
+use std::io;
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+use std::path::{Path, PathBuf};
+use yown_fd::RawFd;
+
+#[inline]
+fn path_cstring(path: &Path) -> io::Result<CString> {
+    CString::new(path.as_os_str().as_bytes())
+        .map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))
+}
+
+/// Create the directory at path through mkdirat(2) where a relative path is resolved
+/// against dirfd or the current working directory with None.
+///
+/// The mode is masked by the umask. Path with an interior NUL is
+/// [`io::ErrorKind::InvalidInput`].
+#[inline]
+pub fn mkdirat(dirfd: Option<RawFd>, path: &Path, mode: libc::mode_t) -> io::Result<()> {
+    let path = path_cstring(path)?;
+    let dirfd = dirfd.unwrap_or(libc::AT_FDCWD);
+    // SAFETY: path is NUL terminated.
+    match unsafe { libc::mkdirat(dirfd, path.as_ptr(), mode) } {
+        0 => Ok(()),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+/// Create a uniquely named directory with mode 0700 through mkdtemp(3) returning its path.
+///
+/// The template has to end in XXXXXX which is replaced to make the name unique, otherwise
+/// it fails with EINVAL. Removing the directory is up to the caller. Template with an
+/// interior NUL is [`io::ErrorKind::InvalidInput`].
+#[inline]
+pub fn mkdtemp(template: &Path) -> io::Result<PathBuf> {
+    let mut buf = path_cstring(template)?.into_bytes_with_nul();
+    // SAFETY: buf is NUL terminated and mkdtemp only replaces the trailing XXXXXX in place.
+    if unsafe { libc::mkdtemp(buf.as_mut_ptr() as *mut libc::c_char) }.is_null() {
+        return Err(io::Error::last_os_error());
+    }
+    buf.pop();
+    Ok(PathBuf::from(OsString::from_vec(buf)))
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use rstest::rstest;
+    use std::fs::File;
+    use std::os::unix::fs::PermissionsExt;
+    use yown_fd::AsRawFd;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("ymkdir-{}-{}", std::process::id(), name))
+    }
+
+    #[test]
+    fn mkdirat_cwd_and_dirfd() {
+        let parent = temp_path("mkdirat");
+        let _ = std::fs::remove_dir_all(&parent);
+        mkdirat(None, &parent, 0o755).unwrap();
+        assert!(parent.is_dir());
+        let dir = File::open(&parent).unwrap();
+        mkdirat(Some(dir.as_raw_fd()), Path::new("child"), 0o700).unwrap();
+        let child = parent.join("child");
+        let mode = std::fs::metadata(&child).unwrap().permissions().mode();
+        let err = mkdirat(Some(dir.as_raw_fd()), Path::new("child"), 0o700).unwrap_err();
+        std::fs::remove_dir_all(&parent).unwrap();
+        assert_eq!(mode & 0o777, 0o700);
+        assert_eq!(err.raw_os_error(), Some(libc::EEXIST));
+    }
+
+    #[test]
+    fn mkdtemp_unique() {
+        let template = temp_path("tmp-XXXXXX");
+        let first = mkdtemp(&template).unwrap();
+        let second = mkdtemp(&template).unwrap();
+        let first_meta = std::fs::metadata(&first);
+        File::create(second.join("file")).unwrap();
+        std::fs::remove_dir_all(&first).unwrap();
+        std::fs::remove_dir_all(&second).unwrap();
+        assert_ne!(first, second);
+        assert_eq!(first.parent(), template.parent());
+        assert!(!first.to_str().unwrap().ends_with("XXXXXX"));
+        assert_eq!(first_meta.unwrap().permissions().mode() & 0o777, 0o700);
+    }
+
+    #[rstest]
+    #[case(Path::new("/tmp/ymkdir-no-template"), Some(libc::EINVAL))]
+    #[case(Path::new("/nonexistent-ymkdir/XXXXXX"), Some(libc::ENOENT))]
+    #[case(Path::new("/tmp/nul\0XXXXXX"), None)]
+    fn mkdtemp_errors(#[case] template: &Path, #[case] errno: Option<i32>) {
+        let err = mkdtemp(template).unwrap_err();
+        assert_eq!(err.raw_os_error(), errno);
+    }
+
+    #[rstest]
+    #[case(Path::new("/nonexistent-ymkdir/child"), Some(libc::ENOENT))]
+    #[case(Path::new("nul\0byte"), None)]
+    fn mkdirat_errors(#[case] path: &Path, #[case] errno: Option<i32>) {
+        let err = mkdirat(None, path, 0o755).unwrap_err();
+        assert_eq!(err.raw_os_error(), errno);
+    }
+}
@@ -0,0 +1,217 @@
+#![warn(
+    clippy::unwrap_used,
+    missing_docs,
+    rust_2018_idioms,
+    unused_lifetimes,
+    unused_qualifications
+)]
+#![doc = include_str!("../README.md")]
+
+#[cfg(not(target_os = "linux"))]
+compile_error!("Crate ymount is Linux specific dependency but is used in non-linux system.");
+
+use std::ffi::{CStr, CString};
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+
+bitflags::bitflags! {
+    /// mount(2) mountflags
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    pub struct MountFlags: libc::c_ulong {
+        /// Bind mount the source onto the target
+        const MS_BIND = libc::MS_BIND;
+        /// Recursively with MS_BIND including the submounts
+        const MS_REC = libc::MS_REC;
+        /// Read-only
+        const MS_RDONLY = libc::MS_RDONLY;
+        /// Change the flags of an existing mount
+        const MS_REMOUNT = libc::MS_REMOUNT;
+        /// Move the existing source mount to the target
+        const MS_MOVE = libc::MS_MOVE;
+        /// Ignore set-user-ID and set-group-ID bits
+        const MS_NOSUID = libc::MS_NOSUID;
+        /// No access to device special files
+        const MS_NODEV = libc::MS_NODEV;
+        /// No execution of programs
+        const MS_NOEXEC = libc::MS_NOEXEC;
+    }
+}
+
+bitflags::bitflags! {
+    /// umount2(2) flags
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    pub struct UmountFlags: libc::c_int {
+        /// Force the unmount even if busy, only for some network filesystems
+        const MNT_FORCE = libc::MNT_FORCE;
+        /// Lazy unmount detaching now and cleaning up when no longer busy
+        const MNT_DETACH = libc::MNT_DETACH;
+        /// Mark as expired, unmounting on the second call if untouched in between
+        const MNT_EXPIRE = libc::MNT_EXPIRE;
+        /// Do not dereference the target if it is a symlink
+        const UMOUNT_NOFOLLOW = libc::UMOUNT_NOFOLLOW;
+    }
+}
+
+#[inline]
+fn path_cstring(path: &Path) -> io::Result<CString> {
+    CString::new(path.as_os_str().as_bytes())
+        .map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))
+}
+
+#[inline]
+fn opt_ptr(s: Option<&CStr>) -> *const libc::c_char {
+    s.map_or(core::ptr::null(), |s| s.as_ptr())
+}
+
+/// Mount the source on the target through mount(2) e.g. a bind mount with
+/// `mount(Some(source), target, None, MountFlags::MS_BIND, None)`.
+///
+/// Requires CAP_SYS_ADMIN in the user namespace owning the mount namespace and a path with an
+/// interior NUL is InvalidInput.
+#[inline]
+pub fn mount(
+    source: Option<&Path>,
+    target: &Path,
+    fstype: Option<&CStr>,
+    flags: MountFlags,
+    data: Option<&CStr>,
+) -> io::Result<()> {
+    let source = source.map(path_cstring).transpose()?;
+    let target = path_cstring(target)?;
+    // SAFETY: All strings are valid NUL terminated for the duration of the call.
+    let r = unsafe {
+        libc::mount(
+            opt_ptr(source.as_deref()),
+            target.as_ptr(),
+            opt_ptr(fstype),
+            flags.bits(),
+            opt_ptr(data).cast(),
+        )
+    };
+    match r {
+        0 => Ok(()),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+/// Unmount the target through umount2(2)
+#[inline]
+pub fn umount2(target: &Path, flags: UmountFlags) -> io::Result<()> {
+    let target = path_cstring(target)?;
+    // SAFETY: target is valid NUL terminated for the duration of the call.
+    match unsafe { libc::umount2(target.as_ptr(), flags.bits()) } {
+        0 => Ok(()),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use std::path::PathBuf;
+
+    // Removed recursively unless a mount target which only ever gets an empty dir removed
+    // so a mount still in place can not be emptied through it.
+    struct TempDir {
+        path: PathBuf,
+        mount_target: bool,
+    }
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("ymount-{}-{}", std::process::id(), name));
+            std::fs::create_dir_all(&path).unwrap();
+            Self {
+                path,
+                mount_target: false,
+            }
+        }
+        fn mount_target(name: &str) -> Self {
+            let mut dir = Self::new(name);
+            dir.mount_target = true;
+            dir
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = match self.mount_target {
+                true => std::fs::remove_dir(&self.path),
+                false => std::fs::remove_dir_all(&self.path),
+            };
+        }
+    }
+
+    // Lazily unmounts the target even when unwinding, dropped before the target dir.
+    struct Mounted<'a>(&'a Path);
+
+    impl Drop for Mounted<'_> {
+        fn drop(&mut self) {
+            // Already unmounted by the test unless it panicked in between.
+            let _ = umount2(self.0, UmountFlags::MNT_DETACH);
+        }
+    }
+
+    fn privileged(r: io::Result<()>) -> bool {
+        match r {
+            Ok(()) => true,
+            Err(e) if e.raw_os_error() == Some(libc::EPERM) => false,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    #[test]
+    fn bind_tmp_and_unmount() {
+        let target = TempDir::mount_target("bind");
+        let tmp = std::env::temp_dir();
+        if !privileged(mount(
+            Some(&tmp),
+            &target.path,
+            None,
+            MountFlags::MS_BIND,
+            None,
+        )) {
+            return;
+        }
+        let mounted = Mounted(&target.path);
+        let marker = tmp.join(format!("ymount-{}-marker", std::process::id()));
+        std::fs::write(&marker, b"bound").unwrap();
+        let through = target.path.join(marker.file_name().unwrap());
+        let read = std::fs::read(&through);
+        std::fs::remove_file(&marker).unwrap();
+        umount2(&target.path, UmountFlags::empty()).unwrap();
+        drop(mounted);
+        assert_eq!(read.unwrap(), b"bound");
+        assert!(!through.exists());
+    }
+
+    #[test]
+    fn bind_readonly_remount() {
+        let source = TempDir::new("ro-source");
+        let target = TempDir::mount_target("ro-target");
+        if !privileged(mount(
+            Some(&source.path),
+            &target.path,
+            None,
+            MountFlags::MS_BIND,
+            None,
+        )) {
+            return;
+        }
+        let mounted = Mounted(&target.path);
+        let ro = MountFlags::MS_BIND | MountFlags::MS_REMOUNT | MountFlags::MS_RDONLY;
+        mount(None, &target.path, None, ro, None).unwrap();
+        let err = std::fs::write(target.path.join("denied"), b"x").unwrap_err();
+        umount2(&target.path, UmountFlags::MNT_DETACH).unwrap();
+        drop(mounted);
+        assert_eq!(err.raw_os_error(), Some(libc::EROFS));
+    }
+
+    #[test]
+    fn interior_nul() {
+        let err = umount2(Path::new("/tmp/a\0b"), UmountFlags::empty()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+}
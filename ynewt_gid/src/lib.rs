@@ -0,0 +1,126 @@
+#![warn(
+    clippy::unwrap_used,
+    missing_docs,
+    rust_2018_idioms,
+    unused_lifetimes,
+    unused_qualifications
+)]
+#![doc = include_str!("../README.md")]
+
+#[cfg(not(target_os = "linux"))]
+compile_error!("Crate ynewt_gid is Linux specific dependency but is used in non-linux system.");
+
+use std::fmt::Write as _;
+use std::io::{self, Write};
+
+// One "inside outside count" line per mapping.
+fn format_map(mappings: &[(u32, u32, u32)]) -> String {
+    let mut lines = String::new();
+    for (inside, outside, count) in mappings {
+        // Writing into a String can not fail.
+        let _ = writeln!(lines, "{} {} {}", inside, outside, count);
+    }
+    lines
+}
+
+// The kernel only accepts the whole file content in a single write(2).
+fn write_proc(pid: libc::pid_t, file: &str, content: &str) -> io::Result<()> {
+    let path = format!("/proc/{}/{}", pid, file);
+    let mut f = std::fs::OpenOptions::new().write(true).open(path)?;
+    match f.write(content.as_bytes())? {
+        n if n == content.len() => Ok(()),
+        _ => Err(io::Error::from(io::ErrorKind::WriteZero)),
+    }
+}
+
+/// Write the (inside, outside, count) mappings to /proc/pid/uid_map of a process in a new
+/// user namespace. The map can only be written once per namespace.
+#[inline]
+pub fn write_uid_map(pid: libc::pid_t, mappings: &[(u32, u32, u32)]) -> io::Result<()> {
+    write_proc(pid, "uid_map", &format_map(mappings))
+}
+
+/// Write the (inside, outside, count) mappings to /proc/pid/gid_map of a process in a new
+/// user namespace. Without CAP_SETGID in the parent namespace [`deny_setgroups`] needs to be
+/// written first.
+#[inline]
+pub fn write_gid_map(pid: libc::pid_t, mappings: &[(u32, u32, u32)]) -> io::Result<()> {
+    write_proc(pid, "gid_map", &format_map(mappings))
+}
+
+/// Write "deny" to /proc/pid/setgroups disabling setgroups(2) in the user namespace as
+/// required for an unprivileged [`write_gid_map`].
+#[inline]
+pub fn deny_setgroups(pid: libc::pid_t) -> io::Result<()> {
+    write_proc(pid, "setgroups", "deny")
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(&[], "")]
+    #[case(&[(0, 1000, 1)], "0 1000 1\n")]
+    #[case(&[(0, 100000, 65536), (65536, 1000, 1)], "0 100000 65536\n65536 1000 1\n")]
+    fn format_lines(#[case] mappings: &[(u32, u32, u32)], #[case] expected: &str) {
+        assert_eq!(format_map(mappings), expected);
+    }
+
+    fn pipe() -> [libc::c_int; 2] {
+        let mut fds = [0; 2];
+        assert_eq!(unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) }, 0);
+        fds
+    }
+
+    // unshare(CLONE_NEWUSER) needs a single threaded process hence the forked child which only
+    // makes async-signal-safe calls while the maps are written from here.
+    #[test]
+    fn map_root_in_new_user_namespace() {
+        let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+        let (to_parent, to_child) = (pipe(), pipe());
+        let pid = match unsafe { libc::fork() } {
+            0 => unsafe {
+                // Reading EOF instead of blocking forever should the parent go away.
+                libc::close(to_child[1]);
+                let status: u8 = match libc::unshare(libc::CLONE_NEWUSER) {
+                    0 => b'u',
+                    _ => b'p',
+                };
+                libc::write(to_parent[1], (&status as *const u8).cast(), 1);
+                let mut go = 0u8;
+                if status == b'p' || libc::read(to_child[0], (&mut go as *mut u8).cast(), 1) != 1 {
+                    libc::_exit(2);
+                }
+                let ok = libc::getuid() == 0 && libc::getgid() == 0;
+                libc::_exit(if ok { 0 } else { 1 });
+            },
+            pid => pid,
+        };
+        assert!(pid > 0);
+        let mut status = 0u8;
+        assert_eq!(
+            unsafe { libc::read(to_parent[0], (&mut status as *mut u8).cast(), 1) },
+            1
+        );
+        let mut wait_status = 0;
+        if status == b'p' {
+            // No user namespaces for us e.g. in a sandbox.
+            assert_eq!(unsafe { libc::waitpid(pid, &mut wait_status, 0) }, pid);
+            return;
+        }
+        write_uid_map(pid, &[(0, uid, 1)]).unwrap();
+        deny_setgroups(pid).unwrap();
+        write_gid_map(pid, &[(0, gid, 1)]).unwrap();
+        assert!(write_uid_map(pid, &[(0, uid, 1)]).is_err());
+        unsafe { libc::write(to_child[1], (&status as *const u8).cast(), 1) };
+        assert_eq!(unsafe { libc::waitpid(pid, &mut wait_status, 0) }, pid);
+        assert!(libc::WIFEXITED(wait_status));
+        assert_eq!(libc::WEXITSTATUS(wait_status), 0);
+        for fd in to_parent.into_iter().chain(to_child) {
+            unsafe { libc::close(fd) };
+        }
+    }
+}
@@ -0,0 +1,241 @@
+#![warn(
+    clippy::unwrap_used,
+    missing_docs,
+    rust_2018_idioms,
+    unused_lifetimes,
+    unused_qualifications
+)]
+#![doc = include_str!("../README.md")]
+
+#[cfg(not(target_os = "linux"))]
+compile_error!("Crate yopen_direct is Linux specific dependency but is used in non-linux system.");
+
+use anonymous_mmap::{page_size, AnonymousMmap, AnonymousMmapError};
+use core::mem::ManuallyDrop;
+use std::ffi::CString;
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+use yown_fd::{FromRawFd, OwnedFd, RawFd};
+
+/// Buffer for O_DIRECT I/O whose start is aligned to align and whose len is a multiple of it
+#[derive(Debug)]
+pub struct DirectBuf {
+    map: ManuallyDrop<AnonymousMmap>,
+    align: usize,
+}
+
+impl DirectBuf {
+    /// Construct a zeroed buffer of capacity rounded up to a multiple of align e.g. the
+    /// [`block_size`] of the file.
+    ///
+    /// The mapping is page-aligned so align has to be a power of two up to the page size,
+    /// otherwise [`AnonymousMmapError::Misaligned`] with the page size and align.
+    #[inline]
+    pub fn new(capacity: usize, align: usize) -> Result<Self, AnonymousMmapError> {
+        let page = page_size();
+        if !align.is_power_of_two() || align > page {
+            return Err(AnonymousMmapError::Misaligned(page, align));
+        }
+        let len = capacity
+            .max(1)
+            .checked_next_multiple_of(align)
+            .ok_or(AnonymousMmapError::OutOfBounds(0, capacity))?;
+        Ok(Self {
+            map: ManuallyDrop::new(AnonymousMmap::new(len)?),
+            align,
+        })
+    }
+    /// Alignment of the start and len
+    #[inline]
+    pub fn align(&self) -> usize {
+        self.align
+    }
+    /// Len in bytes, a multiple of [`Self::align`]
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+    /// Never empty as capacity is rounded up to at least align
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+    /// The whole buffer e.g. to pwrite(2) through an O_DIRECT fd
+    #[inline]
+    pub fn as_aligned_slice(&self) -> &[u8] {
+        // SAFETY: The mapping is ours, readable and writable without holes for its len.
+        unsafe { core::slice::from_raw_parts(self.map.as_ptr().cast(), self.map.len()) }
+    }
+    /// The whole buffer e.g. to pread(2) into through an O_DIRECT fd
+    #[inline]
+    pub fn as_aligned_slice_mut(&mut self) -> &mut [u8] {
+        // SAFETY: As above and borrowed exclusively.
+        unsafe { core::slice::from_raw_parts_mut(self.map.as_ptr_mut().cast(), self.map.len()) }
+    }
+}
+
+impl Drop for DirectBuf {
+    fn drop(&mut self) {
+        // SAFETY: The slices borrow self so none outlives it.
+        let map = unsafe { ManuallyDrop::take(&mut self.map) };
+        // Nothing to give the error back to.
+        let _ = unsafe { map.try_drop() };
+    }
+}
+
+/// Open path read-write through open(2) with O_DIRECT | O_CLOEXEC bypassing the page cache.
+///
+/// Filesystems without O_DIRECT support e.g. tmpfs fail with EINVAL. Path with an interior
+/// NUL is [`io::ErrorKind::InvalidInput`].
+#[inline]
+pub fn open_direct(path: &Path) -> io::Result<OwnedFd> {
+    let path = CString::new(path.as_os_str().as_bytes())
+        .map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))?;
+    // SAFETY: path is NUL terminated.
+    let fd = unsafe {
+        libc::open(
+            path.as_ptr(),
+            libc::O_RDWR | libc::O_DIRECT | libc::O_CLOEXEC,
+        )
+    };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // SAFETY: fd was just opened and is owned by nobody else.
+    Ok(unsafe { OwnedFd::from_raw_fd(fd) })
+}
+
+/// Granularity of O_DIRECT offsets and lens on fd: the logical sector size through
+/// BLKSSZGET for block devices, otherwise the statx(2) STATX_DIOALIGN offset alignment
+/// (Linux 6.1) falling back to the filesystem block size.
+#[inline]
+pub fn block_size(fd: RawFd) -> io::Result<u32> {
+    // SAFETY: statx is valid when zeroed.
+    let mut stx: libc::statx = unsafe { core::mem::zeroed() };
+    // SAFETY: The empty path with AT_EMPTY_PATH refers to fd and stx is valid to write.
+    let r = unsafe {
+        libc::statx(
+            fd,
+            c"".as_ptr(),
+            libc::AT_EMPTY_PATH,
+            libc::STATX_TYPE | libc::STATX_DIOALIGN,
+            &mut stx,
+        )
+    };
+    if r != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    if u32::from(stx.stx_mode) & libc::S_IFMT == libc::S_IFBLK {
+        let mut size: libc::c_int = 0;
+        // SAFETY: BLKSSZGET writes an int.
+        if unsafe { libc::ioctl(fd, libc::BLKSSZGET, &mut size) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        return Ok(size as u32);
+    }
+    match stx.stx_mask & libc::STATX_DIOALIGN != 0 && stx.stx_dio_offset_align != 0 {
+        true => Ok(stx.stx_dio_offset_align),
+        false => Ok(stx.stx_blksize),
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use rstest::rstest;
+    use std::path::PathBuf;
+    use yown_fd::AsRawFd;
+
+    // The tests skip when the temp dir is e.g. tmpfs expectedly without O_DIRECT.
+    fn temp_file(name: &str, len: u64) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("yopen_direct-{}-{}", name, std::process::id()));
+        std::fs::File::create(&path).unwrap().set_len(len).unwrap();
+        path
+    }
+
+    #[rstest]
+    #[case(1, 512, 512)]
+    #[case(4096, 4096, 4096)]
+    #[case(4097, 512, 4608)]
+    #[case(0, 4096, 4096)]
+    fn rounded_up(#[case] capacity: usize, #[case] align: usize, #[case] len: usize) {
+        let buf = DirectBuf::new(capacity, align).unwrap();
+        assert_eq!(buf.len(), len);
+        assert_eq!(buf.align(), align);
+        assert!((buf.as_aligned_slice().as_ptr() as usize).is_multiple_of(align));
+        assert!(buf.as_aligned_slice().iter().all(|b| *b == 0));
+    }
+
+    #[rstest]
+    #[case(3)]
+    #[case(0)]
+    #[case(page_size() * 2)]
+    fn align_rejected(#[case] align: usize) {
+        assert!(matches!(
+            DirectBuf::new(4096, align),
+            Err(AnonymousMmapError::Misaligned(_, a)) if a == align
+        ));
+    }
+
+    #[test]
+    fn direct_write_read_blocks() {
+        let path = temp_file("rw", 0);
+        let fd = match open_direct(&path) {
+            Ok(fd) => fd,
+            // Filesystems without O_DIRECT have nothing to test.
+            Err(e) if e.raw_os_error() == Some(libc::EINVAL) => {
+                std::fs::remove_file(&path).unwrap();
+                return;
+            }
+            Err(e) => panic!("open_direct: {}", e),
+        };
+        let block = block_size(fd.as_raw_fd()).unwrap() as usize;
+        assert!(block.is_power_of_two());
+        let mut out = DirectBuf::new(block * 4, block).unwrap();
+        for (i, chunk) in out.as_aligned_slice_mut().chunks_mut(block).enumerate() {
+            chunk.fill(i as u8 + 1);
+        }
+        let w = out.as_aligned_slice();
+        let r = unsafe { libc::pwrite(fd.as_raw_fd(), w.as_ptr().cast(), w.len(), 0) };
+        assert_eq!(r as usize, w.len(), "{}", io::Error::last_os_error());
+
+        let mut back = DirectBuf::new(block * 2, block).unwrap();
+        let b = back.as_aligned_slice_mut();
+        let offset = block as libc::off_t * 2;
+        let r = unsafe { libc::pread(fd.as_raw_fd(), b.as_mut_ptr().cast(), b.len(), offset) };
+        assert_eq!(r as usize, b.len(), "{}", io::Error::last_os_error());
+        assert!(back.as_aligned_slice()[..block].iter().all(|x| *x == 3));
+        assert!(back.as_aligned_slice()[block..].iter().all(|x| *x == 4));
+        drop(fd);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn misaligned_direct_rejected() {
+        let path = temp_file("misaligned", 8192);
+        let Ok(fd) = open_direct(&path) else {
+            std::fs::remove_file(&path).unwrap();
+            return;
+        };
+        let buf = DirectBuf::new(8192, 4096).unwrap();
+        // One byte off the aligned start.
+        let p = unsafe { buf.as_aligned_slice().as_ptr().add(1) };
+        let r = unsafe { libc::pwrite(fd.as_raw_fd(), p.cast(), 4096, 0) };
+        assert_eq!(r, -1);
+        assert_eq!(
+            io::Error::last_os_error().raw_os_error(),
+            Some(libc::EINVAL)
+        );
+        drop(fd);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn open_direct_missing() {
+        let err = open_direct(Path::new("/nonexistent/yopen_direct")).unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(libc::ENOENT));
+    }
+}
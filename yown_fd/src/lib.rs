@@ -0,0 +1,31 @@
+#![warn(
+    clippy::unwrap_used,
+    missing_docs,
+    rust_2018_idioms,
+    unused_lifetimes,
+    unused_qualifications
+)]
+#![doc = include_str!("../README.md")]
+
+pub use std::os::fd::{AsFd, AsRawFd, BorrowedFd, FromRawFd, IntoRawFd, OwnedFd, RawFd};
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use std::fs::File;
+
+    #[test]
+    fn owned_borrowed_roundtrip() {
+        let file = File::open("/dev/null").unwrap();
+        let raw = file.as_raw_fd();
+        let owned = unsafe { OwnedFd::from_raw_fd(file.into_raw_fd()) };
+        assert_eq!(owned.as_raw_fd(), raw);
+        let borrowed: BorrowedFd<'_> = owned.as_fd();
+        assert_eq!(borrowed.as_raw_fd(), raw);
+        let raw_back = owned.into_raw_fd();
+        assert_eq!(raw_back, raw);
+        drop(unsafe { OwnedFd::from_raw_fd(raw_back) });
+        assert_eq!(unsafe { libc::fcntl(raw, libc::F_GETFD) }, -1);
+    }
+}
@@ -0,0 +1,147 @@
+#![warn(
+    clippy::unwrap_used,
+    missing_docs,
+    rust_2018_idioms,
+    unused_lifetimes,
+    unused_qualifications
+)]
+#![doc = include_str!("../README.md")]
+
+#[cfg(not(target_os = "linux"))]
+compile_error!("Crate ypath_fd is Linux specific dependency but is used in non-linux system.");
+
+use std::ffi::CString;
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use yown_fd::{FromRawFd, OwnedFd, RawFd};
+
+/// Open an O_PATH | O_CLOEXEC fd to the path entry without opening the file for I/O e.g.
+/// to later fstatat(2) or openat(2) relative to it without racing on the path.
+///
+/// Without follow_symlinks a symlink at path is opened itself through O_NOFOLLOW. Path with
+/// an interior NUL is [`io::ErrorKind::InvalidInput`].
+#[inline]
+pub fn open_path(path: &Path, follow_symlinks: bool) -> io::Result<OwnedFd> {
+    let path = CString::new(path.as_os_str().as_bytes())
+        .map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))?;
+    let flags = match follow_symlinks {
+        true => libc::O_PATH | libc::O_CLOEXEC,
+        false => libc::O_PATH | libc::O_CLOEXEC | libc::O_NOFOLLOW,
+    };
+    // SAFETY: path is NUL terminated.
+    let fd = unsafe { libc::open(path.as_ptr(), flags) };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // SAFETY: fd was just opened and is owned by nobody else.
+    Ok(unsafe { OwnedFd::from_raw_fd(fd) })
+}
+
+/// Path the fd currently refers to through the /proc/self/fd/{fd} magic link.
+///
+/// The path follows renames of the entry, one unlinked since gets " (deleted)" appended.
+#[inline]
+pub fn path_to_fd_path(fd: RawFd) -> io::Result<PathBuf> {
+    yreadlink::readlink(Path::new(&format!("/proc/self/fd/{}", fd)))
+}
+
+/// fstatat(2) of the entry the fd refers to itself through the empty path with
+/// AT_EMPTY_PATH | AT_SYMLINK_NOFOLLOW, a symlink opened through [`open_path`] without
+/// following it being S_IFLNK.
+#[inline]
+pub fn fstatat_path(path_fd: RawFd) -> io::Result<libc::stat> {
+    // SAFETY: stat is valid when zeroed.
+    let mut st: libc::stat = unsafe { core::mem::zeroed() };
+    // SAFETY: The empty path is NUL terminated and st is valid to write.
+    let r = unsafe {
+        libc::fstatat(
+            path_fd,
+            c"".as_ptr(),
+            &mut st,
+            libc::AT_EMPTY_PATH | libc::AT_SYMLINK_NOFOLLOW,
+        )
+    };
+    if r != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(st)
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use rstest::rstest;
+    use yown_fd::AsRawFd;
+
+    // Canonical so the /proc/self/fd link compares equal even if the temp dir is a symlink.
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().canonicalize().unwrap().join(format!(
+            "ypath_fd-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir(&dir).unwrap();
+        dir
+    }
+
+    #[rstest]
+    #[case(true, libc::S_IFREG)]
+    #[case(false, libc::S_IFLNK)]
+    fn open_path_symlink(#[case] follow_symlinks: bool, #[case] file_type: libc::mode_t) {
+        let dir = temp_dir(&format!("follow-{}", follow_symlinks));
+        let file = dir.join("file");
+        std::fs::write(&file, b"ypath_fd").unwrap();
+        let link = dir.join("link");
+        std::os::unix::fs::symlink(&file, &link).unwrap();
+
+        let fd = open_path(&link, follow_symlinks).unwrap();
+        let expected = match follow_symlinks {
+            true => &file,
+            false => &link,
+        };
+        assert_eq!(&path_to_fd_path(fd.as_raw_fd()).unwrap(), expected);
+        let st = fstatat_path(fd.as_raw_fd()).unwrap();
+        assert_eq!(st.st_mode & libc::S_IFMT, file_type);
+        if follow_symlinks {
+            assert_eq!(st.st_size, 8);
+        }
+        drop(fd);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn path_fd_no_io() {
+        let dir = temp_dir("no-io");
+        let file = dir.join("file");
+        std::fs::write(&file, b"ypath_fd").unwrap();
+        let fd = open_path(&file, true).unwrap();
+        let mut buf = [0u8; 8];
+        let r = unsafe { libc::read(fd.as_raw_fd(), buf.as_mut_ptr().cast(), buf.len()) };
+        assert_eq!(r, -1);
+        assert_eq!(io::Error::last_os_error().raw_os_error(), Some(libc::EBADF));
+
+        // The fd keeps referring to the entry across a rename.
+        let renamed = dir.join("renamed");
+        std::fs::rename(&file, &renamed).unwrap();
+        assert_eq!(path_to_fd_path(fd.as_raw_fd()).unwrap(), renamed);
+        drop(fd);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[rstest]
+    #[case(Path::new("/nonexistent/ypath_fd"), io::ErrorKind::NotFound)]
+    #[case(Path::new("ypath\0fd"), io::ErrorKind::InvalidInput)]
+    fn open_path_errors(#[case] path: &Path, #[case] kind: io::ErrorKind) {
+        assert_eq!(open_path(path, true).unwrap_err().kind(), kind);
+    }
+
+    #[test]
+    fn bad_fd() {
+        assert!(path_to_fd_path(-1).is_err());
+        let err = fstatat_path(-1).unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(libc::EBADF));
+    }
+}
@@ -0,0 +1,211 @@
+#![warn(
+    clippy::unwrap_used,
+    missing_docs,
+    rust_2018_idioms,
+    unused_lifetimes,
+    unused_qualifications
+)]
+#![doc = include_str!("../README.md")]
+
+#[cfg(not(target_os = "linux"))]
+compile_error!("Crate ypkeys is Linux specific dependency but is used in non-linux system.");
+
+use std::io;
+
+bitflags::bitflags! {
+    /// Access rights of a protection key
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    pub struct PkeyRights: libc::c_uint {
+        /// PKEY_DISABLE_ACCESS - any data access to the pages faults
+        const DISABLE_ACCESS = 0x1;
+        /// PKEY_DISABLE_WRITE - writes to the pages fault
+        const DISABLE_WRITE = 0x2;
+    }
+}
+
+/// Allocate a protection key through pkey_alloc(2) with the initial rights of the calling
+/// thread. Other threads get the default rights of the key.
+///
+/// Fails with EINVAL or ENOSPC when the CPU or the kernel has no protection keys and with
+/// ENOSYS when the syscall itself is missing.
+#[inline]
+pub fn pkey_alloc(initial_rights: PkeyRights) -> io::Result<libc::c_int> {
+    // SAFETY: Only takes the flags and the rights.
+    match unsafe { libc::syscall(libc::SYS_pkey_alloc, 0, initial_rights.bits()) } {
+        -1 => Err(io::Error::last_os_error()),
+        pkey => Ok(pkey as libc::c_int),
+    }
+}
+
+/// Free a protection key through pkey_free(2).
+///
+/// Pages still tagged with the key keep it and would change rights with any later
+/// allocation reusing the key so re-assign them first.
+#[inline]
+pub fn pkey_free(pkey: libc::c_int) -> io::Result<()> {
+    // SAFETY: Only takes the key.
+    match unsafe { libc::syscall(libc::SYS_pkey_free, pkey) } {
+        0 => Ok(()),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+/// Set the protection of [addr, addr + len) to prot and tag the pages with pkey through
+/// pkey_mprotect(2). pkey -1 is the default key which is plain mprotect(2).
+///
+/// # Safety
+///
+/// The range must be mapped and owned by the caller as any reference into it may fault
+/// after.
+#[inline]
+pub unsafe fn pkey_mprotect(
+    addr: *mut libc::c_void,
+    len: usize,
+    prot: libc::c_int,
+    pkey: libc::c_int,
+) -> io::Result<()> {
+    // SAFETY: The caller guarantees the range is theirs.
+    match unsafe { libc::syscall(libc::SYS_pkey_mprotect, addr, len, prot, pkey) } {
+        0 => Ok(()),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+#[inline]
+fn pkey_shift(pkey: libc::c_int) -> io::Result<u32> {
+    match pkey {
+        0..=15 => Ok(pkey as u32 * 2),
+        _ => Err(io::Error::from_raw_os_error(libc::EINVAL)),
+    }
+}
+
+/// Read the PKRU register of the calling thread with rdpkru.
+///
+/// Only meaningful with a protection key capable CPU where the kernel has enabled the
+/// register (OSPKE) as otherwise rdpkru raises SIGILL - check [`pkey_alloc`] succeeds first.
+#[cfg(target_arch = "x86_64")]
+#[inline]
+pub fn read_pkru() -> u32 {
+    let pkru: u32;
+    // SAFETY: rdpkru only reads the register into eax with ecx zero.
+    unsafe {
+        core::arch::asm!(
+            "rdpkru",
+            in("ecx") 0,
+            out("eax") pkru,
+            out("edx") _,
+            options(nomem, nostack, preserves_flags)
+        );
+    }
+    pkru
+}
+
+/// Get the rights of the calling thread to pkey from PKRU like glibc pkey_get(3).
+///
+/// Fails with EINVAL for a key out of range and with ENOSYS beyond x86_64.
+#[inline]
+pub fn pkey_get(pkey: libc::c_int) -> io::Result<PkeyRights> {
+    let shift = pkey_shift(pkey)?;
+    #[cfg(target_arch = "x86_64")]
+    {
+        Ok(PkeyRights::from_bits_truncate(read_pkru() >> shift))
+    }
+    #[cfg(not(target_arch = "x86_64"))]
+    {
+        let _ = shift;
+        Err(io::Error::from_raw_os_error(libc::ENOSYS))
+    }
+}
+
+/// Set the rights of the calling thread to pkey in PKRU like glibc pkey_set(3).
+/// The change is immediate and there is no syscall involved.
+///
+/// Fails with EINVAL for a key out of range and with ENOSYS beyond x86_64. See
+/// [`read_pkru`] about calling this without protection keys.
+#[inline]
+pub fn pkey_set(pkey: libc::c_int, rights: PkeyRights) -> io::Result<()> {
+    let shift = pkey_shift(pkey)?;
+    #[cfg(target_arch = "x86_64")]
+    {
+        let pkru = (read_pkru() & !(0b11 << shift)) | (rights.bits() << shift);
+        // SAFETY: wrpkru only writes eax into the register with ecx and edx zero.
+        unsafe {
+            core::arch::asm!(
+                "wrpkru",
+                in("eax") pkru,
+                in("ecx") 0,
+                in("edx") 0,
+                options(nostack, preserves_flags)
+            );
+        }
+        Ok(())
+    }
+    #[cfg(not(target_arch = "x86_64"))]
+    {
+        let _ = (shift, rights);
+        Err(io::Error::from_raw_os_error(libc::ENOSYS))
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use rstest::rstest;
+
+    // Machines without protection keys fail the allocation in which case there's nothing to test.
+    fn alloc_or_skip(rights: PkeyRights) -> Option<libc::c_int> {
+        match pkey_alloc(rights) {
+            Ok(pkey) => Some(pkey),
+            Err(e)
+                if matches!(
+                    e.raw_os_error(),
+                    Some(libc::EINVAL | libc::ENOSPC | libc::ENOSYS)
+                ) =>
+            {
+                None
+            }
+            Err(e) => panic!("pkey_alloc: {}", e),
+        }
+    }
+
+    #[rstest]
+    #[case(PkeyRights::empty())]
+    #[case(PkeyRights::DISABLE_WRITE)]
+    #[case(PkeyRights::DISABLE_ACCESS)]
+    #[case(PkeyRights::DISABLE_ACCESS | PkeyRights::DISABLE_WRITE)]
+    fn alloc_initial_rights(#[case] rights: PkeyRights) {
+        let Some(pkey) = alloc_or_skip(rights) else {
+            return;
+        };
+        assert!(pkey > 0);
+        #[cfg(target_arch = "x86_64")]
+        assert_eq!(pkey_get(pkey).unwrap(), rights);
+        pkey_free(pkey).unwrap();
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[test]
+    fn set_get_roundtrip() {
+        let Some(pkey) = alloc_or_skip(PkeyRights::empty()) else {
+            return;
+        };
+        pkey_set(pkey, PkeyRights::DISABLE_WRITE).unwrap();
+        assert_eq!(pkey_get(pkey).unwrap(), PkeyRights::DISABLE_WRITE);
+        pkey_set(pkey, PkeyRights::empty()).unwrap();
+        assert_eq!(pkey_get(pkey).unwrap(), PkeyRights::empty());
+        pkey_free(pkey).unwrap();
+    }
+
+    #[test]
+    fn key_out_of_range() {
+        assert_eq!(
+            pkey_set(16, PkeyRights::empty())
+                .unwrap_err()
+                .raw_os_error(),
+            Some(libc::EINVAL)
+        );
+        assert_eq!(pkey_get(-1).unwrap_err().raw_os_error(), Some(libc::EINVAL));
+        assert!(pkey_free(-1).is_err());
+    }
+}
@@ -0,0 +1,217 @@
+#![warn(
+    clippy::unwrap_used,
+    missing_docs,
+    rust_2018_idioms,
+    unused_lifetimes,
+    unused_qualifications
+)]
+#![doc = include_str!("../README.md")]
+
+#[cfg(not(target_os = "linux"))]
+compile_error!("Crate ypoll is Linux specific dependency but is used in non-linux system.");
+
+use std::io;
+use std::time::Duration;
+use yown_fd::RawFd;
+
+bitflags::bitflags! {
+    /// pollfd events and revents
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    pub struct PollEvents: libc::c_short {
+        /// Available for read
+        const POLLIN = libc::POLLIN;
+        /// Available for write
+        const POLLOUT = libc::POLLOUT;
+        /// Error condition, only in revents
+        const POLLERR = libc::POLLERR;
+        /// Hang up, only in revents
+        const POLLHUP = libc::POLLHUP;
+        /// The fd is not open, only in revents
+        const POLLNVAL = libc::POLLNVAL;
+        /// Stream peer closed or shut down writing
+        const POLLRDHUP = libc::POLLRDHUP;
+    }
+}
+
+/// A single libc::pollfd entry for [`poll`] and [`ppoll`]
+#[derive(Copy, Clone)]
+#[repr(transparent)]
+pub struct PollFd(libc::pollfd);
+
+impl core::fmt::Debug for PollFd {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("PollFd")
+            .field("fd", &self.0.fd)
+            .field("events", &PollEvents::from_bits_retain(self.0.events))
+            .field("revents", &self.revents())
+            .finish()
+    }
+}
+
+impl PollFd {
+    /// Poll fd for the given events. A negative fd is ignored and gets no revents.
+    #[inline]
+    pub fn new(fd: RawFd, events: PollEvents) -> Self {
+        Self(libc::pollfd {
+            fd,
+            events: events.bits(),
+            revents: 0,
+        })
+    }
+    /// The polled fd
+    #[inline]
+    pub fn fd(&self) -> RawFd {
+        self.0.fd
+    }
+    /// The events returned by the last [`poll`] or [`ppoll`]
+    #[inline]
+    pub fn revents(&self) -> PollEvents {
+        PollEvents::from_bits_retain(self.0.revents)
+    }
+}
+
+/// Wait up to timeout_ms (-1 blocks) through poll(2) for any of the fds and return the
+/// count of fds with non-empty [`PollFd::revents`].
+#[inline]
+pub fn poll(fds: &mut [PollFd], timeout_ms: i32) -> io::Result<usize> {
+    // SAFETY: PollFd is transparent over pollfd and the slice is borrowed mutably.
+    let r = unsafe {
+        libc::poll(
+            fds.as_mut_ptr() as *mut libc::pollfd,
+            fds.len() as libc::nfds_t,
+            timeout_ms,
+        )
+    };
+    match r {
+        -1 => Err(io::Error::last_os_error()),
+        n => Ok(n as usize),
+    }
+}
+
+/// Like [`poll`] through ppoll(2) where None timeout blocks and the sigmask, if any,
+/// replaces the signal mask of the calling thread for the duration of the call.
+///
+/// The mask is swapped atomically with the wait so a signal blocked outside of it can
+/// only be delivered while waiting, failing with EINTR, instead of being lost between
+/// the check and the wait.
+#[inline]
+pub fn ppoll(
+    fds: &mut [PollFd],
+    timeout: Option<Duration>,
+    sigmask: Option<&libc::sigset_t>,
+) -> io::Result<usize> {
+    let ts = match timeout {
+        Some(timeout) => Some(libc::timespec {
+            tv_sec: libc::time_t::try_from(timeout.as_secs())
+                .map_err(|_| io::Error::from_raw_os_error(libc::EINVAL))?,
+            tv_nsec: timeout.subsec_nanos() as _,
+        }),
+        None => None,
+    };
+    let ts_ptr = match &ts {
+        Some(ts) => ts as *const libc::timespec,
+        None => core::ptr::null(),
+    };
+    let sigmask_ptr = match sigmask {
+        Some(sigmask) => sigmask as *const libc::sigset_t,
+        None => core::ptr::null(),
+    };
+    // SAFETY: PollFd is transparent over pollfd, the slice is borrowed mutably and the
+    //         timespec and sigmask outlive the call if given.
+    let r = unsafe {
+        libc::ppoll(
+            fds.as_mut_ptr() as *mut libc::pollfd,
+            fds.len() as libc::nfds_t,
+            ts_ptr,
+            sigmask_ptr,
+        )
+    };
+    match r {
+        -1 => Err(io::Error::last_os_error()),
+        n => Ok(n as usize),
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use rstest::rstest;
+    use std::fs::File;
+    use std::io::Write;
+    use yown_fd::{AsRawFd, FromRawFd};
+
+    fn pipe() -> (File, File) {
+        let mut fds = [0; 2];
+        assert_eq!(unsafe { libc::pipe2(fds.as_mut_ptr(), 0) }, 0);
+        unsafe { (File::from_raw_fd(fds[0]), File::from_raw_fd(fds[1])) }
+    }
+
+    #[rstest]
+    #[case(0, 0, PollEvents::empty())]
+    #[case(1, 1, PollEvents::POLLIN)]
+    #[case(4096, 1, PollEvents::POLLIN)]
+    fn poll_read_end(#[case] len: usize, #[case] ready: usize, #[case] revents: PollEvents) {
+        let (rx, mut tx) = pipe();
+        tx.write_all(&vec![7u8; len]).unwrap();
+        let mut fds = [PollFd::new(rx.as_raw_fd(), PollEvents::POLLIN)];
+        assert_eq!(poll(&mut fds, 0).unwrap(), ready);
+        assert_eq!(fds[0].revents(), revents);
+    }
+
+    #[test]
+    fn poll_closed_writer_hangs_up() {
+        let (rx, tx) = pipe();
+        drop(tx);
+        let mut fds = [
+            PollFd::new(rx.as_raw_fd(), PollEvents::POLLIN),
+            PollFd::new(-1, PollEvents::POLLIN),
+        ];
+        assert_eq!(poll(&mut fds, -1).unwrap(), 1);
+        assert!(fds[0].revents().contains(PollEvents::POLLHUP));
+        assert_eq!(fds[1].revents(), PollEvents::empty());
+    }
+
+    #[test]
+    fn poll_closed_fd_invalid() {
+        let (rx, _tx) = pipe();
+        let fd = rx.as_raw_fd();
+        drop(rx);
+        let mut fds = [PollFd::new(fd, PollEvents::POLLIN)];
+        assert_eq!(poll(&mut fds, 0).unwrap(), 1);
+        assert_eq!(fds[0].revents(), PollEvents::POLLNVAL);
+    }
+
+    #[rstest]
+    #[case(Some(Duration::ZERO))]
+    #[case(Some(Duration::from_millis(1)))]
+    fn ppoll_times_out(#[case] timeout: Option<Duration>) {
+        let (rx, _tx) = pipe();
+        let mut fds = [PollFd::new(rx.as_raw_fd(), PollEvents::POLLIN)];
+        assert_eq!(ppoll(&mut fds, timeout, None).unwrap(), 0);
+    }
+
+    #[test]
+    fn ppoll_read_end_with_sigmask() {
+        let (rx, mut tx) = pipe();
+        tx.write_all(b"x").unwrap();
+        let mut mask = unsafe { core::mem::zeroed::<libc::sigset_t>() };
+        unsafe {
+            libc::sigemptyset(&mut mask);
+            libc::sigaddset(&mut mask, libc::SIGUSR1);
+        }
+        let mut fds = [PollFd::new(
+            rx.as_raw_fd(),
+            PollEvents::POLLIN | PollEvents::POLLRDHUP,
+        )];
+        assert_eq!(ppoll(&mut fds, None, Some(&mask)).unwrap(), 1);
+        assert_eq!(fds[0].revents(), PollEvents::POLLIN);
+    }
+
+    #[test]
+    fn ppoll_timeout_beyond_time_t() {
+        let mut fds: [PollFd; 0] = [];
+        let err = ppoll(&mut fds, Some(Duration::MAX), None).unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(libc::EINVAL));
+    }
+}
@@ -0,0 +1,128 @@
+#![warn(
+    clippy::unwrap_used,
+    missing_docs,
+    rust_2018_idioms,
+    unused_lifetimes,
+    unused_qualifications
+)]
+#![doc = include_str!("../README.md")]
+
+#[cfg(not(target_os = "linux"))]
+compile_error!("Crate yreadlink is Linux specific dependency but is used in non-linux system.");
+
+use std::ffi::{CString, OsString};
+use std::io;
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+use std::path::{Path, PathBuf};
+use yown_fd::RawFd;
+
+#[inline]
+fn path_cstring(path: &Path) -> io::Result<CString> {
+    CString::new(path.as_os_str().as_bytes())
+        .map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))
+}
+
+/// Read the target of the symlink at path through readlink(2).
+///
+/// Path with an interior NUL is [`io::ErrorKind::InvalidInput`].
+#[inline]
+pub fn readlink(path: &Path) -> io::Result<PathBuf> {
+    readlinkat(None, path)
+}
+
+/// Read the target of the symlink at path through readlinkat(2) where a relative path is
+/// resolved against dirfd or the current working directory with None.
+///
+/// readlink does not tell the target len nor NUL terminates it, so the buffer starts at
+/// PATH_MAX and is doubled as long as the target fills it completely as it may have been
+/// truncated. Path with an interior NUL is [`io::ErrorKind::InvalidInput`].
+#[inline]
+pub fn readlinkat(dirfd: Option<RawFd>, path: &Path) -> io::Result<PathBuf> {
+    let path = path_cstring(path)?;
+    let dirfd = dirfd.unwrap_or(libc::AT_FDCWD);
+    let mut buf: Vec<u8> = Vec::with_capacity(libc::PATH_MAX as usize);
+    loop {
+        // SAFETY: path is NUL terminated and readlinkat writes at most capacity bytes.
+        let r = unsafe {
+            libc::readlinkat(
+                dirfd,
+                path.as_ptr(),
+                buf.as_mut_ptr() as *mut libc::c_char,
+                buf.capacity(),
+            )
+        };
+        if r < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let len = r as usize;
+        if len < buf.capacity() {
+            // SAFETY: readlinkat initialized the first len bytes.
+            unsafe { buf.set_len(len) };
+            buf.shrink_to_fit();
+            return Ok(PathBuf::from(OsString::from_vec(buf)));
+        }
+        // Nothing is kept as len is still 0 so this doubles the capacity.
+        buf.reserve(buf.capacity() * 2);
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use rstest::rstest;
+    use std::fs::File;
+    use yown_fd::AsRawFd;
+
+    fn symlink_to(name: &str, target: &Path) -> PathBuf {
+        let link = std::env::temp_dir().join(format!("yreadlink-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_file(&link);
+        std::os::unix::fs::symlink(target, &link).unwrap();
+        link
+    }
+
+    #[test]
+    fn readlink_dev_null() {
+        let link = symlink_to("dev-null", Path::new("/dev/null"));
+        let target = readlink(&link);
+        std::fs::remove_file(&link).unwrap();
+        assert_eq!(target.unwrap(), Path::new("/dev/null"));
+    }
+
+    #[test]
+    fn readlink_proc_self_exe() {
+        let exe = readlink(Path::new("/proc/self/exe")).unwrap();
+        assert_eq!(exe, std::env::current_exe().unwrap());
+    }
+
+    #[rstest]
+    #[case(1)]
+    #[case(255)]
+    #[case(libc::PATH_MAX as usize - 1)]
+    fn readlink_long_target(#[case] len: usize) {
+        // The target is not resolved so it does not need to exist but can not exceed PATH_MAX - 1.
+        let target = PathBuf::from("a".repeat(len));
+        let link = symlink_to(&format!("long-{}", len), &target);
+        let read = readlink(&link);
+        std::fs::remove_file(&link).unwrap();
+        assert_eq!(read.unwrap(), target);
+    }
+
+    #[test]
+    fn readlinkat_relative_to_dirfd() {
+        let link = symlink_to("at", Path::new("relative/target"));
+        let dir = File::open(std::env::temp_dir()).unwrap();
+        let target = readlinkat(Some(dir.as_raw_fd()), Path::new(link.file_name().unwrap()));
+        std::fs::remove_file(&link).unwrap();
+        assert_eq!(target.unwrap(), Path::new("relative/target"));
+    }
+
+    #[rstest]
+    #[case(Path::new("/dev/null"), Some(libc::EINVAL))]
+    #[case(Path::new("/nonexistent-yreadlink"), Some(libc::ENOENT))]
+    #[case(Path::new("nul\0byte"), None)]
+    fn readlink_errors(#[case] path: &Path, #[case] errno: Option<i32>) {
+        let err = readlink(path).unwrap_err();
+        assert_eq!(err.raw_os_error(), errno);
+    }
+}
@@ -0,0 +1,178 @@
+#![warn(
+    clippy::unwrap_used,
+    missing_docs,
+    rust_2018_idioms,
+    unused_lifetimes,
+    unused_qualifications
+)]
+#![doc = include_str!("../README.md")]
+
+#[cfg(not(target_os = "linux"))]
+compile_error!("Crate yrename is Linux specific dependency but is used in non-linux system.");
+
+use std::ffi::CString;
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+use yown_fd::RawFd;
+
+bitflags::bitflags! {
+    /// renameat2(2) flags
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    pub struct RenameFlags: libc::c_uint {
+        /// Atomically swap old and new which both need to exist
+        const RENAME_EXCHANGE = libc::RENAME_EXCHANGE;
+        /// Fail with EEXIST instead of replacing an existing new
+        const RENAME_NOREPLACE = libc::RENAME_NOREPLACE;
+        /// Leave a whiteout at old for overlay / union filesystems. Requires CAP_MKNOD
+        const RENAME_WHITEOUT = libc::RENAME_WHITEOUT;
+    }
+}
+
+#[inline]
+fn path_cstring(path: &Path) -> io::Result<CString> {
+    CString::new(path.as_os_str().as_bytes())
+        .map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))
+}
+
+/// Rename old to new through rename(2) replacing any existing new atomically.
+///
+/// Paths with an interior NUL are [`io::ErrorKind::InvalidInput`].
+#[inline]
+pub fn rename(old: &Path, new: &Path) -> io::Result<()> {
+    renameat(None, old, None, new)
+}
+
+/// Rename old to new through renameat(2) where relative paths are resolved against their
+/// dirfd or the current working directory with None.
+#[inline]
+pub fn renameat(
+    olddirfd: Option<RawFd>,
+    old: &Path,
+    newdirfd: Option<RawFd>,
+    new: &Path,
+) -> io::Result<()> {
+    renameat2(olddirfd, old, newdirfd, new, RenameFlags::empty())
+}
+
+/// Rename old to new through renameat2(2) like [`renameat`] with the given flags.
+///
+/// The flags are filesystem specific where unsupported ones fail with EINVAL and kernels
+/// before 3.15 with ENOSYS.
+#[inline]
+pub fn renameat2(
+    olddirfd: Option<RawFd>,
+    old: &Path,
+    newdirfd: Option<RawFd>,
+    new: &Path,
+    flags: RenameFlags,
+) -> io::Result<()> {
+    let old = path_cstring(old)?;
+    let new = path_cstring(new)?;
+    // SAFETY: Both paths are NUL terminated. Through the syscall as the glibc wrapper
+    //         only exists since 2.28.
+    let r = unsafe {
+        libc::syscall(
+            libc::SYS_renameat2,
+            olddirfd.unwrap_or(libc::AT_FDCWD),
+            old.as_ptr(),
+            newdirfd.unwrap_or(libc::AT_FDCWD),
+            new.as_ptr(),
+            flags.bits(),
+        )
+    };
+    match r {
+        0 => Ok(()),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use rstest::rstest;
+    use std::fs::File;
+    use std::path::PathBuf;
+    use yown_fd::AsRawFd;
+
+    struct TempDir(PathBuf, File);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let path =
+                std::env::temp_dir().join(format!("yrename-{}-{}", std::process::id(), name));
+            let _ = std::fs::remove_dir_all(&path);
+            std::fs::create_dir(&path).unwrap();
+            let dir = File::open(&path).unwrap();
+            Self(path, dir)
+        }
+        fn fd(&self) -> Option<RawFd> {
+            Some(self.1.as_raw_fd())
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn exchange_swaps_contents() {
+        let dir = TempDir::new("exchange");
+        std::fs::write(dir.0.join("a"), b"first").unwrap();
+        std::fs::write(dir.0.join("b"), b"second").unwrap();
+        renameat2(
+            dir.fd(),
+            Path::new("a"),
+            dir.fd(),
+            Path::new("b"),
+            RenameFlags::RENAME_EXCHANGE,
+        )
+        .unwrap();
+        assert_eq!(std::fs::read(dir.0.join("a")).unwrap(), b"second");
+        assert_eq!(std::fs::read(dir.0.join("b")).unwrap(), b"first");
+    }
+
+    #[rstest]
+    #[case(RenameFlags::empty(), None, b"first")]
+    #[case(RenameFlags::RENAME_NOREPLACE, Some(libc::EEXIST), b"second")]
+    fn rename_onto_existing(
+        #[case] flags: RenameFlags,
+        #[case] errno: Option<i32>,
+        #[case] expected: &[u8],
+    ) {
+        let dir = TempDir::new(&format!("existing-{}", flags.bits()));
+        std::fs::write(dir.0.join("a"), b"first").unwrap();
+        std::fs::write(dir.0.join("b"), b"second").unwrap();
+        let r = renameat2(None, &dir.0.join("a"), None, &dir.0.join("b"), flags);
+        assert_eq!(r.err().and_then(|e| e.raw_os_error()), errno);
+        assert_eq!(std::fs::read(dir.0.join("b")).unwrap(), expected);
+    }
+
+    #[test]
+    fn rename_and_renameat() {
+        let dir = TempDir::new("plain");
+        std::fs::write(dir.0.join("a"), b"data").unwrap();
+        rename(&dir.0.join("a"), &dir.0.join("b")).unwrap();
+        renameat(dir.fd(), Path::new("b"), dir.fd(), Path::new("c")).unwrap();
+        assert!(!dir.0.join("a").exists() && !dir.0.join("b").exists());
+        assert_eq!(std::fs::read(dir.0.join("c")).unwrap(), b"data");
+    }
+
+    #[test]
+    fn exchange_missing_new() {
+        let dir = TempDir::new("missing");
+        std::fs::write(dir.0.join("a"), b"data").unwrap();
+        let err = renameat2(
+            dir.fd(),
+            Path::new("a"),
+            dir.fd(),
+            Path::new("b"),
+            RenameFlags::RENAME_EXCHANGE,
+        )
+        .unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(libc::ENOENT));
+    }
+}
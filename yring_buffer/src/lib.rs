@@ -0,0 +1,279 @@
+#![warn(
+    clippy::unwrap_used,
+    missing_docs,
+    rust_2018_idioms,
+    unused_lifetimes,
+    unused_qualifications
+)]
+#![doc = include_str!("../README.md")]
+
+use anonymous_mmap::{AnonymousMmap, AnonymousMmapError, OsError};
+use core::mem::ManuallyDrop;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// Error pushing into a ring with no space available
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RingFull;
+
+impl core::fmt::Display for RingFull {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "Ring buffer is full")
+    }
+}
+
+impl core::error::Error for RingFull {}
+
+/// Error popping from a ring with no data available
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RingEmpty;
+
+impl core::fmt::Display for RingEmpty {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "Ring buffer is empty")
+    }
+}
+
+impl core::error::Error for RingEmpty {}
+
+/// Shared state of the ring where head and tail are monotonically increasing (wrapping)
+/// positions masked into the power of two capacity.
+#[derive(Debug)]
+pub struct RingBuffer {
+    map: ManuallyDrop<AnonymousMmap>,
+    head: AtomicUsize,
+    tail: AtomicUsize,
+    capacity: usize,
+}
+
+// SAFETY: The mapping is not tied to a thread. The bytes between tail and head are only
+// read by the single consumer and the bytes between head and tail + capacity are only
+// written by the single producer which is enforced by the &mut self of the split halves.
+unsafe impl Send for RingBuffer {}
+// SAFETY: See above, shared access only reads the atomics.
+unsafe impl Sync for RingBuffer {}
+
+impl RingBuffer {
+    /// Construct a new ring with the given capacity rounded up to the next power of two.
+    ///
+    /// A capacity with no power of two in usize is ENOMEM as with mmap(2) of such a len.
+    #[inline]
+    pub fn new(capacity: usize) -> Result<Self, AnonymousMmapError> {
+        let Some(capacity) = capacity.checked_next_power_of_two() else {
+            return Err(AnonymousMmapError::MmapFailed {
+                len: capacity,
+                flags: libc::MAP_ANONYMOUS | libc::MAP_SHARED | libc::MAP_POPULATE,
+                error: OsError::from_raw_os_error(libc::ENOMEM),
+            });
+        };
+        Ok(Self {
+            map: ManuallyDrop::new(AnonymousMmap::new(capacity)?),
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+            capacity,
+        })
+    }
+    /// Split into the producer and consumer halves which can be moved into separate threads.
+    #[inline]
+    pub fn split(self) -> (RingProducer, RingConsumer) {
+        let ring = Arc::new(self);
+        (RingProducer { ring: ring.clone() }, RingConsumer { ring })
+    }
+    /// Capacity of the ring in bytes
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+    /// Bytes available to be popped
+    #[inline]
+    pub fn available_read(&self) -> usize {
+        let head = self.head.load(Ordering::Acquire);
+        let tail = self.tail.load(Ordering::Acquire);
+        head.wrapping_sub(tail)
+    }
+    /// Bytes available to be pushed
+    #[inline]
+    pub fn available_write(&self) -> usize {
+        self.capacity - self.available_read()
+    }
+    #[inline]
+    fn base(&self) -> *mut u8 {
+        self.map.as_ptr_mut().cast()
+    }
+}
+
+impl Drop for RingBuffer {
+    fn drop(&mut self) {
+        // SAFETY: Both halves are gone by the time the last Arc drops and no slices outlive them.
+        let map = unsafe { ManuallyDrop::take(&mut self.map) };
+        // Nothing to give the error back to.
+        let _ = unsafe { map.try_drop() };
+    }
+}
+
+/// Producer half of the [`RingBuffer`]
+#[derive(Debug)]
+pub struct RingProducer {
+    ring: Arc<RingBuffer>,
+}
+
+impl RingProducer {
+    /// Push as much of the data as there is space available returning the bytes pushed.
+    /// Err if there is no space at all for non-empty data.
+    #[inline]
+    pub fn push(&mut self, data: &[u8]) -> Result<usize, RingFull> {
+        let ring = &*self.ring;
+        let n = data.len().min(ring.available_write());
+        if n == 0 && !data.is_empty() {
+            return Err(RingFull);
+        }
+        let head = ring.head.load(Ordering::Relaxed);
+        let at = head & (ring.capacity - 1);
+        let first = n.min(ring.capacity - at);
+        // SAFETY: [head, head + n) is free space only written by the single producer.
+        unsafe {
+            core::ptr::copy_nonoverlapping(data.as_ptr(), ring.base().add(at), first);
+            core::ptr::copy_nonoverlapping(data.as_ptr().add(first), ring.base(), n - first);
+        }
+        ring.head.store(head.wrapping_add(n), Ordering::Release);
+        Ok(n)
+    }
+    /// The shared ring
+    #[inline]
+    pub fn ring(&self) -> &RingBuffer {
+        &self.ring
+    }
+}
+
+/// Consumer half of the [`RingBuffer`]
+#[derive(Debug)]
+pub struct RingConsumer {
+    ring: Arc<RingBuffer>,
+}
+
+impl RingConsumer {
+    /// Pop up to len continuous bytes without copying. The slice may be shorter than len when
+    /// there is less data available or the data wraps around the end of the ring.
+    ///
+    /// The bytes are released back to the producer when the returned [`RingRead`] drops.
+    #[inline]
+    pub fn pop(&mut self, len: usize) -> Result<RingRead<'_>, RingEmpty> {
+        let ring = &*self.ring;
+        let available = ring.available_read();
+        if available == 0 {
+            return Err(RingEmpty);
+        }
+        let tail = ring.tail.load(Ordering::Relaxed);
+        let at = tail & (ring.capacity - 1);
+        let n = len.min(available).min(ring.capacity - at);
+        // SAFETY: [tail, tail + n) is published by the producer and only released on RingRead drop.
+        let slice = unsafe { core::slice::from_raw_parts(ring.base().add(at), n) };
+        Ok(RingRead {
+            slice,
+            tail: &ring.tail,
+            next: tail.wrapping_add(n),
+        })
+    }
+    /// The shared ring
+    #[inline]
+    pub fn ring(&self) -> &RingBuffer {
+        &self.ring
+    }
+}
+
+/// Popped bytes which are released back to the producer on drop.
+#[derive(Debug)]
+pub struct RingRead<'a> {
+    slice: &'a [u8],
+    tail: &'a AtomicUsize,
+    next: usize,
+}
+
+impl core::ops::Deref for RingRead<'_> {
+    type Target = [u8];
+    #[inline]
+    fn deref(&self) -> &[u8] {
+        self.slice
+    }
+}
+
+impl Drop for RingRead<'_> {
+    fn drop(&mut self) {
+        self.tail.store(self.next, Ordering::Release);
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(1000, 1024)]
+    #[case(4096, 4096)]
+    fn capacity_power_of_two(#[case] capacity: usize, #[case] expected: usize) {
+        let ring = RingBuffer::new(capacity).unwrap();
+        assert_eq!(ring.capacity(), expected);
+        assert_eq!(ring.available_write(), expected);
+        assert_eq!(ring.available_read(), 0);
+    }
+
+    #[test]
+    fn capacity_overflow() {
+        match RingBuffer::new(usize::MAX) {
+            Err(AnonymousMmapError::MmapFailed { len, error, .. }) => {
+                assert_eq!(len, usize::MAX);
+                assert_eq!(error.raw_os_error(), Some(libc::ENOMEM));
+            }
+            r => panic!("Expected MmapFailed, got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn full_empty_wraparound() {
+        let (mut tx, mut rx) = RingBuffer::new(8).unwrap().split();
+        assert_eq!(rx.pop(8).unwrap_err(), RingEmpty);
+        assert_eq!(tx.push(b"abcdef").unwrap(), 6);
+        assert_eq!(&*rx.pop(4).unwrap(), b"abcd");
+        assert_eq!(tx.push(b"ghijklmn").unwrap(), 6);
+        assert_eq!(tx.push(b"x").unwrap_err(), RingFull);
+        // Wraps around the end of the ring
+        assert_eq!(&*rx.pop(8).unwrap(), b"efgh");
+        assert_eq!(&*rx.pop(8).unwrap(), b"ijkl");
+        assert_eq!(rx.ring().available_read(), 0);
+    }
+
+    #[test]
+    fn spsc_threads() {
+        const N: usize = 1_000_000;
+        let (mut tx, mut rx) = RingBuffer::new(4096).unwrap().split();
+        let producer = std::thread::spawn(move || {
+            let data: Vec<u8> = (0..N).map(|i| i as u8).collect();
+            let mut sent = 0;
+            while sent < N {
+                match tx.push(&data[sent..(sent + 1000).min(N)]) {
+                    Ok(n) => sent += n,
+                    Err(RingFull) => std::thread::yield_now(),
+                }
+            }
+        });
+        let consumer = std::thread::spawn(move || {
+            let mut received = 0;
+            while received < N {
+                match rx.pop(777) {
+                    Ok(read) => {
+                        for (i, b) in read.iter().enumerate() {
+                            assert_eq!(*b, (received + i) as u8);
+                        }
+                        received += read.len();
+                    }
+                    Err(RingEmpty) => std::thread::yield_now(),
+                }
+            }
+            received
+        });
+        producer.join().unwrap();
+        assert_eq!(consumer.join().unwrap(), N);
+    }
+}
@@ -0,0 +1,274 @@
+#![warn(
+    clippy::unwrap_used,
+    missing_docs,
+    rust_2018_idioms,
+    unused_lifetimes,
+    unused_qualifications
+)]
+#![doc = include_str!("../README.md")]
+
+#[cfg(not(target_os = "linux"))]
+compile_error!("Crate yselect is Linux specific dependency but is used in non-linux system.");
+
+use std::io;
+use std::time::Duration;
+use yown_fd::RawFd;
+
+/// A libc::fd_set of fds in [0, FD_SETSIZE) for [`pselect`]
+#[derive(Copy, Clone)]
+#[repr(transparent)]
+pub struct FdSet(libc::fd_set);
+
+impl core::fmt::Debug for FdSet {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_set()
+            .entries((0..libc::FD_SETSIZE as RawFd).filter(|fd| self.isset(*fd)))
+            .finish()
+    }
+}
+
+impl Default for FdSet {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[inline]
+fn in_range(fd: RawFd) -> bool {
+    (0..libc::FD_SETSIZE as RawFd).contains(&fd)
+}
+
+impl FdSet {
+    /// An empty set
+    #[inline]
+    pub fn new() -> Self {
+        // SAFETY: fd_set is a plain bit array where all zeroes is the empty set.
+        let mut set = Self(unsafe { core::mem::zeroed() });
+        set.zero();
+        set
+    }
+    /// Add fd to the set.
+    ///
+    /// # Panics
+    ///
+    /// Panics if fd is negative or not below FD_SETSIZE which select can not watch - see
+    /// poll(2) for those.
+    #[inline]
+    pub fn set(&mut self, fd: RawFd) {
+        assert!(in_range(fd), "fd {} is outside of FD_SETSIZE", fd);
+        // SAFETY: fd is within the set.
+        unsafe { libc::FD_SET(fd, &mut self.0) };
+    }
+    /// Remove fd from the set, fds outside of FD_SETSIZE are never in the set.
+    #[inline]
+    pub fn clear(&mut self, fd: RawFd) {
+        if in_range(fd) {
+            // SAFETY: fd is within the set.
+            unsafe { libc::FD_CLR(fd, &mut self.0) };
+        }
+    }
+    /// Is fd in the set, e.g. left ready by [`pselect`]
+    #[inline]
+    pub fn isset(&self, fd: RawFd) -> bool {
+        // SAFETY: fd is within the set.
+        in_range(fd) && unsafe { libc::FD_ISSET(fd, &self.0) }
+    }
+    /// Remove all fds from the set
+    #[inline]
+    pub fn zero(&mut self) {
+        // SAFETY: Only clears the set.
+        unsafe { libc::FD_ZERO(&mut self.0) };
+    }
+}
+
+#[inline]
+fn set_ptr(set: Option<&mut FdSet>) -> *mut libc::fd_set {
+    match set {
+        Some(set) => &mut set.0,
+        None => core::ptr::null_mut(),
+    }
+}
+
+/// Wait through pselect(2) for any of the fds below nfds in the given sets where None
+/// timeout blocks and return the count of fds left ready in the sets which are reduced
+/// to the ready fds.
+///
+/// The sigmask, if any, replaces the signal mask of the calling thread atomically with
+/// the wait so a signal blocked outside of it can only be delivered while waiting,
+/// failing with EINTR, instead of being lost between the check and the wait.
+///
+/// nfds negative or beyond FD_SETSIZE, which the kernel would read and write past the
+/// sets for, is EINVAL without calling pselect(2).
+#[inline]
+pub fn pselect(
+    nfds: RawFd,
+    readfds: Option<&mut FdSet>,
+    writefds: Option<&mut FdSet>,
+    exceptfds: Option<&mut FdSet>,
+    timeout: Option<Duration>,
+    sigmask: Option<&libc::sigset_t>,
+) -> io::Result<usize> {
+    if !(0..=libc::FD_SETSIZE as RawFd).contains(&nfds) {
+        return Err(io::Error::from_raw_os_error(libc::EINVAL));
+    }
+    let ts = match timeout {
+        Some(timeout) => Some(libc::timespec {
+            tv_sec: libc::time_t::try_from(timeout.as_secs())
+                .map_err(|_| io::Error::from_raw_os_error(libc::EINVAL))?,
+            tv_nsec: timeout.subsec_nanos() as _,
+        }),
+        None => None,
+    };
+    let ts_ptr = match &ts {
+        Some(ts) => ts as *const libc::timespec,
+        None => core::ptr::null(),
+    };
+    let sigmask_ptr = match sigmask {
+        Some(sigmask) => sigmask as *const libc::sigset_t,
+        None => core::ptr::null(),
+    };
+    // SAFETY: The sets are borrowed mutably and the timespec and sigmask outlive the call
+    //         if given.
+    let r = unsafe {
+        libc::pselect(
+            nfds,
+            set_ptr(readfds),
+            set_ptr(writefds),
+            set_ptr(exceptfds),
+            ts_ptr,
+            sigmask_ptr,
+        )
+    };
+    match r {
+        -1 => Err(io::Error::last_os_error()),
+        n => Ok(n as usize),
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use rstest::rstest;
+    use std::fs::File;
+    use std::io::Write;
+    use yown_fd::{AsRawFd, FromRawFd};
+
+    fn pipe() -> (File, File) {
+        let mut fds = [0; 2];
+        assert_eq!(unsafe { libc::pipe2(fds.as_mut_ptr(), 0) }, 0);
+        unsafe { (File::from_raw_fd(fds[0]), File::from_raw_fd(fds[1])) }
+    }
+
+    #[rstest]
+    #[case(0)]
+    #[case(3)]
+    #[case(libc::FD_SETSIZE as RawFd - 1)]
+    fn set_clear_isset(#[case] fd: RawFd) {
+        let mut set = FdSet::new();
+        assert!(!set.isset(fd));
+        set.set(fd);
+        assert!(set.isset(fd));
+        set.clear(fd);
+        assert!(!set.isset(fd));
+        set.set(fd);
+        set.zero();
+        assert!(!set.isset(fd));
+    }
+
+    #[test]
+    #[should_panic(expected = "outside of FD_SETSIZE")]
+    fn set_beyond_fd_setsize() {
+        FdSet::new().set(libc::FD_SETSIZE as RawFd);
+    }
+
+    #[test]
+    fn pselect_read_end() {
+        let (rx, mut tx) = pipe();
+        let fd = rx.as_raw_fd();
+        let mut readfds = FdSet::new();
+        readfds.set(fd);
+        let r = pselect(
+            fd + 1,
+            Some(&mut readfds),
+            None,
+            None,
+            Some(Duration::ZERO),
+            None,
+        );
+        assert_eq!(r.unwrap(), 0);
+        assert!(!readfds.isset(fd));
+
+        tx.write_all(b"x").unwrap();
+        readfds.set(fd);
+        assert_eq!(
+            pselect(fd + 1, Some(&mut readfds), None, None, None, None).unwrap(),
+            1
+        );
+        assert!(readfds.isset(fd));
+    }
+
+    #[test]
+    fn pselect_write_end_with_sigmask() {
+        let (_rx, tx) = pipe();
+        let fd = tx.as_raw_fd();
+        let mut mask = unsafe { core::mem::zeroed::<libc::sigset_t>() };
+        unsafe {
+            libc::sigemptyset(&mut mask);
+            libc::sigaddset(&mut mask, libc::SIGUSR1);
+        }
+        let mut writefds = FdSet::new();
+        writefds.set(fd);
+        let mut exceptfds = FdSet::new();
+        exceptfds.set(fd);
+        let r = pselect(
+            fd + 1,
+            None,
+            Some(&mut writefds),
+            Some(&mut exceptfds),
+            Some(Duration::from_millis(1)),
+            Some(&mask),
+        );
+        assert_eq!(r.unwrap(), 1);
+        assert!(writefds.isset(fd));
+        assert!(!exceptfds.isset(fd));
+    }
+
+    #[rstest]
+    #[case(-1)]
+    #[case(libc::FD_SETSIZE as RawFd + 1)]
+    #[case(RawFd::MAX)]
+    fn pselect_nfds_out_of_range(#[case] nfds: RawFd) {
+        let mut readfds = FdSet::new();
+        let r = pselect(
+            nfds,
+            Some(&mut readfds),
+            None,
+            None,
+            Some(Duration::ZERO),
+            None,
+        );
+        assert_eq!(r.unwrap_err().raw_os_error(), Some(libc::EINVAL));
+    }
+
+    #[test]
+    fn pselect_closed_fd() {
+        // The kernel only looks at fds within the fd table so grow it first with a dup
+        // far above what the tests open so nothing else can take it in between.
+        let (rx, _tx) = pipe();
+        let fd = 900;
+        assert_eq!(unsafe { libc::dup2(rx.as_raw_fd(), fd) }, fd);
+        assert_eq!(unsafe { libc::close(fd) }, 0);
+        let mut readfds = FdSet::new();
+        readfds.set(fd);
+        let r = pselect(
+            fd + 1,
+            Some(&mut readfds),
+            None,
+            None,
+            Some(Duration::ZERO),
+            None,
+        );
+        assert_eq!(r.unwrap_err().raw_os_error(), Some(libc::EBADF));
+    }
+}
@@ -0,0 +1,267 @@
+#![warn(
+    clippy::unwrap_used,
+    missing_docs,
+    rust_2018_idioms,
+    unused_lifetimes,
+    unused_qualifications
+)]
+#![doc = include_str!("../README.md")]
+
+#[cfg(not(target_os = "linux"))]
+compile_error!("Crate ysendmmsg is Linux specific dependency but is used in non-linux system.");
+
+use core::cell::UnsafeCell;
+use core::marker::PhantomData;
+use std::io::{self, IoSlice, IoSliceMut};
+use std::time::Duration;
+use yown_fd::RawFd;
+
+bitflags::bitflags! {
+    /// sendmmsg(2) flags
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    pub struct SendFlags: libc::c_int {
+        /// Non-blocking for this call only
+        const DONTWAIT = libc::MSG_DONTWAIT;
+        /// Do not raise SIGPIPE on a broken stream
+        const NOSIGNAL = libc::MSG_NOSIGNAL;
+        /// More data is coming, corking the packet
+        const MORE = libc::MSG_MORE;
+        /// Tell the link layer forward progress happened
+        const CONFIRM = libc::MSG_CONFIRM;
+    }
+}
+
+bitflags::bitflags! {
+    /// recvmmsg(2) flags
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    pub struct RecvFlags: libc::c_int {
+        /// Non-blocking for this call only
+        const DONTWAIT = libc::MSG_DONTWAIT;
+        /// Turn on DONTWAIT after the first message has been received
+        const WAITFORONE = libc::MSG_WAITFORONE;
+        /// Peek without removing the messages from the queue
+        const PEEK = libc::MSG_PEEK;
+        /// Receive from the socket error queue
+        const ERRQUEUE = libc::MSG_ERRQUEUE;
+    }
+}
+
+#[inline]
+fn mmsghdr(iov: *mut libc::iovec, iovlen: usize) -> libc::mmsghdr {
+    // SAFETY: mmsghdr is valid when zeroed.
+    let mut hdr: libc::mmsghdr = unsafe { core::mem::zeroed() };
+    hdr.msg_hdr.msg_iov = iov;
+    hdr.msg_hdr.msg_iovlen = iovlen as _;
+    hdr
+}
+
+/// A datagram to send gathered from the borrowed buffers
+#[repr(transparent)]
+pub struct MsgHdr<'a> {
+    // The kernel writes msg_len even when sending.
+    hdr: UnsafeCell<libc::mmsghdr>,
+    _bufs: PhantomData<&'a [u8]>,
+}
+
+impl<'a> MsgHdr<'a> {
+    /// Datagram gathered from the given buffers
+    #[inline]
+    pub fn new(bufs: &'a [IoSlice<'_>]) -> Self {
+        // IoSlice is guaranteed ABI compatible with iovec on unix.
+        Self {
+            hdr: UnsafeCell::new(mmsghdr(bufs.as_ptr() as *mut libc::iovec, bufs.len())),
+            _bufs: PhantomData,
+        }
+    }
+    /// Bytes sent of this datagram by the last sendmmsg
+    #[inline]
+    pub fn len(&self) -> usize {
+        // SAFETY: Only written by the kernel during sendmmsg which borrows all of us.
+        unsafe { (*self.hdr.get()).msg_len as usize }
+    }
+    /// No bytes were sent of this datagram by the last sendmmsg
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// A datagram to receive scattered into the borrowed buffers
+#[repr(transparent)]
+pub struct MsgHdrMut<'a> {
+    hdr: libc::mmsghdr,
+    _bufs: PhantomData<&'a mut [u8]>,
+}
+
+impl<'a> MsgHdrMut<'a> {
+    /// Datagram scattered into the given buffers
+    #[inline]
+    pub fn new(bufs: &'a mut [IoSliceMut<'_>]) -> Self {
+        // IoSliceMut is guaranteed ABI compatible with iovec on unix.
+        Self {
+            hdr: mmsghdr(bufs.as_mut_ptr() as *mut libc::iovec, bufs.len()),
+            _bufs: PhantomData,
+        }
+    }
+    /// Bytes received into this datagram by the last recvmmsg
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.hdr.msg_len as usize
+    }
+    /// No bytes were received into this datagram by the last recvmmsg
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// The datagram was larger than the buffers and the rest discarded
+    #[inline]
+    pub fn is_truncated(&self) -> bool {
+        self.hdr.msg_hdr.msg_flags & libc::MSG_TRUNC != 0
+    }
+}
+
+/// Send the batch of datagrams through sendmmsg(2) returning how many of the first were sent.
+#[inline]
+pub fn sendmmsg(fd: RawFd, messages: &[MsgHdr<'_>], flags: SendFlags) -> io::Result<usize> {
+    // SAFETY: MsgHdr is a transparent UnsafeCell<mmsghdr> pointing to borrowed iovecs so the
+    // kernel writing msg_len through the shared borrow is allowed.
+    let r = unsafe {
+        libc::sendmmsg(
+            fd,
+            messages.as_ptr() as *mut libc::mmsghdr,
+            messages.len() as libc::c_uint,
+            flags.bits(),
+        )
+    };
+    if r < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(r as usize)
+}
+
+/// Receive a batch of datagrams through recvmmsg(2) returning how many of the first were received.
+///
+/// The timeout is only checked after each received datagram and does not bound the wait for the
+/// first one - see BUGS in recvmmsg(2). Use [`RecvFlags::WAITFORONE`] or a non-blocking socket
+/// to avoid waiting for the whole batch. A timeout beyond time_t is EINVAL.
+#[inline]
+pub fn recvmmsg(
+    fd: RawFd,
+    messages: &mut [MsgHdrMut<'_>],
+    flags: RecvFlags,
+    timeout: Option<Duration>,
+) -> io::Result<usize> {
+    let mut ts = match timeout {
+        Some(timeout) => Some(libc::timespec {
+            tv_sec: libc::time_t::try_from(timeout.as_secs())
+                .map_err(|_| io::Error::from_raw_os_error(libc::EINVAL))?,
+            tv_nsec: timeout.subsec_nanos() as _,
+        }),
+        None => None,
+    };
+    let ts_ptr = match ts.as_mut() {
+        Some(ts) => ts as *mut libc::timespec,
+        None => core::ptr::null_mut(),
+    };
+    // SAFETY: MsgHdrMut is a transparent mmsghdr pointing to exclusively borrowed iovecs.
+    let r = unsafe {
+        libc::recvmmsg(
+            fd,
+            messages.as_mut_ptr() as *mut libc::mmsghdr,
+            messages.len() as libc::c_uint,
+            flags.bits(),
+            ts_ptr,
+        )
+    };
+    if r < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(r as usize)
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use std::net::UdpSocket;
+    use yown_fd::AsRawFd;
+
+    fn udp_pair() -> (UdpSocket, UdpSocket) {
+        let a = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let b = UdpSocket::bind("127.0.0.1:0").unwrap();
+        a.connect(b.local_addr().unwrap()).unwrap();
+        b.connect(a.local_addr().unwrap()).unwrap();
+        (a, b)
+    }
+
+    #[test]
+    fn batch_ten() {
+        let (a, b) = udp_pair();
+        let payloads: Vec<Vec<u8>> = (0..10u8).map(|i| vec![i; 1 + i as usize]).collect();
+        let slices: Vec<[IoSlice<'_>; 1]> = payloads.iter().map(|p| [IoSlice::new(p)]).collect();
+        let send: Vec<MsgHdr<'_>> = slices.iter().map(|s| MsgHdr::new(s)).collect();
+        assert_eq!(
+            sendmmsg(a.as_raw_fd(), &send, SendFlags::empty()).unwrap(),
+            10
+        );
+        for (msg, payload) in send.iter().zip(&payloads) {
+            assert_eq!(msg.len(), payload.len());
+        }
+
+        let mut bufs = [[0u8; 16]; 10];
+        let mut slices: Vec<[IoSliceMut<'_>; 1]> =
+            bufs.iter_mut().map(|b| [IoSliceMut::new(b)]).collect();
+        let mut recv: Vec<MsgHdrMut<'_>> = slices.iter_mut().map(|s| MsgHdrMut::new(s)).collect();
+        let n = recvmmsg(
+            b.as_raw_fd(),
+            &mut recv,
+            RecvFlags::WAITFORONE,
+            Some(Duration::from_secs(1)),
+        )
+        .unwrap();
+        assert_eq!(n, 10);
+        let lens: Vec<usize> = recv.iter().map(|m| m.len()).collect();
+        assert!(recv.iter().all(|m| !m.is_truncated()));
+        drop(recv);
+        drop(slices);
+        for (i, (buf, len)) in bufs.iter().zip(lens).enumerate() {
+            assert_eq!(&buf[..len], &payloads[i][..]);
+        }
+    }
+
+    #[test]
+    fn recv_truncated() {
+        let (a, b) = udp_pair();
+        a.send(&[1u8; 8]).unwrap();
+        let mut buf = [0u8; 4];
+        let mut slices = [IoSliceMut::new(&mut buf)];
+        let mut recv = [MsgHdrMut::new(&mut slices)];
+        assert_eq!(
+            recvmmsg(b.as_raw_fd(), &mut recv, RecvFlags::DONTWAIT, None).unwrap(),
+            1
+        );
+        assert_eq!(recv[0].len(), 4);
+        assert!(recv[0].is_truncated());
+    }
+
+    #[test]
+    fn recv_would_block() {
+        let (_a, b) = udp_pair();
+        let mut buf = [0u8; 4];
+        let mut slices = [IoSliceMut::new(&mut buf)];
+        let mut recv = [MsgHdrMut::new(&mut slices)];
+        let err = recvmmsg(b.as_raw_fd(), &mut recv, RecvFlags::DONTWAIT, None).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::WouldBlock);
+    }
+
+    #[test]
+    fn recv_timeout_beyond_time_t() {
+        let (_a, b) = udp_pair();
+        let mut buf = [0u8; 4];
+        let mut slices = [IoSliceMut::new(&mut buf)];
+        let mut recv = [MsgHdrMut::new(&mut slices)];
+        let timeout = Some(Duration::from_secs(u64::MAX));
+        let err = recvmmsg(b.as_raw_fd(), &mut recv, RecvFlags::DONTWAIT, timeout).unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(libc::EINVAL));
+    }
+}
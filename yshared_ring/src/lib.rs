@@ -0,0 +1,236 @@
+#![warn(
+    clippy::unwrap_used,
+    missing_docs,
+    rust_2018_idioms,
+    unused_lifetimes,
+    unused_qualifications
+)]
+#![doc = include_str!("../README.md")]
+
+#[cfg(not(target_os = "linux"))]
+compile_error!("Crate yshared_ring is Linux specific dependency but is used in non-linux system.");
+
+use anonymous_mmap::{page_size, AnonymousMmap, AnonymousMmapError, SplitError};
+use core::cell::Cell;
+use core::mem::ManuallyDrop;
+use yown_fd::{AsRawFd, FromRawFd, OwnedFd};
+
+pub use yring_buffer::{RingEmpty, RingFull};
+
+/// Error constructing the [`SharedRing`]
+#[derive(Debug)]
+pub enum SharedRingError {
+    /// Reserving or splitting the address range failed
+    Mmap(AnonymousMmapError),
+    /// Splitting the reserved address range into the halves failed
+    Split(SplitError),
+    /// Call to memfd_create or ftruncate failed with errno
+    Memfd(std::io::Error),
+    /// Mapping the memfd over the reserved range failed with errno
+    MapFixed(std::io::Error),
+}
+
+impl core::fmt::Display for SharedRingError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Mmap(e) => write!(f, "Reserve Failed: {}", e),
+            Self::Split(e) => write!(f, "Split Failed: {}", e),
+            Self::Memfd(e) => write!(f, "memfd Failed: {}", e),
+            Self::MapFixed(e) => write!(f, "mmap MAP_FIXED Failed: {}", e),
+        }
+    }
+}
+
+impl core::error::Error for SharedRingError {}
+
+impl From<AnonymousMmapError> for SharedRingError {
+    fn from(e: AnonymousMmapError) -> Self {
+        Self::Mmap(e)
+    }
+}
+
+/// Ring buffer where the memfd is mapped twice back to back so that
+/// first_map[i] and second_map[i] alias the same byte.
+#[derive(Debug)]
+pub struct SharedRing {
+    first_map: ManuallyDrop<AnonymousMmap>,
+    second_map: ManuallyDrop<AnonymousMmap>,
+    memfd: OwnedFd,
+    capacity: usize,
+    head: usize,
+    tail: Cell<usize>,
+}
+
+#[inline]
+fn map_fixed(map: &AnonymousMmap, len: usize, fd: &OwnedFd) -> Result<(), SharedRingError> {
+    // SAFETY: The range is owned by the given reservation which is replaced atomically.
+    let p = unsafe {
+        libc::mmap(
+            map.as_ptr_mut(),
+            len,
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_SHARED | libc::MAP_FIXED,
+            fd.as_raw_fd(),
+            0,
+        )
+    };
+    if p == libc::MAP_FAILED || p != map.as_ptr_mut() {
+        return Err(SharedRingError::MapFixed(std::io::Error::last_os_error()));
+    }
+    Ok(())
+}
+
+impl SharedRing {
+    /// Construct a new ring with the given capacity rounded up to the next power of two
+    /// and at least the page size.
+    #[inline]
+    pub fn new(capacity: usize) -> Result<Self, SharedRingError> {
+        let capacity = capacity.max(page_size()).next_power_of_two();
+
+        // SAFETY: The name is a valid C string.
+        let fd = unsafe { libc::memfd_create(c"yshared_ring".as_ptr(), libc::MFD_CLOEXEC) };
+        if fd < 0 {
+            return Err(SharedRingError::Memfd(std::io::Error::last_os_error()));
+        }
+        // SAFETY: fd is valid and ours.
+        let memfd = unsafe { OwnedFd::from_raw_fd(fd) };
+        // SAFETY: memfd is valid.
+        if unsafe { libc::ftruncate(memfd.as_raw_fd(), capacity as libc::off_t) } != 0 {
+            return Err(SharedRingError::Memfd(std::io::Error::last_os_error()));
+        }
+
+        // Reserve the continuous range for both halves and split the bookkeeping so each is
+        // unmapped on its own once the memfd is mapped over them.
+        let reserved = AnonymousMmap::new_private(capacity * 2)?;
+        let (first_map, second_map) = match reserved.split_off(capacity) {
+            Ok(halves) => halves,
+            Err((reserved, e)) => {
+                // SAFETY: Nothing was handed out of the reservation. Nothing to give an
+                // unmap error back to besides the split error.
+                let _ = unsafe { reserved.try_drop() };
+                return Err(SharedRingError::Split(e));
+            }
+        };
+        let ring = Self {
+            first_map: ManuallyDrop::new(first_map),
+            second_map: ManuallyDrop::new(second_map),
+            memfd,
+            capacity,
+            head: 0,
+            tail: Cell::new(0),
+        };
+        map_fixed(&ring.first_map, capacity, &ring.memfd)?;
+        map_fixed(&ring.second_map, capacity, &ring.memfd)?;
+        Ok(ring)
+    }
+    /// Capacity of the ring in bytes
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+    /// Bytes available to be read
+    #[inline]
+    pub fn available_read(&self) -> usize {
+        self.head.wrapping_sub(self.tail.get())
+    }
+    /// Bytes available to be written
+    #[inline]
+    pub fn available_write(&self) -> usize {
+        self.capacity - self.available_read()
+    }
+    #[inline]
+    fn at(&self, pos: usize) -> *mut u8 {
+        // SAFETY: Masked within the first mapping and any len up to capacity continues into the second.
+        unsafe {
+            self.first_map
+                .as_ptr_mut()
+                .cast::<u8>()
+                .add(pos & (self.capacity - 1))
+        }
+    }
+    /// Write as much of the data as there is space available in a single copy returning the bytes
+    /// written. Err if there is no space at all for non-empty data.
+    #[inline]
+    pub fn write_slice(&mut self, data: &[u8]) -> Result<usize, RingFull> {
+        let n = data.len().min(self.available_write());
+        if n == 0 && !data.is_empty() {
+            return Err(RingFull);
+        }
+        // SAFETY: n bytes from head are free and continuous across the double mapping.
+        unsafe { core::ptr::copy_nonoverlapping(data.as_ptr(), self.at(self.head), n) };
+        self.head = self.head.wrapping_add(n);
+        Ok(n)
+    }
+    /// Read up to len bytes as one continuous slice even when wrapping around the end of the ring.
+    /// The bytes are consumed and the slice is valid until the next [`Self::write_slice`].
+    #[inline]
+    pub fn read_slice(&self, len: usize) -> Result<&[u8], RingEmpty> {
+        let available = self.available_read();
+        if available == 0 {
+            return Err(RingEmpty);
+        }
+        let n = len.min(available);
+        let tail = self.tail.get();
+        // SAFETY: n bytes from tail are written and continuous across the double mapping. Writing
+        // over them requires &mut self which can not happen while the slice is borrowed.
+        let slice = unsafe { core::slice::from_raw_parts(self.at(tail), n) };
+        self.tail.set(tail.wrapping_add(n));
+        Ok(slice)
+    }
+}
+
+impl Drop for SharedRing {
+    fn drop(&mut self) {
+        // SAFETY: No slices can outlive the ring.
+        let (first, second) = unsafe {
+            (
+                ManuallyDrop::take(&mut self.first_map),
+                ManuallyDrop::take(&mut self.second_map),
+            )
+        };
+        // Nothing to give the errors back to.
+        let _ = unsafe { first.try_drop() };
+        let _ = unsafe { second.try_drop() };
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn mirrored() {
+        let mut ring = SharedRing::new(1).unwrap();
+        let cap = ring.capacity();
+        assert_eq!(cap, page_size());
+        ring.write_slice(b"mirror").unwrap();
+        let second =
+            unsafe { core::slice::from_raw_parts(ring.second_map.as_ptr().cast::<u8>(), 6) };
+        assert_eq!(second, b"mirror");
+    }
+
+    #[test]
+    fn straddling_message_continuous() {
+        let mut ring = SharedRing::new(4096).unwrap();
+        let cap = ring.capacity();
+        let filler = vec![0xEEu8; cap - 10];
+        assert_eq!(ring.write_slice(&filler).unwrap(), cap - 10);
+        assert_eq!(ring.read_slice(cap).unwrap().len(), cap - 10);
+
+        let message: Vec<u8> = (0..100u8).collect();
+        assert_eq!(ring.write_slice(&message).unwrap(), 100);
+        let read = ring.read_slice(100).unwrap();
+        assert_eq!(read, &message[..]);
+        assert_eq!(ring.read_slice(1).unwrap_err(), RingEmpty);
+    }
+
+    #[test]
+    fn full() {
+        let mut ring = SharedRing::new(4096).unwrap();
+        let cap = ring.capacity();
+        assert_eq!(ring.write_slice(&vec![1u8; cap + 1]).unwrap(), cap);
+        assert_eq!(ring.write_slice(b"x").unwrap_err(), RingFull);
+        assert_eq!(ring.available_write(), 0);
+    }
+}
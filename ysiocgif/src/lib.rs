@@ -0,0 +1,153 @@
+#![warn(
+    clippy::unwrap_used,
+    missing_docs,
+    rust_2018_idioms,
+    unused_lifetimes,
+    unused_qualifications
+)]
+#![doc = include_str!("../README.md")]
+
+#[cfg(not(target_os = "linux"))]
+compile_error!("Crate ysiocgif is Linux specific dependency but is used in non-linux system.");
+
+use std::ffi::{CStr, CString};
+use std::io;
+use yown_fd::RawFd;
+use ysockaddr::YSockAddrC;
+
+/// Index of the named interface through if_nametoindex(3).
+#[inline]
+pub fn if_nametoindex(name: &CStr) -> io::Result<u32> {
+    // SAFETY: name is a valid C string.
+    match unsafe { libc::if_nametoindex(name.as_ptr()) } {
+        0 => Err(io::Error::last_os_error()),
+        index => Ok(index),
+    }
+}
+
+/// Name of the interface with the given index through if_indextoname(3).
+#[inline]
+pub fn if_indextoname(index: u32) -> io::Result<CString> {
+    let mut buf = [0 as libc::c_char; libc::IF_NAMESIZE];
+    // SAFETY: buf holds IF_NAMESIZE bytes as required.
+    let p = unsafe { libc::if_indextoname(index, buf.as_mut_ptr()) };
+    if p.is_null() {
+        return Err(io::Error::last_os_error());
+    }
+    // SAFETY: if_indextoname NUL terminated the name within buf.
+    Ok(unsafe { CStr::from_ptr(buf.as_ptr()) }.to_owned())
+}
+
+#[inline]
+fn ifreq_of(name: &CStr) -> io::Result<libc::ifreq> {
+    let name = name.to_bytes();
+    // The name and the NUL need to fit ifr_name.
+    if name.len() >= libc::IFNAMSIZ {
+        return Err(io::Error::from_raw_os_error(libc::EINVAL));
+    }
+    // SAFETY: ifreq is plain integers and arrays valid when zeroed.
+    let mut ifr: libc::ifreq = unsafe { core::mem::zeroed() };
+    for (dst, src) in ifr.ifr_name.iter_mut().zip(name) {
+        *dst = *src as libc::c_char;
+    }
+    Ok(ifr)
+}
+
+#[inline]
+fn siocgif(fd: RawFd, name: &CStr, request: libc::c_ulong) -> io::Result<libc::ifreq> {
+    let mut ifr = ifreq_of(name)?;
+    // SAFETY: The SIOCGIF* requests read the name from and write the result into the ifreq.
+    unsafe { yioctl::ioctl_read(fd, request as _, &mut ifr) }?;
+    Ok(ifr)
+}
+
+#[inline]
+fn sockaddr_in_of(sa: &libc::sockaddr) -> io::Result<YSockAddrC> {
+    if sa.sa_family as libc::c_int != libc::AF_INET {
+        return Err(io::Error::from_raw_os_error(libc::EAFNOSUPPORT));
+    }
+    // SAFETY: Family is AF_INET and sockaddr_in is the same size as sockaddr.
+    let sa4_in = unsafe { core::ptr::read_unaligned((sa as *const libc::sockaddr).cast()) };
+    Ok(YSockAddrC::V4(
+        sa4_in,
+        size_of::<libc::sockaddr_in>() as libc::socklen_t,
+    ))
+}
+
+/// IPv4 address of the named interface through SIOCGIFADDR on any AF_INET socket fd.
+#[inline]
+pub fn siocgifaddr(fd: RawFd, name: &CStr) -> io::Result<YSockAddrC> {
+    let ifr = siocgif(fd, name, libc::SIOCGIFADDR)?;
+    // SAFETY: SIOCGIFADDR filled ifru_addr.
+    sockaddr_in_of(unsafe { &ifr.ifr_ifru.ifru_addr })
+}
+
+/// IPv4 netmask of the named interface through SIOCGIFNETMASK on any AF_INET socket fd.
+#[inline]
+pub fn siocgifnetmask(fd: RawFd, name: &CStr) -> io::Result<YSockAddrC> {
+    let ifr = siocgif(fd, name, libc::SIOCGIFNETMASK)?;
+    // SAFETY: SIOCGIFNETMASK filled ifru_netmask.
+    sockaddr_in_of(unsafe { &ifr.ifr_ifru.ifru_netmask })
+}
+
+/// MTU of the named interface through SIOCGIFMTU on any socket fd.
+#[inline]
+pub fn siocgifmtu(fd: RawFd, name: &CStr) -> io::Result<i32> {
+    let ifr = siocgif(fd, name, libc::SIOCGIFMTU)?;
+    // SAFETY: SIOCGIFMTU filled ifru_mtu.
+    Ok(unsafe { ifr.ifr_ifru.ifru_mtu })
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use rstest::rstest;
+    use std::net::{Ipv4Addr, UdpSocket};
+    use yown_fd::AsRawFd;
+    use ysockaddr::YSockAddrR;
+
+    fn ipv4_of(sa: YSockAddrC) -> Ipv4Addr {
+        match YSockAddrR::try_from(sa).unwrap().as_sockaddr().ip() {
+            std::net::IpAddr::V4(ip) => ip,
+            ip => panic!("not IPv4 {}", ip),
+        }
+    }
+
+    #[test]
+    fn loopback_index_roundtrip() {
+        let index = if_nametoindex(c"lo").unwrap();
+        assert!(index > 0);
+        assert_eq!(if_indextoname(index).unwrap().as_c_str(), c"lo");
+    }
+
+    #[test]
+    fn loopback_addr_netmask_mtu() {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let fd = socket.as_raw_fd();
+        assert_eq!(
+            ipv4_of(siocgifaddr(fd, c"lo").unwrap()),
+            Ipv4Addr::LOCALHOST
+        );
+        assert_eq!(
+            ipv4_of(siocgifnetmask(fd, c"lo").unwrap()),
+            Ipv4Addr::new(255, 0, 0, 0)
+        );
+        assert_eq!(siocgifmtu(fd, c"lo").unwrap(), 65536);
+    }
+
+    #[rstest]
+    #[case(c"ynosuchif0", libc::ENODEV)]
+    #[case(c"waytoolonginterfacename", libc::EINVAL)]
+    fn unknown_interface(#[case] name: &CStr, #[case] errno: i32) {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let err = siocgifmtu(socket.as_raw_fd(), name).unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(errno));
+    }
+
+    #[test]
+    fn unknown_index() {
+        assert!(if_indextoname(u32::MAX).is_err());
+        assert!(if_nametoindex(c"ynosuchif0").is_err());
+    }
+}
@@ -0,0 +1,131 @@
+#![warn(
+    clippy::unwrap_used,
+    missing_docs,
+    rust_2018_idioms,
+    unused_lifetimes,
+    unused_qualifications
+)]
+#![doc = include_str!("../README.md")]
+
+#[cfg(not(target_os = "linux"))]
+compile_error!("Crate yso_error is Linux specific dependency but is used in non-linux system.");
+
+use std::io;
+use yown_fd::RawFd;
+
+/// Pending error of the socket through getsockopt(2) SO_ERROR which also clears it.
+///
+/// After a non-blocking connect(2) becomes writable None means the connection succeeded and
+/// Some the reason it failed e.g. ECONNREFUSED. Err is getsockopt(2) itself failing.
+#[inline]
+pub fn get_socket_error(fd: RawFd) -> io::Result<Option<io::Error>> {
+    let mut err: libc::c_int = 0;
+    let mut len = size_of::<libc::c_int>() as libc::socklen_t;
+    // SAFETY: err and len are valid for the duration of the call.
+    let r = unsafe {
+        libc::getsockopt(
+            fd,
+            libc::SOL_SOCKET,
+            libc::SO_ERROR,
+            core::ptr::addr_of_mut!(err) as *mut libc::c_void,
+            &mut len,
+        )
+    };
+    if r != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    match err {
+        0 => Ok(None),
+        e => Ok(Some(io::Error::from_raw_os_error(e))),
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use std::cell::Cell;
+    use std::net::{SocketAddrV4, TcpListener};
+    use std::rc::Rc;
+    use yepoll::{EpollDispatcher, EpollEvents};
+    use yown_fd::{AsRawFd, FromRawFd, OwnedFd};
+
+    fn connect_nonblocking(addr: SocketAddrV4) -> OwnedFd {
+        let fd = unsafe {
+            libc::socket(
+                libc::AF_INET,
+                libc::SOCK_STREAM | libc::SOCK_NONBLOCK | libc::SOCK_CLOEXEC,
+                0,
+            )
+        };
+        assert!(fd >= 0);
+        let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+        let sin = libc::sockaddr_in {
+            sin_family: libc::AF_INET as libc::sa_family_t,
+            sin_port: addr.port().to_be(),
+            sin_addr: libc::in_addr {
+                s_addr: u32::from(*addr.ip()).to_be(),
+            },
+            sin_zero: [0; 8],
+        };
+        let r = unsafe {
+            libc::connect(
+                fd.as_raw_fd(),
+                core::ptr::addr_of!(sin) as *const libc::sockaddr,
+                size_of::<libc::sockaddr_in>() as libc::socklen_t,
+            )
+        };
+        if r != 0 {
+            assert_eq!(
+                io::Error::last_os_error().raw_os_error(),
+                Some(libc::EINPROGRESS)
+            );
+        }
+        fd
+    }
+
+    fn wait_writable(fd: RawFd) -> EpollEvents {
+        let mut epoll = EpollDispatcher::new().unwrap();
+        let seen = Rc::new(Cell::new(EpollEvents::empty()));
+        let seen_handler = seen.clone();
+        epoll
+            .register(fd, EpollEvents::EPOLLOUT, move |ev| seen_handler.set(ev))
+            .unwrap();
+        assert_eq!(epoll.run_once(5000).unwrap(), 1);
+        seen.get()
+    }
+
+    fn local_addr(listener: &TcpListener) -> SocketAddrV4 {
+        match listener.local_addr().unwrap() {
+            std::net::SocketAddr::V4(a) => a,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn connect_refused() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = local_addr(&listener);
+        drop(listener);
+
+        let fd = connect_nonblocking(addr);
+        assert!(wait_writable(fd.as_raw_fd()).contains(EpollEvents::EPOLLOUT));
+        let err = get_socket_error(fd.as_raw_fd()).unwrap().unwrap();
+        assert_eq!(err.raw_os_error(), Some(libc::ECONNREFUSED));
+        // Reading SO_ERROR clears it.
+        assert!(get_socket_error(fd.as_raw_fd()).unwrap().is_none());
+    }
+
+    #[test]
+    fn connect_succeeded() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let fd = connect_nonblocking(local_addr(&listener));
+        assert!(wait_writable(fd.as_raw_fd()).contains(EpollEvents::EPOLLOUT));
+        assert!(get_socket_error(fd.as_raw_fd()).unwrap().is_none());
+    }
+
+    #[test]
+    fn not_socket() {
+        assert!(get_socket_error(-1).is_err());
+    }
+}
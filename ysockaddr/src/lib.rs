@@ -17,6 +17,33 @@ use core::net::{Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
 #[cfg(feature = "std")]
 use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
 
+#[cfg(feature = "std")]
+mod sockname;
+#[cfg(feature = "std")]
+pub use sockname::{
+    getpeername, getpeername_unix, getsockname, getsockname_unix, local_addr, peer_addr,
+};
+
+mod netlink;
+pub use netlink::{NetlinkAddr, NetlinkGroupError, NetlinkGroups};
+
+mod packet;
+pub use packet::PacketAddr;
+
+mod raw;
+pub use raw::{YSockAddrMut, YSockAddrRef};
+
+mod storage;
+pub use storage::{YSockAddrError, YSockAddrStorage};
+
+mod vsock;
+pub use vsock::{VsockAddr, VMADDR_CID_ANY, VMADDR_CID_HOST, VMADDR_CID_LOCAL, VMADDR_PORT_ANY};
+
+#[cfg(feature = "std")]
+mod unix;
+#[cfg(feature = "std")]
+pub use unix::{UnixAddrError, UnixAddrKind, YUnixAddr, SUN_PATH_LEN};
+
 //-----------------------------------------------
 // Conversions
 //-----------------------------------------------
@@ -28,15 +55,83 @@ pub enum YSockAddrC {
     V4(libc::sockaddr_in, libc::socklen_t),
     /// IPv6
     V6(libc::sockaddr_in6, libc::socklen_t),
+    /// AF_VSOCK, see [`VsockAddr`]
+    Vsock(libc::sockaddr_vm, libc::socklen_t),
+    /// AF_NETLINK, see [`NetlinkAddr`]
+    Netlink(libc::sockaddr_nl, libc::socklen_t),
+    /// AF_PACKET, see [`PacketAddr`]
+    Packet(libc::sockaddr_ll, libc::socklen_t),
 }
 
 impl YSockAddrC {
-    /// Cast to C/FFI sockaddr + len
+    /// Cast to C/FFI sockaddr + len e.g. for bind(2) or connect(2)
+    ///
+    /// The pointer is into self and only valid while self is borrowed and not moved, see
+    /// [`YSockAddrRef::from`] for a view tied to the borrow.
     #[inline]
     pub fn as_c_sockaddr_len(&self) -> (*const libc::sockaddr, libc::socklen_t) {
         match self {
             Self::V4(sa4_in, len) => (core::ptr::addr_of!(*sa4_in) as *const libc::sockaddr, *len),
             Self::V6(sa6_in, len) => (core::ptr::addr_of!(*sa6_in) as *const libc::sockaddr, *len),
+            Self::Vsock(svm, len) => (core::ptr::addr_of!(*svm) as *const libc::sockaddr, *len),
+            Self::Netlink(snl, len) => (core::ptr::addr_of!(*snl) as *const libc::sockaddr, *len),
+            Self::Packet(sll, len) => (core::ptr::addr_of!(*sll) as *const libc::sockaddr, *len),
+        }
+    }
+    /// Is this an IPv4 address
+    #[inline]
+    pub fn is_ipv4(&self) -> bool {
+        matches!(self, Self::V4(..))
+    }
+    /// Is this an IPv6 address
+    #[inline]
+    pub fn is_ipv6(&self) -> bool {
+        matches!(self, Self::V6(..))
+    }
+    /// Is this an AF_VSOCK address
+    #[inline]
+    pub fn is_vsock(&self) -> bool {
+        matches!(self, Self::Vsock(..))
+    }
+    /// Is this an AF_NETLINK address
+    #[inline]
+    pub fn is_netlink(&self) -> bool {
+        matches!(self, Self::Netlink(..))
+    }
+    /// Is this an AF_PACKET address
+    #[inline]
+    pub fn is_packet(&self) -> bool {
+        matches!(self, Self::Packet(..))
+    }
+    /// C/FFI address family e.g. AF_INET or AF_INET6
+    #[inline]
+    pub fn family(&self) -> libc::sa_family_t {
+        match self {
+            Self::V4(..) => libc::AF_INET as libc::sa_family_t,
+            Self::V6(..) => libc::AF_INET6 as libc::sa_family_t,
+            Self::Vsock(..) => libc::AF_VSOCK as libc::sa_family_t,
+            Self::Netlink(..) => libc::AF_NETLINK as libc::sa_family_t,
+            Self::Packet(..) => libc::AF_PACKET as libc::sa_family_t,
+        }
+    }
+    /// Port in host byte order, 0 for families other than IPv4 and IPv6 e.g. the u32 port
+    /// of [`VsockAddr`]
+    #[inline]
+    pub fn port(&self) -> u16 {
+        match self {
+            Self::V4(sa4_in, _) => u16::from_be(sa4_in.sin_port),
+            Self::V6(sa6_in, _) => u16::from_be(sa6_in.sin6_port),
+            Self::Vsock(..) | Self::Netlink(..) | Self::Packet(..) => 0,
+        }
+    }
+    /// Set the port given in host byte order, leaving families other than IPv4 and IPv6
+    /// untouched
+    #[inline]
+    pub fn set_port(&mut self, port: u16) {
+        match self {
+            Self::V4(sa4_in, _) => sa4_in.sin_port = port.to_be(),
+            Self::V6(sa6_in, _) => sa6_in.sin6_port = port.to_be(),
+            Self::Vsock(..) | Self::Netlink(..) | Self::Packet(..) => {}
         }
     }
 }
@@ -72,7 +167,25 @@ impl From<SocketAddr> for YSockAddrC {
     }
 }
 
+impl From<(Ipv4Addr, u16)> for YSockAddrC {
+    #[inline]
+    fn from((ip, port): (Ipv4Addr, u16)) -> YSockAddrC {
+        SocketAddr::V4(SocketAddrV4::new(ip, port)).into()
+    }
+}
+
+impl From<(Ipv6Addr, u16)> for YSockAddrC {
+    #[inline]
+    fn from((ip, port): (Ipv6Addr, u16)) -> YSockAddrC {
+        SocketAddr::V6(SocketAddrV6::new(ip, port, 0, 0)).into()
+    }
+}
+
 /// Immutable Raw SockAddr
+#[deprecated(
+    since = "0.3.0",
+    note = "the public raw pointers can not be read soundly, use YSockAddrRef instead"
+)]
 #[derive(Debug, Clone)]
 pub enum YSockAddrCrawImm {
     /// IPv4
@@ -82,6 +195,10 @@ pub enum YSockAddrCrawImm {
 }
 
 /// Mutable Raw SockAddr
+#[deprecated(
+    since = "0.3.0",
+    note = "the public raw pointers can not be read soundly, use YSockAddrMut instead"
+)]
 #[derive(Debug, Clone)]
 pub enum YSockAddrCrawMut {
     /// IPv4
@@ -112,24 +229,106 @@ impl YSockAddrR {
     }
 }
 
-impl From<YSockAddrC> for YSockAddrR {
+impl TryFrom<YSockAddrC> for YSockAddrR {
+    type Error = YSockAddrC;
+    /// Families other than IPv4 and IPv6 are given back as the Err
     #[inline]
-    fn from(sa: YSockAddrC) -> YSockAddrR {
-        match sa {
+    fn try_from(sa: YSockAddrC) -> Result<YSockAddrR, YSockAddrC> {
+        Ok(match sa {
             YSockAddrC::V4(c_sa4, _) => {
                 let ip4 = Ipv4Addr::from_bits(u32::from_be(c_sa4.sin_addr.s_addr));
-                Self(SocketAddr::V4(SocketAddrV4::new(ip4, c_sa4.sin_port)))
+                Self(SocketAddr::V4(SocketAddrV4::new(
+                    ip4,
+                    u16::from_be(c_sa4.sin_port),
+                )))
             }
             YSockAddrC::V6(c_sa6, _) => {
                 let in6_bits = u128::from_be_bytes(c_sa6.sin6_addr.s6_addr);
                 let ip6 = Ipv6Addr::from_bits(in6_bits);
                 Self(SocketAddr::V6(SocketAddrV6::new(
                     ip6,
-                    c_sa6.sin6_port,
+                    u16::from_be(c_sa6.sin6_port),
                     c_sa6.sin6_flowinfo,
                     c_sa6.sin6_scope_id,
                 )))
             }
+            other => return Err(other),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("127.0.0.1:80", true, false, libc::AF_INET)]
+    #[case("[::1]:80", false, true, libc::AF_INET6)]
+    fn families(
+        #[case] sa: &str,
+        #[case] is_ipv4: bool,
+        #[case] is_ipv6: bool,
+        #[case] family: libc::c_int,
+    ) {
+        let c: YSockAddrC = sa.parse::<SocketAddr>().unwrap().into();
+        assert_eq!(c.is_ipv4(), is_ipv4);
+        assert_eq!(c.is_ipv6(), is_ipv6);
+        assert_eq!(c.family(), family as libc::sa_family_t);
+    }
+
+    fn c_bytes(c: &YSockAddrC) -> &[u8] {
+        let (ptr, len) = c.as_c_sockaddr_len();
+        unsafe { core::slice::from_raw_parts(ptr as *const u8, len as usize) }
+    }
+
+    #[test]
+    fn from_ipv4_port() {
+        let c = YSockAddrC::from((Ipv4Addr::LOCALHOST, 80u16));
+        let via: YSockAddrC = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 80)).into();
+        assert_eq!(c_bytes(&c), c_bytes(&via));
+        match c {
+            YSockAddrC::V4(sa4_in, _) => assert_eq!(sa4_in.sin_zero, [0; 8]),
+            _ => panic!("Expected V4"),
         }
     }
+
+    #[test]
+    fn from_ipv6_port() {
+        let c = YSockAddrC::from((Ipv6Addr::LOCALHOST, 443u16));
+        let via: YSockAddrC =
+            SocketAddr::V6(SocketAddrV6::new(Ipv6Addr::LOCALHOST, 443, 0, 0)).into();
+        assert_eq!(c_bytes(&c), c_bytes(&via));
+        match c {
+            YSockAddrC::V6(sa6_in, _) => {
+                assert_eq!(sa6_in.sin6_flowinfo, 0);
+                assert_eq!(sa6_in.sin6_scope_id, 0);
+            }
+            _ => panic!("Expected V6"),
+        }
+    }
+
+    #[rstest]
+    fn round_trip_ports(
+        #[values(0, 1, 80, 255, 256, 443, 0x1234, 8080, 0xFF00, u16::MAX)] port: u16,
+        #[values("10.1.2.3", "::1", "fe80::1234:5678")] ip: &str,
+    ) {
+        let sa = SocketAddr::new(ip.parse().unwrap(), port);
+        let c = YSockAddrR::from_sockaddr(sa).as_c();
+        assert_eq!(c.port(), port);
+        let r = YSockAddrR::try_from(c).unwrap();
+        assert_eq!(r.as_sockaddr(), sa);
+        assert_eq!(r.as_sockaddr().port(), port);
+    }
+
+    #[rstest]
+    #[case("127.0.0.1:80", 80, 0x1234)]
+    #[case("[::1]:443", 443, 0x1234)]
+    fn ports(#[case] sa: &str, #[case] port: u16, #[case] new_port: u16) {
+        let mut c: YSockAddrC = sa.parse::<SocketAddr>().unwrap().into();
+        assert_eq!(c.port(), port);
+        c.set_port(new_port);
+        assert_eq!(c.port(), new_port);
+    }
 }
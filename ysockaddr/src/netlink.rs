@@ -0,0 +1,176 @@
+//! AF_NETLINK sockaddr_nl and the legacy multicast group bitmask
+
+use crate::YSockAddrC;
+
+/// Multicast group number which does not fit [`NetlinkGroups`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NetlinkGroupError(pub u32);
+
+impl core::fmt::Display for NetlinkGroupError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "Netlink group {} is outside the legacy 1..=32 bitmask, join it through NETLINK_ADD_MEMBERSHIP",
+            self.0
+        )
+    }
+}
+
+impl core::error::Error for NetlinkGroupError {}
+
+/// Builder of the legacy 32 bit nl_groups bitmask where group n e.g. RTNLGRP_LINK is bit
+/// n - 1
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct NetlinkGroups(u32);
+
+impl NetlinkGroups {
+    /// No groups
+    #[inline]
+    pub fn new() -> Self {
+        Self(0)
+    }
+    /// Join the group number e.g. libc::RTNLGRP_IPV4_ROUTE. Groups above 32 or 0 are
+    /// [`NetlinkGroupError`] as only setsockopt(2) NETLINK_ADD_MEMBERSHIP can join them.
+    #[inline]
+    pub fn join(self, group: u32) -> Result<Self, NetlinkGroupError> {
+        match group {
+            1..=32 => Ok(Self(self.0 | 1 << (group - 1))),
+            _ => Err(NetlinkGroupError(group)),
+        }
+    }
+    /// Is the group number joined
+    #[inline]
+    pub fn contains(&self, group: u32) -> bool {
+        matches!(group, 1..=32 if self.0 & 1 << (group - 1) != 0)
+    }
+    /// The nl_groups bitmask
+    #[inline]
+    pub fn bits(&self) -> u32 {
+        self.0
+    }
+}
+
+impl From<u32> for NetlinkGroups {
+    #[inline]
+    fn from(bits: u32) -> Self {
+        Self(bits)
+    }
+}
+
+/// Rust form of an AF_NETLINK address as there is none in std
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NetlinkAddr {
+    /// Port ID, 0 for the kernel or to have the kernel assign one on bind
+    pub pid: u32,
+    /// Legacy multicast group bitmask
+    pub groups: NetlinkGroups,
+}
+
+impl NetlinkAddr {
+    /// Address of the given port ID and multicast groups
+    #[inline]
+    pub fn new(pid: u32, groups: impl Into<NetlinkGroups>) -> Self {
+        Self {
+            pid,
+            groups: groups.into(),
+        }
+    }
+}
+
+impl core::fmt::Display for NetlinkAddr {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "netlink:{}:{:#x}", self.pid, self.groups.bits())
+    }
+}
+
+impl From<NetlinkAddr> for YSockAddrC {
+    #[inline]
+    fn from(na: NetlinkAddr) -> YSockAddrC {
+        // SAFETY: sockaddr_nl is valid when zeroed which covers the padding.
+        let mut snl: libc::sockaddr_nl = unsafe { core::mem::zeroed() };
+        snl.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+        snl.nl_pid = na.pid;
+        snl.nl_groups = na.groups.bits();
+        YSockAddrC::Netlink(snl, size_of::<libc::sockaddr_nl>() as libc::socklen_t)
+    }
+}
+
+impl TryFrom<YSockAddrC> for NetlinkAddr {
+    type Error = YSockAddrC;
+    /// Other families are given back as the Err
+    #[inline]
+    fn try_from(sa: YSockAddrC) -> Result<NetlinkAddr, YSockAddrC> {
+        match sa {
+            YSockAddrC::Netlink(snl, _) => Ok(NetlinkAddr::new(snl.nl_pid, snl.nl_groups)),
+            other => Err(other),
+        }
+    }
+}
+
+impl YSockAddrC {
+    /// AF_NETLINK address of the given port ID and legacy multicast group bitmask
+    #[inline]
+    pub fn netlink(pid: u32, groups: impl Into<NetlinkGroups>) -> Self {
+        NetlinkAddr::new(pid, groups).into()
+    }
+    /// AF_NETLINK address of the kernel without groups e.g. to send requests to
+    #[inline]
+    pub fn netlink_kernel() -> Self {
+        Self::netlink(0, 0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(&[], 0)]
+    #[case(&[libc::RTNLGRP_LINK], 0x1)]
+    #[case(&[libc::RTNLGRP_LINK, libc::RTNLGRP_IPV4_IFADDR, libc::RTNLGRP_IPV6_ROUTE], 0x411)]
+    #[case(&[libc::RTNLGRP_NEXTHOP, libc::RTNLGRP_NEXTHOP], 0x8000_0000)]
+    fn groups(#[case] joined: &[u32], #[case] bits: u32) {
+        let groups = joined
+            .iter()
+            .try_fold(NetlinkGroups::new(), |g, n| g.join(*n))
+            .unwrap();
+        assert_eq!(groups.bits(), bits);
+        assert!(joined.iter().all(|n| groups.contains(*n)));
+        assert!(!groups.contains(0));
+    }
+
+    #[rstest]
+    #[case(0)]
+    #[case(33)]
+    #[case(u32::MAX)]
+    fn groups_out_of_mask(#[case] group: u32) {
+        let err = NetlinkGroups::new().join(group).unwrap_err();
+        assert_eq!(err, NetlinkGroupError(group));
+    }
+
+    #[rstest]
+    #[case(YSockAddrC::netlink_kernel(), 0, 0, "netlink:0:0x0")]
+    #[case(YSockAddrC::netlink(1234, 0x411), 1234, 0x411, "netlink:1234:0x411")]
+    fn round_trip(
+        #[case] c: YSockAddrC,
+        #[case] pid: u32,
+        #[case] bits: u32,
+        #[case] display: &str,
+    ) {
+        assert!(c.is_netlink());
+        assert_eq!(c.family(), libc::AF_NETLINK as libc::sa_family_t);
+        assert_eq!(c.port(), 0);
+        let mut unchanged = c.clone();
+        unchanged.set_port(80);
+        assert_eq!(unchanged.port(), 0);
+        assert_eq!(
+            c.as_c_sockaddr_len().1 as usize,
+            size_of::<libc::sockaddr_nl>()
+        );
+        let na = NetlinkAddr::try_from(c).unwrap();
+        assert_eq!(na, NetlinkAddr::new(pid, bits));
+        assert_eq!(na.to_string(), display);
+    }
+}
@@ -0,0 +1,155 @@
+//! AF_PACKET sockaddr_ll of raw link layer sockets
+
+use crate::YSockAddrC;
+
+/// Rust form of a received AF_PACKET address e.g. from recvfrom(2) on a raw socket
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PacketAddr {
+    /// Interface index
+    pub ifindex: libc::c_int,
+    /// Ethertype in host byte order e.g. libc::ETH_P_IP
+    pub protocol: u16,
+    /// ARP hardware type e.g. libc::ARPHRD_ETHER
+    pub hatype: u16,
+    /// Packet type e.g. libc::PACKET_HOST or libc::PACKET_BROADCAST
+    pub pkttype: u8,
+    /// Len of the hardware address within addr
+    pub halen: u8,
+    /// Hardware address e.g. the source MAC of a received frame
+    pub addr: [u8; 8],
+}
+
+impl PacketAddr {
+    /// The hardware address as a MAC if it is one of six bytes
+    #[inline]
+    pub fn mac(&self) -> Option<[u8; 6]> {
+        match self.halen {
+            6 => {
+                let mut mac = [0; 6];
+                mac.copy_from_slice(&self.addr[..6]);
+                Some(mac)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl TryFrom<YSockAddrC> for PacketAddr {
+    type Error = YSockAddrC;
+    /// Other families are given back as the Err
+    #[inline]
+    fn try_from(sa: YSockAddrC) -> Result<PacketAddr, YSockAddrC> {
+        match sa {
+            YSockAddrC::Packet(sll, _) => Ok(PacketAddr {
+                ifindex: sll.sll_ifindex,
+                protocol: u16::from_be(sll.sll_protocol),
+                hatype: sll.sll_hatype,
+                pkttype: sll.sll_pkttype,
+                halen: sll.sll_halen,
+                addr: sll.sll_addr,
+            }),
+            other => Err(other),
+        }
+    }
+}
+
+#[inline]
+fn packet(ifindex: libc::c_int, ethertype: u16) -> libc::sockaddr_ll {
+    // SAFETY: sockaddr_ll is valid when zeroed.
+    let mut sll: libc::sockaddr_ll = unsafe { core::mem::zeroed() };
+    sll.sll_family = libc::AF_PACKET as libc::sa_family_t;
+    sll.sll_protocol = ethertype.to_be();
+    sll.sll_ifindex = ifindex;
+    sll
+}
+
+impl YSockAddrC {
+    /// AF_PACKET address to bind(2) a raw socket to ifname receiving the ethertype given in
+    /// host byte order e.g. libc::ETH_P_ALL, resolving the index through if_nametoindex(3).
+    ///
+    /// Unknown interfaces are Err with ENODEV and names with a NUL
+    /// [`std::io::ErrorKind::InvalidInput`].
+    #[cfg(feature = "std")]
+    #[inline]
+    pub fn packet_bind_addr(ifname: &str, ethertype: u16) -> std::io::Result<Self> {
+        let name = std::ffi::CString::new(ifname)
+            .map_err(|_| std::io::Error::from(std::io::ErrorKind::InvalidInput))?;
+        // SAFETY: name is NUL terminated.
+        let ifindex = match unsafe { libc::if_nametoindex(name.as_ptr()) } {
+            0 => return Err(std::io::Error::last_os_error()),
+            ifindex => ifindex as libc::c_int,
+        };
+        let sll = packet(ifindex, ethertype);
+        Ok(YSockAddrC::Packet(
+            sll,
+            size_of::<libc::sockaddr_ll>() as libc::socklen_t,
+        ))
+    }
+    /// AF_PACKET address to sendto(2) a frame of the ethertype given in host byte order out
+    /// of ifindex to dest_mac
+    #[inline]
+    pub fn packet_send_addr(ifindex: libc::c_int, ethertype: u16, dest_mac: [u8; 6]) -> Self {
+        let mut sll = packet(ifindex, ethertype);
+        sll.sll_halen = 6;
+        sll.sll_addr[..6].copy_from_slice(&dest_mac);
+        YSockAddrC::Packet(sll, size_of::<libc::sockaddr_ll>() as libc::socklen_t)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(libc::ETH_P_ALL as u16)]
+    #[case(libc::ETH_P_IP as u16)]
+    #[case(0x88cc)]
+    fn send_addr_byte_order(#[case] ethertype: u16) {
+        let mac = [0x02, 0, 0, 0x12, 0x34, 0x56];
+        let c = YSockAddrC::packet_send_addr(3, ethertype, mac);
+        assert!(c.is_packet());
+        assert_eq!(c.family(), libc::AF_PACKET as libc::sa_family_t);
+        assert_eq!(c.port(), 0);
+        let mut unchanged = c.clone();
+        unchanged.set_port(80);
+        assert_eq!(unchanged.port(), 0);
+        match &c {
+            YSockAddrC::Packet(sll, len) => {
+                assert_eq!(sll.sll_protocol, ethertype.to_be());
+                assert_eq!(sll.sll_addr, [0x02, 0, 0, 0x12, 0x34, 0x56, 0, 0]);
+                assert_eq!(*len as usize, size_of::<libc::sockaddr_ll>());
+            }
+            _ => panic!("Expected Packet"),
+        }
+        let pa = PacketAddr::try_from(c).unwrap();
+        assert_eq!(pa.ifindex, 3);
+        assert_eq!(pa.protocol, ethertype);
+        assert_eq!(pa.mac(), Some(mac));
+    }
+
+    #[cfg(feature = "std")]
+    #[rstest]
+    #[case("lo", None)]
+    #[case("ysockaddr-none", Some(libc::ENODEV))]
+    fn bind_addr_ifindex(#[case] ifname: &str, #[case] errno: Option<libc::c_int>) {
+        match YSockAddrC::packet_bind_addr(ifname, libc::ETH_P_IP as u16) {
+            Ok(YSockAddrC::Packet(sll, _)) => {
+                assert_eq!(errno, None);
+                assert!(sll.sll_ifindex > 0);
+                assert_eq!(sll.sll_protocol, (libc::ETH_P_IP as u16).to_be());
+                assert_eq!(sll.sll_halen, 0);
+            }
+            Ok(c) => panic!("Expected Packet, got {:?}", c),
+            Err(e) => assert_eq!(e.raw_os_error(), errno),
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn bind_addr_nul() {
+        let err = YSockAddrC::packet_bind_addr("l\0o", 0).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    }
+}
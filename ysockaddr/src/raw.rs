@@ -0,0 +1,329 @@
+//! Borrowed views of C sockaddrs behind pointers e.g. from FFI callbacks or out-parameters
+
+use crate::{YSockAddrC, YSockAddrError};
+use core::marker::PhantomData;
+
+// Minimum len of the sockaddr of the family, AF_PACKET only up to the hardware address as
+// the kernel gives it.
+#[inline]
+fn family_len(family: libc::sa_family_t) -> Result<usize, YSockAddrError> {
+    Ok(match family as libc::c_int {
+        libc::AF_INET => size_of::<libc::sockaddr_in>(),
+        libc::AF_INET6 => size_of::<libc::sockaddr_in6>(),
+        libc::AF_VSOCK => size_of::<libc::sockaddr_vm>(),
+        libc::AF_NETLINK => size_of::<libc::sockaddr_nl>(),
+        libc::AF_PACKET => core::mem::offset_of!(libc::sockaddr_ll, sll_addr),
+        _ => return Err(YSockAddrError::UnsupportedFamily(family)),
+    })
+}
+
+// Copy up to len bytes at ptr into a zeroed T with the len clamped to T so the len kept
+// alongside never reaches past it.
+#[inline]
+unsafe fn read_zeroed<T>(ptr: *const libc::sockaddr, len: libc::socklen_t) -> (T, libc::socklen_t) {
+    let len = (len as usize).min(size_of::<T>());
+    // SAFETY: Every sockaddr is valid when zeroed.
+    let mut t: T = unsafe { core::mem::zeroed() };
+    // SAFETY: The caller guarantees len bytes at ptr and t is at least the copied bytes.
+    unsafe {
+        core::ptr::copy_nonoverlapping(ptr as *const u8, core::ptr::addr_of_mut!(t) as *mut u8, len)
+    };
+    (t, len as libc::socklen_t)
+}
+
+/// Borrowed sockaddr of a family [`YSockAddrC`] supports, validated against its len
+#[derive(Debug, Clone, Copy)]
+pub struct YSockAddrRef<'a> {
+    ptr: *const libc::sockaddr,
+    len: libc::socklen_t,
+    family: libc::sa_family_t,
+    _borrow: PhantomData<&'a [u8]>,
+}
+
+impl<'a> YSockAddrRef<'a> {
+    /// View the sockaddr of len bytes at ptr sniffing the family and validating the len
+    /// against it.
+    ///
+    /// Null is [`YSockAddrError::Null`], families other than AF_INET, AF_INET6, AF_VSOCK,
+    /// AF_NETLINK and AF_PACKET [`YSockAddrError::UnsupportedFamily`] and a len short of the
+    /// family's sockaddr [`YSockAddrError::TooShort`].
+    ///
+    /// # Safety
+    ///
+    /// Unless null ptr has to be valid for reads of len bytes which need not be aligned for
+    /// the lifetime 'a.
+    #[inline]
+    pub unsafe fn from_parts(
+        ptr: *const libc::sockaddr,
+        len: libc::socklen_t,
+    ) -> Result<Self, YSockAddrError> {
+        if ptr.is_null() {
+            return Err(YSockAddrError::Null);
+        }
+        let family_field = size_of::<libc::sa_family_t>() as libc::socklen_t;
+        if len < family_field {
+            return Err(YSockAddrError::TooShort {
+                len,
+                expected: family_field,
+            });
+        }
+        // SAFETY: len covers at least the family which leads every sockaddr.
+        let family = unsafe { core::ptr::read_unaligned(ptr as *const libc::sa_family_t) };
+        let expected = family_len(family)? as libc::socklen_t;
+        if len < expected {
+            return Err(YSockAddrError::TooShort { len, expected });
+        }
+        Ok(Self {
+            ptr,
+            len,
+            family,
+            _borrow: PhantomData,
+        })
+    }
+    /// C/FFI address family e.g. AF_INET or AF_INET6
+    #[inline]
+    pub fn family(&self) -> libc::sa_family_t {
+        self.family
+    }
+    /// Len of the sockaddr
+    #[inline]
+    pub fn len(&self) -> libc::socklen_t {
+        self.len
+    }
+    /// Never empty as the len covers the sockaddr of the family
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    /// Pointer to the sockaddr e.g. for sendto(2)
+    #[inline]
+    pub fn as_ptr(&self) -> *const libc::sockaddr {
+        self.ptr
+    }
+    /// Copy the sockaddr out into the owned [`YSockAddrC`] keeping the len up to the size of
+    /// the family's sockaddr e.g. 16 for an AF_INET one given within a sockaddr_storage.
+    #[inline]
+    pub fn to_owned(&self) -> Result<YSockAddrC, YSockAddrError> {
+        let (ptr, len) = (self.ptr, self.len);
+        // SAFETY: from_parts validated len bytes at ptr covering the family's sockaddr.
+        unsafe {
+            Ok(match self.family as libc::c_int {
+                libc::AF_INET => {
+                    let (sa, len) = read_zeroed(ptr, len);
+                    YSockAddrC::V4(sa, len)
+                }
+                libc::AF_INET6 => {
+                    let (sa, len) = read_zeroed(ptr, len);
+                    YSockAddrC::V6(sa, len)
+                }
+                libc::AF_VSOCK => {
+                    let (sa, len) = read_zeroed(ptr, len);
+                    YSockAddrC::Vsock(sa, len)
+                }
+                libc::AF_NETLINK => {
+                    let (sa, len) = read_zeroed(ptr, len);
+                    YSockAddrC::Netlink(sa, len)
+                }
+                libc::AF_PACKET => {
+                    let (sa, len) = read_zeroed(ptr, len);
+                    YSockAddrC::Packet(sa, len)
+                }
+                _ => return Err(YSockAddrError::UnsupportedFamily(self.family)),
+            })
+        }
+    }
+}
+
+impl<'a> From<&'a YSockAddrC> for YSockAddrRef<'a> {
+    #[inline]
+    fn from(c: &'a YSockAddrC) -> Self {
+        let (ptr, len) = c.as_c_sockaddr_len();
+        Self {
+            ptr,
+            len,
+            family: c.family(),
+            _borrow: PhantomData,
+        }
+    }
+}
+
+/// Borrowed caller-provided storage to write a sockaddr into e.g. a recvmsg(2) style
+/// out-parameter
+#[derive(Debug)]
+pub struct YSockAddrMut<'a> {
+    ptr: *mut libc::sockaddr,
+    cap: libc::socklen_t,
+    _borrow: PhantomData<&'a mut [u8]>,
+}
+
+impl<'a> YSockAddrMut<'a> {
+    /// View the storage of cap bytes at ptr, null being [`YSockAddrError::Null`].
+    ///
+    /// # Safety
+    ///
+    /// Unless null ptr has to be valid for writes of cap bytes which need not be aligned and
+    /// not otherwise accessed for the lifetime 'a.
+    #[inline]
+    pub unsafe fn from_parts(
+        ptr: *mut libc::sockaddr,
+        cap: libc::socklen_t,
+    ) -> Result<Self, YSockAddrError> {
+        if ptr.is_null() {
+            return Err(YSockAddrError::Null);
+        }
+        Ok(Self {
+            ptr,
+            cap,
+            _borrow: PhantomData,
+        })
+    }
+    /// Len of the storage
+    #[inline]
+    pub fn capacity(&self) -> libc::socklen_t {
+        self.cap
+    }
+    /// Pointer to the storage
+    #[inline]
+    pub fn as_mut_ptr(&mut self) -> *mut libc::sockaddr {
+        self.ptr
+    }
+    /// Copy the sockaddr into the storage returning its len, one beyond the capacity being
+    /// [`YSockAddrError::Truncated`] without writing anything.
+    #[inline]
+    pub fn write(&mut self, sa: &YSockAddrC) -> Result<libc::socklen_t, YSockAddrError> {
+        let (src, len) = sa.as_c_sockaddr_len();
+        if len > self.cap {
+            return Err(YSockAddrError::Truncated { len, max: self.cap });
+        }
+        // SAFETY: from_parts guarantees cap bytes at ptr which cover len and sa is ours.
+        unsafe {
+            core::ptr::copy_nonoverlapping(src as *const u8, self.ptr as *mut u8, len as usize)
+        };
+        Ok(len)
+    }
+}
+
+impl<'a> From<&'a mut libc::sockaddr_storage> for YSockAddrMut<'a> {
+    #[inline]
+    fn from(storage: &'a mut libc::sockaddr_storage) -> Self {
+        Self {
+            ptr: core::ptr::addr_of_mut!(*storage) as *mut libc::sockaddr,
+            cap: size_of::<libc::sockaddr_storage>() as libc::socklen_t,
+            _borrow: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use rstest::rstest;
+    use std::net::SocketAddr;
+
+    fn ip(sa: &str) -> YSockAddrC {
+        YSockAddrC::from(sa.parse::<SocketAddr>().unwrap())
+    }
+
+    fn c_bytes(c: &YSockAddrC) -> Vec<u8> {
+        let (ptr, len) = c.as_c_sockaddr_len();
+        unsafe { core::slice::from_raw_parts(ptr as *const u8, len as usize) }.to_vec()
+    }
+
+    #[rstest]
+    #[case(ip("127.0.0.1:80"))]
+    #[case(ip("[fe80::1%2]:443"))]
+    #[case(YSockAddrC::vsock(3, 1024))]
+    #[case(YSockAddrC::netlink(7, 0x11))]
+    #[case(YSockAddrC::packet_send_addr(1, 0x0800, [2, 0, 0, 0, 0, 1]))]
+    fn parts_round_trip(#[case] c: YSockAddrC) {
+        let bytes = c_bytes(&c);
+        // One byte in so the view is unaligned.
+        let mut buf = vec![0u8; bytes.len() + 1];
+        buf[1..].copy_from_slice(&bytes);
+        let raw = buf[1..].as_ptr() as *const libc::sockaddr;
+        let view =
+            unsafe { YSockAddrRef::from_parts(raw, bytes.len() as libc::socklen_t) }.unwrap();
+        assert_eq!(view.family(), c.family());
+        assert_eq!(view.len() as usize, bytes.len());
+        let owned = view.to_owned().unwrap();
+        drop(buf);
+        assert_eq!(c_bytes(&owned), bytes);
+
+        let view = YSockAddrRef::from(&c);
+        assert_eq!(view.as_ptr(), c.as_c_sockaddr_len().0);
+        assert_eq!(c_bytes(&view.to_owned().unwrap()), bytes);
+    }
+
+    #[rstest]
+    #[case(ip("127.0.0.1:80"), 16)]
+    #[case(ip("[::1]:80"), 28)]
+    #[case(YSockAddrC::vsock(3, 1024), 16)]
+    #[case(YSockAddrC::netlink(7, 0x11), 12)]
+    #[case(YSockAddrC::packet_send_addr(1, 0x0800, [2, 0, 0, 0, 0, 1]), 20)]
+    fn oversized_len_clamped(#[case] c: YSockAddrC, #[case] len: libc::socklen_t) {
+        let mut storage: libc::sockaddr_storage = unsafe { core::mem::zeroed() };
+        YSockAddrMut::from(&mut storage).write(&c).unwrap();
+        let raw = core::ptr::addr_of!(storage) as *const libc::sockaddr;
+        let all = size_of::<libc::sockaddr_storage>() as libc::socklen_t;
+        let view = unsafe { YSockAddrRef::from_parts(raw, all) }.unwrap();
+        assert_eq!(view.len(), all);
+        let owned = view.to_owned().unwrap();
+        assert_eq!(owned.as_c_sockaddr_len().1, len);
+        assert_eq!(c_bytes(&owned), c_bytes(&c));
+    }
+
+    #[rstest]
+    #[case(libc::AF_UNIX, 110, YSockAddrError::UnsupportedFamily(libc::AF_UNIX as u16))]
+    #[case(libc::AF_INET, 15, YSockAddrError::TooShort { len: 15, expected: 16 })]
+    #[case(libc::AF_PACKET, 11, YSockAddrError::TooShort { len: 11, expected: 12 })]
+    #[case(libc::AF_VSOCK, 1, YSockAddrError::TooShort { len: 1, expected: 2 })]
+    fn parts_rejected(
+        #[case] family: libc::c_int,
+        #[case] len: libc::socklen_t,
+        #[case] expected: YSockAddrError,
+    ) {
+        let mut storage: libc::sockaddr_storage = unsafe { core::mem::zeroed() };
+        storage.ss_family = family as libc::sa_family_t;
+        let raw = core::ptr::addr_of!(storage) as *const libc::sockaddr;
+        assert_eq!(
+            unsafe { YSockAddrRef::from_parts(raw, len) }.unwrap_err(),
+            expected
+        );
+    }
+
+    #[test]
+    fn parts_null() {
+        let r = unsafe { YSockAddrRef::from_parts(core::ptr::null(), 16) };
+        assert_eq!(r.unwrap_err(), YSockAddrError::Null);
+        let r = unsafe { YSockAddrMut::from_parts(core::ptr::null_mut(), 16) };
+        assert_eq!(r.unwrap_err(), YSockAddrError::Null);
+    }
+
+    #[rstest]
+    #[case(ip("10.0.0.1:53"), 16)]
+    #[case(ip("[::1]:53"), 28)]
+    #[case(YSockAddrC::vsock(2, 9), 16)]
+    fn write_into_storage(#[case] c: YSockAddrC, #[case] written: libc::socklen_t) {
+        let mut storage: libc::sockaddr_storage = unsafe { core::mem::zeroed() };
+        let mut out = YSockAddrMut::from(&mut storage);
+        assert_eq!(out.capacity() as usize, size_of::<libc::sockaddr_storage>());
+        assert_eq!(out.write(&c).unwrap(), written);
+        let raw = core::ptr::addr_of!(storage) as *const libc::sockaddr;
+        let back = unsafe { YSockAddrRef::from_parts(raw, written) }.unwrap();
+        assert_eq!(c_bytes(&back.to_owned().unwrap()), c_bytes(&c));
+    }
+
+    #[test]
+    fn write_truncated() {
+        let mut buf = [0u8; 16];
+        let raw = buf.as_mut_ptr() as *mut libc::sockaddr;
+        let mut out = unsafe { YSockAddrMut::from_parts(raw, 16) }.unwrap();
+        assert_eq!(
+            out.write(&ip("[::1]:53")).unwrap_err(),
+            YSockAddrError::Truncated { len: 28, max: 16 }
+        );
+        assert_eq!(out.write(&ip("127.0.0.1:53")).unwrap(), 16);
+        assert_eq!(&buf[2..4], &53u16.to_be_bytes());
+    }
+}
@@ -0,0 +1,356 @@
+//! getsockname(2) and getpeername(2) wrappers
+
+use crate::{YSockAddrC, YSockAddrError, YSockAddrR, YSockAddrRef, YSockAddrStorage, YUnixAddr};
+use std::io;
+use yown_fd::{AsRawFd, BorrowedFd, RawFd};
+
+type NameFn =
+    unsafe extern "C" fn(libc::c_int, *mut libc::sockaddr, *mut libc::socklen_t) -> libc::c_int;
+
+impl YSockAddrC {
+    /// Convert the sockaddr of len bytes at ptr e.g. filled by the kernel into the given
+    /// family validating the length against it.
+    ///
+    /// Families other than AF_INET, AF_INET6, AF_VSOCK, AF_NETLINK and AF_PACKET are
+    /// [`YSockAddrError::UnsupportedFamily`] and a len short of the family's sockaddr
+    /// [`YSockAddrError::TooShort`]. AF_PACKET only needs to cover the hardware address len
+    /// as the kernel gives it. The len kept is clamped to the family's sockaddr e.g. given
+    /// all of a sockaddr_storage.
+    ///
+    /// # Safety
+    ///
+    /// Unless null ptr has to be valid for reads of len bytes which need not be aligned.
+    #[inline]
+    pub unsafe fn from_raw(
+        ptr: *const libc::sockaddr,
+        len: libc::socklen_t,
+    ) -> Result<Self, YSockAddrError> {
+        // SAFETY: The caller guarantees len bytes at ptr.
+        unsafe { YSockAddrRef::from_parts(ptr, len) }?.to_owned()
+    }
+    /// Like [`Self::from_raw`] over the bytes of a sockaddr e.g. off the wire.
+    #[inline]
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, YSockAddrError> {
+        // Beyond any family's sockaddr so reading less of a huge slice converts the same.
+        let len = libc::socklen_t::try_from(bytes.len()).unwrap_or(libc::socklen_t::MAX);
+        // SAFETY: bytes is valid for reads of its len and from_raw reads unaligned.
+        unsafe { Self::from_raw(bytes.as_ptr().cast(), len) }
+    }
+}
+
+#[inline]
+fn storage_of(fd: RawFd, f: NameFn) -> io::Result<(libc::sockaddr_storage, libc::socklen_t)> {
+    // SAFETY: sockaddr_storage is valid when zeroed.
+    let mut storage: libc::sockaddr_storage = unsafe { core::mem::zeroed() };
+    let mut len = size_of::<libc::sockaddr_storage>() as libc::socklen_t;
+    // SAFETY: storage and len are valid for the duration of the call.
+    let r = unsafe {
+        f(
+            fd,
+            core::ptr::addr_of_mut!(storage) as *mut libc::sockaddr,
+            &mut len,
+        )
+    };
+    if r != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok((storage, len))
+}
+
+#[inline]
+fn name_of(fd: RawFd, f: NameFn) -> io::Result<YSockAddrC> {
+    let (storage, len) = storage_of(fd, f)?;
+    // SAFETY: The kernel filled len bytes of storage.
+    unsafe { YSockAddrC::from_raw(core::ptr::addr_of!(storage).cast(), len) }.map_err(|e| match e {
+        YSockAddrError::UnsupportedFamily(_) => io::Error::from_raw_os_error(libc::EAFNOSUPPORT),
+        _ => io::Error::from_raw_os_error(libc::EINVAL),
+    })
+}
+
+#[inline]
+fn unix_name_of(fd: RawFd, f: NameFn) -> io::Result<YUnixAddr> {
+    let (storage, len) = storage_of(fd, f)?;
+    // SAFETY: sockaddr_storage is large and aligned enough for any and from_c checks the family.
+    let sun = unsafe { &*(core::ptr::addr_of!(storage) as *const libc::sockaddr_un) };
+    YUnixAddr::from_c(sun, len)
+}
+
+#[inline]
+fn addr_of(fd: BorrowedFd<'_>, f: NameFn) -> Result<YSockAddrR, YSockAddrError> {
+    let mut storage = YSockAddrStorage::new();
+    let (ptr, len) = storage.as_mut_parts();
+    // SAFETY: ptr and len are into storage which outlives the call.
+    if unsafe { f(fd.as_raw_fd(), ptr, len) } != 0 {
+        return Err(match io::Error::last_os_error().raw_os_error() {
+            Some(libc::ENOTCONN) => YSockAddrError::NotConnected,
+            errno => YSockAddrError::Os(errno.unwrap_or(libc::EINVAL)),
+        });
+    }
+    storage.finish()
+}
+
+/// Local IP address the socket is bound to through getsockname(2) into a
+/// [`YSockAddrStorage`].
+/// Families other than AF_INET and AF_INET6 are [`YSockAddrError::UnsupportedFamily`].
+#[inline]
+pub fn local_addr(fd: BorrowedFd<'_>) -> Result<YSockAddrR, YSockAddrError> {
+    addr_of(fd, libc::getsockname)
+}
+
+/// Peer IP address the socket is connected to through getpeername(2) into a
+/// [`YSockAddrStorage`].
+/// Unconnected sockets are [`YSockAddrError::NotConnected`] and families other than AF_INET
+/// and AF_INET6 [`YSockAddrError::UnsupportedFamily`].
+#[inline]
+pub fn peer_addr(fd: BorrowedFd<'_>) -> Result<YSockAddrR, YSockAddrError> {
+    addr_of(fd, libc::getpeername)
+}
+
+/// Local address the socket is bound to through getsockname(2).
+/// Families other than AF_INET, AF_INET6, AF_VSOCK, AF_NETLINK and AF_PACKET are Err with EAFNOSUPPORT.
+#[inline]
+pub fn getsockname(fd: RawFd) -> io::Result<YSockAddrC> {
+    name_of(fd, libc::getsockname)
+}
+
+/// Peer address the socket is connected to through getpeername(2).
+/// Families other than AF_INET, AF_INET6, AF_VSOCK, AF_NETLINK and AF_PACKET are Err with EAFNOSUPPORT.
+#[inline]
+pub fn getpeername(fd: RawFd) -> io::Result<YSockAddrC> {
+    name_of(fd, libc::getpeername)
+}
+
+/// Local AF_UNIX address the socket is bound to through getsockname(2).
+/// Other families are Err with EAFNOSUPPORT.
+#[inline]
+pub fn getsockname_unix(fd: RawFd) -> io::Result<YUnixAddr> {
+    unix_name_of(fd, libc::getsockname)
+}
+
+/// Peer AF_UNIX address the socket is connected to through getpeername(2).
+/// Other families are Err with EAFNOSUPPORT.
+#[inline]
+pub fn getpeername_unix(fd: RawFd) -> io::Result<YUnixAddr> {
+    unix_name_of(fd, libc::getpeername)
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use crate::YSockAddrR;
+    use rstest::rstest;
+    use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream, UdpSocket};
+    use std::os::unix::net::UnixDatagram;
+    use yown_fd::{AsFd, AsRawFd, FromRawFd, OwnedFd};
+
+    #[rstest]
+    #[case("127.0.0.1:0", libc::AF_INET)]
+    #[case("[::1]:0", libc::AF_INET6)]
+    fn bind_as_c_sockaddr_len(#[case] sa: &str, #[case] domain: libc::c_int) {
+        let sa: SocketAddr = sa.parse().unwrap();
+        let fd = unsafe { libc::socket(domain, libc::SOCK_DGRAM | libc::SOCK_CLOEXEC, 0) };
+        assert!(fd >= 0);
+        let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+        let c = YSockAddrC::from(sa);
+        let (ptr, len) = c.as_c_sockaddr_len();
+        let r = unsafe { libc::bind(fd.as_raw_fd(), ptr, len) };
+        assert_eq!(r, 0, "{}", io::Error::last_os_error());
+        let bound = getsockname(fd.as_raw_fd()).unwrap();
+        assert_eq!(bound.family(), c.family());
+        assert_ne!(bound.port(), 0);
+        let bound = YSockAddrR::try_from(bound).unwrap().as_sockaddr();
+        assert_eq!(bound.ip(), sa.ip());
+    }
+
+    #[test]
+    fn tcp_sockname_peername() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let local = listener.local_addr().unwrap();
+        let c = getsockname(listener.as_raw_fd()).unwrap();
+        assert!(c.is_ipv4());
+        assert_eq!(c.port(), local.port());
+        match c {
+            YSockAddrC::V4(sa4_in, _) => {
+                assert_eq!(sa4_in.sin_addr.s_addr.to_ne_bytes(), [127, 0, 0, 1])
+            }
+            _ => panic!("Expected V4"),
+        }
+
+        let stream = TcpStream::connect(local).unwrap();
+        let peer = getpeername(stream.as_raw_fd()).unwrap();
+        assert_eq!(peer.port(), local.port());
+    }
+
+    #[test]
+    fn unsupported_family() {
+        let (a, _b) = UnixDatagram::pair().unwrap();
+        let err = getsockname(a.as_raw_fd()).unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(libc::EAFNOSUPPORT));
+        let err = getpeername(a.as_raw_fd()).unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(libc::EAFNOSUPPORT));
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let err = getsockname_unix(listener.as_raw_fd()).unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(libc::EAFNOSUPPORT));
+    }
+
+    // Bytes of the sockaddr in the given C form cut to len.
+    fn c_bytes(c: &YSockAddrC, len: usize) -> Vec<u8> {
+        let (ptr, c_len) = c.as_c_sockaddr_len();
+        let bytes = unsafe { core::slice::from_raw_parts(ptr as *const u8, c_len as usize) };
+        bytes[..len].to_vec()
+    }
+
+    #[rstest]
+    #[case(YSockAddrC::vsock(3, 1024), 16, None)]
+    #[case(YSockAddrC::vsock(3, 1024), 15, Some(YSockAddrError::TooShort { len: 15, expected: 16 }))]
+    #[case(YSockAddrC::from("127.0.0.1:80".parse::<SocketAddr>().unwrap()), 16, None)]
+    #[case(YSockAddrC::from("127.0.0.1:80".parse::<SocketAddr>().unwrap()), 8, Some(YSockAddrError::TooShort { len: 8, expected: 16 }))]
+    #[case(YSockAddrC::from("[::1]:80".parse::<SocketAddr>().unwrap()), 28, None)]
+    #[case(YSockAddrC::from("[::1]:80".parse::<SocketAddr>().unwrap()), 16, Some(YSockAddrError::TooShort { len: 16, expected: 28 }))]
+    #[case(YSockAddrC::vsock(3, 1024), 1, Some(YSockAddrError::TooShort { len: 1, expected: 2 }))]
+    #[case(YSockAddrC::netlink(7, 0x11), 12, None)]
+    #[case(YSockAddrC::netlink(7, 0x11), 11, Some(YSockAddrError::TooShort { len: 11, expected: 12 }))]
+    #[case(YSockAddrC::packet_send_addr(1, 0x0800, [2, 0, 0, 0, 0, 1]), 18, None)]
+    #[case(YSockAddrC::packet_send_addr(1, 0x0800, [2, 0, 0, 0, 0, 1]), 11, Some(YSockAddrError::TooShort { len: 11, expected: 12 }))]
+    fn from_bytes_validated(
+        #[case] c: YSockAddrC,
+        #[case] len: usize,
+        #[case] expected: Option<YSockAddrError>,
+    ) {
+        match YSockAddrC::from_bytes(&c_bytes(&c, len)) {
+            Ok(parsed) => {
+                assert_eq!(expected, None);
+                assert_eq!(parsed.family(), c.family());
+                assert_eq!(c_bytes(&parsed, len), c_bytes(&c, len));
+            }
+            Err(e) => assert_eq!(Some(e), expected),
+        }
+    }
+
+    #[rstest]
+    #[case(YSockAddrC::from("127.0.0.1:80".parse::<SocketAddr>().unwrap()), 16)]
+    #[case(YSockAddrC::from("[::1]:80".parse::<SocketAddr>().unwrap()), 28)]
+    #[case(YSockAddrC::vsock(3, 1024), 16)]
+    #[case(YSockAddrC::netlink(7, 0x11), 12)]
+    #[case(YSockAddrC::packet_send_addr(1, 0x0800, [2, 0, 0, 0, 0, 1]), 20)]
+    fn from_bytes_oversized_clamped(#[case] c: YSockAddrC, #[case] len: usize) {
+        let mut bytes = c_bytes(&c, len);
+        bytes.resize(size_of::<libc::sockaddr_storage>(), 0);
+        let parsed = YSockAddrC::from_bytes(&bytes).unwrap();
+        assert_eq!(parsed.as_c_sockaddr_len().1 as usize, len);
+        assert_eq!(c_bytes(&parsed, len), c_bytes(&c, len));
+    }
+
+    #[test]
+    fn from_bytes_unsupported_family() {
+        let mut bytes = c_bytes(&YSockAddrC::vsock(3, 1024), 16);
+        bytes[..2].copy_from_slice(&(libc::AF_UNIX as libc::sa_family_t).to_ne_bytes());
+        let err = YSockAddrC::from_bytes(&bytes).unwrap_err();
+        assert_eq!(
+            err,
+            YSockAddrError::UnsupportedFamily(libc::AF_UNIX as libc::sa_family_t)
+        );
+    }
+
+    #[test]
+    fn vsock_sockname() {
+        let fd = unsafe { libc::socket(libc::AF_VSOCK, libc::SOCK_STREAM | libc::SOCK_CLOEXEC, 0) };
+        // Kernels without vsock have nothing to test.
+        if fd < 0 {
+            return;
+        }
+        let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+        let c = YSockAddrC::vsock(crate::VMADDR_CID_ANY, crate::VMADDR_PORT_ANY);
+        let (ptr, len) = c.as_c_sockaddr_len();
+        assert_eq!(unsafe { libc::bind(fd.as_raw_fd(), ptr, len) }, 0);
+        let bound = crate::VsockAddr::try_from(getsockname(fd.as_raw_fd()).unwrap()).unwrap();
+        assert_eq!(bound.cid, crate::VMADDR_CID_ANY);
+        assert_ne!(bound.port, crate::VMADDR_PORT_ANY);
+    }
+
+    #[test]
+    fn netlink_sockname() {
+        let fd = unsafe {
+            libc::socket(
+                libc::AF_NETLINK,
+                libc::SOCK_RAW | libc::SOCK_CLOEXEC,
+                libc::NETLINK_ROUTE,
+            )
+        };
+        assert!(fd >= 0, "{}", io::Error::last_os_error());
+        let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+        let groups = crate::NetlinkGroups::new()
+            .join(libc::RTNLGRP_LINK)
+            .unwrap()
+            .join(libc::RTNLGRP_IPV4_IFADDR)
+            .unwrap();
+        // pid 0 has the kernel assign the port ID.
+        let c = YSockAddrC::netlink(0, groups);
+        let (ptr, len) = c.as_c_sockaddr_len();
+        assert_eq!(unsafe { libc::bind(fd.as_raw_fd(), ptr, len) }, 0);
+        let bound = crate::NetlinkAddr::try_from(getsockname(fd.as_raw_fd()).unwrap()).unwrap();
+        assert_ne!(bound.pid, 0);
+        assert_eq!(bound.groups, groups);
+    }
+
+    #[test]
+    fn packet_sockname() {
+        let protocol = (libc::ETH_P_ALL as u16).to_be() as libc::c_int;
+        let fd = unsafe {
+            libc::socket(
+                libc::AF_PACKET,
+                libc::SOCK_RAW | libc::SOCK_CLOEXEC,
+                protocol,
+            )
+        };
+        // Raw sockets need CAP_NET_RAW.
+        if fd < 0 {
+            return;
+        }
+        let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+        let c = YSockAddrC::packet_bind_addr("lo", libc::ETH_P_IP as u16).unwrap();
+        let (ptr, len) = c.as_c_sockaddr_len();
+        assert_eq!(unsafe { libc::bind(fd.as_raw_fd(), ptr, len) }, 0);
+        let bound = crate::PacketAddr::try_from(getsockname(fd.as_raw_fd()).unwrap()).unwrap();
+        assert_eq!(
+            bound.ifindex,
+            unsafe { libc::if_nametoindex(c"lo".as_ptr()) } as libc::c_int
+        );
+        assert_eq!(bound.protocol, libc::ETH_P_IP as u16);
+        assert_eq!(bound.hatype, libc::ARPHRD_LOOPBACK);
+        assert_eq!(bound.mac(), Some([0; 6]));
+    }
+
+    #[test]
+    fn udp_local_peer_addr() {
+        let a = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let local = local_addr(a.as_fd()).unwrap().as_sockaddr();
+        assert_ne!(local.port(), 0);
+        assert_eq!(local.ip(), Ipv4Addr::LOCALHOST);
+        assert_eq!(local, a.local_addr().unwrap());
+        assert_eq!(
+            peer_addr(a.as_fd()).unwrap_err(),
+            YSockAddrError::NotConnected
+        );
+
+        let b = UdpSocket::bind("[::1]:0").unwrap();
+        let c = UdpSocket::bind("[::1]:0").unwrap();
+        b.connect(c.local_addr().unwrap()).unwrap();
+        let peer = peer_addr(b.as_fd()).unwrap().as_sockaddr();
+        assert_eq!(peer, c.local_addr().unwrap());
+    }
+
+    #[test]
+    fn local_addr_typed_errors() {
+        let (a, _b) = UnixDatagram::pair().unwrap();
+        assert_eq!(
+            local_addr(a.as_fd()).unwrap_err(),
+            YSockAddrError::UnsupportedFamily(libc::AF_UNIX as libc::sa_family_t)
+        );
+        let file = std::fs::File::open("/proc/self/stat").unwrap();
+        let err = local_addr(file.as_fd()).unwrap_err();
+        assert_eq!(err, YSockAddrError::Os(libc::ENOTSOCK));
+        assert_eq!(io::Error::from(err).raw_os_error(), Some(libc::ENOTSOCK));
+    }
+}
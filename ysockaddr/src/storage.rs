@@ -0,0 +1,294 @@
+//! sockaddr_storage out-parameter of accept(2), recvfrom(2) and getpeername(2)
+
+use crate::{YSockAddrR, YSockAddrRef};
+
+/// Error getting or converting a kernel filled [`YSockAddrStorage`] or raw sockaddr
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum YSockAddrError {
+    /// The raw sockaddr pointer is null
+    Null,
+    /// ENOTCONN asking for the peer of an unconnected socket
+    NotConnected,
+    /// Any other errno of the syscall e.g. ENOTSOCK or EBADF
+    Os(libc::c_int),
+    /// The address len exceeds the storage it is read from or written into e.g. the kernel
+    /// reporting a len beyond sockaddr_storage as the address was truncated
+    Truncated {
+        /// Len the whole address needs
+        len: libc::socklen_t,
+        /// Len of the storage
+        max: libc::socklen_t,
+    },
+    /// The len does not cover the sockaddr of the family
+    TooShort {
+        /// Len given e.g. written back by the kernel
+        len: libc::socklen_t,
+        /// Len the family needs
+        expected: libc::socklen_t,
+    },
+    /// Family the conversion does not support e.g. other than AF_INET or AF_INET6 into
+    /// [`YSockAddrR`]
+    UnsupportedFamily(libc::sa_family_t),
+}
+
+impl core::fmt::Display for YSockAddrError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Null => write!(f, "Socket address pointer is null"),
+            Self::NotConnected => write!(f, "Socket is not connected"),
+            Self::Os(errno) => write!(f, "Socket address syscall failed with errno {}", errno),
+            Self::Truncated { len, max } => write!(
+                f,
+                "Socket address of len {} truncated to storage len {}",
+                len, max
+            ),
+            Self::TooShort { len, expected } => write!(
+                f,
+                "Socket address len {} is short of the family len {}",
+                len, expected
+            ),
+            Self::UnsupportedFamily(family) => {
+                write!(f, "Socket address family {} is not supported", family)
+            }
+        }
+    }
+}
+
+impl core::error::Error for YSockAddrError {}
+
+#[cfg(feature = "std")]
+impl From<YSockAddrError> for std::io::Error {
+    #[inline]
+    fn from(e: YSockAddrError) -> std::io::Error {
+        match e {
+            YSockAddrError::NotConnected => std::io::Error::from_raw_os_error(libc::ENOTCONN),
+            YSockAddrError::Os(errno) => std::io::Error::from_raw_os_error(errno),
+            _ => std::io::Error::new(std::io::ErrorKind::InvalidData, e),
+        }
+    }
+}
+
+/// Zeroed sockaddr_storage with its in/out len for the kernel to write any family into
+#[derive(Clone)]
+pub struct YSockAddrStorage {
+    storage: libc::sockaddr_storage,
+    len: libc::socklen_t,
+}
+
+const STORAGE_LEN: libc::socklen_t = size_of::<libc::sockaddr_storage>() as libc::socklen_t;
+
+impl Default for YSockAddrStorage {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl core::fmt::Debug for YSockAddrStorage {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("YSockAddrStorage")
+            .field("family", &self.storage.ss_family)
+            .field("len", &self.len)
+            .finish()
+    }
+}
+
+impl YSockAddrStorage {
+    /// Zeroed storage with the len set to all of it
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            // SAFETY: sockaddr_storage is valid when zeroed.
+            storage: unsafe { core::mem::zeroed() },
+            len: STORAGE_LEN,
+        }
+    }
+    /// Pointers to the storage and its len to pass to the syscall e.g. accept(2).
+    ///
+    /// The len is reset to all of the storage so the same value can be passed again. The
+    /// pointers are into self and only valid while self is borrowed and not moved.
+    #[inline]
+    pub fn as_mut_parts(&mut self) -> (*mut libc::sockaddr, *mut libc::socklen_t) {
+        self.len = STORAGE_LEN;
+        (
+            core::ptr::addr_of_mut!(self.storage) as *mut libc::sockaddr,
+            &mut self.len,
+        )
+    }
+    /// Len currently set e.g. as written back by the kernel
+    #[inline]
+    pub fn len(&self) -> libc::socklen_t {
+        self.len
+    }
+    /// Is the len written back zero e.g. recvfrom(2) on a connected socket
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    /// Convert the kernel filled address validating the family and the len written back
+    /// against it through [`YSockAddrR::try_from_raw`] including a len beyond the storage of
+    /// a truncated address.
+    #[inline]
+    pub fn finish(self) -> Result<YSockAddrR, YSockAddrError> {
+        if self.len > STORAGE_LEN {
+            return Err(YSockAddrError::Truncated {
+                len: self.len,
+                max: STORAGE_LEN,
+            });
+        }
+        // SAFETY: The storage covers len bytes.
+        unsafe { YSockAddrR::try_from_raw(core::ptr::addr_of!(self.storage).cast(), self.len) }
+    }
+}
+
+impl YSockAddrR {
+    /// Copy the sockaddr of len bytes at sa e.g. handed to an FFI callback out validating
+    /// the family and the len against it, the pointer not being retained.
+    ///
+    /// Null is [`YSockAddrError::Null`], families other than AF_INET and AF_INET6
+    /// [`YSockAddrError::UnsupportedFamily`] and a len short of the family's sockaddr
+    /// [`YSockAddrError::TooShort`].
+    ///
+    /// # Safety
+    ///
+    /// Unless null sa has to be valid for reads of len bytes which need not be aligned.
+    #[inline]
+    pub unsafe fn try_from_raw(
+        sa: *const libc::sockaddr,
+        len: libc::socklen_t,
+    ) -> Result<Self, YSockAddrError> {
+        // SAFETY: The caller guarantees len bytes at sa.
+        let c = unsafe { YSockAddrRef::from_parts(sa, len) }?.to_owned()?;
+        YSockAddrR::try_from(c).map_err(|c| YSockAddrError::UnsupportedFamily(c.family()))
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+
+    use super::*;
+    use crate::YSockAddrC;
+    use rstest::rstest;
+    use std::net::{SocketAddr, TcpListener, TcpStream, UdpSocket};
+    use std::os::unix::net::UnixDatagram;
+    use yown_fd::AsRawFd;
+
+    #[rstest]
+    #[case("127.0.0.1:0")]
+    #[case("[::1]:0")]
+    fn accept_peer(#[case] sa: &str) {
+        let listener = TcpListener::bind(sa).unwrap();
+        let stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let mut storage = YSockAddrStorage::new();
+        let (ptr, len) = storage.as_mut_parts();
+        let fd = unsafe { libc::accept4(listener.as_raw_fd(), ptr, len, libc::SOCK_CLOEXEC) };
+        assert!(fd >= 0, "{}", std::io::Error::last_os_error());
+        unsafe { libc::close(fd) };
+        let peer = storage.finish().unwrap().as_sockaddr();
+        assert_eq!(peer, stream.local_addr().unwrap());
+    }
+
+    #[test]
+    fn recvfrom_peer() {
+        let a = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let b = UdpSocket::bind("127.0.0.1:0").unwrap();
+        b.send_to(b"y", a.local_addr().unwrap()).unwrap();
+        let mut storage = YSockAddrStorage::default();
+        let mut buf = [0u8; 4];
+        let (ptr, len) = storage.as_mut_parts();
+        let r = unsafe { libc::recvfrom(a.as_raw_fd(), buf.as_mut_ptr().cast(), 4, 0, ptr, len) };
+        assert_eq!(r, 1);
+        assert_eq!(storage.len() as usize, size_of::<libc::sockaddr_in>());
+        let from: SocketAddr = storage.finish().unwrap().as_sockaddr();
+        assert_eq!(from, b.local_addr().unwrap());
+    }
+
+    #[test]
+    fn unix_peer_unsupported() {
+        let (a, _b) = UnixDatagram::pair().unwrap();
+        let mut storage = YSockAddrStorage::new();
+        let (ptr, len) = storage.as_mut_parts();
+        assert_eq!(unsafe { libc::getpeername(a.as_raw_fd(), ptr, len) }, 0);
+        assert_eq!(
+            storage.finish().unwrap_err(),
+            YSockAddrError::UnsupportedFamily(libc::AF_UNIX as libc::sa_family_t)
+        );
+    }
+
+    #[rstest]
+    #[case(libc::AF_INET, STORAGE_LEN + 1, YSockAddrError::Truncated { len: STORAGE_LEN + 1, max: STORAGE_LEN })]
+    #[case(libc::AF_INET, 8, YSockAddrError::TooShort { len: 8, expected: 16 })]
+    #[case(libc::AF_INET6, 16, YSockAddrError::TooShort { len: 16, expected: 28 })]
+    #[case(libc::AF_UNSPEC, 0, YSockAddrError::TooShort { len: 0, expected: 2 })]
+    #[case(libc::AF_UNSPEC, 2, YSockAddrError::UnsupportedFamily(0))]
+    fn finish_rejected(
+        #[case] family: libc::c_int,
+        #[case] written: libc::socklen_t,
+        #[case] expected: YSockAddrError,
+    ) {
+        let mut storage = YSockAddrStorage::new();
+        let (ptr, len) = storage.as_mut_parts();
+        unsafe {
+            (*ptr).sa_family = family as libc::sa_family_t;
+            *len = written;
+        }
+        assert_eq!(storage.finish().unwrap_err(), expected);
+    }
+
+    #[rstest]
+    #[case("10.1.2.3:53")]
+    #[case("[fe80::1%3]:853")]
+    fn raw_unaligned_round_trip(#[case] sa: &str) {
+        let sa: SocketAddr = sa.parse().unwrap();
+        let c = YSockAddrC::from(sa);
+        let (ptr, len) = c.as_c_sockaddr_len();
+        let bytes = unsafe { core::slice::from_raw_parts(ptr as *const u8, len as usize) };
+        // One byte in so the copy is unaligned and longer than the family needs.
+        let mut buf = vec![0u8; 1 + bytes.len() + 8];
+        buf[1..=bytes.len()].copy_from_slice(bytes);
+        let raw = buf[1..].as_ptr() as *const libc::sockaddr;
+        let r = unsafe { YSockAddrR::try_from_raw(raw, buf.len() as libc::socklen_t - 1) };
+        drop(buf);
+        assert_eq!(r.unwrap().as_sockaddr(), sa);
+    }
+
+    #[rstest]
+    #[case("10.1.2.3:53", 16)]
+    #[case("[fe80::1%3]:853", 28)]
+    fn raw_oversized_clamped(#[case] sa: &str, #[case] len: libc::socklen_t) {
+        let sa: SocketAddr = sa.parse().unwrap();
+        let mut storage: libc::sockaddr_storage = unsafe { core::mem::zeroed() };
+        crate::YSockAddrMut::from(&mut storage)
+            .write(&YSockAddrC::from(sa))
+            .unwrap();
+        let raw = core::ptr::addr_of!(storage) as *const libc::sockaddr;
+        let r = unsafe { YSockAddrR::try_from_raw(raw, STORAGE_LEN) }.unwrap();
+        assert_eq!(r.as_sockaddr(), sa);
+        assert_eq!(YSockAddrC::from(r.as_sockaddr()).as_c_sockaddr_len().1, len);
+    }
+
+    #[rstest]
+    #[case(libc::AF_UNIX, 110, YSockAddrError::UnsupportedFamily(libc::AF_UNIX as u16))]
+    #[case(libc::AF_PACKET, 20, YSockAddrError::UnsupportedFamily(libc::AF_PACKET as u16))]
+    #[case(libc::AF_INET6, 27, YSockAddrError::TooShort { len: 27, expected: 28 })]
+    #[case(libc::AF_INET, 1, YSockAddrError::TooShort { len: 1, expected: 2 })]
+    fn raw_rejected(
+        #[case] family: libc::c_int,
+        #[case] len: libc::socklen_t,
+        #[case] expected: YSockAddrError,
+    ) {
+        let mut storage: libc::sockaddr_storage = unsafe { core::mem::zeroed() };
+        storage.ss_family = family as libc::sa_family_t;
+        let raw = core::ptr::addr_of!(storage) as *const libc::sockaddr;
+        assert_eq!(
+            unsafe { YSockAddrR::try_from_raw(raw, len) }.unwrap_err(),
+            expected
+        );
+    }
+
+    #[test]
+    fn raw_null() {
+        let r = unsafe { YSockAddrR::try_from_raw(core::ptr::null(), 16) };
+        assert_eq!(r.unwrap_err(), YSockAddrError::Null);
+    }
+}
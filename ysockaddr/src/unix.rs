@@ -0,0 +1,318 @@
+//! AF_UNIX sockaddr_un in its pathname, abstract and unnamed forms
+
+use std::ffi::OsStr;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+
+/// Capacity of sun_path including the NUL terminator of a pathname
+pub const SUN_PATH_LEN: usize = 108;
+
+// sun_path follows sun_family in sockaddr_un.
+const SUN_PATH_OFFSET: usize = core::mem::offset_of!(libc::sockaddr_un, sun_path);
+
+/// Error constructing a [`YUnixAddr`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UnixAddrError {
+    /// The pathname or abstract name does not fit sun_path along its leading or trailing NUL
+    TooLong {
+        /// Len of the given pathname or abstract name
+        len: usize,
+        /// Maximum len of the given form
+        max: usize,
+    },
+    /// Pathname is empty or contains a NUL which would truncate it
+    InvalidPath,
+}
+
+impl core::fmt::Display for UnixAddrError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::TooLong { len, max } => {
+                write!(f, "Unix address of len {} exceeds max len {}", len, max)
+            }
+            Self::InvalidPath => write!(f, "Unix pathname is empty or contains a NUL"),
+        }
+    }
+}
+
+impl core::error::Error for UnixAddrError {}
+
+impl From<UnixAddrError> for std::io::Error {
+    #[inline]
+    fn from(e: UnixAddrError) -> std::io::Error {
+        std::io::Error::new(std::io::ErrorKind::InvalidInput, e)
+    }
+}
+
+/// Rust form of a [`YUnixAddr`] e.g. for logging through Display
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UnixAddrKind {
+    /// Not bound e.g. either end of a socketpair(2)
+    Unnamed,
+    /// Bound to a path in the filesystem
+    Pathname(PathBuf),
+    /// Bound to a name in the abstract namespace without the leading NUL where any NULs
+    /// are significant
+    Abstract(Vec<u8>),
+}
+
+impl core::fmt::Display for UnixAddrKind {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Unnamed => write!(f, "(unnamed)"),
+            Self::Pathname(path) => write!(f, "{}", path.display()),
+            // Like ss(8) with @ standing for the leading NUL.
+            Self::Abstract(name) => write!(f, "@{}", name.escape_ascii()),
+        }
+    }
+}
+
+/// C/FFI AF_UNIX sockaddr_un with the socklen covering only the used part of sun_path
+#[derive(Clone)]
+pub struct YUnixAddr {
+    sun: libc::sockaddr_un,
+    len: libc::socklen_t,
+}
+
+impl core::fmt::Debug for YUnixAddr {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("YUnixAddr")
+            .field("kind", &self.kind())
+            .field("len", &self.len)
+            .finish()
+    }
+}
+
+impl YUnixAddr {
+    #[inline]
+    fn with_path(bytes: &[u8], extra: usize) -> Self {
+        // SAFETY: sockaddr_un is valid when zeroed.
+        let mut sun: libc::sockaddr_un = unsafe { core::mem::zeroed() };
+        sun.sun_family = libc::AF_UNIX as libc::sa_family_t;
+        for (dst, src) in sun.sun_path[extra..].iter_mut().zip(bytes) {
+            *dst = *src as libc::c_char;
+        }
+        let len = (SUN_PATH_OFFSET + extra + bytes.len()) as libc::socklen_t;
+        Self { sun, len }
+    }
+    /// Pathname address with the socklen covering the path and its NUL terminator.
+    ///
+    /// Paths longer than [`SUN_PATH_LEN`] - 1 are [`UnixAddrError::TooLong`], empty ones or
+    /// ones containing a NUL [`UnixAddrError::InvalidPath`].
+    #[inline]
+    pub fn from_path(path: &Path) -> Result<Self, UnixAddrError> {
+        let bytes = path.as_os_str().as_bytes();
+        if bytes.is_empty() || bytes.contains(&0) {
+            return Err(UnixAddrError::InvalidPath);
+        }
+        if bytes.len() >= SUN_PATH_LEN {
+            return Err(UnixAddrError::TooLong {
+                len: bytes.len(),
+                max: SUN_PATH_LEN - 1,
+            });
+        }
+        let mut addr = Self::with_path(bytes, 0);
+        addr.len += 1;
+        Ok(addr)
+    }
+    /// Linux abstract namespace address of name without the leading NUL with the socklen
+    /// covering exactly the name as any NULs within or trailing it are part of the name.
+    ///
+    /// Names longer than [`SUN_PATH_LEN`] - 1 are [`UnixAddrError::TooLong`].
+    #[inline]
+    pub fn abstract_name(name: &[u8]) -> Result<Self, UnixAddrError> {
+        if name.len() >= SUN_PATH_LEN {
+            return Err(UnixAddrError::TooLong {
+                len: name.len(),
+                max: SUN_PATH_LEN - 1,
+            });
+        }
+        Ok(Self::with_path(name, 1))
+    }
+    /// Unnamed address of only the family e.g. to autobind(7) into an abstract name on bind
+    #[inline]
+    pub fn unnamed() -> Self {
+        Self::with_path(&[], 0)
+    }
+    /// Convert the kernel filled sockaddr_un of the given len e.g. from getsockname(2).
+    /// Other families or a len beyond sockaddr_un are Err with EAFNOSUPPORT / EINVAL.
+    #[inline]
+    pub fn from_c(sun: &libc::sockaddr_un, len: libc::socklen_t) -> std::io::Result<Self> {
+        if sun.sun_family as libc::c_int != libc::AF_UNIX {
+            return Err(std::io::Error::from_raw_os_error(libc::EAFNOSUPPORT));
+        }
+        let len_bytes = len as usize;
+        if !(size_of::<libc::sa_family_t>()..=size_of::<libc::sockaddr_un>()).contains(&len_bytes) {
+            return Err(std::io::Error::from_raw_os_error(libc::EINVAL));
+        }
+        Ok(Self { sun: *sun, len })
+    }
+    /// Cast to C/FFI sockaddr + len e.g. for bind(2) or connect(2)
+    ///
+    /// The pointer is into self and only valid while self is borrowed and not moved.
+    #[inline]
+    pub fn as_c_sockaddr_len(&self) -> (*const libc::sockaddr, libc::socklen_t) {
+        (
+            core::ptr::addr_of!(self.sun) as *const libc::sockaddr,
+            self.len,
+        )
+    }
+    // The used part of sun_path as per len.
+    #[inline]
+    fn sun_path(&self) -> &[u8] {
+        let used = (self.len as usize).saturating_sub(SUN_PATH_OFFSET);
+        // SAFETY: c_char and u8 have the same layout and used is within sun_path.
+        unsafe { core::slice::from_raw_parts(self.sun.sun_path.as_ptr().cast(), used) }
+    }
+    /// Which of the forms the address is in
+    #[inline]
+    pub fn kind(&self) -> UnixAddrKind {
+        match self.sun_path() {
+            [] => UnixAddrKind::Unnamed,
+            [0, name @ ..] => UnixAddrKind::Abstract(name.to_vec()),
+            // The kernel may or may not count the NUL terminator of a pathname.
+            path => {
+                let end = path.iter().position(|b| *b == 0).unwrap_or(path.len());
+                UnixAddrKind::Pathname(PathBuf::from(OsStr::from_bytes(&path[..end])))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use crate::{getpeername_unix, getsockname_unix};
+    use rstest::rstest;
+    use std::os::unix::net::{UnixDatagram, UnixListener, UnixStream};
+    use yown_fd::{AsRawFd, FromRawFd, OwnedFd};
+
+    fn unix_socket() -> OwnedFd {
+        let fd = unsafe { libc::socket(libc::AF_UNIX, libc::SOCK_STREAM | libc::SOCK_CLOEXEC, 0) };
+        assert!(fd >= 0);
+        unsafe { OwnedFd::from_raw_fd(fd) }
+    }
+
+    fn bind(fd: &OwnedFd, addr: &YUnixAddr) {
+        let (ptr, len) = addr.as_c_sockaddr_len();
+        let r = unsafe { libc::bind(fd.as_raw_fd(), ptr, len) };
+        assert_eq!(r, 0, "{}", std::io::Error::last_os_error());
+    }
+
+    #[rstest]
+    #[case(b"", 3)]
+    #[case(b"ysockaddr", 12)]
+    #[case(b"ysock\0addr\0", 14)]
+    fn abstract_len(#[case] name: &[u8], #[case] len: libc::socklen_t) {
+        let addr = YUnixAddr::abstract_name(name).unwrap();
+        assert_eq!(addr.as_c_sockaddr_len().1, len);
+        assert_eq!(addr.kind(), UnixAddrKind::Abstract(name.to_vec()));
+    }
+
+    #[rstest]
+    #[case(SUN_PATH_LEN - 1, true)]
+    #[case(SUN_PATH_LEN, false)]
+    fn limits(#[case] len: usize, #[case] fits: bool) {
+        let name = vec![b'a'; len];
+        let path = PathBuf::from(OsStr::from_bytes(&name));
+        let too_long = UnixAddrError::TooLong {
+            len,
+            max: SUN_PATH_LEN - 1,
+        };
+        match fits {
+            true => {
+                let addr = YUnixAddr::from_path(&path).unwrap();
+                assert_eq!(
+                    addr.as_c_sockaddr_len().1 as usize,
+                    size_of::<libc::sockaddr_un>()
+                );
+                assert!(YUnixAddr::abstract_name(&name).is_ok());
+            }
+            false => {
+                assert_eq!(YUnixAddr::from_path(&path).unwrap_err(), too_long);
+                assert_eq!(YUnixAddr::abstract_name(&name).unwrap_err(), too_long);
+            }
+        }
+    }
+
+    #[rstest]
+    #[case("")]
+    #[case("a\0b")]
+    fn invalid_path(#[case] path: &str) {
+        let err = YUnixAddr::from_path(Path::new(path)).unwrap_err();
+        assert_eq!(err, UnixAddrError::InvalidPath);
+        let err: std::io::Error = err.into();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn bind_pathname() {
+        let dir = std::env::temp_dir().join(format!("ysockaddr-unix-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("sock");
+        let _ = std::fs::remove_file(&path);
+        let addr = YUnixAddr::from_path(&path).unwrap();
+        assert_eq!(addr.kind().to_string(), path.display().to_string());
+        let fd = unix_socket();
+        bind(&fd, &addr);
+        let bound = getsockname_unix(fd.as_raw_fd()).unwrap();
+        assert_eq!(bound.kind(), UnixAddrKind::Pathname(path.clone()));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn bind_abstract() {
+        let name = format!("ysockaddr\0{}", std::process::id()).into_bytes();
+        let addr = YUnixAddr::abstract_name(&name).unwrap();
+        let fd = unix_socket();
+        bind(&fd, &addr);
+        let bound = getsockname_unix(fd.as_raw_fd()).unwrap();
+        assert_eq!(bound.kind(), UnixAddrKind::Abstract(name));
+        assert_eq!(bound.as_c_sockaddr_len().1, addr.as_c_sockaddr_len().1);
+    }
+
+    #[test]
+    fn bind_unnamed_autobinds() {
+        let fd = unix_socket();
+        bind(&fd, &YUnixAddr::unnamed());
+        // autobind(7) picks five hex digits in the abstract namespace.
+        match getsockname_unix(fd.as_raw_fd()).unwrap().kind() {
+            UnixAddrKind::Abstract(name) => assert_eq!(name.len(), 5),
+            kind => panic!("Expected Abstract, got {:?}", kind),
+        }
+    }
+
+    #[test]
+    fn socketpair_unnamed() {
+        let (a, b) = UnixDatagram::pair().unwrap();
+        assert_eq!(
+            getsockname_unix(a.as_raw_fd()).unwrap().kind(),
+            UnixAddrKind::Unnamed
+        );
+        assert_eq!(
+            getpeername_unix(b.as_raw_fd()).unwrap().kind(),
+            UnixAddrKind::Unnamed
+        );
+        assert_eq!(UnixAddrKind::Unnamed.to_string(), "(unnamed)");
+    }
+
+    #[test]
+    fn peername_pathname() {
+        let dir = std::env::temp_dir().join(format!("ysockaddr-peer-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("sock");
+        let _listener = UnixListener::bind(&path).unwrap();
+        let stream = UnixStream::connect(&path).unwrap();
+        let peer = getpeername_unix(stream.as_raw_fd()).unwrap();
+        assert_eq!(peer.kind(), UnixAddrKind::Pathname(path));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[rstest]
+    #[case(UnixAddrKind::Abstract(b"a\0b".to_vec()), "@a\\x00b")]
+    #[case(UnixAddrKind::Pathname(PathBuf::from("/run/y.sock")), "/run/y.sock")]
+    fn display(#[case] kind: UnixAddrKind, #[case] expected: &str) {
+        assert_eq!(kind.to_string(), expected);
+    }
+}
@@ -0,0 +1,102 @@
+//! AF_VSOCK sockaddr_vm between guests and the host
+
+use crate::YSockAddrC;
+
+/// Bind to any CID
+pub const VMADDR_CID_ANY: u32 = libc::VMADDR_CID_ANY;
+/// vsock loopback within the same host or guest
+pub const VMADDR_CID_LOCAL: u32 = libc::VMADDR_CID_LOCAL;
+/// The host as seen from a guest
+pub const VMADDR_CID_HOST: u32 = libc::VMADDR_CID_HOST;
+/// Bind to any free port
+pub const VMADDR_PORT_ANY: u32 = u32::MAX;
+
+/// Rust form of an AF_VSOCK address as there is none in std
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct VsockAddr {
+    /// Context ID e.g. [`VMADDR_CID_HOST`]
+    pub cid: u32,
+    /// Port
+    pub port: u32,
+}
+
+impl VsockAddr {
+    /// Address of the given context ID and port
+    #[inline]
+    pub fn new(cid: u32, port: u32) -> Self {
+        Self { cid, port }
+    }
+}
+
+impl core::fmt::Display for VsockAddr {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "vsock:{}:{}", self.cid, self.port)
+    }
+}
+
+impl From<VsockAddr> for YSockAddrC {
+    #[inline]
+    fn from(va: VsockAddr) -> YSockAddrC {
+        // SAFETY: sockaddr_vm is valid when zeroed which covers the reserved and zero fields.
+        let mut svm: libc::sockaddr_vm = unsafe { core::mem::zeroed() };
+        svm.svm_family = libc::AF_VSOCK as libc::sa_family_t;
+        svm.svm_cid = va.cid;
+        svm.svm_port = va.port;
+        YSockAddrC::Vsock(svm, size_of::<libc::sockaddr_vm>() as libc::socklen_t)
+    }
+}
+
+impl TryFrom<YSockAddrC> for VsockAddr {
+    type Error = YSockAddrC;
+    /// Other families are given back as the Err
+    #[inline]
+    fn try_from(sa: YSockAddrC) -> Result<VsockAddr, YSockAddrC> {
+        match sa {
+            YSockAddrC::Vsock(svm, _) => Ok(VsockAddr::new(svm.svm_cid, svm.svm_port)),
+            other => Err(other),
+        }
+    }
+}
+
+impl YSockAddrC {
+    /// AF_VSOCK address of the given context ID and port
+    #[inline]
+    pub fn vsock(cid: u32, port: u32) -> Self {
+        VsockAddr::new(cid, port).into()
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(VMADDR_CID_ANY, VMADDR_PORT_ANY, "vsock:4294967295:4294967295")]
+    #[case(VMADDR_CID_HOST, 1024, "vsock:2:1024")]
+    #[case(VMADDR_CID_LOCAL, 0, "vsock:1:0")]
+    #[case(3, 0x12345678, "vsock:3:305419896")]
+    fn round_trip(#[case] cid: u32, #[case] port: u32, #[case] display: &str) {
+        let c = YSockAddrC::vsock(cid, port);
+        assert!(c.is_vsock());
+        assert_eq!(c.family(), libc::AF_VSOCK as libc::sa_family_t);
+        assert_eq!(c.port(), 0);
+        let mut unchanged = c.clone();
+        unchanged.set_port(80);
+        assert_eq!(unchanged.port(), 0);
+        assert_eq!(
+            c.as_c_sockaddr_len().1 as usize,
+            size_of::<libc::sockaddr_vm>()
+        );
+        let va = VsockAddr::try_from(c).unwrap();
+        assert_eq!(va, VsockAddr::new(cid, port));
+        assert_eq!(va.to_string(), display);
+    }
+
+    #[test]
+    fn other_family_given_back() {
+        let c = YSockAddrC::from((core::net::Ipv4Addr::LOCALHOST, 80u16));
+        assert!(VsockAddr::try_from(c).unwrap_err().is_ipv4());
+    }
+}
@@ -0,0 +1,146 @@
+#![warn(
+    clippy::unwrap_used,
+    missing_docs,
+    rust_2018_idioms,
+    unused_lifetimes,
+    unused_qualifications
+)]
+#![doc = include_str!("../README.md")]
+
+#[cfg(not(target_os = "linux"))]
+compile_error!("Crate ysocketpair is Linux specific dependency but is used in non-linux system.");
+
+use std::io;
+use yown_fd::{FromRawFd, OwnedFd};
+
+/// Communication domain of socket(2) / socketpair(2)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SocketDomain {
+    /// AF_UNIX, the only domain Linux supports socketpair(2) for
+    Unix,
+    /// AF_INET
+    Inet,
+    /// AF_INET6
+    Inet6,
+    /// AF_NETLINK
+    Netlink,
+    /// AF_PACKET
+    Packet,
+    /// AF_VSOCK
+    Vsock,
+}
+
+impl SocketDomain {
+    #[inline]
+    fn as_c(self) -> libc::c_int {
+        match self {
+            Self::Unix => libc::AF_UNIX,
+            Self::Inet => libc::AF_INET,
+            Self::Inet6 => libc::AF_INET6,
+            Self::Netlink => libc::AF_NETLINK,
+            Self::Packet => libc::AF_PACKET,
+            Self::Vsock => libc::AF_VSOCK,
+        }
+    }
+}
+
+/// Socket type of socket(2) / socketpair(2)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SocketType {
+    /// SOCK_STREAM
+    Stream,
+    /// SOCK_DGRAM
+    Datagram,
+    /// SOCK_SEQPACKET
+    SeqPacket,
+    /// SOCK_RAW
+    Raw,
+}
+
+impl SocketType {
+    #[inline]
+    fn as_c(self) -> libc::c_int {
+        match self {
+            Self::Stream => libc::SOCK_STREAM,
+            Self::Datagram => libc::SOCK_DGRAM,
+            Self::SeqPacket => libc::SOCK_SEQPACKET,
+            Self::Raw => libc::SOCK_RAW,
+        }
+    }
+}
+
+/// Pair of connected sockets through socketpair(2) with SOCK_CLOEXEC, protocol usually 0.
+///
+/// Linux only supports [`SocketDomain::Unix`], other domains fail with EOPNOTSUPP e.g.
+/// an IPv4 UDP pair which needs two sockets bound to and connected over loopback instead.
+#[inline]
+pub fn socketpair(
+    domain: SocketDomain,
+    type_: SocketType,
+    protocol: i32,
+) -> io::Result<(OwnedFd, OwnedFd)> {
+    let mut fds: [libc::c_int; 2] = [-1; 2];
+    // SAFETY: fds is valid for the two fds written.
+    let r = unsafe {
+        libc::socketpair(
+            domain.as_c(),
+            type_.as_c() | libc::SOCK_CLOEXEC,
+            protocol,
+            fds.as_mut_ptr(),
+        )
+    };
+    if r != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // SAFETY: Both fds were just created and are owned by nobody else.
+    Ok(unsafe { (OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1])) })
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use rstest::rstest;
+    use yown_fd::AsRawFd;
+
+    fn send(fd: &OwnedFd, msg: &[u8]) {
+        let r = unsafe { libc::send(fd.as_raw_fd(), msg.as_ptr().cast(), msg.len(), 0) };
+        assert_eq!(r as usize, msg.len(), "{}", io::Error::last_os_error());
+    }
+
+    fn recv(fd: &OwnedFd) -> Vec<u8> {
+        let mut buf = [0u8; 64];
+        let r = unsafe { libc::recv(fd.as_raw_fd(), buf.as_mut_ptr().cast(), buf.len(), 0) };
+        assert!(r >= 0, "{}", io::Error::last_os_error());
+        buf[..r as usize].to_vec()
+    }
+
+    #[rstest]
+    #[case(SocketType::Stream)]
+    #[case(SocketType::Datagram)]
+    #[case(SocketType::SeqPacket)]
+    fn unix_round_trip(#[case] type_: SocketType) {
+        let (a, b) = socketpair(SocketDomain::Unix, type_, 0).unwrap();
+        send(&a, b"ping");
+        assert_eq!(recv(&b), b"ping");
+        send(&b, b"pong");
+        assert_eq!(recv(&a), b"pong");
+    }
+
+    #[test]
+    fn cloexec() {
+        let (a, b) = socketpair(SocketDomain::Unix, SocketType::Stream, 0).unwrap();
+        for fd in [a, b] {
+            let flags = unsafe { libc::fcntl(fd.as_raw_fd(), libc::F_GETFD) };
+            assert_eq!(flags & libc::FD_CLOEXEC, libc::FD_CLOEXEC);
+        }
+    }
+
+    #[rstest]
+    #[case(SocketDomain::Inet, SocketType::Datagram)]
+    #[case(SocketDomain::Inet6, SocketType::Stream)]
+    fn ip_unsupported(#[case] domain: SocketDomain, #[case] type_: SocketType) {
+        let err = socketpair(domain, type_, 0).unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(libc::EOPNOTSUPP));
+    }
+}
@@ -0,0 +1,214 @@
+#![warn(
+    clippy::unwrap_used,
+    missing_docs,
+    rust_2018_idioms,
+    unused_lifetimes,
+    unused_qualifications
+)]
+#![doc = include_str!("../README.md")]
+
+#[cfg(not(target_os = "linux"))]
+compile_error!("Crate ysymlink is Linux specific dependency but is used in non-linux system.");
+
+use std::ffi::CString;
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+use yown_fd::RawFd;
+
+bitflags::bitflags! {
+    /// linkat(2) flags
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    pub struct LinkatFlags: libc::c_int {
+        /// Link the file referred to by olddirfd itself given an empty oldpath, e.g. an
+        /// O_TMPFILE. Requires CAP_DAC_READ_SEARCH
+        const AT_EMPTY_PATH = libc::AT_EMPTY_PATH;
+        /// Dereference oldpath if it is a symlink instead of linking the symlink itself
+        const AT_SYMLINK_FOLLOW = libc::AT_SYMLINK_FOLLOW;
+    }
+}
+
+bitflags::bitflags! {
+    /// unlinkat(2) flags
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    pub struct UnlinkatFlags: libc::c_int {
+        /// Remove the empty directory at path like rmdir(2)
+        const AT_REMOVEDIR = libc::AT_REMOVEDIR;
+    }
+}
+
+#[inline]
+fn path_cstring(path: &Path) -> io::Result<CString> {
+    CString::new(path.as_os_str().as_bytes())
+        .map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))
+}
+
+#[inline]
+fn check(r: libc::c_int) -> io::Result<()> {
+    match r {
+        0 => Ok(()),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+/// Create the symlink linkpath pointing to target through symlink(2). The target is
+/// stored as is and does not need to exist.
+///
+/// Paths with an interior NUL are [`io::ErrorKind::InvalidInput`].
+#[inline]
+pub fn symlink(target: &Path, linkpath: &Path) -> io::Result<()> {
+    symlinkat(target, None, linkpath)
+}
+
+/// Create the symlink linkpath pointing to target through symlinkat(2) where a relative
+/// linkpath is resolved against dirfd or the current working directory with None.
+#[inline]
+pub fn symlinkat(target: &Path, dirfd: Option<RawFd>, linkpath: &Path) -> io::Result<()> {
+    let target = path_cstring(target)?;
+    let linkpath = path_cstring(linkpath)?;
+    let dirfd = dirfd.unwrap_or(libc::AT_FDCWD);
+    // SAFETY: Both paths are NUL terminated.
+    check(unsafe { libc::symlinkat(target.as_ptr(), dirfd, linkpath.as_ptr()) })
+}
+
+/// Create the hard link newpath to oldpath through linkat(2) where relative paths are
+/// resolved against their dirfd or the current working directory with None.
+#[inline]
+pub fn linkat(
+    olddirfd: Option<RawFd>,
+    oldpath: &Path,
+    newdirfd: Option<RawFd>,
+    newpath: &Path,
+    flags: LinkatFlags,
+) -> io::Result<()> {
+    let oldpath = path_cstring(oldpath)?;
+    let newpath = path_cstring(newpath)?;
+    // SAFETY: Both paths are NUL terminated.
+    check(unsafe {
+        libc::linkat(
+            olddirfd.unwrap_or(libc::AT_FDCWD),
+            oldpath.as_ptr(),
+            newdirfd.unwrap_or(libc::AT_FDCWD),
+            newpath.as_ptr(),
+            flags.bits(),
+        )
+    })
+}
+
+/// Remove the name at path through unlinkat(2) where a relative path is resolved against
+/// dirfd or the current working directory with None. Symlinks are removed themselves.
+#[inline]
+pub fn unlinkat(dirfd: Option<RawFd>, path: &Path, flags: UnlinkatFlags) -> io::Result<()> {
+    let path = path_cstring(path)?;
+    let dirfd = dirfd.unwrap_or(libc::AT_FDCWD);
+    // SAFETY: path is NUL terminated.
+    check(unsafe { libc::unlinkat(dirfd, path.as_ptr(), flags.bits()) })
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use rstest::rstest;
+    use std::fs::File;
+    use std::os::unix::fs::MetadataExt;
+    use std::path::PathBuf;
+    use yown_fd::AsRawFd;
+
+    struct TempDir(PathBuf, File);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let path =
+                std::env::temp_dir().join(format!("ysymlink-{}-{}", std::process::id(), name));
+            let _ = std::fs::remove_dir_all(&path);
+            std::fs::create_dir(&path).unwrap();
+            let dir = File::open(&path).unwrap();
+            Self(path, dir)
+        }
+        fn fd(&self) -> Option<RawFd> {
+            Some(self.1.as_raw_fd())
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[rstest]
+    #[case("/dev/null")]
+    #[case("relative/dangling")]
+    fn symlink_and_unlink(#[case] target: &str) {
+        let dir = TempDir::new(&format!("symlink-{}", target.len()));
+        let link = dir.0.join("link");
+        symlink(Path::new(target), &link).unwrap();
+        assert_eq!(std::fs::read_link(&link).unwrap(), Path::new(target));
+        let err = symlink(Path::new(target), &link).unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(libc::EEXIST));
+        unlinkat(None, &link, UnlinkatFlags::empty()).unwrap();
+        assert!(std::fs::symlink_metadata(&link).is_err());
+    }
+
+    #[test]
+    fn symlinkat_relative_to_dirfd() {
+        let dir = TempDir::new("symlinkat");
+        symlinkat(Path::new("/dev/null"), dir.fd(), Path::new("link")).unwrap();
+        assert_eq!(
+            std::fs::read_link(dir.0.join("link")).unwrap(),
+            Path::new("/dev/null")
+        );
+        unlinkat(dir.fd(), Path::new("link"), UnlinkatFlags::empty()).unwrap();
+    }
+
+    #[rstest]
+    #[case(LinkatFlags::empty(), true)]
+    #[case(LinkatFlags::AT_SYMLINK_FOLLOW, false)]
+    fn linkat_symlink(#[case] flags: LinkatFlags, #[case] links_symlink: bool) {
+        let dir = TempDir::new(&format!("linkat-{}", flags.bits()));
+        std::fs::write(dir.0.join("file"), b"data").unwrap();
+        symlinkat(Path::new("file"), dir.fd(), Path::new("sym")).unwrap();
+        linkat(
+            dir.fd(),
+            Path::new("sym"),
+            dir.fd(),
+            Path::new("hard"),
+            flags,
+        )
+        .unwrap();
+        let hard = std::fs::symlink_metadata(dir.0.join("hard")).unwrap();
+        assert_eq!(hard.file_type().is_symlink(), links_symlink);
+        let file = std::fs::metadata(dir.0.join("file")).unwrap();
+        assert_eq!(hard.ino() == file.ino(), !links_symlink);
+    }
+
+    #[test]
+    fn linkat_empty_path() {
+        let dir = TempDir::new("empty-path");
+        std::fs::write(dir.0.join("file"), b"data").unwrap();
+        let file = File::open(dir.0.join("file")).unwrap();
+        let r = linkat(
+            Some(file.as_raw_fd()),
+            Path::new(""),
+            dir.fd(),
+            Path::new("named"),
+            LinkatFlags::AT_EMPTY_PATH,
+        );
+        match r {
+            Ok(()) => assert_eq!(std::fs::read(dir.0.join("named")).unwrap(), b"data"),
+            // Without CAP_DAC_READ_SEARCH.
+            Err(e) => assert_eq!(e.raw_os_error(), Some(libc::ENOENT)),
+        }
+    }
+
+    #[test]
+    fn unlinkat_removedir() {
+        let dir = TempDir::new("removedir");
+        std::fs::create_dir(dir.0.join("sub")).unwrap();
+        let err = unlinkat(dir.fd(), Path::new("sub"), UnlinkatFlags::empty()).unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(libc::EISDIR));
+        unlinkat(dir.fd(), Path::new("sub"), UnlinkatFlags::AT_REMOVEDIR).unwrap();
+        assert!(!dir.0.join("sub").exists());
+    }
+}
@@ -0,0 +1,88 @@
+#![warn(
+    clippy::unwrap_used,
+    missing_docs,
+    rust_2018_idioms,
+    unused_lifetimes,
+    unused_qualifications
+)]
+#![doc = include_str!("../README.md")]
+
+#[cfg(not(target_os = "linux"))]
+compile_error!(
+    "Crate ysync_file_range is Linux specific dependency but is used in non-linux system."
+);
+
+use std::io;
+use yown_fd::RawFd;
+
+bitflags::bitflags! {
+    /// sync_file_range(2) flags
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    pub struct SyncFileRangeFlags: libc::c_uint {
+        /// Wait for the write-out of the already dirty pages in the range first
+        const WAIT_BEFORE = libc::SYNC_FILE_RANGE_WAIT_BEFORE;
+        /// Start the write-out of the dirty pages in the range not already under write-out
+        const WRITE = libc::SYNC_FILE_RANGE_WRITE;
+        /// Wait for the write-out of the pages in the range to complete
+        const WAIT_AFTER = libc::SYNC_FILE_RANGE_WAIT_AFTER;
+    }
+}
+
+/// Flush [offset, offset + nbytes) of the file through sync_file_range(2) where nbytes 0
+/// means to the end of file.
+///
+/// Neither the metadata nor the disk write cache are flushed so this is no durability
+/// guarantee - see fdatasync(2) for that.
+#[inline]
+pub fn sync_file_range(
+    fd: RawFd,
+    offset: u64,
+    nbytes: u64,
+    flags: SyncFileRangeFlags,
+) -> io::Result<()> {
+    let offset =
+        libc::off64_t::try_from(offset).map_err(|_| io::Error::from_raw_os_error(libc::EINVAL))?;
+    let nbytes =
+        libc::off64_t::try_from(nbytes).map_err(|_| io::Error::from_raw_os_error(libc::EINVAL))?;
+    // SAFETY: Only takes the fd, the range and the flags.
+    match unsafe { libc::sync_file_range(fd, offset, nbytes, flags.bits()) } {
+        0 => Ok(()),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use rstest::rstest;
+    use std::io::Write;
+    use yown_fd::AsRawFd;
+
+    #[rstest]
+    #[case(SyncFileRangeFlags::WAIT_AFTER)]
+    #[case(SyncFileRangeFlags::WRITE)]
+    #[case(SyncFileRangeFlags::all())]
+    fn flush_written(#[case] flags: SyncFileRangeFlags) {
+        let path = std::env::temp_dir().join(format!(
+            "ysync_file_range-{}-{}",
+            std::process::id(),
+            flags.bits()
+        ));
+        let mut file = std::fs::File::create(&path).unwrap();
+        file.write_all(&[1u8; 8192]).unwrap();
+        let r = sync_file_range(file.as_raw_fd(), 0, 8192, flags);
+        let whole = sync_file_range(file.as_raw_fd(), 4096, 0, flags);
+        std::fs::remove_file(&path).unwrap();
+        r.unwrap();
+        whole.unwrap();
+    }
+
+    #[test]
+    fn offset_beyond_off64() {
+        let file = std::fs::File::open("/dev/null").unwrap();
+        let err =
+            sync_file_range(file.as_raw_fd(), u64::MAX, 0, SyncFileRangeFlags::WRITE).unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(libc::EINVAL));
+    }
+}
@@ -0,0 +1,95 @@
+#![warn(
+    clippy::unwrap_used,
+    missing_docs,
+    rust_2018_idioms,
+    unused_lifetimes,
+    unused_qualifications
+)]
+#![doc = include_str!("../README.md")]
+
+#[cfg(not(target_os = "linux"))]
+compile_error!("Crate ysysinfo is Linux specific dependency but is used in non-linux system.");
+
+use std::io;
+use std::time::Duration;
+
+/// System statistics as returned by sysinfo(2) with the memory sizes in bytes
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SysInfo {
+    /// Time since boot
+    pub uptime: Duration,
+    /// 1, 5 and 15 minute load averages
+    pub loads: [f64; 3],
+    /// Total usable main memory
+    pub total_ram: u64,
+    /// Available memory not counting the page cache
+    pub free_ram: u64,
+    /// Shared memory
+    pub shared_ram: u64,
+    /// Memory used by buffers
+    pub buffer_ram: u64,
+    /// Total swap space
+    pub total_swap: u64,
+    /// Swap space still available
+    pub free_swap: u64,
+    /// Number of current processes
+    pub procs: u16,
+}
+
+/// System statistics through sysinfo(2)
+///
+/// The kernel reports the memory sizes in units of mem_unit which are scaled to bytes here
+/// saturating at u64::MAX.
+#[inline]
+pub fn sysinfo() -> io::Result<SysInfo> {
+    // SAFETY: sysinfo is plain integers for which zeroed is valid.
+    let mut info: libc::sysinfo = unsafe { core::mem::zeroed() };
+    // SAFETY: info is valid for writes.
+    if unsafe { libc::sysinfo(&mut info) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let unit = u64::from(info.mem_unit.max(1));
+    // c_ulong is only u64 on 64 bit.
+    #[allow(clippy::useless_conversion)]
+    let bytes = |units: libc::c_ulong| u64::from(units).saturating_mul(unit);
+    let load = |load: libc::c_ulong| load as f64 / f64::from(1u32 << libc::SI_LOAD_SHIFT);
+    Ok(SysInfo {
+        uptime: Duration::from_secs(info.uptime.max(0) as u64),
+        loads: info.loads.map(load),
+        total_ram: bytes(info.totalram),
+        free_ram: bytes(info.freeram),
+        shared_ram: bytes(info.sharedram),
+        buffer_ram: bytes(info.bufferram),
+        total_swap: bytes(info.totalswap),
+        free_swap: bytes(info.freeswap),
+        procs: info.procs,
+    })
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn sysinfo_sane() {
+        let info = sysinfo().unwrap();
+        assert!(info.total_ram > info.free_ram);
+        assert!(info.total_swap >= info.free_swap);
+        assert!(info.uptime.as_secs() > 0);
+        assert!(info.procs > 0);
+        assert!(info.loads.iter().all(|load| *load >= 0.0));
+    }
+
+    #[test]
+    fn total_ram_matches_meminfo() {
+        let meminfo = std::fs::read_to_string("/proc/meminfo").unwrap();
+        let kb: u64 = meminfo
+            .lines()
+            .find_map(|line| line.strip_prefix("MemTotal:"))
+            .and_then(|rest| rest.trim().strip_suffix("kB"))
+            .map(|kb| kb.trim().parse().unwrap())
+            .unwrap();
+        assert_eq!(sysinfo().unwrap().total_ram, kb * 1024);
+    }
+}
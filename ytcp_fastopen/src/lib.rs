@@ -0,0 +1,166 @@
+#![warn(
+    clippy::unwrap_used,
+    missing_docs,
+    rust_2018_idioms,
+    unused_lifetimes,
+    unused_qualifications
+)]
+#![doc = include_str!("../README.md")]
+
+#[cfg(not(target_os = "linux"))]
+compile_error!("Crate ytcp_fastopen is Linux specific dependency but is used in non-linux system.");
+
+use std::io;
+use yown_fd::RawFd;
+use ysockaddr::YSockAddrC;
+
+const FASTOPEN_KEY_PATH: &str = "/proc/sys/net/ipv4/tcp_fastopen_key";
+
+/// Enable TCP Fast Open on the listening socket through setsockopt(2) TCP_FASTOPEN with the
+/// given max queue len of pending TFO connections not yet through the three-way handshake.
+///
+/// Data on the SYN is only accepted when the net.ipv4.tcp_fastopen sysctl has the server bit
+/// (0x2) set.
+#[inline]
+pub fn set_tcp_fastopen(fd: RawFd, qlen: i32) -> io::Result<()> {
+    let qlen: libc::c_int = qlen;
+    // SAFETY: qlen is valid for the duration of the call.
+    let r = unsafe {
+        libc::setsockopt(
+            fd,
+            libc::IPPROTO_TCP,
+            libc::TCP_FASTOPEN,
+            core::ptr::addr_of!(qlen) as *const libc::c_void,
+            size_of::<libc::c_int>() as libc::socklen_t,
+        )
+    };
+    match r {
+        0 => Ok(()),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+/// Connect the unconnected TCP socket to the address sending the data on the SYN through
+/// sendto(2) MSG_FASTOPEN returning the bytes sent or queued.
+///
+/// Without a cached cookie from the server the kernel sends a plain SYN requesting one and
+/// the data follows the handshake. Fails with EOPNOTSUPP when the client bit (0x1) is not set
+/// in the net.ipv4.tcp_fastopen sysctl. On a non-blocking socket EINPROGRESS is returned.
+#[inline]
+pub fn connect_with_data(fd: RawFd, addr: &YSockAddrC, data: &[u8]) -> io::Result<usize> {
+    let (sa, sa_len) = addr.as_c_sockaddr_len();
+    // SAFETY: data and the sockaddr are valid for the duration of the call.
+    let r = unsafe {
+        libc::sendto(
+            fd,
+            data.as_ptr() as *const libc::c_void,
+            data.len(),
+            libc::MSG_FASTOPEN,
+            sa,
+            sa_len,
+        )
+    };
+    if r < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(r as usize)
+}
+
+/// Parse the "xxxxxxxx-xxxxxxxx-xxxxxxxx-xxxxxxxx" primary key optionally followed by the
+/// comma-separated backup key.
+#[inline]
+fn parse_fastopen_key(s: &str) -> io::Result<u128> {
+    let invalid = || io::Error::from(io::ErrorKind::InvalidData);
+    let primary = s.trim().split(',').next().ok_or_else(invalid)?;
+    let mut words = primary.split('-');
+    let mut key = 0u128;
+    for _ in 0..4 {
+        let word = words.next().ok_or_else(invalid)?;
+        if word.len() != 8 {
+            return Err(invalid());
+        }
+        key = (key << 32) | u32::from_str_radix(word, 16).map_err(|_| invalid())? as u128;
+    }
+    match words.next() {
+        None => Ok(key),
+        Some(_) => Err(invalid()),
+    }
+}
+
+/// Primary TCP Fast Open cookie key from /proc/sys/net/ipv4/tcp_fastopen_key.
+///
+/// The key is 128 bits printed as four 32 bit hex words, the first word being the most
+/// significant. Reading it requires root.
+#[inline]
+pub fn get_fastopen_key() -> io::Result<u128> {
+    parse_fastopen_key(&std::fs::read_to_string(FASTOPEN_KEY_PATH)?)
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use rstest::rstest;
+    use std::io::Read;
+    use std::net::{Ipv4Addr, SocketAddr, TcpListener};
+    use yown_fd::{AsRawFd, FromRawFd, OwnedFd};
+
+    fn unsupported(e: &io::Error) -> bool {
+        matches!(
+            e.raw_os_error(),
+            Some(libc::ENOPROTOOPT) | Some(libc::EOPNOTSUPP)
+        )
+    }
+
+    #[test]
+    fn loopback_fastopen() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        match set_tcp_fastopen(listener.as_raw_fd(), 16) {
+            Err(e) if unsupported(&e) => return,
+            r => r.unwrap(),
+        }
+        let port = listener.local_addr().unwrap().port();
+
+        let fd = unsafe { libc::socket(libc::AF_INET, libc::SOCK_STREAM | libc::SOCK_CLOEXEC, 0) };
+        assert!(fd >= 0);
+        let client = unsafe { OwnedFd::from_raw_fd(fd) };
+        let addr = YSockAddrC::from((Ipv4Addr::LOCALHOST, port));
+        match connect_with_data(client.as_raw_fd(), &addr, b"syn data") {
+            Err(e) if unsupported(&e) => return,
+            r => assert_eq!(r.unwrap(), 8),
+        }
+
+        let (mut server, peer) = listener.accept().unwrap();
+        assert!(matches!(peer, SocketAddr::V4(_)));
+        let mut buf = [0u8; 8];
+        server.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"syn data");
+    }
+
+    #[test]
+    fn fastopen_key() {
+        match get_fastopen_key() {
+            Ok(_) => {}
+            Err(e) if e.kind() == io::ErrorKind::PermissionDenied => {}
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    #[rstest]
+    #[case("00000000-00000000-00000000-00000000\n", Some(0))]
+    #[case(
+        "00000001-00000002-00000003-00000004",
+        Some(0x00000001_00000002_00000003_00000004)
+    )]
+    #[case(
+        "ffffffff-00000000-00000000-0000000a,00000000-00000000-00000000-00000001\n",
+        Some(0xffffffff_00000000_00000000_0000000a)
+    )]
+    #[case("00000000-00000000-00000000", None)]
+    #[case("00000000-00000000-00000000-00000000-00000000", None)]
+    #[case("0000000g-00000000-00000000-00000000", None)]
+    fn parse_key(#[case] input: &str, #[case] expected: Option<u128>) {
+        assert_eq!(parse_fastopen_key(input).ok(), expected);
+    }
+}
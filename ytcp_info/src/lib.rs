@@ -0,0 +1,185 @@
+#![warn(
+    clippy::unwrap_used,
+    missing_docs,
+    rust_2018_idioms,
+    unused_lifetimes,
+    unused_qualifications
+)]
+#![doc = include_str!("../README.md")]
+
+#[cfg(not(target_os = "linux"))]
+compile_error!("Crate ytcp_info is Linux specific dependency but is used in non-linux system.");
+
+use std::io;
+use yown_fd::RawFd;
+
+/// The kernel struct tcp_info from linux/tcp.h up to tcpi_segs_in (Linux 4.2).
+///
+/// libc::tcp_info stops at tcpi_total_retrans on glibc and misses the wscale bitfield byte on
+/// most musl targets so the kernel layout is kept here instead.
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct RawTcpInfo {
+    state: u8,
+    ca_state: u8,
+    retransmits: u8,
+    probes: u8,
+    backoff: u8,
+    options: u8,
+    snd_rcv_wscale: u8,
+    delivery_fastopen_bitfields: u8,
+    rto: u32,
+    ato: u32,
+    snd_mss: u32,
+    rcv_mss: u32,
+    unacked: u32,
+    sacked: u32,
+    lost: u32,
+    retrans: u32,
+    fackets: u32,
+    last_data_sent: u32,
+    last_ack_sent: u32,
+    last_data_recv: u32,
+    last_ack_recv: u32,
+    pmtu: u32,
+    rcv_ssthresh: u32,
+    rtt: u32,
+    rttvar: u32,
+    snd_ssthresh: u32,
+    snd_cwnd: u32,
+    advmss: u32,
+    reordering: u32,
+    rcv_rtt: u32,
+    rcv_space: u32,
+    total_retrans: u32,
+    pacing_rate: u64,
+    max_pacing_rate: u64,
+    bytes_acked: u64,
+    bytes_received: u64,
+    segs_out: u32,
+    segs_in: u32,
+}
+
+/// TCP_INFO statistics of a TCP socket
+#[derive(Clone, Copy)]
+pub struct TcpInfo {
+    raw: RawTcpInfo,
+}
+
+impl core::fmt::Debug for TcpInfo {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("TcpInfo")
+            .field("rtt_us", &self.rtt_us())
+            .field("rttvar_us", &self.rttvar_us())
+            .field("snd_cwnd", &self.snd_cwnd())
+            .field("snd_ssthresh", &self.snd_ssthresh())
+            .field("rcv_rtt", &self.rcv_rtt())
+            .field("bytes_acked", &self.bytes_acked())
+            .field("bytes_received", &self.bytes_received())
+            .field("segs_out", &self.segs_out())
+            .field("segs_in", &self.segs_in())
+            .finish()
+    }
+}
+
+impl TcpInfo {
+    /// Smoothed round trip time in microseconds
+    #[inline]
+    pub fn rtt_us(&self) -> u32 {
+        self.raw.rtt
+    }
+    /// Round trip time variance in microseconds
+    #[inline]
+    pub fn rttvar_us(&self) -> u32 {
+        self.raw.rttvar
+    }
+    /// Congestion window in segments
+    #[inline]
+    pub fn snd_cwnd(&self) -> u32 {
+        self.raw.snd_cwnd
+    }
+    /// Slow start threshold in segments
+    #[inline]
+    pub fn snd_ssthresh(&self) -> u32 {
+        self.raw.snd_ssthresh
+    }
+    /// Receiver side round trip time estimate in microseconds
+    #[inline]
+    pub fn rcv_rtt(&self) -> u32 {
+        self.raw.rcv_rtt
+    }
+    /// Bytes acknowledged by the peer (Linux 4.1+, zero before)
+    #[inline]
+    pub fn bytes_acked(&self) -> u64 {
+        self.raw.bytes_acked
+    }
+    /// Bytes received from the peer (Linux 4.1+, zero before)
+    #[inline]
+    pub fn bytes_received(&self) -> u64 {
+        self.raw.bytes_received
+    }
+    /// Segments sent (Linux 4.2+, zero before)
+    #[inline]
+    pub fn segs_out(&self) -> u32 {
+        self.raw.segs_out
+    }
+    /// Segments received (Linux 4.2+, zero before)
+    #[inline]
+    pub fn segs_in(&self) -> u32 {
+        self.raw.segs_in
+    }
+}
+
+/// TCP_INFO of the given TCP socket through getsockopt(2).
+/// Fields the running kernel does not know about are left zero.
+#[inline]
+pub fn get_tcp_info(fd: RawFd) -> io::Result<TcpInfo> {
+    // SAFETY: RawTcpInfo is plain integers valid when zeroed.
+    let mut raw: RawTcpInfo = unsafe { core::mem::zeroed() };
+    let mut len = size_of::<RawTcpInfo>() as libc::socklen_t;
+    // SAFETY: raw and len are valid for the duration of the call and the kernel writes at most len.
+    let r = unsafe {
+        libc::getsockopt(
+            fd,
+            libc::IPPROTO_TCP,
+            libc::TCP_INFO,
+            core::ptr::addr_of_mut!(raw) as *mut libc::c_void,
+            &mut len,
+        )
+    };
+    if r != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(TcpInfo { raw })
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::{TcpListener, TcpStream, UdpSocket};
+    use yown_fd::AsRawFd;
+
+    #[test]
+    fn loopback_segs_out() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (mut server, _) = listener.accept().unwrap();
+        client.write_all(b"hello").unwrap();
+        let mut buf = [0u8; 5];
+        server.read_exact(&mut buf).unwrap();
+
+        let info = get_tcp_info(client.as_raw_fd()).unwrap();
+        assert!(info.segs_out() >= 1);
+        assert!(info.snd_cwnd() >= 1);
+        let info = get_tcp_info(server.as_raw_fd()).unwrap();
+        assert_eq!(info.bytes_received(), 5);
+    }
+
+    #[test]
+    fn not_tcp() {
+        let udp = UdpSocket::bind("127.0.0.1:0").unwrap();
+        assert!(get_tcp_info(udp.as_raw_fd()).is_err());
+    }
+}
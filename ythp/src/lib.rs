@@ -0,0 +1,190 @@
+#![warn(
+    clippy::unwrap_used,
+    missing_docs,
+    rust_2018_idioms,
+    unused_lifetimes,
+    unused_qualifications
+)]
+#![doc = include_str!("../README.md")]
+
+#[cfg(not(target_os = "linux"))]
+compile_error!("Crate ythp is Linux specific dependency but is used in non-linux system.");
+
+use std::io;
+
+const THP_ENABLED: &str = "/sys/kernel/mm/transparent_hugepage/enabled";
+const THP_DEFRAG: &str = "/sys/kernel/mm/transparent_hugepage/defrag";
+
+/// System-wide THP mode of /sys/kernel/mm/transparent_hugepage/enabled
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ThpMode {
+    /// always: every suitable anonymous mapping is backed by huge pages
+    Always,
+    /// madvise: only regions given madvise(2) MADV_HUGEPAGE
+    MadviseOnly,
+    /// never
+    Never,
+}
+
+impl ThpMode {
+    /// Name as written to and selected in the sysfs file
+    #[inline]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Always => "always",
+            Self::MadviseOnly => "madvise",
+            Self::Never => "never",
+        }
+    }
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "always" => Some(Self::Always),
+            "madvise" => Some(Self::MadviseOnly),
+            "never" => Some(Self::Never),
+            _ => None,
+        }
+    }
+}
+
+/// System-wide THP defrag mode of /sys/kernel/mm/transparent_hugepage/defrag on a page
+/// fault not finding a free huge page
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ThpDefrag {
+    /// always: stall on direct reclaim and compaction
+    Always,
+    /// defer: wake kswapd and kcompactd falling back to small pages
+    Defer,
+    /// defer+madvise: stall for MADV_HUGEPAGE regions and defer for the rest
+    DeferMadvise,
+    /// madvise: stall only for MADV_HUGEPAGE regions
+    MadviseOnly,
+    /// never
+    Never,
+}
+
+impl ThpDefrag {
+    /// Name as written to and selected in the sysfs file
+    #[inline]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Always => "always",
+            Self::Defer => "defer",
+            Self::DeferMadvise => "defer+madvise",
+            Self::MadviseOnly => "madvise",
+            Self::Never => "never",
+        }
+    }
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "always" => Some(Self::Always),
+            "defer" => Some(Self::Defer),
+            "defer+madvise" => Some(Self::DeferMadvise),
+            "madvise" => Some(Self::MadviseOnly),
+            "never" => Some(Self::Never),
+            _ => None,
+        }
+    }
+}
+
+/// Current THP mode from /sys/kernel/mm/transparent_hugepage/enabled. Kernels built without
+/// THP have no such file and fail with NotFound.
+#[inline]
+pub fn thp_mode() -> io::Result<ThpMode> {
+    let content = std::fs::read_to_string(THP_ENABLED)?;
+    selected(&content)
+        .and_then(ThpMode::from_name)
+        .ok_or_else(|| invalid_data(&content))
+}
+
+/// Set the THP mode through /sys/kernel/mm/transparent_hugepage/enabled, requires root.
+#[inline]
+pub fn set_thp_mode(mode: ThpMode) -> io::Result<()> {
+    std::fs::write(THP_ENABLED, mode.as_str())
+}
+
+/// Current THP defrag mode from /sys/kernel/mm/transparent_hugepage/defrag
+#[inline]
+pub fn thp_defrag() -> io::Result<ThpDefrag> {
+    let content = std::fs::read_to_string(THP_DEFRAG)?;
+    selected(&content)
+        .and_then(ThpDefrag::from_name)
+        .ok_or_else(|| invalid_data(&content))
+}
+
+/// Set the THP defrag mode through /sys/kernel/mm/transparent_hugepage/defrag, requires
+/// root.
+#[inline]
+pub fn set_thp_defrag(mode: ThpDefrag) -> io::Result<()> {
+    std::fs::write(THP_DEFRAG, mode.as_str())
+}
+
+fn invalid_data(what: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("Malformed THP setting {:?}", what),
+    )
+}
+
+// The selected one of the space separated choices e.g. "always [madvise] never".
+fn selected(content: &str) -> Option<&str> {
+    content
+        .split_whitespace()
+        .find_map(|c| c.strip_prefix('[')?.strip_suffix(']'))
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("[always] madvise never\n", Some(ThpMode::Always))]
+    #[case("always [madvise] never\n", Some(ThpMode::MadviseOnly))]
+    #[case("always madvise [never]\n", Some(ThpMode::Never))]
+    #[case("always madvise never\n", None)]
+    #[case("always [inherit] madvise never\n", None)]
+    fn modes(#[case] content: &str, #[case] expected: Option<ThpMode>) {
+        assert_eq!(selected(content).and_then(ThpMode::from_name), expected);
+    }
+
+    #[rstest]
+    #[case("always defer defer+madvise [madvise] never", ThpDefrag::MadviseOnly)]
+    #[case("always defer [defer+madvise] madvise never", ThpDefrag::DeferMadvise)]
+    #[case("always [defer] defer+madvise madvise never", ThpDefrag::Defer)]
+    fn defrags(#[case] content: &str, #[case] expected: ThpDefrag) {
+        let defrag = selected(content).and_then(ThpDefrag::from_name).unwrap();
+        assert_eq!(defrag, expected);
+        assert_eq!(ThpDefrag::from_name(defrag.as_str()), Some(defrag));
+    }
+
+    #[test]
+    fn current_settings() {
+        // Kernels without THP have nothing to test.
+        let mode = match thp_mode() {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return,
+            r => r.unwrap(),
+        };
+        assert_eq!(ThpMode::from_name(mode.as_str()), Some(mode));
+        thp_defrag().unwrap();
+    }
+
+    #[test]
+    fn set_current_settings() {
+        let (Ok(mode), Ok(defrag)) = (thp_mode(), thp_defrag()) else {
+            return;
+        };
+        if unsafe { libc::geteuid() } != 0 {
+            return;
+        }
+        // Writing the current settings back leaves the system as it was.
+        match set_thp_mode(mode) {
+            // Read-only sysfs e.g. within a container.
+            Err(e) if e.raw_os_error() == Some(libc::EROFS) => return,
+            r => r.unwrap(),
+        }
+        set_thp_defrag(defrag).unwrap();
+        assert_eq!(thp_mode().unwrap(), mode);
+        assert_eq!(thp_defrag().unwrap(), defrag);
+    }
+}
@@ -0,0 +1,207 @@
+#![warn(
+    clippy::unwrap_used,
+    missing_docs,
+    rust_2018_idioms,
+    unused_lifetimes,
+    unused_qualifications
+)]
+#![doc = include_str!("../README.md")]
+
+#[cfg(not(target_os = "linux"))]
+compile_error!("Crate yudp_gso is Linux specific dependency but is used in non-linux system.");
+
+use std::io;
+use yown_fd::RawFd;
+use ysockaddr::YSockAddrC;
+
+/// Have the kernel split every send on the UDP socket into datagrams of segment_size
+/// through setsockopt(2) UDP_SEGMENT, 0 disabling it again.
+///
+/// Kernels before 4.18 fail with ENOPROTOOPT.
+#[inline]
+pub fn set_udp_segment(fd: RawFd, segment_size: u16) -> io::Result<()> {
+    let size = libc::c_int::from(segment_size);
+    // SAFETY: size is valid for the duration of the call.
+    let r = unsafe {
+        libc::setsockopt(
+            fd,
+            libc::SOL_UDP,
+            libc::UDP_SEGMENT,
+            core::ptr::addr_of!(size) as *const libc::c_void,
+            size_of::<libc::c_int>() as libc::socklen_t,
+        )
+    };
+    match r {
+        0 => Ok(()),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+// Room in u64 words keeping it aligned for the cmsghdr carrying the u16 segment size.
+const CMSG_BUF_LEN: usize = 4;
+
+/// Send data to the address as datagrams of segment_size through sendmsg(2) with the
+/// SOL_UDP UDP_SEGMENT cmsg returning the bytes sent.
+///
+/// The last datagram carries the remainder. The kernel caps a send at UDP_MAX_SEGMENTS, 64
+/// or 128 on newer kernels, and fails with EINVAL beyond it or when segment_size does not
+/// fit the path MTU.
+#[inline]
+pub fn sendmsg_gso(
+    fd: RawFd,
+    addr: &YSockAddrC,
+    data: &[u8],
+    segment_size: u16,
+) -> io::Result<usize> {
+    let mut cmsg_buf = [0u64; CMSG_BUF_LEN];
+    // SAFETY: CMSG_SPACE only computes the aligned len.
+    let cmsg_space = unsafe { libc::CMSG_SPACE(size_of::<u16>() as u32) } as usize;
+    debug_assert!(cmsg_space <= size_of_val(&cmsg_buf));
+
+    let (sa, sa_len) = addr.as_c_sockaddr_len();
+    let mut iov = libc::iovec {
+        iov_base: data.as_ptr() as *mut libc::c_void,
+        iov_len: data.len(),
+    };
+    // SAFETY: msghdr is valid when zeroed.
+    let mut msg: libc::msghdr = unsafe { core::mem::zeroed() };
+    msg.msg_name = sa as *mut libc::c_void;
+    msg.msg_namelen = sa_len;
+    msg.msg_iov = &mut iov;
+    msg.msg_iovlen = 1;
+    msg.msg_control = cmsg_buf.as_mut_ptr() as *mut libc::c_void;
+    msg.msg_controllen = cmsg_space as _;
+
+    // SAFETY: msg_control points to cmsg_space zeroed bytes aligned for cmsghdr.
+    unsafe {
+        let cmsg = libc::CMSG_FIRSTHDR(&msg);
+        (*cmsg).cmsg_level = libc::SOL_UDP;
+        (*cmsg).cmsg_type = libc::UDP_SEGMENT;
+        (*cmsg).cmsg_len = libc::CMSG_LEN(size_of::<u16>() as u32) as _;
+        core::ptr::write_unaligned(libc::CMSG_DATA(cmsg) as *mut u16, segment_size);
+    }
+
+    // SAFETY: data, the sockaddr and the cmsg are valid for the duration of the call and
+    // the kernel only reads through msg.
+    let r = unsafe { libc::sendmsg(fd, &msg, 0) };
+    if r < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(r as usize)
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use rstest::rstest;
+    use std::net::UdpSocket;
+    use std::time::Duration;
+    use yown_fd::AsRawFd;
+
+    fn unsupported(e: &io::Error) -> bool {
+        e.raw_os_error() == Some(libc::ENOPROTOOPT)
+    }
+
+    // Receive up to max datagrams in a single recvmmsg(2) returning their lens.
+    fn recv_datagrams(sock: &UdpSocket, max: usize, size: usize) -> Vec<Vec<u8>> {
+        let mut bufs = vec![vec![0u8; size]; max];
+        let mut iovs: Vec<libc::iovec> = bufs
+            .iter_mut()
+            .map(|b| libc::iovec {
+                iov_base: b.as_mut_ptr() as *mut libc::c_void,
+                iov_len: b.len(),
+            })
+            .collect();
+        let mut msgs: Vec<libc::mmsghdr> = iovs
+            .iter_mut()
+            .map(|iov| {
+                let mut m: libc::mmsghdr = unsafe { core::mem::zeroed() };
+                m.msg_hdr.msg_iov = iov;
+                m.msg_hdr.msg_iovlen = 1;
+                m
+            })
+            .collect();
+        let n = unsafe {
+            libc::recvmmsg(
+                sock.as_raw_fd(),
+                msgs.as_mut_ptr(),
+                max as libc::c_uint,
+                libc::MSG_WAITFORONE,
+                core::ptr::null_mut(),
+            )
+        };
+        assert!(n >= 0, "{}", io::Error::last_os_error());
+        let lens: Vec<usize> = msgs[..n as usize]
+            .iter()
+            .map(|m| m.msg_len as usize)
+            .collect();
+        bufs.into_iter()
+            .zip(lens)
+            .map(|(mut b, len)| {
+                b.truncate(len);
+                b
+            })
+            .collect()
+    }
+
+    fn recv_all(sock: &UdpSocket, count: usize, size: usize) -> Vec<Vec<u8>> {
+        let mut got = Vec::new();
+        while got.len() < count {
+            got.extend(recv_datagrams(sock, count - got.len(), size));
+        }
+        got
+    }
+
+    #[rstest]
+    #[case(1000, 10_000)]
+    #[case(1000, 10_500)]
+    #[case(1200, 1200)]
+    fn gso_segments_arrive(#[case] segment_size: u16, #[case] len: usize) {
+        let rx = UdpSocket::bind("127.0.0.1:0").unwrap();
+        rx.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let tx = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let data: Vec<u8> = (0..len).map(|i| (i % 251) as u8).collect();
+        let addr = YSockAddrC::from(rx.local_addr().unwrap());
+        match sendmsg_gso(tx.as_raw_fd(), &addr, &data, segment_size) {
+            Err(e) if unsupported(&e) => return,
+            r => assert_eq!(r.unwrap(), len),
+        }
+        let count = len.div_ceil(segment_size as usize);
+        let got = recv_all(&rx, count, segment_size as usize);
+        assert!(got[..count - 1]
+            .iter()
+            .all(|d| d.len() == segment_size as usize));
+        assert_eq!(got.concat(), data);
+    }
+
+    #[test]
+    fn socket_option_segments() {
+        let rx = UdpSocket::bind("127.0.0.1:0").unwrap();
+        rx.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let tx = UdpSocket::bind("127.0.0.1:0").unwrap();
+        match set_udp_segment(tx.as_raw_fd(), 500) {
+            Err(e) if unsupported(&e) => return,
+            r => r.unwrap(),
+        }
+        let data = [7u8; 2000];
+        assert_eq!(tx.send_to(&data, rx.local_addr().unwrap()).unwrap(), 2000);
+        let got = recv_all(&rx, 4, 2000);
+        assert!(got.iter().all(|d| d.len() == 500));
+
+        set_udp_segment(tx.as_raw_fd(), 0).unwrap();
+        tx.send_to(&data, rx.local_addr().unwrap()).unwrap();
+        assert_eq!(recv_all(&rx, 1, 2000)[0].len(), 2000);
+    }
+
+    #[test]
+    fn too_many_segments() {
+        let tx = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = YSockAddrC::from(tx.local_addr().unwrap());
+        let data = vec![0u8; 100 * 129];
+        match sendmsg_gso(tx.as_raw_fd(), &addr, &data, 100) {
+            Err(e) if unsupported(&e) => {}
+            r => assert_eq!(r.unwrap_err().raw_os_error(), Some(libc::EINVAL)),
+        }
+    }
+}
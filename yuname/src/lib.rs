@@ -0,0 +1,115 @@
+#![warn(
+    clippy::unwrap_used,
+    missing_docs,
+    rust_2018_idioms,
+    unused_lifetimes,
+    unused_qualifications
+)]
+#![doc = include_str!("../README.md")]
+
+#[cfg(not(target_os = "linux"))]
+compile_error!("Crate yuname is Linux specific dependency but is used in non-linux system.");
+
+use std::ffi::CStr;
+use std::io;
+
+/// System information as returned by uname(2)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnameInfo {
+    /// Operating system name e.g. Linux
+    pub sysname: String,
+    /// Hostname within the UTS namespace
+    pub nodename: String,
+    /// Kernel release e.g. 6.8.0-45-generic
+    pub release: String,
+    /// Kernel version e.g. #45-Ubuntu SMP PREEMPT_DYNAMIC
+    pub version: String,
+    /// Hardware e.g. x86_64
+    pub machine: String,
+}
+
+impl UnameInfo {
+    /// Parse the leading major.minor.patch of the release where a missing patch is 0 and
+    /// any suffix e.g. -rc1 or -generic is ignored.
+    #[inline]
+    pub fn kernel_version(&self) -> Option<(u32, u32, u32)> {
+        let mut parts = self.release.splitn(3, '.');
+        let major = leading_number(parts.next()?)?;
+        let minor = leading_number(parts.next()?)?;
+        let patch = match parts.next() {
+            Some(patch) => leading_number(patch).unwrap_or(0),
+            None => 0,
+        };
+        Some((major, minor, patch))
+    }
+}
+
+#[inline]
+fn leading_number(part: &str) -> Option<u32> {
+    let end = part
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(part.len());
+    part[..end].parse().ok()
+}
+
+#[inline]
+fn field(chars: &[libc::c_char]) -> String {
+    // SAFETY: The kernel NUL terminates every field within its array.
+    unsafe { CStr::from_ptr(chars.as_ptr()) }
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// System information through uname(2)
+#[inline]
+pub fn uname() -> io::Result<UnameInfo> {
+    // SAFETY: utsname is plain arrays for which zeroed is valid.
+    let mut uts: libc::utsname = unsafe { core::mem::zeroed() };
+    // SAFETY: uts is valid for writes.
+    if unsafe { libc::uname(&mut uts) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(UnameInfo {
+        sysname: field(&uts.sysname),
+        nodename: field(&uts.nodename),
+        release: field(&uts.release),
+        version: field(&uts.version),
+        machine: field(&uts.machine),
+    })
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use rstest::rstest;
+
+    #[test]
+    fn uname_linux() {
+        let info = uname().unwrap();
+        assert_eq!(info.sysname, "Linux");
+        assert!(!info.machine.is_empty());
+        let (major, _, _) = info.kernel_version().unwrap();
+        assert!(major >= 4, "{}", info.release);
+    }
+
+    #[rstest]
+    #[case("6.8.0-45-generic", Some((6, 8, 0)))]
+    #[case("6.18.44-fc-v130", Some((6, 18, 44)))]
+    #[case("5.15.167.4-microsoft-standard-WSL2", Some((5, 15, 167)))]
+    #[case("6.12-rc1", Some((6, 12, 0)))]
+    #[case("4.9", Some((4, 9, 0)))]
+    #[case("6", None)]
+    #[case("", None)]
+    #[case("x.y.z", None)]
+    fn kernel_versions(#[case] release: &str, #[case] expected: Option<(u32, u32, u32)>) {
+        let info = UnameInfo {
+            sysname: "Linux".into(),
+            nodename: String::new(),
+            release: release.into(),
+            version: String::new(),
+            machine: String::new(),
+        };
+        assert_eq!(info.kernel_version(), expected);
+    }
+}
@@ -0,0 +1,125 @@
+#![warn(
+    clippy::unwrap_used,
+    missing_docs,
+    rust_2018_idioms,
+    unused_lifetimes,
+    unused_qualifications
+)]
+#![doc = include_str!("../README.md")]
+
+#[cfg(not(target_os = "linux"))]
+compile_error!("Crate yunshare is Linux specific dependency but is used in non-linux system.");
+
+use std::io;
+use yown_fd::RawFd;
+
+bitflags::bitflags! {
+    /// unshare(2) namespace flags
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    pub struct UnshareFlags: libc::c_int {
+        /// Network namespace
+        const CLONE_NEWNET = libc::CLONE_NEWNET;
+        /// Mount namespace - refused with EINVAL in a multi-threaded process
+        const CLONE_NEWNS = libc::CLONE_NEWNS;
+        /// PID namespace for the children created afterwards
+        const CLONE_NEWPID = libc::CLONE_NEWPID;
+        /// Hostname and NIS domain name namespace
+        const CLONE_NEWUTS = libc::CLONE_NEWUTS;
+        /// System V IPC and POSIX message queue namespace
+        const CLONE_NEWIPC = libc::CLONE_NEWIPC;
+        /// User namespace - refused with EINVAL in a multi-threaded process
+        const CLONE_NEWUSER = libc::CLONE_NEWUSER;
+        /// Cgroup namespace
+        const CLONE_NEWCGROUP = libc::CLONE_NEWCGROUP;
+    }
+}
+
+/// Move the calling thread into new namespaces through unshare(2).
+/// Everything but CLONE_NEWUSER requires CAP_SYS_ADMIN.
+#[inline]
+pub fn unshare(flags: UnshareFlags) -> io::Result<()> {
+    // SAFETY: Only takes the flags.
+    match unsafe { libc::unshare(flags.bits()) } {
+        0 => Ok(()),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+/// Move the calling thread into the namespace referred to by the fd e.g. an open
+/// /proc/[pid]/ns/uts through setns(2). nstype 0 allows any type, otherwise one of the
+/// CLONE_NEW* the fd must refer to.
+#[inline]
+pub fn setns(fd: RawFd, nstype: i32) -> io::Result<()> {
+    // SAFETY: Only takes the fd and the type.
+    match unsafe { libc::setns(fd, nstype) } {
+        0 => Ok(()),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use std::fs::File;
+    use yown_fd::AsRawFd;
+
+    fn hostname() -> Vec<u8> {
+        let mut buf = [0u8; 256];
+        assert_eq!(
+            unsafe { libc::gethostname(buf.as_mut_ptr().cast(), buf.len()) },
+            0
+        );
+        let len = buf.iter().position(|b| *b == 0).unwrap();
+        buf[..len].to_vec()
+    }
+
+    fn sethostname(name: &[u8]) {
+        assert_eq!(
+            unsafe { libc::sethostname(name.as_ptr().cast(), name.len()) },
+            0
+        );
+    }
+
+    #[test]
+    fn uts_unshare_and_restore() {
+        let original = hostname();
+        let expected = original.clone();
+        let (unshared_tx, unshared_rx) = std::sync::mpsc::channel();
+        let (checked_tx, checked_rx) = std::sync::mpsc::channel::<()>();
+        // unshare and setns only affect the calling thread and the threads it creates afterwards.
+        let child = std::thread::spawn(move || {
+            let original_ns = File::open("/proc/thread-self/ns/uts").unwrap();
+            match unshare(UnshareFlags::CLONE_NEWUTS) {
+                Err(e) if e.raw_os_error() == Some(libc::EPERM) => {
+                    unshared_tx.send(false).unwrap();
+                    return;
+                }
+                r => r.unwrap(),
+            }
+            sethostname(b"yunshare-test");
+            assert_eq!(hostname(), b"yunshare-test");
+            unshared_tx.send(true).unwrap();
+            checked_rx.recv().unwrap();
+
+            setns(original_ns.as_raw_fd(), libc::CLONE_NEWUTS).unwrap();
+            assert_eq!(hostname(), original);
+        });
+        if unshared_rx.recv().unwrap() {
+            assert_eq!(hostname(), expected);
+            checked_tx.send(()).unwrap();
+        }
+        child.join().unwrap();
+        assert_eq!(hostname(), expected);
+    }
+
+    #[test]
+    fn setns_wrong_type() {
+        let uts = File::open("/proc/thread-self/ns/uts").unwrap();
+        let err = setns(uts.as_raw_fd(), libc::CLONE_NEWNET).unwrap_err();
+        assert!(matches!(
+            err.raw_os_error(),
+            Some(libc::EINVAL) | Some(libc::EPERM)
+        ));
+    }
+}
@@ -0,0 +1,206 @@
+#![warn(
+    clippy::unwrap_used,
+    missing_docs,
+    rust_2018_idioms,
+    unused_lifetimes,
+    unused_qualifications
+)]
+#![doc = include_str!("../README.md")]
+
+#[cfg(not(target_os = "linux"))]
+compile_error!("Crate yxattr is Linux specific dependency but is used in non-linux system.");
+
+use std::ffi::{CStr, CString};
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+
+bitflags::bitflags! {
+    /// setxattr(2) flags, with neither the attribute is created or replaced
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    pub struct XattrFlags: libc::c_int {
+        /// Fail with EEXIST if the attribute already exists
+        const XATTR_CREATE = libc::XATTR_CREATE;
+        /// Fail with ENODATA if the attribute does not exist yet
+        const XATTR_REPLACE = libc::XATTR_REPLACE;
+    }
+}
+
+#[inline]
+fn path_cstring(path: &Path) -> io::Result<CString> {
+    CString::new(path.as_os_str().as_bytes())
+        .map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))
+}
+
+// Size through a probe with an empty buffer and retry as long as the value grows in
+// between with ERANGE.
+#[inline]
+fn sized_read(mut read: impl FnMut(*mut libc::c_void, usize) -> isize) -> io::Result<Vec<u8>> {
+    loop {
+        let size = match read(core::ptr::null_mut(), 0) {
+            r if r < 0 => return Err(io::Error::last_os_error()),
+            r => r as usize,
+        };
+        let mut buf: Vec<u8> = Vec::with_capacity(size);
+        let r = read(buf.as_mut_ptr().cast(), size);
+        if r < 0 {
+            let e = io::Error::last_os_error();
+            match e.raw_os_error() {
+                Some(libc::ERANGE) => continue,
+                _ => return Err(e),
+            }
+        }
+        // SAFETY: The first r <= size bytes were initialized.
+        unsafe { buf.set_len(r as usize) };
+        return Ok(buf);
+    }
+}
+
+/// Set the extended attribute name of the file at path to value through setxattr(2)
+/// following symlinks.
+///
+/// Names carry their namespace e.g. user.*, trusted.* or security.* where filesystems
+/// without xattr support fail with EOPNOTSUPP. Path with an interior NUL is
+/// [`io::ErrorKind::InvalidInput`].
+#[inline]
+pub fn setxattr(path: &Path, name: &CStr, value: &[u8], flags: XattrFlags) -> io::Result<()> {
+    let path = path_cstring(path)?;
+    // SAFETY: path and name are NUL terminated and value is valid for its len.
+    let r = unsafe {
+        libc::setxattr(
+            path.as_ptr(),
+            name.as_ptr(),
+            value.as_ptr().cast(),
+            value.len(),
+            flags.bits(),
+        )
+    };
+    match r {
+        0 => Ok(()),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+/// Get the value of the extended attribute name of the file at path through getxattr(2)
+/// following symlinks where a missing attribute fails with ENODATA.
+#[inline]
+pub fn getxattr(path: &Path, name: &CStr) -> io::Result<Vec<u8>> {
+    let path = path_cstring(path)?;
+    // SAFETY: path and name are NUL terminated and buf is valid for size bytes.
+    sized_read(|buf, size| unsafe { libc::getxattr(path.as_ptr(), name.as_ptr(), buf, size) })
+}
+
+/// Names of the extended attributes of the file at path through listxattr(2) following
+/// symlinks. Only the names the caller may access are listed e.g. no trusted.* without
+/// CAP_SYS_ADMIN.
+#[inline]
+pub fn listxattr(path: &Path) -> io::Result<Vec<CString>> {
+    let path = path_cstring(path)?;
+    // SAFETY: path is NUL terminated and buf is valid for size bytes.
+    let list = sized_read(|buf, size| unsafe { libc::listxattr(path.as_ptr(), buf.cast(), size) })?;
+    Ok(list
+        .split_inclusive(|b| *b == 0)
+        .filter_map(|name| CStr::from_bytes_with_nul(name).ok())
+        .map(CStr::to_owned)
+        .collect())
+}
+
+/// Remove the extended attribute name of the file at path through removexattr(2)
+/// following symlinks where a missing attribute fails with ENODATA.
+#[inline]
+pub fn removexattr(path: &Path, name: &CStr) -> io::Result<()> {
+    let path = path_cstring(path)?;
+    // SAFETY: path and name are NUL terminated.
+    match unsafe { libc::removexattr(path.as_ptr(), name.as_ptr()) } {
+        0 => Ok(()),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use rstest::rstest;
+    use std::path::PathBuf;
+
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("yxattr-{}-{}", std::process::id(), name));
+            std::fs::write(&path, b"data").unwrap();
+            Self(path)
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    // Filesystems without user xattrs have nothing to test.
+    fn set_or_skip(file: &TempFile, name: &CStr, value: &[u8]) -> bool {
+        match setxattr(&file.0, name, value, XattrFlags::empty()) {
+            Ok(()) => true,
+            Err(e) if e.raw_os_error() == Some(libc::EOPNOTSUPP) => false,
+            Err(e) => panic!("setxattr: {}", e),
+        }
+    }
+
+    #[rstest]
+    #[case(c"user.yxattr", b"value".as_slice())]
+    #[case(c"user.empty", b"".as_slice())]
+    #[case(c"user.large", [7u8; 4000].as_slice())]
+    fn set_list_get_remove(#[case] name: &CStr, #[case] value: &[u8]) {
+        let file = TempFile::new(&format!("roundtrip-{}", value.len()));
+        if !set_or_skip(&file, name, value) {
+            return;
+        }
+        assert!(listxattr(&file.0)
+            .unwrap()
+            .iter()
+            .any(|n| n.as_c_str() == name));
+        assert_eq!(getxattr(&file.0, name).unwrap(), value);
+        removexattr(&file.0, name).unwrap();
+        assert!(!listxattr(&file.0)
+            .unwrap()
+            .iter()
+            .any(|n| n.as_c_str() == name));
+        let err = getxattr(&file.0, name).unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(libc::ENODATA));
+    }
+
+    #[rstest]
+    #[case(XattrFlags::XATTR_CREATE, true, Some(libc::EEXIST))]
+    #[case(XattrFlags::XATTR_REPLACE, true, None)]
+    #[case(XattrFlags::XATTR_CREATE, false, None)]
+    #[case(XattrFlags::XATTR_REPLACE, false, Some(libc::ENODATA))]
+    fn create_or_replace(
+        #[case] flags: XattrFlags,
+        #[case] existing: bool,
+        #[case] errno: Option<i32>,
+    ) {
+        let file = TempFile::new(&format!("flags-{}-{}", flags.bits(), existing));
+        if !set_or_skip(&file, c"user.probe", b"") {
+            return;
+        }
+        if existing {
+            setxattr(&file.0, c"user.flags", b"old", XattrFlags::empty()).unwrap();
+        }
+        let r = setxattr(&file.0, c"user.flags", b"new", flags);
+        assert_eq!(r.err().and_then(|e| e.raw_os_error()), errno);
+    }
+
+    #[test]
+    fn missing_file() {
+        let path = Path::new("/nonexistent-yxattr");
+        let err = listxattr(path).unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(libc::ENOENT));
+        let err = removexattr(path, c"user.none").unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(libc::ENOENT));
+        let err = getxattr(Path::new("nul\0byte"), c"user.none").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+}